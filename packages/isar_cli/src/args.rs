@@ -0,0 +1,246 @@
+use isar_core::core::query_builder::Sort;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: isar <FILE> <COMMAND> [OPTIONS]
+
+Opens a copy of an .isar or .sqlite file, so the original is never modified.
+
+Commands:
+  stats                       Object counts and sizes of all collections
+  schema                      Schemas read from the file as JSON
+  dump [COLLECTION]           All objects as JSON
+  query <COLLECTION>          Objects matching the query options as JSON
+  verify                      Checks the objects and indexes for consistency
+
+Query options:
+  --where <QUERY>                 Query text like 'age >= 18 ORDER BY name',
+                                  combined with the other query options
+  --filter <PROPERTY:OP[:VALUE]>  Condition, repeat to combine them with AND.
+                                  OP is one of eq, ne, gt, gte, lt, lte,
+                                  startswith, endswith, contains, matches,
+                                  regex, isnull or notnull
+  --sort <PROPERTY[:desc]>        Sort by a property, repeat for more
+  --ignore-case                   Compare strings case insensitively
+  --offset <N>                    Skip the first N objects
+  --limit <N>                     Return at most N objects
+
+Options:
+  --key <KEY>                     Encryption key of a SQLCipher database
+  --max-size <MIB>                Maximum size of native instances (default 1024)";
+
+const DEFAULT_MAX_SIZE_MIB: u32 = 1024;
+
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    pub path: PathBuf,
+    pub command: Command,
+    pub encryption_key: Option<String>,
+    pub max_size_mib: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Stats,
+    Schema,
+    Dump(Option<String>),
+    Query(QueryArgs),
+    Verify,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct QueryArgs {
+    pub collection: String,
+    pub text: Option<String>,
+    pub filters: Vec<FilterArg>,
+    pub sorts: Vec<(String, Sort)>,
+    pub case_sensitive: bool,
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterOp {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    StartsWith,
+    EndsWith,
+    Contains,
+    Matches,
+    Regex,
+    IsNull,
+    NotNull,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FilterArg {
+    pub property: String,
+    pub op: FilterOp,
+    pub value: Option<String>,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut positional = vec![];
+    let mut query = QueryArgs {
+        case_sensitive: true,
+        ..Default::default()
+    };
+    let mut encryption_key = None;
+    let mut max_size_mib = DEFAULT_MAX_SIZE_MIB;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}.", name))
+        };
+        match arg.as_str() {
+            "--where" => query.text = Some(value("--where")?),
+            "--filter" => query.filters.push(parse_filter(&value("--filter")?)?),
+            "--sort" => query.sorts.push(parse_sort(&value("--sort")?)),
+            "--ignore-case" => query.case_sensitive = false,
+            "--offset" => query.offset = Some(parse_number("--offset", &value("--offset")?)?),
+            "--limit" => query.limit = Some(parse_number("--limit", &value("--limit")?)?),
+            "--key" => encryption_key = Some(value("--key")?),
+            "--max-size" => max_size_mib = parse_number("--max-size", &value("--max-size")?)?,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}.", arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let path = positional.next().ok_or("Missing file.")?;
+    let command = match positional.next().as_deref() {
+        Some("stats") => Command::Stats,
+        Some("schema") => Command::Schema,
+        Some("dump") => Command::Dump(positional.next()),
+        Some("query") => {
+            query.collection = positional.next().ok_or("Missing collection to query.")?;
+            Command::Query(query)
+        }
+        Some("verify") => Command::Verify,
+        Some(command) => return Err(format!("Unknown command {}.", command)),
+        None => return Err("Missing command.".to_string()),
+    };
+    if let Some(arg) = positional.next() {
+        return Err(format!("Unexpected argument {}.", arg));
+    }
+
+    Ok(Args {
+        path: PathBuf::from(path),
+        command,
+        encryption_key,
+        max_size_mib,
+    })
+}
+
+fn parse_filter(filter: &str) -> Result<FilterArg, String> {
+    let mut parts = filter.splitn(3, ':');
+    let property = parts.next().unwrap_or_default();
+    let op = match parts.next() {
+        Some("eq") => FilterOp::Equal,
+        Some("ne") => FilterOp::NotEqual,
+        Some("gt") => FilterOp::Greater,
+        Some("gte") => FilterOp::GreaterOrEqual,
+        Some("lt") => FilterOp::Less,
+        Some("lte") => FilterOp::LessOrEqual,
+        Some("startswith") => FilterOp::StartsWith,
+        Some("endswith") => FilterOp::EndsWith,
+        Some("contains") => FilterOp::Contains,
+        Some("matches") => FilterOp::Matches,
+        Some("regex") => FilterOp::Regex,
+        Some("isnull") => FilterOp::IsNull,
+        Some("notnull") => FilterOp::NotNull,
+        _ => return Err(format!("Invalid filter {}.", filter)),
+    };
+    let value = parts.next().map(|v| v.to_string());
+    let needs_value = !matches!(op, FilterOp::IsNull | FilterOp::NotNull);
+    if property.is_empty() || needs_value != value.is_some() {
+        return Err(format!("Invalid filter {}.", filter));
+    }
+    Ok(FilterArg {
+        property: property.to_string(),
+        op,
+        value,
+    })
+}
+
+fn parse_sort(sort: &str) -> (String, Sort) {
+    if let Some(property) = sort.strip_suffix(":desc") {
+        (property.to_string(), Sort::Desc)
+    } else {
+        let property = sort.strip_suffix(":asc").unwrap_or(sort);
+        (property.to_string(), Sort::Asc)
+    }
+}
+
+fn parse_number(name: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {} for {}.", value, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        parse_args(args.split(' ').map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let args = parse(
+            "db.isar query Person --filter name:startswith:a:b --filter age:isnull \
+             --sort age:desc --sort name --ignore-case --limit 10",
+        )
+        .unwrap();
+        assert_eq!(args.path, PathBuf::from("db.isar"));
+        assert_eq!(args.max_size_mib, DEFAULT_MAX_SIZE_MIB);
+        assert_eq!(
+            args.command,
+            Command::Query(QueryArgs {
+                collection: "Person".to_string(),
+                text: None,
+                filters: vec![
+                    FilterArg {
+                        property: "name".to_string(),
+                        op: FilterOp::StartsWith,
+                        value: Some("a:b".to_string()),
+                    },
+                    FilterArg {
+                        property: "age".to_string(),
+                        op: FilterOp::IsNull,
+                        value: None,
+                    },
+                ],
+                sorts: vec![
+                    ("age".to_string(), Sort::Desc),
+                    ("name".to_string(), Sort::Asc)
+                ],
+                case_sensitive: false,
+                offset: None,
+                limit: Some(10),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("db.isar").is_err());
+        assert!(parse("db.isar query").is_err());
+        assert!(parse("db.isar stats extra").is_err());
+        assert!(parse("db.isar query Person --filter name:eq").is_err());
+        assert!(parse("db.isar query Person --filter name:isnull:a").is_err());
+        assert!(parse("db.isar query Person --filter name:like:a").is_err());
+        assert!(parse("db.isar query Person --limit").is_err());
+        assert!(parse("db.isar dump --unknown").is_err());
+        assert_eq!(
+            parse("db.sqlite dump --key secret").unwrap().encryption_key,
+            Some("secret".to_string())
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use crate::args::{Command, FilterArg, FilterOp, QueryArgs};
+use isar_core::core::cursor::IsarQueryCursor;
+use isar_core::core::data_type::DataType;
+use isar_core::core::filter::{ConditionType, Filter, FilterCondition};
+use isar_core::core::instance::IsarInstance;
+use isar_core::core::query_builder::IsarQueryBuilder;
+use isar_core::core::query_description::QueryDescription;
+use isar_core::core::query_parser::parse_query;
+use isar_core::core::reader::IsarReader;
+use isar_core::core::schema::IsarSchema;
+use isar_core::core::value::IsarValue;
+use std::io::Write;
+
+const ID_PROPERTY: &str = "id";
+
+pub fn run<I: IsarInstance>(
+    instance: &I,
+    schemas: &[IsarSchema],
+    command: &Command,
+    out: &mut impl Write,
+) -> Result<(), String> {
+    let txn = instance.begin_txn(false).map_err(|e| e.to_string())?;
+    let result = match command {
+        Command::Stats => stats(instance, &txn, schemas, out),
+        Command::Schema => schema(schemas, out),
+        Command::Dump(collection) => dump(instance, &txn, schemas, collection.as_deref(), out),
+        Command::Query(query) => self::query(instance, &txn, schemas, query, out),
+        Command::Verify => verify(instance, &txn, out),
+    };
+    instance.abort_txn(txn);
+    result
+}
+
+fn stats<I: IsarInstance>(
+    instance: &I,
+    txn: &I::Txn,
+    schemas: &[IsarSchema],
+    out: &mut impl Write,
+) -> Result<(), String> {
+    for schema in schemas.iter().filter(|s| !s.embedded) {
+        let index = collection_index(instance, &schema.name)?;
+        let count = instance.count(txn, index).map_err(|e| e.to_string())?;
+        let size = instance
+            .get_size(txn, index, false)
+            .map_err(|e| e.to_string())?;
+        let size_with_indexes = instance
+            .get_size(txn, index, true)
+            .map_err(|e| e.to_string())?;
+        writeln!(
+            out,
+            "{}: {} objects, {} bytes ({} bytes with indexes)",
+            schema.name, count, size, size_with_indexes
+        )
+        .map_err(|e| e.to_string())?;
+
+        let indexes = schema.indexes.iter().map(|i| i.name.as_str());
+        let indexes = indexes.collect::<Vec<_>>().join(", ");
+        if !indexes.is_empty() {
+            writeln!(out, "  indexes: {}", indexes).map_err(|e| e.to_string())?;
+        }
+        if schema.tombstones {
+            let tombstones = instance
+                .get_tombstones(txn, index, 0, None)
+                .map_err(|e| e.to_string())?;
+            writeln!(out, "  tombstones: {}", tombstones.len()).map_err(|e| e.to_string())?;
+        }
+        if schema.field_timestamps {
+            writeln!(out, "  field timestamps: enabled").map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn schema(schemas: &[IsarSchema], out: &mut impl Write) -> Result<(), String> {
+    serde_json::to_writer_pretty(&mut *out, schemas).map_err(|e| e.to_string())?;
+    writeln!(out).map_err(|e| e.to_string())
+}
+
+fn dump<I: IsarInstance>(
+    instance: &I,
+    txn: &I::Txn,
+    schemas: &[IsarSchema],
+    collection: Option<&str>,
+    out: &mut impl Write,
+) -> Result<(), String> {
+    if let Some(collection) = collection {
+        let query = QueryArgs {
+            collection: collection.to_string(),
+            ..Default::default()
+        };
+        return self::query(instance, txn, schemas, &query, out);
+    }
+
+    write!(out, "{{").map_err(|e| e.to_string())?;
+    let collections = schemas.iter().filter(|s| !s.embedded);
+    for (i, schema) in collections.enumerate() {
+        if i > 0 {
+            write!(out, ",").map_err(|e| e.to_string())?;
+        }
+        serde_json::to_writer(&mut *out, &schema.name).map_err(|e| e.to_string())?;
+        write!(out, ":").map_err(|e| e.to_string())?;
+
+        let index = collection_index(instance, &schema.name)?;
+        let query = instance
+            .query(index)
+            .and_then(|qb| qb.build())
+            .map_err(|e| e.to_string())?;
+        let mut cursor = instance
+            .query_cursor(txn, &query, None, None)
+            .map_err(|e| e.to_string())?;
+        write_objects(&mut cursor, out)?;
+    }
+    writeln!(out, "}}").map_err(|e| e.to_string())
+}
+
+fn query<I: IsarInstance>(
+    instance: &I,
+    txn: &I::Txn,
+    schemas: &[IsarSchema],
+    args: &QueryArgs,
+    out: &mut impl Write,
+) -> Result<(), String> {
+    let index = collection_index(instance, &args.collection)?;
+    let schema = schemas
+        .iter()
+        .find(|s| s.name == args.collection)
+        .ok_or_else(|| format!("Unknown collection {}.", args.collection))?;
+
+    let description = match &args.text {
+        Some(text) => parse_query(text, schemas, schema).map_err(|e| e.to_string())?,
+        None => QueryDescription::default(),
+    };
+
+    let mut builder = instance.query(index).map_err(|e| e.to_string())?;
+    let mut filters = args
+        .filters
+        .iter()
+        .map(|f| build_filter(schema, f, args.case_sensitive))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(filter) = &description.filter {
+        filters.push(filter.resolve(schemas, schema).map_err(|e| e.to_string())?);
+    }
+    match filters.len() {
+        0 => {}
+        1 => builder.set_filter(filters.into_iter().next().unwrap()),
+        _ => builder.set_filter(Filter::And(filters)),
+    }
+    for (property, sort) in &args.sorts {
+        let (property_index, _) = find_property(schema, property)?;
+        builder.add_sort(property_index, *sort, args.case_sensitive);
+    }
+    for sort in &description.sort {
+        let (property_index, _) = find_property(schema, &sort.property)?;
+        builder.add_sort(property_index, sort.order, sort.case_sensitive);
+    }
+    for distinct in &description.distinct {
+        let (property_index, _) = find_property(schema, &distinct.property)?;
+        builder.add_distinct(property_index, distinct.case_sensitive);
+    }
+    let query = builder.build().map_err(|e| e.to_string())?;
+
+    let offset = args.offset.or(description.offset);
+    let limit = args.limit.or(description.limit);
+    let mut cursor = instance
+        .query_cursor(txn, &query, offset, limit)
+        .map_err(|e| e.to_string())?;
+    write_objects(&mut cursor, out)?;
+    writeln!(out).map_err(|e| e.to_string())
+}
+
+fn verify<I: IsarInstance>(instance: &I, txn: &I::Txn, out: &mut impl Write) -> Result<(), String> {
+    instance.verify(txn).map_err(|e| e.to_string())?;
+    writeln!(out, "OK").map_err(|e| e.to_string())
+}
+
+fn collection_index<I: IsarInstance>(instance: &I, name: &str) -> Result<u16, String> {
+    instance
+        .get_collections()
+        .position(|c| c == name)
+        .filter(|index| !instance.is_embedded(*index as u16))
+        .map(|index| index as u16)
+        .ok_or_else(|| format!("Unknown collection {}.", name))
+}
+
+fn write_objects<C: IsarQueryCursor>(cursor: &mut C, out: &mut impl Write) -> Result<(), String> {
+    write!(out, "[").map_err(|e| e.to_string())?;
+    let mut first = true;
+    while let Some(reader) = cursor.next() {
+        if !first {
+            write!(out, ",").map_err(|e| e.to_string())?;
+        }
+        first = false;
+        reader
+            .serialize(&mut serde_json::Serializer::new(&mut *out))
+            .map_err(|e| e.to_string())?;
+    }
+    write!(out, "]").map_err(|e| e.to_string())
+}
+
+/// Returns the index of a property as used by filters and sorts. The id has
+/// index 0 and the properties follow in schema order.
+fn find_property(schema: &IsarSchema, name: &str) -> Result<(u16, DataType), String> {
+    let property = schema
+        .properties
+        .iter()
+        .filter_map(|p| p.name.as_deref().map(|n| (n, p.data_type)))
+        .enumerate()
+        .find(|(_, (n, _))| *n == name);
+    match property {
+        Some((index, (_, data_type))) => Ok((index as u16 + 1, data_type)),
+        None if name == ID_PROPERTY => Ok((0, DataType::Long)),
+        None => Err(format!("Unknown property {} of {}.", name, schema.name)),
+    }
+}
+
+fn build_filter(
+    schema: &IsarSchema,
+    arg: &FilterArg,
+    case_sensitive: bool,
+) -> Result<Filter, String> {
+    let (property_index, data_type) = find_property(schema, &arg.property)?;
+    let (condition_type, negate) = match arg.op {
+        FilterOp::Equal => (ConditionType::Equal, false),
+        FilterOp::NotEqual => (ConditionType::Equal, true),
+        FilterOp::Greater => (ConditionType::Greater, false),
+        FilterOp::GreaterOrEqual => (ConditionType::GreaterOrEqual, false),
+        FilterOp::Less => (ConditionType::Less, false),
+        FilterOp::LessOrEqual => (ConditionType::LessOrEqual, false),
+        FilterOp::StartsWith => (ConditionType::StringStartsWith, false),
+        FilterOp::EndsWith => (ConditionType::StringEndsWith, false),
+        FilterOp::Contains => (ConditionType::StringContains, false),
+        FilterOp::Matches => (ConditionType::StringMatches, false),
+        FilterOp::Regex => (ConditionType::StringRegex, false),
+        FilterOp::IsNull => (ConditionType::IsNull, false),
+        FilterOp::NotNull => (ConditionType::IsNull, true),
+    };
+    let values = match &arg.value {
+        Some(value) => vec![Some(parse_value(data_type, value)?)],
+        None => vec![],
+    };
+    let filter = Filter::Condition(FilterCondition::new(
+        property_index,
+        condition_type,
+        values,
+        case_sensitive,
+    ));
+    if negate {
+        Ok(Filter::Not(Box::new(filter)))
+    } else {
+        Ok(filter)
+    }
+}
+
+fn parse_value(data_type: DataType, value: &str) -> Result<IsarValue, String> {
+    let invalid = || format!("Invalid {:?} value {}.", data_type, value);
+    match data_type {
+        DataType::Bool => value.parse().map(IsarValue::Bool).map_err(|_| invalid()),
+        DataType::Byte | DataType::Int | DataType::Long => {
+            value.parse().map(IsarValue::Integer).map_err(|_| invalid())
+        }
+        DataType::Float | DataType::Double => {
+            value.parse().map(IsarValue::Real).map_err(|_| invalid())
+        }
+        DataType::String | DataType::Json | DataType::Uuid => {
+            Ok(IsarValue::String(value.to_string()))
+        }
+        _ => Err(format!(
+            "Properties of type {:?} cannot be compared.",
+            data_type
+        )),
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::args::parse_args;
+    use isar_core::core::insert::IsarInsert;
+    use isar_core::core::schema::PropertySchema;
+    use isar_core::core::writer::IsarWriter;
+    use isar_core::sqlite::sqlite_instance::SQLiteInstance;
+    use isar_core::SQLITE_MEMORY_DIR;
+
+    fn run_command(instance: &SQLiteInstance, schemas: &[IsarSchema], args: &str) -> String {
+        let args = parse_args(args.split(' ').map(|a| a.to_string())).unwrap();
+        let mut out = vec![];
+        run(instance, schemas, &args.command, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_commands() {
+        let schemas = vec![
+            IsarSchema::new(
+                "Person",
+                Some("id"),
+                vec![
+                    PropertySchema::new("name", DataType::String, None),
+                    PropertySchema::new("age", DataType::Int, None),
+                ],
+                vec![],
+                false,
+            ),
+            IsarSchema::new("Empty", Some("id"), vec![], vec![], false),
+        ];
+        let instance = SQLiteInstance::open_instance(
+            0,
+            "test_commands",
+            SQLITE_MEMORY_DIR,
+            schemas.clone(),
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 3)
+            .unwrap();
+        for (id, name, age) in [(1, "Anna", 30), (2, "bob", 25), (3, "Carl", 40)] {
+            insert.write_string(1, name);
+            insert.write_int(2, age);
+            insert.save(id).unwrap();
+        }
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let stats = run_command(&instance, &schemas, "db stats");
+        assert!(stats.starts_with("Person: 3 objects"));
+        assert!(stats.contains("Empty: 0 objects"));
+
+        assert_eq!(
+            run_command(
+                &instance,
+                &schemas,
+                "db query Person --filter age:gte:30 --sort age:desc"
+            ),
+            "[{\"name\":\"Carl\",\"age\":40,\"id\":3},{\"name\":\"Anna\",\"age\":30,\"id\":1}]\n"
+        );
+        assert_eq!(
+            run_command(
+                &instance,
+                &schemas,
+                "db query Person --filter name:startswith:B --ignore-case --filter id:ne:1"
+            ),
+            "[{\"name\":\"bob\",\"age\":25,\"id\":2}]\n"
+        );
+        assert_eq!(
+            run_command(
+                &instance,
+                &schemas,
+                "db query Person --sort name --offset 1 --limit 1"
+            ),
+            "[{\"name\":\"Carl\",\"age\":40,\"id\":3}]\n"
+        );
+
+        let args = [
+            "db",
+            "query",
+            "Person",
+            "--where",
+            "name = 'BOB' IGNORE CASE OR age > 35 ORDER BY age DESC",
+            "--limit",
+            "1",
+        ];
+        let args = parse_args(args.map(String::from)).unwrap();
+        let mut out = vec![];
+        run(&instance, &schemas, &args.command, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[{\"name\":\"Carl\",\"age\":40,\"id\":3}]\n"
+        );
+
+        let dump = run_command(&instance, &schemas, "db dump");
+        let dump: serde_json::Value = serde_json::from_str(&dump).unwrap();
+        assert_eq!(dump["Person"].as_array().unwrap().len(), 3);
+        assert_eq!(dump["Empty"], serde_json::json!([]));
+
+        assert_eq!(run_command(&instance, &schemas, "db verify"), "OK\n");
+
+        let args =
+            parse_args(["db", "query", "Person", "--filter", "age:eq:old"].map(String::from))
+                .unwrap();
+        assert!(run(&instance, &schemas, &args.command, &mut vec![]).is_err());
+        let args =
+            parse_args(["db", "query", "Person", "--where", "age >"].map(String::from)).unwrap();
+        assert_eq!(
+            run(&instance, &schemas, &args.command, &mut vec![]),
+            Err("Query error: Column 6: Expected a value but found end of query.".to_string())
+        );
+        let args = parse_args(["db", "dump", "Missing"].map(String::from)).unwrap();
+        assert!(run(&instance, &schemas, &args.command, &mut vec![]).is_err());
+    }
+}
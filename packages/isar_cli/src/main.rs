@@ -0,0 +1,126 @@
+mod args;
+mod commands;
+
+use args::{parse_args, Args, USAGE};
+use isar_core::core::instance::IsarInstance;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+#[cfg(feature = "native")]
+use isar_core::native::native_instance::NativeInstance;
+
+#[cfg(feature = "sqlite")]
+use isar_core::sqlite::sqlite_instance::SQLiteInstance;
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+
+    if let Err(e) = inspect(&args) {
+        eprintln!("Error: {}", e);
+        exit(1);
+    }
+}
+
+fn inspect(args: &Args) -> Result<(), String> {
+    let copy = TempCopy::new(&args.path)?;
+    let name = copy.name.as_str();
+    let dir = copy.dir.to_str().ok_or("Invalid temporary directory.")?;
+    let mut out = io::stdout().lock();
+
+    let result = match copy.extension.as_str() {
+        #[cfg(feature = "native")]
+        "isar" => {
+            let schemas = NativeInstance::read_schemas(name, dir, args.max_size_mib)
+                .map_err(|e| e.to_string())?;
+            let instance = NativeInstance::open_instance(
+                0,
+                name,
+                dir,
+                schemas.clone(),
+                args.max_size_mib,
+                None,
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+            let result = commands::run(instance.as_ref(), &schemas, &args.command, &mut out);
+            NativeInstance::close(instance, false);
+            result
+        }
+        #[cfg(feature = "sqlite")]
+        "sqlite" => {
+            let key = args.encryption_key.as_deref();
+            let schemas =
+                SQLiteInstance::read_schemas(name, dir, key).map_err(|e| e.to_string())?;
+            let instance = SQLiteInstance::open_instance(
+                0,
+                name,
+                dir,
+                schemas.clone(),
+                args.max_size_mib,
+                key,
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+            let result = commands::run(&instance, &schemas, &args.command, &mut out);
+            SQLiteInstance::close(instance, false);
+            result
+        }
+        extension => Err(format!("Unsupported file type .{}.", extension)),
+    };
+    out.flush().map_err(|e| e.to_string())?;
+    result
+}
+
+/// Copy of the inspected file in a temporary directory. Opening an instance
+/// migrates it to the read schema, so the original file is never opened.
+struct TempCopy {
+    dir: PathBuf,
+    name: String,
+    extension: String,
+}
+
+impl TempCopy {
+    fn new(path: &Path) -> Result<Self, String> {
+        let name = path.file_stem().and_then(|n| n.to_str());
+        let extension = path.extension().and_then(|e| e.to_str());
+        let (Some(name), Some(extension)) = (name, extension) else {
+            return Err(format!("Invalid file {}.", path.display()));
+        };
+        if !path.is_file() {
+            return Err(format!("File {} does not exist.", path.display()));
+        }
+
+        let dir = std::env::temp_dir().join(format!("isar-inspect-{}", std::process::id()));
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let copy = TempCopy {
+            dir,
+            name: name.to_string(),
+            extension: extension.to_string(),
+        };
+
+        let file_name = path.file_name().unwrap();
+        fs::copy(path, copy.dir.join(file_name)).map_err(|e| e.to_string())?;
+        // uncheckpointed SQLite writes are still in the write-ahead log
+        let mut wal = file_name.to_os_string();
+        wal.push("-wal");
+        let wal_path = path.with_file_name(&wal);
+        if wal_path.is_file() {
+            fs::copy(wal_path, copy.dir.join(wal)).map_err(|e| e.to_string())?;
+        }
+        Ok(copy)
+    }
+}
+
+impl Drop for TempCopy {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::core::data_type::DataType;
 use crate::core::reader::IsarReader;
+use crate::core::uuid::format_uuid;
 use crate::core::watcher::{ChangeDetail, ChangeType, FieldChange};
 use serde_json::Value as JsonValue;
 use std::collections::HashSet;
@@ -60,6 +61,7 @@ impl ChangeDetector {
             object_id,
             field_changes,
             full_document,
+            remote: false,
         })
     }
 
@@ -79,6 +81,7 @@ impl ChangeDetector {
             object_id,
             field_changes,
             full_document,
+            remote: false,
         })
     }
 
@@ -103,6 +106,7 @@ impl ChangeDetector {
             object_id,
             field_changes,
             full_document,
+            remote: false,
         })
     }
 
@@ -240,6 +244,7 @@ impl ChangeDetector {
             DataType::String | DataType::Json => {
                 reader.read_string(index).map(|s| s.to_string())
             }
+            DataType::Uuid => reader.read_uuid(index).map(|v| format_uuid(&v)),
             DataType::Object => Self::read_object_field(reader, index),
             DataType::BoolList | DataType::ByteList | DataType::IntList | 
             DataType::FloatList | DataType::LongList | DataType::DoubleList | 
@@ -282,6 +287,7 @@ impl ChangeDetector {
             object_id,
             field_changes,
             full_document,
+            remote: false,
         })
     }
 
@@ -301,6 +307,7 @@ impl ChangeDetector {
             object_id,
             field_changes,
             full_document,
+            remote: false,
         })
     }
 
@@ -325,6 +332,7 @@ impl ChangeDetector {
             object_id,
             field_changes,
             full_document,
+            remote: false,
         })
     }
 
@@ -0,0 +1,97 @@
+use super::error::{IsarError, Result};
+use super::value::IsarValue;
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+
+/// Position of the last object returned by a query cursor. A cursor resumed
+/// with it starts strictly after that object in sort order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryContinuation {
+    pub values: Vec<Option<IsarValue>>,
+    pub id: i64,
+}
+
+impl QueryContinuation {
+    pub fn new(values: Vec<Option<IsarValue>>, id: i64) -> Self {
+        Self { values, id }
+    }
+
+    pub fn to_token(&self) -> String {
+        let values = self
+            .values
+            .iter()
+            .map(|value| match value {
+                None => Value::Null,
+                Some(IsarValue::Bool(value)) => json!({ "b": value }),
+                Some(IsarValue::Integer(value)) => json!({ "i": value }),
+                Some(IsarValue::Real(value)) => json!({ "r": value.to_bits() }),
+                Some(IsarValue::String(value)) => json!({ "s": value }),
+                // lists and objects are not sortable
+                Some(IsarValue::List(_) | IsarValue::Object(_)) => Value::Null,
+            })
+            .collect::<Vec<_>>();
+        let json = json!([self.id, values]).to_string();
+        general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn from_token(token: &str) -> Result<Self> {
+        Self::parse_token(token).ok_or(IsarError::IllegalArgument {})
+    }
+
+    fn parse_token(token: &str) -> Option<Self> {
+        let bytes = general_purpose::URL_SAFE_NO_PAD.decode(token).ok()?;
+        let json: Value = serde_json::from_slice(&bytes).ok()?;
+        let [id, values] = json.as_array()?.as_slice() else {
+            return None;
+        };
+        let id = id.as_i64()?;
+        let mut result = vec![];
+        for value in values.as_array()? {
+            let value = match value {
+                Value::Null => None,
+                Value::Object(map) => {
+                    let (tag, value) = map.iter().next()?;
+                    let value = match tag.as_str() {
+                        "b" => IsarValue::Bool(value.as_bool()?),
+                        "i" => IsarValue::Integer(value.as_i64()?),
+                        "r" => IsarValue::Real(f64::from_bits(value.as_u64()?)),
+                        "s" => IsarValue::String(value.as_str()?.to_string()),
+                        _ => return None,
+                    };
+                    Some(value)
+                }
+                _ => return None,
+            };
+            result.push(value);
+        }
+        Some(Self::new(result, id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_roundtrip() {
+        let continuation = QueryContinuation::new(
+            vec![
+                None,
+                Some(IsarValue::Bool(true)),
+                Some(IsarValue::Integer(-5)),
+                Some(IsarValue::Real(f64::NEG_INFINITY)),
+                Some(IsarValue::String("hello /+".to_string())),
+            ],
+            i64::MIN,
+        );
+        let token = continuation.to_token();
+        assert_eq!(QueryContinuation::from_token(&token).unwrap(), continuation);
+    }
+
+    #[test]
+    fn test_invalid_token() {
+        assert!(QueryContinuation::from_token("not a token").is_err());
+        let token = general_purpose::URL_SAFE_NO_PAD.encode("[1, [{\"x\": 1}]]");
+        assert!(QueryContinuation::from_token(&token).is_err());
+    }
+}
@@ -0,0 +1,433 @@
+use super::cursor::IsarQueryCursor;
+use super::data_type::DataType;
+use super::error::{IsarError, Result};
+use super::insert::IsarInsert;
+use super::instance::IsarInstance;
+use super::query_builder::IsarQueryBuilder;
+use super::reader::IsarReader;
+use super::writer::IsarWriter;
+
+/// Number of objects written per insert when converting an instance.
+pub const CONVERT_BATCH_SIZE: u32 = 1000;
+
+/// Copies all objects of `src` to `dst`, usually an instance of the other
+/// backend. Collections are matched by name and the objects of the destination
+/// collections are replaced in a single write transaction which is aborted if
+/// anything fails.
+///
+/// Ids, versions and auto increments are preserved. Returns the number of copied
+/// objects.
+pub fn convert<S: IsarInstance, D: IsarInstance>(src: &S, dst: &D, batch_size: u32) -> Result<u32> {
+    let collections = match_collections(src, dst)?;
+    let src_txn = src.begin_txn(false)?;
+    let result = dst.begin_txn(true).and_then(|mut dst_txn| {
+        let mut count = 0;
+        for (src_index, dst_index) in &collections {
+            let converted = convert_collection(
+                src,
+                &src_txn,
+                *src_index,
+                dst,
+                dst_txn,
+                *dst_index,
+                batch_size.max(1),
+            );
+            match converted {
+                Ok((txn, copied)) => {
+                    dst_txn = txn;
+                    count += copied;
+                }
+                Err((txn, e)) => {
+                    if let Some(txn) = txn {
+                        dst.abort_txn(txn);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        dst.commit_txn(dst_txn)?;
+        Ok(count)
+    });
+    src.abort_txn(src_txn);
+
+    if result.is_ok() {
+        for (src_index, dst_index) in &collections {
+            let next_id = src.next_auto_increment(*src_index);
+            dst.update_auto_increment(*dst_index, next_id - 1);
+        }
+    }
+    result
+}
+
+/// Result of a step that consumes the destination transaction. On failure the
+/// transaction is returned unless the instance already aborted it.
+type ConvertResult<T, Txn> = std::result::Result<(Txn, T), (Option<Txn>, IsarError)>;
+
+fn match_collections<S: IsarInstance, D: IsarInstance>(
+    src: &S,
+    dst: &D,
+) -> Result<Vec<(u16, u16)>> {
+    let mut collections = vec![];
+    for (src_index, name) in src.get_collections().enumerate() {
+        if src.is_embedded(src_index as u16) {
+            continue;
+        }
+        let dst_index = dst
+            .get_collections()
+            .position(|n| n == name)
+            .filter(|i| !dst.is_embedded(*i as u16))
+            .ok_or_else(|| IsarError::SchemaError {
+                message: format!("Collection {} does not exist in the destination.", name),
+            })?;
+        collections.push((src_index as u16, dst_index as u16));
+    }
+    Ok(collections)
+}
+
+fn convert_collection<S: IsarInstance, D: IsarInstance>(
+    src: &S,
+    src_txn: &S::Txn,
+    src_index: u16,
+    dst: &D,
+    dst_txn: D::Txn,
+    dst_index: u16,
+    batch_size: u32,
+) -> ConvertResult<u32, D::Txn> {
+    let prepared = src.count(src_txn, src_index).and_then(|count| {
+        dst.clear(&dst_txn, dst_index)?;
+        Ok((count, src.query(src_index)?.build()?))
+    });
+    let (count, query) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => return Err((Some(dst_txn), e)),
+    };
+    let mut cursor = match src.query_cursor(src_txn, &query, None, None) {
+        Ok(cursor) => cursor,
+        Err(e) => return Err((Some(dst_txn), e)),
+    };
+
+    let (txn, _) = copy_objects(&mut cursor, count, dst, dst_txn, dst_index, batch_size)?;
+    match dst.count(&txn, dst_index) {
+        Ok(copied) if copied == count => Ok((txn, count)),
+        Ok(_) => Err((Some(txn), IsarError::InsertIncomplete {})),
+        Err(e) => Err((Some(txn), e)),
+    }
+}
+
+fn copy_objects<C: IsarQueryCursor, D: IsarInstance>(
+    cursor: &mut C,
+    count: u32,
+    dst: &D,
+    mut txn: D::Txn,
+    dst_index: u16,
+    batch_size: u32,
+) -> ConvertResult<(), D::Txn> {
+    let mut copied = 0;
+    while copied < count {
+        let batch = (count - copied).min(batch_size);
+        // inserts abort the transaction themselves if they cannot be created
+        // or finished
+        let mut insert = dst.insert(txn, dst_index, batch).map_err(|e| (None, e))?;
+        insert.keep_versions();
+        let result = (0..batch).try_for_each(|_| {
+            let reader = cursor.next().ok_or(IsarError::InsertIncomplete {})?;
+            copy_object(&reader, &mut insert)?;
+            insert.save(reader.read_id())
+        });
+        if let Err(e) = result {
+            return Err((Some(insert.abort()), e));
+        }
+        txn = insert.finish().map_err(|e| (None, e))?;
+        copied += batch;
+    }
+    if cursor.next().is_some() {
+        return Err((Some(txn), IsarError::InsertIncomplete {}));
+    }
+    Ok((txn, ()))
+}
+
+fn copy_object<'a, R: IsarReader, W: IsarWriter<'a>>(reader: &R, writer: &mut W) -> Result<()> {
+    let properties = writer
+        .properties()
+        .map(|(name, data_type)| {
+            let src_index = reader.properties().position(|p| p == (name, data_type))?;
+            Some((src_index as u32 + 1, data_type))
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|p| p.len() == reader.properties().count())
+        .ok_or_else(|| IsarError::SchemaError {
+            message: "Properties of the source and destination do not match.".to_string(),
+        })?;
+
+    for (index, (src_index, data_type)) in properties.into_iter().enumerate() {
+        copy_value(reader, src_index, writer, index as u32 + 1, data_type)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn copy_value<'a, R: IsarReader, W: IsarWriter<'a>>(
+    reader: &R,
+    src_index: u32,
+    writer: &mut W,
+    index: u32,
+    data_type: DataType,
+) -> Result<()> {
+    if reader.is_null(src_index) {
+        writer.write_null(index);
+        return Ok(());
+    }
+    match data_type {
+        DataType::Bool => match reader.read_bool(src_index) {
+            Some(value) => writer.write_bool(index, value),
+            None => writer.write_null(index),
+        },
+        DataType::Byte => writer.write_byte(index, reader.read_byte(src_index)),
+        DataType::Int => writer.write_int(index, reader.read_int(src_index)),
+        DataType::Float => writer.write_float(index, reader.read_float(src_index)),
+        DataType::Long => writer.write_long(index, reader.read_long(src_index)),
+        DataType::Double => writer.write_double(index, reader.read_double(src_index)),
+        DataType::String | DataType::Json => match reader.read_string(src_index) {
+            Some(value) => writer.write_string(index, value),
+            None => writer.write_null(index),
+        },
+        DataType::Uuid => match reader.read_uuid(src_index) {
+            Some(value) => writer.write_uuid(index, &value),
+            None => writer.write_null(index),
+        },
+        DataType::Object => {
+            if let Some(object) = reader.read_object(src_index) {
+                if let Some(mut object_writer) = writer.begin_object(index) {
+                    copy_object(&object, &mut object_writer)?;
+                    writer.end_object(object_writer);
+                }
+            } else {
+                writer.write_null(index);
+            }
+        }
+        DataType::ByteList => match reader.read_blob(src_index) {
+            Some(value) => writer.write_byte_list(index, &value),
+            None => writer.write_null(index),
+        },
+        _ => {
+            let element_type = data_type.element_type();
+            match (element_type, reader.read_list(src_index)) {
+                (Some(element_type), Some((list, length))) => {
+                    if let Some(mut list_writer) = writer.begin_list(index, length) {
+                        for i in 0..length {
+                            copy_value(&list, i, &mut list_writer, i, element_type)?;
+                        }
+                        writer.end_list(list_writer);
+                    }
+                }
+                _ => writer.write_null(index),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "native", feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::core::cursor::IsarCursor;
+    use crate::core::schema::{IsarSchema, PropertySchema};
+    use crate::native::native_instance::NativeInstance;
+    use crate::sqlite::sqlite_instance::SQLiteInstance;
+    use crate::SQLITE_MEMORY_DIR;
+
+    fn schemas() -> Vec<IsarSchema> {
+        let person = IsarSchema::new(
+            "Person",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("scores", DataType::IntList, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+            ],
+            vec![],
+            false,
+        );
+        let address = IsarSchema::new(
+            "Address",
+            None,
+            vec![PropertySchema::new("street", DataType::String, None)],
+            vec![],
+            true,
+        );
+        vec![person, address]
+    }
+
+    fn native_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("isar_convert_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.to_str().unwrap().to_string()
+    }
+
+    fn write_people<I: IsarInstance>(instance: &I) {
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 2)
+            .unwrap();
+        insert.write_string(1, "a");
+        let mut scores = insert.begin_list(2, 2).unwrap();
+        scores.write_int(0, 1);
+        scores.write_int(1, 2);
+        insert.end_list(scores);
+        let mut address = insert.begin_object(3).unwrap();
+        address.write_string(1, "street");
+        insert.end_object(address);
+        insert.save(5).unwrap();
+        insert.write_null(1);
+        insert.write_null(2);
+        insert.write_null(3);
+        insert.save(9).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        // ids that were handed out but not used yet
+        assert_eq!(instance.auto_increment(0), 10);
+        assert_eq!(instance.auto_increment(0), 11);
+    }
+
+    fn verify_people<I: IsarInstance>(instance: &I) {
+        let txn = instance.begin_txn(false).unwrap();
+        assert_eq!(instance.count(&txn, 0), Ok(2));
+        verify_cursor(&mut instance.cursor(&txn, 0).unwrap());
+        instance.abort_txn(txn);
+        assert_eq!(instance.auto_increment(0), 12);
+    }
+
+    fn verify_cursor<C: IsarCursor>(cursor: &mut C) {
+        {
+            let reader = cursor.next(5).unwrap();
+            assert_eq!(reader.read_string(1), Some("a"));
+            let (scores, length) = reader.read_list(2).unwrap();
+            assert_eq!(length, 2);
+            assert_eq!((scores.read_int(0), scores.read_int(1)), (1, 2));
+            let address = reader.read_object(3).unwrap();
+            assert_eq!(address.read_string(1), Some("street"));
+        }
+        let reader = cursor.next(9).unwrap();
+        assert!(reader.is_null(1));
+        assert!(reader.is_null(2));
+        assert!(reader.is_null(3));
+    }
+
+    #[test]
+    fn converts_native_to_sqlite() {
+        let dir = native_dir("native_to_sqlite");
+        let src =
+            NativeInstance::open_instance(101, "src", &dir, schemas(), 10, None, None).unwrap();
+        let dst = SQLiteInstance::open_instance(
+            101,
+            "converts_native_to_sqlite",
+            SQLITE_MEMORY_DIR,
+            schemas(),
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+
+        write_people(&*src);
+        assert_eq!(convert(&*src, &dst, 1), Ok(2));
+        verify_people(&dst);
+        NativeInstance::close(src, true);
+    }
+
+    #[test]
+    fn converts_sqlite_to_native() {
+        let dir = native_dir("sqlite_to_native");
+        let src = SQLiteInstance::open_instance(
+            102,
+            "converts_sqlite_to_native",
+            SQLITE_MEMORY_DIR,
+            schemas(),
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let dst =
+            NativeInstance::open_instance(102, "dst", &dir, schemas(), 10, None, None).unwrap();
+
+        write_people(&src);
+        assert_eq!(convert(&src, &*dst, CONVERT_BATCH_SIZE), Ok(2));
+        verify_people(&*dst);
+        NativeInstance::close(dst, true);
+    }
+
+    #[test]
+    fn aborts_destination_txn_on_failure() {
+        let dir = native_dir("aborts_destination_txn");
+        let src =
+            NativeInstance::open_instance(103, "src", &dir, schemas(), 10, None, None).unwrap();
+        write_people(&*src);
+
+        let other = SQLiteInstance::open_instance(
+            103,
+            "aborts_destination_txn_on_failure",
+            SQLITE_MEMORY_DIR,
+            vec![IsarSchema::new(
+                "Person",
+                Some("id"),
+                vec![PropertySchema::new("name", DataType::Int, None)],
+                vec![],
+                false,
+            )],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            convert(&*src, &other, CONVERT_BATCH_SIZE),
+            Err(IsarError::SchemaError { .. })
+        ));
+
+        // the destination is not left with an open write transaction
+        let txn = other.begin_txn(true).unwrap();
+        assert_eq!(other.count(&txn, 0), Ok(0));
+        other.abort_txn(txn);
+        NativeInstance::close(src, true);
+    }
+
+    #[test]
+    fn converts_versions_as_is() {
+        let schema = || {
+            let note = IsarSchema::new(
+                "Note",
+                Some("id"),
+                vec![PropertySchema::new("version", DataType::Long, None)],
+                vec![],
+                false,
+            );
+            vec![note.with_version("version")]
+        };
+        let dir = native_dir("versions");
+        let src =
+            NativeInstance::open_instance(104, "src", &dir, schema(), 10, None, None).unwrap();
+        let dst = SQLiteInstance::open_instance(
+            104,
+            "converts_versions_as_is",
+            SQLITE_MEMORY_DIR,
+            schema(),
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        for _ in 0..2 {
+            let mut insert = src.insert(src.begin_txn(true).unwrap(), 0, 1).unwrap();
+            insert.write_null(1);
+            insert.save(1).unwrap();
+            src.commit_txn(insert.finish().unwrap()).unwrap();
+        }
+
+        assert_eq!(convert(&*src, &dst, CONVERT_BATCH_SIZE), Ok(1));
+        let txn = dst.begin_txn(false).unwrap();
+        let version = dst.cursor(&txn, 0).unwrap().next(1).map(|r| r.read_long(1));
+        assert_eq!(version, Some(2));
+        dst.abort_txn(txn);
+        NativeInstance::close(src, true);
+    }
+}
@@ -1,4 +1,9 @@
+use super::continuation::QueryContinuation;
+use super::error::{IsarError, Result};
 use super::reader::IsarReader;
+use super::ser::IsarPropertySerialize;
+use serde::ser::SerializeSeq;
+use serde::Serializer;
 
 pub trait IsarCursor {
     type Reader<'a>: IsarReader
@@ -14,4 +19,32 @@ pub trait IsarQueryCursor {
         Self: 'a;
 
     fn next(&mut self) -> Option<Self::Reader<'_>>;
+
+    fn continuation(&self) -> Option<QueryContinuation>;
+
+    fn serialize_values<S: Serializer>(&mut self, property_index: u16, serializer: S) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let json_err = |e: S::Error| IsarError::JsonError {
+            message: e.to_string(),
+        };
+        let mut seq = serializer.serialize_seq(None).map_err(json_err)?;
+        while let Some(reader) = self.next() {
+            if property_index == 0 {
+                seq.serialize_element(&reader.read_id()).map_err(json_err)?;
+            } else {
+                let data_type = reader.properties().nth(property_index as usize - 1);
+                if let Some((_, data_type)) = data_type {
+                    let value =
+                        IsarPropertySerialize::new(&reader, property_index as u32, data_type);
+                    seq.serialize_element(&value).map_err(json_err)?;
+                } else {
+                    return Err(IsarError::IllegalArgument {});
+                }
+            }
+        }
+        seq.end().map_err(json_err)?;
+        Ok(())
+    }
 }
@@ -21,6 +21,7 @@ pub enum DataType {
     DoubleList,
     StringList,
     ObjectList,
+    Uuid,
 }
 
 impl DataType {
@@ -66,6 +67,7 @@ mod tests {
         assert!(DataType::DoubleList.is_list());
         assert!(DataType::StringList.is_list());
         assert!(DataType::ObjectList.is_list());
+        assert!(!DataType::Uuid.is_list());
     }
 
     #[test]
@@ -87,5 +89,6 @@ mod tests {
         assert_eq!(DataType::DoubleList.element_type(), Some(DataType::Double));
         assert_eq!(DataType::StringList.element_type(), Some(DataType::String));
         assert_eq!(DataType::ObjectList.element_type(), Some(DataType::Object));
+        assert_eq!(DataType::Uuid.element_type(), None);
     }
 }
@@ -2,6 +2,7 @@ use super::data_type::DataType;
 use super::error::IsarError;
 use super::insert::IsarInsert;
 use super::instance::IsarInstance;
+use super::uuid::parse_uuid;
 use super::writer::IsarWriter;
 use serde::de::{Error, IgnoredAny, MapAccess, Visitor};
 use serde::Deserializer;
@@ -49,9 +50,14 @@ impl<'a, I: IsarInstance, F: Fn(&str) -> i64> IsarJsonImportVisitor<'a, I, F> {
             if let Some(id) = id {
                 insert.save(id)?;
             } else {
-                return Err(IsarError::JsonError {
-                    message: "Missing id property".to_string(),
-                });
+                match insert.save_by_key() {
+                    Err(IsarError::UnsupportedOperation {}) => {
+                        return Err(IsarError::JsonError {
+                            message: "Missing id property".to_string(),
+                        })
+                    }
+                    result => result?,
+                };
             }
         }
 
@@ -174,6 +180,16 @@ impl<'a, 'de: 'a, W: IsarWriter<'a>, F: Fn(&str) -> i64> Visitor<'de>
                             }
                         }
                     }
+                    DataType::Uuid => {
+                        let value = map.next_value::<Option<Cow<'_, str>>>()?;
+                        if let Some(value) = value {
+                            let uuid = parse_uuid(&value)
+                                .ok_or_else(|| Error::custom(format!("invalid UUID {}", value)))?;
+                            self.writer.write_uuid(index as u32, &uuid);
+                        } else {
+                            self.writer.write_null(index as u32);
+                        }
+                    }
                     DataType::Json => {
                         let value = map.next_value::<Option<&RawValue>>()?;
                         if let Some(value) = value {
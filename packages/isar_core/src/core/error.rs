@@ -71,4 +71,17 @@ pub enum IsarError {
         code: i32,
         message: String,
     },
+
+    #[snafu(display("Query error: {}", message))]
+    QueryError {
+        message: String,
+    },
+
+    #[snafu(display("Key violation: {}", message))]
+    KeyViolation {
+        message: String,
+    },
+
+    #[snafu(display("Version conflict. The object has been changed in the meantime."))]
+    VersionConflict {},
 }
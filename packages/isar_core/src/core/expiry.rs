@@ -0,0 +1,27 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Maximum number of expired objects purged when a write transaction commits.
+pub const PURGE_BATCH_SIZE: u32 = 100;
+
+#[cfg(test)]
+thread_local! {
+    static TEST_NOW: std::cell::Cell<Option<i64>> = const { std::cell::Cell::new(None) };
+}
+
+/// Current time in microseconds since the epoch, the unit of expiry properties.
+pub fn now_micros() -> i64 {
+    #[cfg(test)]
+    if let Some(now) = TEST_NOW.with(|now| now.get()) {
+        return now;
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as i64)
+}
+
+/// Sets the time returned by [now_micros] on the current thread. `None`
+/// restores the system clock.
+#[cfg(test)]
+pub(crate) fn set_test_now(now: Option<i64>) {
+    TEST_NOW.with(|test_now| test_now.set(now));
+}
@@ -1,16 +1,65 @@
+use crate::core::error::{IsarError, Result};
+use crate::core::filter_regex::FilterRegex;
+use crate::core::geo::GeoShape;
+use crate::core::normalize::StringNormalization;
 use crate::core::value::IsarValue;
+use serde::{Deserialize, Serialize};
 
 #[derive(PartialEq, Clone, Debug)]
 pub enum Filter {
     Condition(FilterCondition),
-    Json(JsonCondition),
+    Param(FilterParam),
+    Json(FilterJson),
     Nested(FilterNested),
+    Elements(FilterElements),
+    Geo(FilterGeo),
     And(Vec<Filter>),
     Or(Vec<Filter>),
     Not(Box<Filter>),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+impl Filter {
+    pub fn has_params(&self) -> bool {
+        match self {
+            Filter::Param(_) => true,
+            Filter::Nested(nested) => nested.filter.has_params(),
+            Filter::Elements(elements) => elements.filter.has_params(),
+            Filter::And(filters) | Filter::Or(filters) => filters.iter().any(|f| f.has_params()),
+            Filter::Not(filter) => filter.has_params(),
+            Filter::Condition(_) | Filter::Json(_) | Filter::Geo(_) => false,
+        }
+    }
+
+    /// Replaces the parameter conditions with conditions on the bound values.
+    pub fn bind(&self, values: &[Option<IsarValue>]) -> Result<Filter> {
+        let bind_all = |filters: &[Filter]| {
+            filters
+                .iter()
+                .map(|f| f.bind(values))
+                .collect::<Result<Vec<_>>>()
+        };
+        let filter = match self {
+            Filter::Param(param) => Filter::Condition(param.bind(values)?),
+            Filter::Nested(nested) => Filter::Nested(FilterNested::new(
+                nested.property_index,
+                nested.filter.bind(values)?,
+            )),
+            Filter::Elements(elements) => Filter::Elements(FilterElements::new(
+                elements.property_index,
+                elements.quantifier,
+                elements.filter.bind(values)?,
+            )),
+            Filter::And(filters) => Filter::And(bind_all(filters)?),
+            Filter::Or(filters) => Filter::Or(bind_all(filters)?),
+            Filter::Not(filter) => Filter::Not(Box::new(filter.bind(values)?)),
+            Filter::Condition(_) | Filter::Json(_) | Filter::Geo(_) => self.clone(),
+        };
+        Ok(filter)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum ConditionType {
     IsNull,
     Equal,
@@ -19,10 +68,14 @@ pub enum ConditionType {
     Less,
     LessOrEqual,
     Between,
+    In,
     StringStartsWith,
     StringEndsWith,
     StringContains,
     StringMatches,
+    StringRegex,
+    ListLength,
+    ListIsEmpty,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -31,6 +84,8 @@ pub struct FilterCondition {
     pub condition_type: ConditionType,
     pub values: Vec<Option<IsarValue>>,
     pub case_sensitive: bool,
+    // case folding is controlled by `case_sensitive`
+    pub normalization: StringNormalization,
 }
 
 impl FilterCondition {
@@ -39,12 +94,55 @@ impl FilterCondition {
         condition_type: ConditionType,
         values: Vec<Option<IsarValue>>,
         case_sensitive: bool,
+    ) -> Self {
+        Self::new_normalized(
+            property_index,
+            condition_type,
+            values,
+            StringNormalization::from_case_sensitive(case_sensitive),
+        )
+    }
+
+    pub const fn new_normalized(
+        property_index: u16,
+        condition_type: ConditionType,
+        values: Vec<Option<IsarValue>>,
+        normalization: StringNormalization,
     ) -> Self {
         Self {
             property_index,
             condition_type,
             values,
-            case_sensitive,
+            case_sensitive: !normalization.case_fold,
+            normalization,
+        }
+    }
+
+    pub fn set_normalization(&mut self, normalization: StringNormalization) {
+        self.case_sensitive = !normalization.case_fold;
+        self.normalization = normalization;
+    }
+
+    /// The normalization applied to strings, with case folding derived from
+    /// `case_sensitive`.
+    pub const fn string_normalization(&self) -> StringNormalization {
+        StringNormalization {
+            case_fold: !self.case_sensitive,
+            ..self.normalization
+        }
+    }
+
+    /// Compiles the pattern of a `StringRegex` condition. Fails with
+    /// `IllegalArgument` if the pattern is invalid.
+    pub(crate) fn regex(&self) -> Result<Option<FilterRegex>> {
+        if self.condition_type != ConditionType::StringRegex {
+            return Ok(None);
+        }
+        match self.values.first() {
+            Some(Some(IsarValue::String(pattern))) => {
+                FilterRegex::new(pattern, self.string_normalization()).map(Some)
+            }
+            _ => Ok(None),
         }
     }
 }
@@ -54,9 +152,13 @@ pub struct JsonCondition {
     pub path: Vec<String>,
     pub condition_type: ConditionType,
     pub is_list: bool,
+    // string values are already normalized
     pub values: Vec<Option<IsarValue>>,
-    // if false, values are all lowercase
     pub case_sensitive: bool,
+    // case folding is controlled by `case_sensitive`
+    pub normalization: StringNormalization,
+    pub regex: Option<FilterRegex>,
+    pub quantifier: ListQuantifier,
 }
 
 impl JsonCondition {
@@ -67,7 +169,33 @@ impl JsonCondition {
         values: Vec<Option<IsarValue>>,
         case_sensitive: bool,
     ) -> Self {
-        let values = if case_sensitive {
+        Self::new_normalized(
+            path,
+            condition_type,
+            is_list,
+            values,
+            StringNormalization::from_case_sensitive(case_sensitive),
+        )
+    }
+
+    pub fn new_normalized(
+        path: Vec<String>,
+        condition_type: ConditionType,
+        is_list: bool,
+        values: Vec<Option<IsarValue>>,
+        normalization: StringNormalization,
+    ) -> Self {
+        let regex = if condition_type == ConditionType::StringRegex {
+            match values.first() {
+                Some(Some(IsarValue::String(pattern))) => {
+                    FilterRegex::new(pattern, normalization).ok()
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+        let values = if normalization.is_none() {
             values
         } else {
             values
@@ -75,7 +203,7 @@ impl JsonCondition {
                 .map(|v| {
                     v.map(|v| {
                         if let IsarValue::String(s) = v {
-                            IsarValue::String(s.to_lowercase())
+                            IsarValue::String(normalization.normalize(&s).into_owned())
                         } else {
                             v
                         }
@@ -88,7 +216,95 @@ impl JsonCondition {
             condition_type,
             is_list,
             values,
-            case_sensitive,
+            case_sensitive: !normalization.case_fold,
+            normalization,
+            regex,
+            quantifier: ListQuantifier::Any,
+        }
+    }
+
+    /// The normalization applied to strings, with case folding derived from
+    /// `case_sensitive`.
+    pub const fn string_normalization(&self) -> StringNormalization {
+        StringNormalization {
+            case_fold: !self.case_sensitive,
+            ..self.normalization
+        }
+    }
+
+    /// Fails with `IllegalArgument` if the regex pattern of the condition
+    /// could not be compiled.
+    pub(crate) fn verify(&self) -> Result<()> {
+        let has_pattern = matches!(self.values.first(), Some(Some(IsarValue::String(_))));
+        if self.condition_type == ConditionType::StringRegex && has_pattern && self.regex.is_none()
+        {
+            return Err(IsarError::IllegalArgument {});
+        }
+        Ok(())
+    }
+
+    pub fn with_quantifier(mut self, quantifier: ListQuantifier) -> Self {
+        self.quantifier = quantifier;
+        self
+    }
+}
+
+/// Condition whose values are parameters bound when the query is executed.
+/// Queries with parameters are built once and executed with different values
+/// using [query_cursor_with_params](crate::core::instance::IsarInstance::query_cursor_with_params).
+/// Unbound parameter conditions never match.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FilterParam {
+    pub property_index: u16,
+    pub condition_type: ConditionType,
+    /// Parameter index of each condition value.
+    pub params: Vec<u16>,
+    pub normalization: StringNormalization,
+}
+
+impl FilterParam {
+    pub fn new(
+        property_index: u16,
+        condition_type: ConditionType,
+        params: Vec<u16>,
+        normalization: StringNormalization,
+    ) -> Self {
+        Self {
+            property_index,
+            condition_type,
+            params,
+            normalization,
+        }
+    }
+
+    pub fn bind(&self, values: &[Option<IsarValue>]) -> Result<FilterCondition> {
+        let values = self
+            .params
+            .iter()
+            .map(|param| values.get(*param as usize).cloned())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(IsarError::IllegalArgument {})?;
+        Ok(FilterCondition::new_normalized(
+            self.property_index,
+            self.condition_type,
+            values,
+            self.normalization,
+        ))
+    }
+}
+
+/// Applies a condition to the value at a path inside a `Json` property.
+#[derive(PartialEq, Clone, Debug)]
+pub struct FilterJson {
+    pub property_index: u16,
+    pub condition: JsonCondition,
+}
+
+impl FilterJson {
+    pub fn new(property_index: u16, condition: JsonCondition) -> Self {
+        FilterJson {
+            property_index,
+            condition,
         }
     }
 }
@@ -107,3 +323,62 @@ impl FilterNested {
         }
     }
 }
+
+/// Matches objects whose geo point, stored as a `Double` latitude and a
+/// `Double` longitude property, is inside the shape.
+#[derive(PartialEq, Clone, Debug)]
+pub struct FilterGeo {
+    pub latitude_index: u16,
+    pub longitude_index: u16,
+    pub shape: GeoShape,
+}
+
+impl FilterGeo {
+    pub fn new(latitude_index: u16, longitude_index: u16, shape: GeoShape) -> Self {
+        FilterGeo {
+            latitude_index,
+            longitude_index,
+            shape,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ListQuantifier {
+    #[default]
+    Any,
+    All,
+    None,
+}
+
+impl ListQuantifier {
+    pub fn evaluate<I: IntoIterator<Item = bool>>(&self, results: I) -> bool {
+        let mut results = results.into_iter();
+        match self {
+            ListQuantifier::Any => results.any(|r| r),
+            ListQuantifier::All => results.all(|r| r),
+            ListQuantifier::None => !results.any(|r| r),
+        }
+    }
+}
+
+/// Applies a filter to the elements of a list property. For primitive lists
+/// the filter has to be a single condition on the list property itself, for
+/// object lists it is a filter of the embedded collection.
+#[derive(PartialEq, Clone, Debug)]
+pub struct FilterElements {
+    pub property_index: u16,
+    pub quantifier: ListQuantifier,
+    pub filter: Box<Filter>,
+}
+
+impl FilterElements {
+    pub fn new(property_index: u16, quantifier: ListQuantifier, filter: Filter) -> Self {
+        FilterElements {
+            property_index,
+            quantifier,
+            filter: Box::new(filter),
+        }
+    }
+}
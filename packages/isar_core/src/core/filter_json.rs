@@ -1,11 +1,13 @@
 use super::fast_wild_match::fast_wild_match;
 use super::filter::{ConditionType, JsonCondition};
+use super::filter_regex::FilterRegex;
+use super::normalize::StringNormalization;
 use super::value::IsarValue;
 use serde_json::Value;
 
 impl JsonCondition {
-    pub fn matches(&self, json: Value) -> bool {
-        let value = extract_value(&json, &self.path);
+    pub fn matches(&self, json: &Value) -> bool {
+        let value = extract_value(json, &self.path);
         if let Some(value) = value {
             self.matches_value(value).unwrap_or(false)
         } else {
@@ -14,132 +16,65 @@ impl JsonCondition {
     }
 
     fn matches_value(&self, value: &Value) -> Option<bool> {
-        let result = if self.is_list {
-            match self.condition_type {
-                ConditionType::IsNull => value == &Value::Null,
-                ConditionType::Equal => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| equal(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::Greater => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| greater(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::GreaterOrEqual => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| greater_or_equal(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::Less => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| less(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::LessOrEqual => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| less_or_equal(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::Between => {
-                    let lower = self.values.get(0)?;
-                    let upper = self.values.get(1)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| between(value, lower, upper, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::StringStartsWith => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| string_starts_with(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::StringEndsWith => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| string_ends_with(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::StringContains => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| string_contains(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
-                ConditionType::StringMatches => {
-                    let cond_value = self.values.get(0)?;
-                    if let Value::Array(arr) = value {
-                        arr.iter()
-                            .any(|value| string_matches(value, cond_value, self.case_sensitive))
-                    } else {
-                        false
-                    }
-                }
+        match self.condition_type {
+            ConditionType::IsNull | ConditionType::ListLength | ConditionType::ListIsEmpty => {
+                self.matches_element(value)
             }
-        } else {
-            match self.condition_type {
-                ConditionType::IsNull => value == &Value::Null,
-                ConditionType::Equal => equal(value, self.values.get(0)?, self.case_sensitive),
-                ConditionType::Greater => greater(value, self.values.get(0)?, self.case_sensitive),
-                ConditionType::GreaterOrEqual => {
-                    greater_or_equal(value, self.values.get(0)?, self.case_sensitive)
-                }
-                ConditionType::Less => less(value, self.values.get(0)?, self.case_sensitive),
-                ConditionType::LessOrEqual => {
-                    less_or_equal(value, self.values.get(0)?, self.case_sensitive)
-                }
-                ConditionType::Between => between(
-                    value,
-                    self.values.get(0)?,
-                    self.values.get(1)?,
-                    self.case_sensitive,
-                ),
-                ConditionType::StringStartsWith => {
-                    string_starts_with(value, self.values.get(0)?, self.case_sensitive)
-                }
-                ConditionType::StringEndsWith => {
-                    string_ends_with(value, self.values.get(0)?, self.case_sensitive)
-                }
-                ConditionType::StringContains => {
-                    string_contains(value, self.values.get(0)?, self.case_sensitive)
-                }
-                ConditionType::StringMatches => {
-                    string_matches(value, self.values.get(0)?, self.case_sensitive)
+            _ if self.is_list => {
+                if let Value::Array(arr) = value {
+                    let mut valid = true;
+                    let result = self.quantifier.evaluate(arr.iter().map(|value| {
+                        let result = self.matches_element(value);
+                        valid &= result.is_some();
+                        result.unwrap_or(false)
+                    }));
+                    valid.then_some(result)
+                } else {
+                    Some(false)
                 }
             }
+            _ => self.matches_element(value),
+        }
+    }
+
+    fn matches_element(&self, value: &Value) -> Option<bool> {
+        let normalization = self.string_normalization();
+        let result = match self.condition_type {
+            ConditionType::IsNull => value == &Value::Null,
+            ConditionType::ListLength => {
+                list_length(value, self.values.first()?, self.values.get(1)?)
+            }
+            ConditionType::ListIsEmpty => matches!(value, Value::Array(arr) if arr.is_empty()),
+            ConditionType::Equal => equal(value, self.values.get(0)?, normalization),
+            ConditionType::Greater => greater(value, self.values.get(0)?, normalization),
+            ConditionType::GreaterOrEqual => {
+                greater_or_equal(value, self.values.get(0)?, normalization)
+            }
+            ConditionType::Less => less(value, self.values.get(0)?, normalization),
+            ConditionType::LessOrEqual => less_or_equal(value, self.values.get(0)?, normalization),
+            ConditionType::Between => between(
+                value,
+                self.values.get(0)?,
+                self.values.get(1)?,
+                normalization,
+            ),
+            ConditionType::In => self
+                .values
+                .iter()
+                .any(|cond_value| equal(value, cond_value, normalization)),
+            ConditionType::StringStartsWith => {
+                string_starts_with(value, self.values.get(0)?, normalization)
+            }
+            ConditionType::StringEndsWith => {
+                string_ends_with(value, self.values.get(0)?, normalization)
+            }
+            ConditionType::StringContains => {
+                string_contains(value, self.values.get(0)?, normalization)
+            }
+            ConditionType::StringMatches => {
+                string_matches(value, self.values.get(0)?, normalization)
+            }
+            ConditionType::StringRegex => string_regex(value, self.regex.as_ref()?),
         };
         Some(result)
     }
@@ -160,7 +95,22 @@ fn extract_value<'a>(json: &'a Value, path: &[String]) -> Option<&'a Value> {
     Some(value)
 }
 
-fn equal(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn list_length(value: &Value, lower: &Option<IsarValue>, upper: &Option<IsarValue>) -> bool {
+    if let Value::Array(arr) = value {
+        let lower = lower.as_ref().and_then(|v| v.i64()).unwrap_or(0);
+        let upper = upper.as_ref().and_then(|v| v.i64()).unwrap_or(i64::MAX);
+        let length = arr.len() as i64;
+        lower <= length && length <= upper
+    } else {
+        false
+    }
+}
+
+fn equal(
+    value: &Value,
+    cond_value: &Option<IsarValue>,
+    normalization: StringNormalization,
+) -> bool {
     match (value, cond_value) {
         (Value::Null, None) => true,
         (Value::Bool(value), Some(IsarValue::Bool(cond_value))) => value == cond_value,
@@ -171,17 +121,17 @@ fn equal(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) ->
             value.as_f64() == Some(*cond_value)
         }
         (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                value == cond_value
-            } else {
-                &value.to_lowercase() == cond_value
-            }
+            normalization.normalize(value).as_ref() == cond_value.as_str()
         }
         _ => false,
     }
 }
 
-fn greater(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn greater(
+    value: &Value,
+    cond_value: &Option<IsarValue>,
+    normalization: StringNormalization,
+) -> bool {
     match (value, cond_value) {
         (value, None) => !value.is_null(),
         (Value::Bool(value), Some(IsarValue::Bool(cond_value))) => value > cond_value,
@@ -192,17 +142,17 @@ fn greater(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool)
             value.as_f64() > Some(*cond_value)
         }
         (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                value > cond_value
-            } else {
-                &value.to_lowercase() > cond_value
-            }
+            normalization.normalize(value).as_ref() > cond_value.as_str()
         }
         _ => false,
     }
 }
 
-fn greater_or_equal(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn greater_or_equal(
+    value: &Value,
+    cond_value: &Option<IsarValue>,
+    normalization: StringNormalization,
+) -> bool {
     match (value, cond_value) {
         (_, None) => true,
         (Value::Bool(value), Some(IsarValue::Bool(cond_value))) => value >= cond_value,
@@ -213,17 +163,13 @@ fn greater_or_equal(value: &Value, cond_value: &Option<IsarValue>, case_sensitiv
             value.as_f64() >= Some(*cond_value)
         }
         (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                value >= cond_value
-            } else {
-                &value.to_lowercase() >= cond_value
-            }
+            normalization.normalize(value).as_ref() >= cond_value.as_str()
         }
         _ => false,
     }
 }
 
-fn less(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn less(value: &Value, cond_value: &Option<IsarValue>, normalization: StringNormalization) -> bool {
     match (value, cond_value) {
         (Value::Null, cond_value) => cond_value.is_some(),
         (Value::Bool(value), Some(IsarValue::Bool(cond_value))) => value < cond_value,
@@ -234,17 +180,17 @@ fn less(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) ->
             value.as_f64() < Some(*cond_value)
         }
         (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                value < cond_value
-            } else {
-                &value.to_lowercase() < cond_value
-            }
+            normalization.normalize(value).as_ref() < cond_value.as_str()
         }
         _ => false,
     }
 }
 
-fn less_or_equal(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn less_or_equal(
+    value: &Value,
+    cond_value: &Option<IsarValue>,
+    normalization: StringNormalization,
+) -> bool {
     match (value, cond_value) {
         (Value::Null, _) => true,
         (Value::Bool(value), Some(IsarValue::Bool(cond_value))) => value <= cond_value,
@@ -255,11 +201,7 @@ fn less_or_equal(value: &Value, cond_value: &Option<IsarValue>, case_sensitive:
             value.as_f64() <= Some(*cond_value)
         }
         (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                value <= cond_value
-            } else {
-                &value.to_lowercase() <= cond_value
-            }
+            normalization.normalize(value).as_ref() <= cond_value.as_str()
         }
         _ => false,
     }
@@ -269,10 +211,10 @@ fn between(
     value: &Value,
     lower: &Option<IsarValue>,
     upper: &Option<IsarValue>,
-    case_sensitive: bool,
+    normalization: StringNormalization,
 ) -> bool {
     match (value, lower, upper) {
-        (value, None, upper) => less_or_equal(value, upper, case_sensitive),
+        (value, None, upper) => less_or_equal(value, upper, normalization),
         (Value::Bool(value), Some(IsarValue::Bool(lower)), Some(IsarValue::Bool(upper))) => {
             value >= lower && value <= upper
         }
@@ -285,65 +227,68 @@ fn between(
             value.as_f64() >= Some(*lower) && value.as_f64() <= Some(*upper)
         }
         (Value::String(value), Some(IsarValue::String(lower)), Some(IsarValue::String(upper))) => {
-            if case_sensitive {
-                value >= lower && value <= upper
-            } else {
-                let value = value.to_lowercase();
-                &value >= lower && &value <= upper
-            }
+            let value = normalization.normalize(value);
+            value.as_ref() >= lower.as_str() && value.as_ref() <= upper.as_str()
         }
         _ => false,
     }
 }
 
-fn string_starts_with(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn string_starts_with(
+    value: &Value,
+    cond_value: &Option<IsarValue>,
+    normalization: StringNormalization,
+) -> bool {
     match (value, cond_value) {
-        (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                value.starts_with(cond_value)
-            } else {
-                value.to_lowercase().starts_with(cond_value)
-            }
-        }
+        (Value::String(value), Some(IsarValue::String(cond_value))) => normalization
+            .normalize(value)
+            .starts_with(cond_value.as_str()),
         _ => false,
     }
 }
 
-fn string_ends_with(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn string_ends_with(
+    value: &Value,
+    cond_value: &Option<IsarValue>,
+    normalization: StringNormalization,
+) -> bool {
     match (value, cond_value) {
-        (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                value.ends_with(cond_value)
-            } else {
-                value.to_lowercase().ends_with(cond_value)
-            }
-        }
+        (Value::String(value), Some(IsarValue::String(cond_value))) => normalization
+            .normalize(value)
+            .ends_with(cond_value.as_str()),
         _ => false,
     }
 }
 
-fn string_contains(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn string_contains(
+    value: &Value,
+    cond_value: &Option<IsarValue>,
+    normalization: StringNormalization,
+) -> bool {
     match (value, cond_value) {
         (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                value.contains(cond_value)
-            } else {
-                value.to_lowercase().contains(cond_value)
-            }
+            normalization.normalize(value).contains(cond_value.as_str())
         }
         _ => false,
     }
 }
 
-fn string_matches(value: &Value, cond_value: &Option<IsarValue>, case_sensitive: bool) -> bool {
+fn string_matches(
+    value: &Value,
+    cond_value: &Option<IsarValue>,
+    normalization: StringNormalization,
+) -> bool {
     match (value, cond_value) {
         (Value::String(value), Some(IsarValue::String(cond_value))) => {
-            if case_sensitive {
-                fast_wild_match(value, cond_value)
-            } else {
-                fast_wild_match(&value.to_lowercase(), cond_value)
-            }
+            fast_wild_match(&normalization.normalize(value), cond_value)
         }
         _ => false,
     }
 }
+
+fn string_regex(value: &Value, regex: &FilterRegex) -> bool {
+    match value {
+        Value::String(value) => regex.is_match(value),
+        _ => false,
+    }
+}
@@ -0,0 +1,109 @@
+use super::error::{IsarError, Result};
+use super::normalize::StringNormalization;
+use regex::{Regex, RegexBuilder};
+
+/// Compiled pattern of a `StringRegex` condition. The regex engine matches in
+/// linear time so user supplied patterns cannot cause catastrophic
+/// backtracking.
+#[derive(Clone, Debug)]
+pub struct FilterRegex {
+    regex: Regex,
+    normalization: StringNormalization,
+}
+
+impl FilterRegex {
+    const SIZE_LIMIT: usize = 1 << 20;
+
+    pub fn new(pattern: &str, normalization: StringNormalization) -> Result<Self> {
+        // Case folding the pattern would change the meaning of classes like
+        // \W, so case insensitivity is left to the regex engine.
+        let pattern_normalization = StringNormalization {
+            case_fold: false,
+            ..normalization
+        };
+        let regex = RegexBuilder::new(&pattern_normalization.normalize(pattern))
+            .case_insensitive(normalization.case_fold)
+            .size_limit(Self::SIZE_LIMIT)
+            .build()
+            .map_err(|_| IsarError::IllegalArgument {})?;
+        Ok(Self {
+            regex,
+            normalization,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(&self.normalization.normalize(value))
+    }
+}
+
+impl PartialEq for FilterRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str() && self.normalization == other.normalization
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::{ConditionType, JsonCondition};
+    use crate::core::value::IsarValue;
+    use serde_json::json;
+
+    #[test]
+    fn test_regex_match() {
+        let regex = FilterRegex::new(r"^[a-z]+@\w+\.com$", StringNormalization::NONE).unwrap();
+        assert!(regex.is_match("joe@example.com"));
+        assert!(!regex.is_match("Joe@example.com"));
+        assert!(!regex.is_match("joe@example.org"));
+
+        let regex =
+            FilterRegex::new(r"^[a-z]+@\w+\.com$", StringNormalization::CASE_INSENSITIVE).unwrap();
+        assert!(regex.is_match("Joe@Example.COM"));
+    }
+
+    #[test]
+    fn test_regex_normalization() {
+        let normalization = StringNormalization::from_flags(
+            StringNormalization::CASE_FOLD | StringNormalization::STRIP_DIACRITICS,
+        );
+        let regex = FilterRegex::new("^fran[cç]ois$", normalization).unwrap();
+        assert!(regex.is_match("FRANÇOIS"));
+        assert!(regex.is_match("francois"));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        assert!(FilterRegex::new("(abc", StringNormalization::NONE).is_err());
+        assert!(FilterRegex::new(r"\w{10000}{10000}", StringNormalization::NONE).is_err());
+    }
+
+    #[test]
+    fn test_json_condition_regex() {
+        let pattern = Some(IsarValue::String("^a[0-9]+$".to_string()));
+        let condition = JsonCondition::new(
+            vec!["code".to_string()],
+            ConditionType::StringRegex,
+            false,
+            vec![pattern.clone()],
+            false,
+        );
+        assert!(condition.matches(&json!({"code": "A123"})));
+        assert!(!condition.matches(&json!({"code": "A12b"})));
+        assert!(!condition.matches(&json!({"code": 123})));
+
+        let condition = JsonCondition::new(
+            vec![],
+            ConditionType::StringRegex,
+            true,
+            vec![pattern],
+            true,
+        );
+        assert!(condition.matches(&json!(["b1", "a1"])));
+        assert!(!condition.matches(&json!(["A1"])));
+    }
+}
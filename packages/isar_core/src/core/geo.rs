@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Number of bits used for each coordinate of a geohash.
+const GEOHASH_BITS: u32 = 31;
+
+/// Maximum number of cells a shape is covered with when querying a geo index.
+const MAX_COVER_CELLS: u64 = 16;
+
+/// Area that a geo point (a latitude and longitude property in degrees) has to
+/// be inside of.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum GeoShape {
+    /// A box that crosses the antimeridian if `min_longitude` is greater than
+    /// `max_longitude`.
+    BoundingBox {
+        min_latitude: f64,
+        min_longitude: f64,
+        max_latitude: f64,
+        max_longitude: f64,
+    },
+    Radius {
+        latitude: f64,
+        longitude: f64,
+        meters: f64,
+    },
+    /// Vertices as `(latitude, longitude)` pairs. Edges are straight lines in
+    /// the latitude / longitude plane and must not cross the antimeridian.
+    Polygon(Vec<(f64, f64)>),
+}
+
+impl GeoShape {
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        if !is_valid(latitude, longitude) {
+            return false;
+        }
+        match self {
+            GeoShape::BoundingBox {
+                min_latitude,
+                min_longitude,
+                max_latitude,
+                max_longitude,
+            } => {
+                let longitude_matches = if min_longitude <= max_longitude {
+                    *min_longitude <= longitude && longitude <= *max_longitude
+                } else {
+                    *min_longitude <= longitude || longitude <= *max_longitude
+                };
+                *min_latitude <= latitude && latitude <= *max_latitude && longitude_matches
+            }
+            GeoShape::Radius {
+                latitude: center_latitude,
+                longitude: center_longitude,
+                meters,
+            } => distance(*center_latitude, *center_longitude, latitude, longitude) <= *meters,
+            GeoShape::Polygon(vertices) => polygon_contains(vertices, latitude, longitude),
+        }
+    }
+
+    /// Boxes (min latitude, min longitude, max latitude, max longitude) that
+    /// contain the shape. Shapes crossing the antimeridian are split in two.
+    fn bounding_boxes(&self) -> Vec<(f64, f64, f64, f64)> {
+        match self {
+            GeoShape::BoundingBox {
+                min_latitude,
+                min_longitude,
+                max_latitude,
+                max_longitude,
+            } => {
+                if min_longitude <= max_longitude {
+                    vec![(*min_latitude, *min_longitude, *max_latitude, *max_longitude)]
+                } else {
+                    vec![
+                        (*min_latitude, *min_longitude, *max_latitude, 180.0),
+                        (*min_latitude, -180.0, *max_latitude, *max_longitude),
+                    ]
+                }
+            }
+            GeoShape::Radius {
+                latitude,
+                longitude,
+                meters,
+            } => {
+                let angle = meters / EARTH_RADIUS_METERS;
+                let delta_latitude = angle.to_degrees();
+                let min_latitude = latitude - delta_latitude;
+                let max_latitude = latitude + delta_latitude;
+                let delta_longitude = (angle.sin() / latitude.to_radians().cos())
+                    .asin()
+                    .to_degrees();
+                if min_latitude <= -90.0
+                    || max_latitude >= 90.0
+                    || angle >= PI / 2.0
+                    || delta_longitude.is_nan()
+                {
+                    return vec![(
+                        min_latitude.max(-90.0),
+                        -180.0,
+                        max_latitude.min(90.0),
+                        180.0,
+                    )];
+                }
+                let min_longitude = longitude - delta_longitude;
+                let max_longitude = longitude + delta_longitude;
+                if min_longitude < -180.0 {
+                    vec![
+                        (min_latitude, min_longitude + 360.0, max_latitude, 180.0),
+                        (min_latitude, -180.0, max_latitude, max_longitude),
+                    ]
+                } else if max_longitude > 180.0 {
+                    vec![
+                        (min_latitude, min_longitude, max_latitude, 180.0),
+                        (min_latitude, -180.0, max_latitude, max_longitude - 360.0),
+                    ]
+                } else {
+                    vec![(min_latitude, min_longitude, max_latitude, max_longitude)]
+                }
+            }
+            GeoShape::Polygon(vertices) => {
+                if vertices.len() < 3 {
+                    return vec![];
+                }
+                let mut bounds = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+                for (latitude, longitude) in vertices {
+                    bounds.0 = bounds.0.min(*latitude);
+                    bounds.1 = bounds.1.min(*longitude);
+                    bounds.2 = bounds.2.max(*latitude);
+                    bounds.3 = bounds.3.max(*longitude);
+                }
+                vec![bounds]
+            }
+        }
+    }
+
+    /// Sorted and disjoint geohash ranges (both inclusive) that contain every
+    /// point of the shape.
+    pub fn geohash_ranges(&self) -> Vec<(u64, u64)> {
+        let mut ranges = vec![];
+        for (min_latitude, min_longitude, max_latitude, max_longitude) in self.bounding_boxes() {
+            if !is_valid(min_latitude, min_longitude)
+                || !is_valid(max_latitude, max_longitude)
+                || min_latitude > max_latitude
+                || min_longitude > max_longitude
+            {
+                continue;
+            }
+            let (x0, y0) = quantize(min_latitude, min_longitude);
+            let (x1, y1) = quantize(max_latitude, max_longitude);
+
+            // use the finest level at which the box is covered by a few cells
+            let mut shift = 0;
+            while (((x1 >> shift) - (x0 >> shift) + 1) as u64)
+                * (((y1 >> shift) - (y0 >> shift) + 1) as u64)
+                > MAX_COVER_CELLS
+            {
+                shift += 1;
+            }
+            for x in (x0 >> shift)..=(x1 >> shift) {
+                for y in (y0 >> shift)..=(y1 >> shift) {
+                    let lower = interleave(x << shift, y << shift);
+                    let upper = lower | ((1u64 << (shift * 2)) - 1);
+                    ranges.push((lower, upper));
+                }
+            }
+        }
+
+        ranges.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = vec![];
+        for (lower, upper) in ranges {
+            if let Some(last) = merged.last_mut() {
+                if lower <= last.1.saturating_add(1) {
+                    last.1 = last.1.max(upper);
+                    continue;
+                }
+            }
+            merged.push((lower, upper));
+        }
+        merged
+    }
+}
+
+/// Z-order geohash of a point. Nearby points share a long common prefix so an
+/// index on the geohash can be queried with a few ranges. Returns `None` for
+/// invalid coordinates.
+pub fn geohash(latitude: f64, longitude: f64) -> Option<u64> {
+    if is_valid(latitude, longitude) {
+        let (x, y) = quantize(latitude, longitude);
+        Some(interleave(x, y))
+    } else {
+        None
+    }
+}
+
+/// Great-circle distance between two points in meters.
+pub fn distance(latitude1: f64, longitude1: f64, latitude2: f64, longitude2: f64) -> f64 {
+    let latitude1 = latitude1.to_radians();
+    let latitude2 = latitude2.to_radians();
+    let delta_latitude = latitude2 - latitude1;
+    let delta_longitude = (longitude2 - longitude1).to_radians();
+    let a = (delta_latitude / 2.0).sin().powi(2)
+        + latitude1.cos() * latitude2.cos() * (delta_longitude / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+}
+
+fn is_valid(latitude: f64, longitude: f64) -> bool {
+    (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)
+}
+
+fn quantize(latitude: f64, longitude: f64) -> (u32, u32) {
+    let max = (1u32 << GEOHASH_BITS) - 1;
+    let scale = (1u64 << GEOHASH_BITS) as f64;
+    let x = ((longitude + 180.0) / 360.0 * scale) as u64;
+    let y = ((latitude + 90.0) / 180.0 * scale) as u64;
+    ((x as u32).min(max), (y as u32).min(max))
+}
+
+fn interleave(x: u32, y: u32) -> u64 {
+    fn spread(value: u32) -> u64 {
+        let mut value = value as u64;
+        value = (value | (value << 16)) & 0x0000_ffff_0000_ffff;
+        value = (value | (value << 8)) & 0x00ff_00ff_00ff_00ff;
+        value = (value | (value << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+        value = (value | (value << 2)) & 0x3333_3333_3333_3333;
+        (value | (value << 1)) & 0x5555_5555_5555_5555
+    }
+    (spread(x) << 1) | spread(y)
+}
+
+fn polygon_contains(vertices: &[(f64, f64)], latitude: f64, longitude: f64) -> bool {
+    if vertices.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut previous = vertices[vertices.len() - 1];
+    for &current in vertices {
+        let (latitude1, longitude1) = previous;
+        let (latitude2, longitude2) = current;
+        if (latitude1 > latitude) != (latitude2 > latitude) {
+            let crossing = longitude1
+                + (latitude - latitude1) / (latitude2 - latitude1) * (longitude2 - longitude1);
+            if longitude < crossing {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covered(shape: &GeoShape, latitude: f64, longitude: f64) -> bool {
+        let hash = geohash(latitude, longitude).unwrap();
+        shape
+            .geohash_ranges()
+            .iter()
+            .any(|(lower, upper)| *lower <= hash && hash <= *upper)
+    }
+
+    #[test]
+    fn test_geohash_order() {
+        assert_eq!(geohash(-90.0, -180.0), Some(0));
+        assert_eq!(geohash(90.0, 180.0), Some((1 << 62) - 1));
+        assert_eq!(geohash(91.0, 0.0), None);
+        assert_eq!(geohash(f64::NAN, 0.0), None);
+
+        let hash = geohash(52.52, 13.405).unwrap();
+        let nearby = geohash(52.5201, 13.4051).unwrap();
+        assert_eq!(hash >> 30, nearby >> 30);
+    }
+
+    #[test]
+    fn test_distance() {
+        let berlin_paris = distance(52.52, 13.405, 48.8566, 2.3522);
+        assert!((berlin_paris - 877_500.0).abs() < 2_000.0);
+        assert_eq!(distance(10.0, 10.0, 10.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let shape = GeoShape::BoundingBox {
+            min_latitude: 10.0,
+            min_longitude: 20.0,
+            max_latitude: 11.0,
+            max_longitude: 21.0,
+        };
+        assert!(shape.contains(10.5, 20.5));
+        assert!(!shape.contains(10.5, 21.5));
+        assert!(covered(&shape, 10.0, 20.0));
+        assert!(covered(&shape, 11.0, 21.0));
+        assert!(!covered(&shape, -10.0, 20.0));
+
+        let shape = GeoShape::BoundingBox {
+            min_latitude: -10.0,
+            min_longitude: 170.0,
+            max_latitude: 10.0,
+            max_longitude: -170.0,
+        };
+        assert!(shape.contains(0.0, 175.0));
+        assert!(shape.contains(0.0, -175.0));
+        assert!(!shape.contains(0.0, 0.0));
+        assert!(covered(&shape, 0.0, 175.0));
+        assert!(covered(&shape, 0.0, -175.0));
+        assert!(!covered(&shape, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_radius() {
+        let shape = GeoShape::Radius {
+            latitude: 52.52,
+            longitude: 13.405,
+            meters: 1_000.0,
+        };
+        assert!(shape.contains(52.525, 13.41));
+        assert!(!shape.contains(52.54, 13.405));
+        assert!(covered(&shape, 52.528, 13.405));
+        assert!(covered(&shape, 52.52, 13.418));
+        assert!(!covered(&shape, 48.8566, 2.3522));
+        assert!(shape.geohash_ranges().len() <= MAX_COVER_CELLS as usize);
+
+        let shape = GeoShape::Radius {
+            latitude: 0.0,
+            longitude: 179.999,
+            meters: 1_000.0,
+        };
+        assert!(shape.contains(0.0, -179.999));
+        assert!(covered(&shape, 0.0, -179.999));
+    }
+
+    #[test]
+    fn test_polygon() {
+        let shape = GeoShape::Polygon(vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]);
+        assert!(shape.contains(2.0, 2.0));
+        assert!(!shape.contains(8.0, 8.0));
+        assert!(!shape.contains(-1.0, 2.0));
+        assert!(covered(&shape, 2.0, 2.0));
+
+        let shape = GeoShape::Polygon(vec![(0.0, 0.0), (10.0, 0.0)]);
+        assert!(!shape.contains(0.0, 0.0));
+        assert!(shape.geohash_ranges().is_empty());
+    }
+}
@@ -6,5 +6,22 @@ pub trait IsarInsert<'a>: IsarWriter<'a> + Sized {
 
     fn save(&mut self, id: i64) -> Result<()>;
 
+    /// Saves the object but only overwrites the properties that have been
+    /// written. All other properties keep their stored value or are null if
+    /// the object does not exist yet.
+    fn save_merge(&mut self, id: i64) -> Result<()>;
+
+    /// Saves the object under the id of its string key. Unknown keys get a
+    /// new id. Returns the id of the object.
+    fn save_by_key(&mut self) -> Result<i64>;
+
+    /// Writes the version property as is instead of verifying and
+    /// incrementing it. Used to copy objects together with their version.
+    fn keep_versions(&mut self);
+
     fn finish(self) -> Result<Self::Txn>;
+
+    /// Discards the objects that have not been written yet and returns the
+    /// transaction so it can be aborted.
+    fn abort(self) -> Self::Txn;
 }
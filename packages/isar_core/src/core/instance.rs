@@ -1,13 +1,20 @@
+use super::continuation::QueryContinuation;
 use super::cursor::{IsarCursor, IsarQueryCursor};
 use super::de::IsarJsonImportVisitor;
 use super::error::{IsarError, Result};
 use super::insert::IsarInsert;
+use super::merge::{MergeResult, ObjectChange};
 use super::query_builder::IsarQueryBuilder;
+use super::query_plan::QueryPlan;
 use super::reader::IsarReader;
 use super::schema::IsarSchema;
+use super::tombstone::Tombstone;
+use super::update::UpdateOp;
 use super::value::IsarValue;
+use super::vector::VectorMetric;
 use crate::core::watcher::{WatchHandle, WatcherCallback, DetailedWatcherCallback};
 use serde::Deserializer;
+use std::fmt::{Debug, Formatter};
 
 pub struct CompactCondition {
     pub min_file_size: u32,
@@ -50,6 +57,12 @@ pub trait IsarInstance: Sized {
 
     fn get_collections(&self) -> impl Iterator<Item = &str>;
 
+    /// Schemas of all collections in the order of their collection index.
+    fn get_schemas(&self) -> &[IsarSchema];
+
+    /// Whether the collection is only stored as embedded objects.
+    fn is_embedded(&self, collection_index: u16) -> bool;
+
     fn open_instance(
         instance_id: u32,
         name: &str,
@@ -68,6 +81,13 @@ pub trait IsarInstance: Sized {
 
     fn auto_increment(&self, collection_index: u16) -> i64;
 
+    /// Returns the id the next call to `auto_increment` will return without
+    /// reserving it.
+    fn next_auto_increment(&self, collection_index: u16) -> i64;
+
+    /// Makes sure the auto increment of the collection continues after `id`.
+    fn update_auto_increment(&self, collection_index: u16, id: i64);
+
     fn cursor<'a>(&'a self, txn: &'a Self::Txn, collection_index: u16) -> Result<Self::Cursor<'a>>;
 
     fn insert(&self, txn: Self::Txn, collection_index: u16, count: u32)
@@ -78,11 +98,19 @@ pub trait IsarInstance: Sized {
         txn: &Self::Txn,
         collection_index: u16,
         id: i64,
-        updates: &[(u16, Option<IsarValue>)],
+        updates: &[(u16, UpdateOp)],
     ) -> Result<bool>;
 
     fn delete(&self, txn: &Self::Txn, collection_index: u16, id: i64) -> Result<bool>;
 
+    /// Returns the id of the object with the given string key.
+    fn get_id_by_key(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        key: &str,
+    ) -> Result<Option<i64>>;
+
     fn count(&self, txn: &Self::Txn, collection_index: u16) -> Result<u32>;
 
     fn clear(&self, txn: &Self::Txn, collection_index: u16) -> Result<()>;
@@ -104,6 +132,26 @@ pub trait IsarInstance: Sized {
         limit: Option<u32>,
     ) -> Result<Self::QueryCursor<'a>>;
 
+    /// Like [query_cursor](Self::query_cursor) but binds `params` to the
+    /// [FilterParam](crate::core::filter::FilterParam) conditions of the
+    /// query. Missing parameters are an error.
+    fn query_cursor_with_params<'a>(
+        &'a self,
+        txn: &'a Self::Txn,
+        query: &'a Self::Query,
+        params: &[Option<IsarValue>],
+        offset: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Self::QueryCursor<'a>>;
+
+    fn query_cursor_after<'a>(
+        &'a self,
+        txn: &'a Self::Txn,
+        query: &'a Self::Query,
+        after: Option<&QueryContinuation>,
+        limit: Option<u32>,
+    ) -> Result<Self::QueryCursor<'a>>;
+
     fn query_aggregate(
         &self,
         txn: &Self::Txn,
@@ -112,13 +160,36 @@ pub trait IsarInstance: Sized {
         property_index: Option<u16>,
     ) -> Result<Option<IsarValue>>;
 
+    fn query_facets(
+        &self,
+        txn: &Self::Txn,
+        query: &Self::Query,
+        property_index: u16,
+        sort_by_count: bool,
+        limit: Option<u32>,
+    ) -> Result<Vec<(Option<IsarValue>, u32)>>;
+
+    /// Returns the ids and distances of the `k` objects matching the query
+    /// whose vector property is nearest to `vector`.
+    fn query_nearest(
+        &self,
+        txn: &Self::Txn,
+        query: &Self::Query,
+        property_index: u16,
+        vector: &[f64],
+        metric: VectorMetric,
+        k: u32,
+    ) -> Result<Vec<(i64, f64)>>;
+
+    fn explain(&self, txn: &Self::Txn, query: &Self::Query) -> Result<QueryPlan>;
+
     fn query_update(
         &self,
         txn: &Self::Txn,
         query: &Self::Query,
         offset: Option<u32>,
         limit: Option<u32>,
-        updates: &[(u16, Option<IsarValue>)],
+        updates: &[(u16, UpdateOp)],
     ) -> Result<u32>;
 
     fn query_delete(
@@ -129,6 +200,49 @@ pub trait IsarInstance: Sized {
         limit: Option<u32>,
     ) -> Result<u32>;
 
+    /// Deletes up to `limit` expired objects and returns how many were deleted.
+    fn purge_expired(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        limit: Option<u32>,
+    ) -> Result<u32>;
+
+    /// Returns up to `limit` tombstones with a sequence greater than
+    /// `after_sequence`, ordered by sequence.
+    fn get_tombstones(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        after_sequence: i64,
+        limit: Option<u32>,
+    ) -> Result<Vec<Tombstone>>;
+
+    /// Removes the tombstones of objects deleted at or before `deleted_before`.
+    fn purge_tombstones(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        deleted_before: i64,
+    ) -> Result<u32>;
+
+    /// Applies field updates stamped with hybrid logical clock timestamps. A
+    /// field is only written if the update is newer than the last write of the
+    /// field, so replicas converge regardless of the order changes arrive in.
+    ///
+    /// Local edits are merged with `remote` set to false. Remote changes notify
+    /// all watchers and mark their detailed change notifications as remote.
+    /// Local writes outside of merges stamp the written fields with the
+    /// current time. Newer changes of objects that do not exist are not
+    /// applied and their ids are returned as missing.
+    fn merge_changes(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        changes: &[ObjectChange],
+        remote: bool,
+    ) -> Result<MergeResult>;
+
     fn import_json<'a, T: Deserializer<'a>>(
         &self,
         txn: Self::Txn,
@@ -177,4 +291,34 @@ pub enum Aggregation {
     Max,
     Sum,
     Average,
+    CountDistinct,
+    Median,
+    /// Linearly interpolated percentile. The value has to be between 0 and 1.
+    Percentile(Percentile),
+    /// Population variance.
+    Variance,
+    /// Population standard deviation.
+    StdDev,
+}
+
+/// A percentile stored by its bits so that [Aggregation] can be compared with `Eq`.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct Percentile(u64);
+
+impl Percentile {
+    pub fn value(self) -> f64 {
+        f64::from_bits(self.0)
+    }
+}
+
+impl From<f64> for Percentile {
+    fn from(value: f64) -> Self {
+        Self(value.to_bits())
+    }
+}
+
+impl Debug for Percentile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value())
+    }
 }
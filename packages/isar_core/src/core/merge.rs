@@ -0,0 +1,214 @@
+use super::expiry::now_micros;
+use super::update::UpdateOp;
+use super::value::IsarValue;
+use serde::{Deserialize, Serialize};
+
+/// Hybrid logical clock timestamp. Timestamps are ordered by time, counter and
+/// finally node, so concurrent writes resolve the same way on every replica.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct HlcTimestamp {
+    /// Physical time in milliseconds since the epoch.
+    pub millis: u64,
+    pub counter: u32,
+    pub node: u32,
+}
+
+impl HlcTimestamp {
+    pub const fn new(millis: u64, counter: u32, node: u32) -> Self {
+        Self {
+            millis,
+            counter,
+            node,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldUpdate {
+    pub property_index: u16,
+    pub value: Option<IsarValue>,
+    pub timestamp: HlcTimestamp,
+}
+
+/// Field updates of a single object.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectChange {
+    pub id: i64,
+    pub updates: Vec<FieldUpdate>,
+}
+
+/// Outcome of merging changes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeResult {
+    /// Number of updated objects.
+    pub merged: u32,
+    /// Ids of objects that do not exist. Their changes were not applied.
+    pub missing: Vec<i64>,
+}
+
+/// Property index and timestamp of the last write of a field.
+pub(crate) type FieldTimestamp = (u16, HlcTimestamp);
+
+const ENCODED_SIZE: usize = 18;
+
+/// Returns the updates that are newer than the stored timestamps of their
+/// fields and advances `timestamps` accordingly. If a field is updated more
+/// than once, only the newest update is returned.
+pub(crate) fn resolve_updates(
+    timestamps: &mut Vec<FieldTimestamp>,
+    updates: &[FieldUpdate],
+) -> Vec<(u16, UpdateOp)> {
+    let mut winners: Vec<&FieldUpdate> = vec![];
+    for update in updates {
+        let stored = timestamps
+            .iter_mut()
+            .find(|(property_index, _)| *property_index == update.property_index);
+        match stored {
+            Some((_, timestamp)) if *timestamp >= update.timestamp => continue,
+            Some((_, timestamp)) => *timestamp = update.timestamp,
+            None => timestamps.push((update.property_index, update.timestamp)),
+        }
+        winners.retain(|w| w.property_index != update.property_index);
+        winners.push(update);
+    }
+    winners
+        .into_iter()
+        .map(|u| (u.property_index, UpdateOp::Set(u.value.clone())))
+        .collect()
+}
+
+/// Stamps the fields written by a local write with the current time, or just
+/// after their last write if the clock is behind, so that older remote changes
+/// do not overwrite them.
+pub(crate) fn stamp_local_write(
+    timestamps: &mut Vec<FieldTimestamp>,
+    property_indexes: impl IntoIterator<Item = u16>,
+    now_millis: u64,
+) {
+    let now = HlcTimestamp::new(now_millis, 0, 0);
+    for property_index in property_indexes {
+        let stored = timestamps
+            .iter_mut()
+            .find(|(index, _)| *index == property_index);
+        match stored {
+            Some((_, timestamp)) if timestamp.millis >= now_millis => {
+                *timestamp =
+                    HlcTimestamp::new(timestamp.millis, timestamp.counter.saturating_add(1), 0)
+            }
+            Some((_, timestamp)) => *timestamp = now,
+            None => timestamps.push((property_index, now)),
+        }
+    }
+}
+
+pub(crate) fn now_millis() -> u64 {
+    (now_micros() / 1000) as u64
+}
+
+pub(crate) fn encode_timestamps(timestamps: &[FieldTimestamp]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(timestamps.len() * ENCODED_SIZE);
+    for (property_index, timestamp) in timestamps {
+        bytes.extend_from_slice(&property_index.to_le_bytes());
+        bytes.extend_from_slice(&timestamp.millis.to_le_bytes());
+        bytes.extend_from_slice(&timestamp.counter.to_le_bytes());
+        bytes.extend_from_slice(&timestamp.node.to_le_bytes());
+    }
+    bytes
+}
+
+pub(crate) fn decode_timestamps(bytes: &[u8]) -> Vec<FieldTimestamp> {
+    bytes
+        .chunks_exact(ENCODED_SIZE)
+        .map(|chunk| {
+            let property_index = u16::from_le_bytes(chunk[..2].try_into().unwrap());
+            let timestamp = HlcTimestamp {
+                millis: u64::from_le_bytes(chunk[2..10].try_into().unwrap()),
+                counter: u32::from_le_bytes(chunk[10..14].try_into().unwrap()),
+                node: u32::from_le_bytes(chunk[14..18].try_into().unwrap()),
+            };
+            (property_index, timestamp)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn update(property_index: u16, value: i64, millis: u64, node: u32) -> FieldUpdate {
+        FieldUpdate {
+            property_index,
+            value: Some(IsarValue::Integer(value)),
+            timestamp: HlcTimestamp::new(millis, 0, node),
+        }
+    }
+
+    #[test]
+    fn test_resolve_updates() {
+        let mut timestamps = vec![(1, HlcTimestamp::new(10, 0, 1))];
+        let updates = [
+            update(1, 1, 5, 2),
+            update(1, 2, 10, 1),
+            update(2, 3, 1, 1),
+            update(2, 4, 3, 1),
+            update(2, 5, 2, 1),
+        ];
+        let resolved = resolve_updates(&mut timestamps, &updates);
+        assert_eq!(
+            resolved,
+            vec![(2, UpdateOp::Set(Some(IsarValue::Integer(4))))]
+        );
+        assert_eq!(
+            timestamps,
+            vec![
+                (1, HlcTimestamp::new(10, 0, 1)),
+                (2, HlcTimestamp::new(3, 0, 1))
+            ]
+        );
+
+        // the node breaks ties between equal times
+        let resolved = resolve_updates(&mut timestamps, &[update(1, 6, 10, 2)]);
+        assert_eq!(
+            resolved,
+            vec![(1, UpdateOp::Set(Some(IsarValue::Integer(6))))]
+        );
+        assert_eq!(timestamps[0], (1, HlcTimestamp::new(10, 0, 2)));
+    }
+
+    #[test]
+    fn test_stamp_local_write() {
+        let mut timestamps = vec![
+            (1, HlcTimestamp::new(5, 0, 1)),
+            (2, HlcTimestamp::new(20, 3, 1)),
+        ];
+        stamp_local_write(&mut timestamps, [1, 2, 3], 10);
+        assert_eq!(
+            timestamps,
+            vec![
+                (1, HlcTimestamp::new(10, 0, 0)),
+                (2, HlcTimestamp::new(20, 4, 0)),
+                (3, HlcTimestamp::new(10, 0, 0))
+            ]
+        );
+
+        // local writes win over remote updates of the same time
+        let resolved = resolve_updates(&mut timestamps, &[update(2, 1, 20, 2)]);
+        assert!(resolved.is_empty());
+        let resolved = resolve_updates(&mut timestamps, &[update(1, 1, 11, 2)]);
+        assert_eq!(resolved.len(), 1);
+    }
+
+    #[test]
+    fn test_encode_timestamps() {
+        let timestamps = vec![
+            (1, HlcTimestamp::new(u64::MAX, 3, 7)),
+            (4, HlcTimestamp::new(1, u32::MAX, 0)),
+        ];
+        let bytes = encode_timestamps(&timestamps);
+        assert_eq!(bytes.len(), 36);
+        assert_eq!(decode_timestamps(&bytes), timestamps);
+        assert!(decode_timestamps(&[]).is_empty());
+    }
+}
@@ -1,17 +1,33 @@
 pub mod change_detector;
+pub mod continuation;
+pub mod convert;
 pub mod cursor;
 pub mod data_type;
 pub mod de;
 pub mod error;
+pub mod expiry;
 pub mod fast_wild_match;
 pub mod filter;
 pub mod filter_json;
+pub mod filter_regex;
+pub mod geo;
 pub mod insert;
+pub mod merge;
 pub mod instance;
+pub mod normalize;
 pub mod query_builder;
+pub mod query_description;
+pub mod query_parser;
+pub mod query_plan;
 pub mod reader;
 pub mod schema;
 mod ser;
+#[cfg(test)]
+pub(crate) mod test_instance;
+pub mod tombstone;
+pub mod update;
+pub mod uuid;
 pub mod value;
+pub mod vector;
 pub mod watcher;
 pub mod writer;
@@ -0,0 +1,229 @@
+use caseless::Caseless;
+use itertools::Either;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalization applied to strings before they are compared, matched or
+/// hashed. Both backends use it so that case-insensitive queries agree for
+/// non-ASCII text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StringNormalization {
+    pub case_fold: bool,
+    pub strip_diacritics: bool,
+    pub nfkc: bool,
+}
+
+impl StringNormalization {
+    pub const NONE: Self = Self {
+        case_fold: false,
+        strip_diacritics: false,
+        nfkc: false,
+    };
+
+    pub const CASE_INSENSITIVE: Self = Self {
+        case_fold: true,
+        strip_diacritics: false,
+        nfkc: false,
+    };
+
+    pub const CASE_FOLD: u8 = 1;
+    pub const STRIP_DIACRITICS: u8 = 2;
+    pub const NFKC: u8 = 4;
+
+    pub const fn from_case_sensitive(case_sensitive: bool) -> Self {
+        if case_sensitive {
+            Self::NONE
+        } else {
+            Self::CASE_INSENSITIVE
+        }
+    }
+
+    pub const fn from_flags(flags: u8) -> Self {
+        Self {
+            case_fold: flags & Self::CASE_FOLD != 0,
+            strip_diacritics: flags & Self::STRIP_DIACRITICS != 0,
+            nfkc: flags & Self::NFKC != 0,
+        }
+    }
+
+    pub const fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.case_fold {
+            flags |= Self::CASE_FOLD;
+        }
+        if self.strip_diacritics {
+            flags |= Self::STRIP_DIACRITICS;
+        }
+        if self.nfkc {
+            flags |= Self::NFKC;
+        }
+        flags
+    }
+
+    pub const fn is_none(&self) -> bool {
+        !self.case_fold && !self.strip_diacritics && !self.nfkc
+    }
+
+    pub fn normalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if self.is_none() {
+            return Cow::Borrowed(value);
+        }
+
+        // ASCII is unaffected by NFKC and has no diacritics.
+        if value.is_ascii() {
+            return if self.case_fold && value.bytes().any(|b| b.is_ascii_uppercase()) {
+                Cow::Owned(value.to_ascii_lowercase())
+            } else {
+                Cow::Borrowed(value)
+            };
+        }
+
+        let mut value = Cow::Borrowed(value);
+        if self.nfkc {
+            value = Cow::Owned(value.nfkc().collect());
+        }
+        if self.strip_diacritics {
+            value = Cow::Owned(
+                value
+                    .nfd()
+                    .filter(|c| !is_combining_mark(*c))
+                    .nfc()
+                    .collect(),
+            );
+        }
+        if self.case_fold {
+            value = Cow::Owned(caseless::default_case_fold_str(&value));
+        }
+        value
+    }
+
+    /// The characters of the normalized value. Unlike [normalize](Self::normalize)
+    /// this never allocates a string.
+    pub fn chars<'a>(&self, value: &'a str) -> impl Iterator<Item = char> + 'a {
+        let chars = if self.nfkc {
+            Either::Left(value.nfkc())
+        } else {
+            Either::Right(value.chars())
+        };
+        let chars = if self.strip_diacritics {
+            Either::Left(chars.nfd().filter(|c| !is_combining_mark(*c)).nfc())
+        } else {
+            Either::Right(chars)
+        };
+        if self.case_fold {
+            Either::Left(chars.default_case_fold())
+        } else {
+            Either::Right(chars)
+        }
+    }
+
+    /// Compares the normalized values without allocating. It is used by sorts
+    /// and collations, which compare the same values many times.
+    pub fn compare(&self, value1: &str, value2: &str) -> Ordering {
+        if self.is_none() {
+            value1.cmp(value2)
+        } else if value1.is_ascii() && value2.is_ascii() {
+            if self.case_fold {
+                let lowercase = |b: u8| b.to_ascii_lowercase();
+                value1
+                    .bytes()
+                    .map(lowercase)
+                    .cmp(value2.bytes().map(lowercase))
+            } else {
+                value1.cmp(value2)
+            }
+        } else {
+            self.chars(value1).cmp(self.chars(value2))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_fold() {
+        let normalization = StringNormalization::CASE_INSENSITIVE;
+        assert_eq!(normalization.normalize("HeLLo"), "hello");
+        assert_eq!(normalization.normalize("ÄÖÜ"), "äöü");
+        assert_eq!(normalization.normalize("Straße"), "strasse");
+        assert_eq!(normalization.compare("Ärger", "ärger"), Ordering::Equal);
+        assert_ne!(normalization.compare("Ärger", "arger"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_strip_diacritics() {
+        let normalization = StringNormalization::from_flags(
+            StringNormalization::CASE_FOLD | StringNormalization::STRIP_DIACRITICS,
+        );
+        assert_eq!(normalization.normalize("Ärger"), "arger");
+        assert_eq!(normalization.normalize("François"), "francois");
+        assert_eq!(normalization.normalize("Łódź"), "łodz");
+    }
+
+    #[test]
+    fn test_nfkc() {
+        let normalization = StringNormalization::from_flags(StringNormalization::NFKC);
+        assert_eq!(normalization.normalize("ｆｕｌｌ"), "full");
+        assert_eq!(normalization.normalize("ﬁ"), "fi");
+        assert_eq!(normalization.normalize("e\u{301}"), "é");
+    }
+
+    #[test]
+    fn test_compare_matches_normalize() {
+        let values = [
+            "",
+            "a",
+            "B",
+            "abc",
+            "ABD",
+            "Ärger",
+            "ärger",
+            "arger",
+            "Straße",
+            "STRASSE",
+            "ﬁx",
+            "fix",
+            "ｆｕｌｌ",
+            "e\u{301}",
+            "é",
+            "Łódź",
+            "lodz",
+        ];
+        for flags in 0..8 {
+            let normalization = StringNormalization::from_flags(flags);
+            for value1 in values {
+                assert_eq!(
+                    normalization.chars(value1).collect::<String>(),
+                    normalization.normalize(value1)
+                );
+                for value2 in values {
+                    assert_eq!(
+                        normalization.compare(value1, value2),
+                        normalization
+                            .normalize(value1)
+                            .cmp(&normalization.normalize(value2)),
+                        "{value1} {value2} {flags}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_flags() {
+        for flags in 0..8 {
+            assert_eq!(StringNormalization::from_flags(flags).flags(), flags);
+        }
+        assert!(StringNormalization::from_case_sensitive(true).is_none());
+        assert_eq!(
+            StringNormalization::from_case_sensitive(false),
+            StringNormalization::CASE_INSENSITIVE
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use super::error::Result;
 use super::filter::Filter;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
 pub enum Sort {
     Asc,
     Desc,
@@ -15,5 +18,7 @@ pub trait IsarQueryBuilder {
 
     fn add_distinct(&mut self, property_index: u16, case_sensitive: bool);
 
-    fn build(self) -> Self::Query;
+    fn add_property(&mut self, property_index: u16);
+
+    fn build(self) -> Result<Self::Query>;
 }
@@ -0,0 +1,623 @@
+use super::data_type::DataType;
+use super::error::{IsarError, Result};
+use super::filter::{
+    ConditionType, Filter, FilterCondition, FilterElements, FilterGeo, FilterJson, FilterNested,
+    JsonCondition, ListQuantifier,
+};
+use super::geo::GeoShape;
+use super::instance::IsarInstance;
+use super::normalize::StringNormalization;
+use super::query_builder::{IsarQueryBuilder, Sort};
+use super::schema::IsarSchema;
+use super::value::IsarValue;
+use serde::{Deserialize, Serialize};
+
+/// Serializable description of a query that references properties by name so
+/// it stays valid when the property order of a collection changes.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryDescription {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<FilterDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sort: Vec<SortDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub distinct: Vec<DistinctDescription>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SortDescription {
+    pub property: String,
+    #[serde(default = "default_sort")]
+    pub order: Sort,
+    #[serde(default = "default_true")]
+    pub case_sensitive: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DistinctDescription {
+    pub property: String,
+    #[serde(default = "default_true")]
+    pub case_sensitive: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum FilterDescription {
+    Condition(ConditionDescription),
+    /// Filter of the embedded collection applied to an object property.
+    Nested {
+        property: String,
+        filter: Box<FilterDescription>,
+    },
+    /// Filter applied to the elements of a list property, see
+    /// [FilterElements]. For a `Json` property the filter is a condition on
+    /// the JSON list at its path.
+    Elements {
+        property: String,
+        #[serde(default)]
+        quantifier: ListQuantifier,
+        filter: Box<FilterDescription>,
+    },
+    Geo {
+        latitude: String,
+        longitude: String,
+        shape: GeoShape,
+    },
+    And(Vec<FilterDescription>),
+    Or(Vec<FilterDescription>),
+    Not(Box<FilterDescription>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionDescription {
+    pub property: String,
+    /// Path of the compared value inside a `Json` property.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<String>,
+    #[serde(rename = "type")]
+    pub condition_type: ConditionType,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<Option<IsarValue>>,
+    #[serde(default, skip_serializing_if = "StringNormalization::is_none")]
+    pub normalization: StringNormalization,
+}
+
+fn default_sort() -> Sort {
+    Sort::Asc
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl QueryDescription {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| IsarError::JsonError {
+            message: e.to_string(),
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Validates the description against the schema of the collection and
+    /// builds the query. The offset and limit are returned to be passed to
+    /// the query cursor.
+    pub fn build<I: IsarInstance>(
+        &self,
+        instance: &I,
+        collection_index: u16,
+    ) -> Result<(I::Query, Option<u32>, Option<u32>)> {
+        let schemas = instance.get_schemas();
+        let schema = schemas
+            .get(collection_index as usize)
+            .filter(|s| !s.embedded)
+            .ok_or(IsarError::IllegalArgument {})?;
+
+        let mut builder = instance.query(collection_index)?;
+        if let Some(filter) = &self.filter {
+            builder.set_filter(filter.resolve(schemas, schema)?);
+        }
+        for sort in &self.sort {
+            let (property_index, data_type, _) = find_property(schema, &sort.property)?;
+            if data_type.is_list() || data_type == DataType::Object {
+                return query_error(format!("Cannot sort by {}.", sort.property));
+            }
+            builder.add_sort(property_index, sort.order, sort.case_sensitive);
+        }
+        for distinct in &self.distinct {
+            let (property_index, data_type, _) = find_property(schema, &distinct.property)?;
+            if data_type == DataType::Object || data_type == DataType::ObjectList {
+                return query_error(format!("Cannot use {} as distinct.", distinct.property));
+            }
+            builder.add_distinct(property_index, distinct.case_sensitive);
+        }
+        Ok((builder.build()?, self.offset, self.limit))
+    }
+}
+
+impl FilterDescription {
+    /// Resolves property names to indexes of `schema` and checks that
+    /// conditions and values match the property types.
+    pub fn resolve(&self, schemas: &[IsarSchema], schema: &IsarSchema) -> Result<Filter> {
+        let filter = match self {
+            FilterDescription::Condition(condition) => {
+                let (property_index, data_type, _) = find_property(schema, &condition.property)?;
+                if !condition.path.is_empty() {
+                    let json = condition.resolve_json(data_type, false)?;
+                    return Ok(Filter::Json(FilterJson::new(property_index, json)));
+                }
+                let values = check_values(
+                    &condition.property,
+                    data_type,
+                    condition.condition_type,
+                    &condition.values,
+                )?;
+                Filter::Condition(FilterCondition::new_normalized(
+                    property_index,
+                    condition.condition_type,
+                    values,
+                    condition.normalization,
+                ))
+            }
+            FilterDescription::Nested { property, filter } => {
+                let (property_index, data_type, target) = find_property(schema, property)?;
+                if data_type != DataType::Object {
+                    return query_error(format!("{} is not an object property.", property));
+                }
+                let embedded = find_embedded(schemas, target)?;
+                let filter = filter.resolve(schemas, embedded)?;
+                Filter::Nested(FilterNested::new(property_index, filter))
+            }
+            FilterDescription::Elements {
+                property,
+                quantifier,
+                filter,
+            } => {
+                let (property_index, data_type, target) = find_property(schema, property)?;
+                if data_type == DataType::ObjectList {
+                    let embedded = find_embedded(schemas, target)?;
+                    let filter = filter.resolve(schemas, embedded)?;
+                    return Ok(Filter::Elements(FilterElements::new(
+                        property_index,
+                        *quantifier,
+                        filter,
+                    )));
+                }
+
+                // elements of primitive lists and JSON lists can only be
+                // filtered by a condition on the list itself
+                let condition = match filter.as_ref() {
+                    FilterDescription::Condition(condition) if &condition.property == property => {
+                        condition
+                    }
+                    _ => {
+                        return query_error(format!(
+                            "Elements of {} can only be filtered by a condition on {}.",
+                            property, property
+                        ))
+                    }
+                };
+                if data_type == DataType::Json && !condition.path.is_empty() {
+                    let json = condition
+                        .resolve_json(data_type, true)?
+                        .with_quantifier(*quantifier);
+                    Filter::Json(FilterJson::new(property_index, json))
+                } else if data_type.is_list() && condition.path.is_empty() {
+                    let filter = filter.resolve(schemas, schema)?;
+                    Filter::Elements(FilterElements::new(property_index, *quantifier, filter))
+                } else {
+                    return query_error(format!("{} is not a list property.", property));
+                }
+            }
+            FilterDescription::Geo {
+                latitude,
+                longitude,
+                shape,
+            } => {
+                let (latitude_index, latitude_type, _) = find_property(schema, latitude)?;
+                let (longitude_index, longitude_type, _) = find_property(schema, longitude)?;
+                if latitude_type != DataType::Double || longitude_type != DataType::Double {
+                    return query_error(format!(
+                        "{} and {} must be double properties.",
+                        latitude, longitude
+                    ));
+                }
+                Filter::Geo(FilterGeo::new(
+                    latitude_index,
+                    longitude_index,
+                    shape.clone(),
+                ))
+            }
+            FilterDescription::And(filters) => Filter::And(
+                filters
+                    .iter()
+                    .map(|f| f.resolve(schemas, schema))
+                    .collect::<Result<_>>()?,
+            ),
+            FilterDescription::Or(filters) => Filter::Or(
+                filters
+                    .iter()
+                    .map(|f| f.resolve(schemas, schema))
+                    .collect::<Result<_>>()?,
+            ),
+            FilterDescription::Not(filter) => {
+                Filter::Not(Box::new(filter.resolve(schemas, schema)?))
+            }
+        };
+        Ok(filter)
+    }
+}
+
+impl ConditionDescription {
+    /// The condition on the value at `path` of a JSON property. `is_list`
+    /// applies the condition to the elements of a JSON list.
+    fn resolve_json(&self, data_type: DataType, is_list: bool) -> Result<JsonCondition> {
+        if data_type != DataType::Json {
+            return query_error(format!("{} is not a JSON property.", self.property));
+        }
+        check_value_count(&self.property, self.condition_type, &self.values)?;
+        Ok(JsonCondition::new_normalized(
+            self.path.clone(),
+            self.condition_type,
+            is_list,
+            self.values.clone(),
+            self.normalization,
+        ))
+    }
+}
+
+fn query_error<T>(message: String) -> Result<T> {
+    Err(IsarError::QueryError { message })
+}
+
+/// Returns the index, type and embedded collection of a property. The id has
+/// index 0 and the properties follow in schema order.
+pub(crate) fn find_property<'a>(
+    schema: &'a IsarSchema,
+    name: &str,
+) -> Result<(u16, DataType, Option<&'a str>)> {
+    let property = schema
+        .properties
+        .iter()
+        .filter(|p| p.name.is_some())
+        .enumerate()
+        .find(|(_, p)| p.name.as_deref() == Some(name));
+    if let Some((index, property)) = property {
+        Ok((
+            index as u16 + 1,
+            property.data_type,
+            property.collection.as_deref(),
+        ))
+    } else if schema.id_name.as_deref() == Some(name) {
+        Ok((0, DataType::Long, None))
+    } else {
+        query_error(format!("Unknown property {} of {}.", name, schema.name))
+    }
+}
+
+pub(crate) fn find_embedded<'a>(
+    schemas: &'a [IsarSchema],
+    target: Option<&str>,
+) -> Result<&'a IsarSchema> {
+    schemas
+        .iter()
+        .find(|s| s.embedded && Some(s.name.as_str()) == target)
+        .ok_or_else(|| IsarError::SchemaError {
+            message: format!("Embedded collection {:?} does not exist.", target),
+        })
+}
+
+/// Checks the number of values of a condition and converts numbers to the
+/// value type of the property.
+fn check_values(
+    name: &str,
+    data_type: DataType,
+    condition_type: ConditionType,
+    values: &[Option<IsarValue>],
+) -> Result<Vec<Option<IsarValue>>> {
+    check_value_count(name, condition_type, values)?;
+
+    let is_list_condition = matches!(
+        condition_type,
+        ConditionType::ListLength | ConditionType::ListIsEmpty
+    );
+    if is_list_condition && !data_type.is_list() {
+        return query_error(format!("{} is not a list property.", name));
+    }
+
+    let value_type = match condition_type {
+        ConditionType::ListLength => DataType::Long,
+        ConditionType::StringStartsWith
+        | ConditionType::StringEndsWith
+        | ConditionType::StringContains
+        | ConditionType::StringMatches
+        | ConditionType::StringRegex => DataType::String,
+        _ => data_type.element_type().unwrap_or(data_type),
+    };
+    values
+        .iter()
+        .map(|value| match value {
+            Some(value) => {
+                convert_value(value, value_type)
+                    .map(Some)
+                    .ok_or_else(|| IsarError::QueryError {
+                        message: format!(
+                            "Invalid value {:?} for {:?} property {}.",
+                            value, data_type, name
+                        ),
+                    })
+            }
+            None => Ok(None),
+        })
+        .collect()
+}
+
+fn check_value_count(
+    name: &str,
+    condition_type: ConditionType,
+    values: &[Option<IsarValue>],
+) -> Result<()> {
+    let count_matches = match condition_type {
+        ConditionType::IsNull | ConditionType::ListIsEmpty => values.is_empty(),
+        ConditionType::Between | ConditionType::ListLength => values.len() == 2,
+        ConditionType::In => true,
+        _ => values.len() == 1,
+    };
+    if count_matches {
+        Ok(())
+    } else {
+        query_error(format!(
+            "Wrong number of values for {:?} condition on {}.",
+            condition_type, name
+        ))
+    }
+}
+
+fn convert_value(value: &IsarValue, data_type: DataType) -> Option<IsarValue> {
+    match (data_type, value) {
+        (DataType::Bool, IsarValue::Bool(_)) => Some(value.clone()),
+        (DataType::Byte | DataType::Int | DataType::Long, IsarValue::Integer(_)) => {
+            Some(value.clone())
+        }
+        (DataType::Byte | DataType::Int | DataType::Long, IsarValue::Real(real))
+            if real.fract() == 0.0 =>
+        {
+            Some(IsarValue::Integer(*real as i64))
+        }
+        (DataType::Float | DataType::Double, IsarValue::Real(_)) => Some(value.clone()),
+        (DataType::Float | DataType::Double, IsarValue::Integer(integer)) => {
+            Some(IsarValue::Real(*integer as f64))
+        }
+        (DataType::String | DataType::Json | DataType::Uuid, IsarValue::String(_)) => {
+            Some(value.clone())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::PropertySchema;
+
+    fn schemas() -> Vec<IsarSchema> {
+        let person = IsarSchema::new(
+            "Person",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("age", DataType::Int, None),
+                PropertySchema::new("score", DataType::Double, None),
+                PropertySchema::new("tags", DataType::StringList, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+                PropertySchema::new("data", DataType::Json, None),
+            ],
+            vec![],
+            false,
+        );
+        let address = IsarSchema::new(
+            "Address",
+            None,
+            vec![PropertySchema::new("city", DataType::String, None)],
+            vec![],
+            true,
+        );
+        vec![person, address]
+    }
+
+    fn resolve(json: &str) -> Result<Filter> {
+        let schemas = schemas();
+        let description = serde_json::from_str::<FilterDescription>(json).unwrap();
+        description.resolve(&schemas, &schemas[0])
+    }
+
+    #[test]
+    fn test_resolve_filter() {
+        let filter = resolve(
+            r#"{"and": [
+                {"condition": {"property": "age", "type": "greaterOrEqual", "values": [18]}},
+                {"condition": {"property": "score", "type": "between", "values": [1, 2.5]}},
+                {"not": {"condition": {"property": "id", "type": "in", "values": [1, 2]}}},
+                {"nested": {"property": "address", "filter": {"condition": {
+                    "property": "city", "type": "equal", "values": ["berlin"],
+                    "normalization": {"caseFold": true}
+                }}}},
+                {"elements": {"property": "tags", "quantifier": "all", "filter": {
+                    "condition": {"property": "tags", "type": "stringStartsWith", "values": ["a"]}
+                }}}
+            ]}"#,
+        )
+        .unwrap();
+        let condition = |index, condition_type, values| {
+            Filter::Condition(FilterCondition::new(index, condition_type, values, true))
+        };
+        assert_eq!(
+            filter,
+            Filter::And(vec![
+                condition(
+                    2,
+                    ConditionType::GreaterOrEqual,
+                    vec![Some(IsarValue::Integer(18))]
+                ),
+                condition(
+                    3,
+                    ConditionType::Between,
+                    vec![Some(IsarValue::Real(1.0)), Some(IsarValue::Real(2.5))]
+                ),
+                Filter::Not(Box::new(condition(
+                    0,
+                    ConditionType::In,
+                    vec![Some(IsarValue::Integer(1)), Some(IsarValue::Integer(2))]
+                ))),
+                Filter::Nested(FilterNested::new(
+                    5,
+                    Filter::Condition(FilterCondition::new(
+                        1,
+                        ConditionType::Equal,
+                        vec![Some(IsarValue::String("berlin".to_string()))],
+                        false
+                    ))
+                )),
+                Filter::Elements(FilterElements::new(
+                    4,
+                    ListQuantifier::All,
+                    condition(
+                        4,
+                        ConditionType::StringStartsWith,
+                        vec![Some(IsarValue::String("a".to_string()))]
+                    )
+                )),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_resolve_filter_errors() {
+        let invalid = [
+            r#"{"condition": {"property": "unknown", "type": "isNull"}}"#,
+            r#"{"condition": {"property": "age", "type": "equal", "values": ["a"]}}"#,
+            r#"{"condition": {"property": "age", "type": "equal", "values": [1.5]}}"#,
+            r#"{"condition": {"property": "age", "type": "between", "values": [1]}}"#,
+            r#"{"condition": {"property": "age", "type": "listIsEmpty"}}"#,
+            r#"{"condition": {"property": "name", "path": ["a"], "type": "isNull"}}"#,
+            r#"{"nested": {"property": "name", "filter": {"and": []}}}"#,
+            r#"{"nested": {"property": "address", "filter": {
+                "condition": {"property": "name", "type": "isNull"}
+            }}}"#,
+            r#"{"elements": {"property": "age", "filter": {"and": []}}}"#,
+            r#"{"elements": {"property": "age", "filter": {
+                "condition": {"property": "age", "type": "equal", "values": [1]}
+            }}}"#,
+            r#"{"elements": {"property": "tags", "filter": {
+                "condition": {"property": "name", "type": "isNull"}
+            }}}"#,
+            r#"{"elements": {"property": "tags", "filter": {"not": {
+                "condition": {"property": "tags", "type": "isNull"}
+            }}}}"#,
+            r#"{"elements": {"property": "data", "filter": {
+                "condition": {"property": "data", "type": "isNull"}
+            }}}"#,
+            r#"{"geo": {"latitude": "age", "longitude": "score", "shape": {
+                "radius": {"latitude": 0, "longitude": 0, "meters": 1}
+            }}}"#,
+        ];
+        for json in invalid {
+            assert!(
+                matches!(resolve(json), Err(IsarError::QueryError { .. })),
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_json_filter() {
+        let filter = resolve(
+            r#"{"and": [
+                {"condition": {"property": "data", "path": ["a"], "type": "equal", "values": [1]}},
+                {"elements": {"property": "data", "quantifier": "all", "filter": {
+                    "condition": {"property": "data", "path": ["tags"], "type": "isNull"}
+                }}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            filter,
+            Filter::And(vec![
+                Filter::Json(FilterJson::new(
+                    6,
+                    JsonCondition::new(
+                        vec!["a".to_string()],
+                        ConditionType::Equal,
+                        false,
+                        vec![Some(IsarValue::Integer(1))],
+                        true
+                    )
+                )),
+                Filter::Json(FilterJson::new(
+                    6,
+                    JsonCondition::new(
+                        vec!["tags".to_string()],
+                        ConditionType::IsNull,
+                        true,
+                        vec![],
+                        true
+                    )
+                    .with_quantifier(ListQuantifier::All)
+                )),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let description = QueryDescription {
+            filter: Some(FilterDescription::Or(vec![
+                FilterDescription::Condition(ConditionDescription {
+                    property: "name".to_string(),
+                    path: vec![],
+                    condition_type: ConditionType::IsNull,
+                    values: vec![],
+                    normalization: StringNormalization::NONE,
+                }),
+                FilterDescription::Geo {
+                    latitude: "lat".to_string(),
+                    longitude: "lng".to_string(),
+                    shape: GeoShape::Polygon(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]),
+                },
+            ])),
+            sort: vec![SortDescription {
+                property: "age".to_string(),
+                order: Sort::Desc,
+                case_sensitive: true,
+            }],
+            distinct: vec![],
+            offset: None,
+            limit: Some(10),
+        };
+        let json = description.to_json();
+        assert_eq!(
+            json,
+            r#"{"filter":{"or":[{"condition":{"property":"name","type":"isNull"}},{"geo":{"latitude":"lat","longitude":"lng","shape":{"polygon":[[0.0,0.0],[1.0,0.0],[0.0,1.0]]}}}]},"sort":[{"property":"age","order":"desc","caseSensitive":true}],"limit":10}"#
+        );
+        assert_eq!(QueryDescription::from_json(&json), Ok(description));
+
+        let defaults = QueryDescription::from_json(r#"{"sort": [{"property": "age"}]}"#).unwrap();
+        assert_eq!(defaults.sort[0].order, Sort::Asc);
+        assert!(defaults.sort[0].case_sensitive);
+        assert!(matches!(
+            QueryDescription::from_json("{\"filter\": 1}"),
+            Err(IsarError::JsonError { .. })
+        ));
+    }
+}
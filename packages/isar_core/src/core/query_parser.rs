@@ -0,0 +1,816 @@
+use super::data_type::DataType;
+use super::error::{IsarError, Result};
+use super::filter::{ConditionType, ListQuantifier};
+use super::normalize::StringNormalization;
+use super::query_builder::Sort;
+use super::query_description::{
+    find_embedded, find_property, ConditionDescription, DistinctDescription, FilterDescription,
+    QueryDescription, SortDescription,
+};
+use super::schema::IsarSchema;
+use super::value::IsarValue;
+
+/// Parses a textual query like
+/// `age >= 18 AND address.city = "Berlin" IGNORE CASE ORDER BY age DESC LIMIT 10`
+/// for the collection `schema`.
+///
+/// Dotted paths descend into object and object list properties and continue
+/// as a JSON path inside `Json` properties. Errors report the column of the
+/// offending token. Values are checked against the property types when the
+/// description is built.
+pub fn parse_query(
+    text: &str,
+    schemas: &[IsarSchema],
+    schema: &IsarSchema,
+) -> Result<QueryDescription> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        schemas,
+        schema,
+    };
+    parser.parse_query()
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Ident(String),
+    /// Identifier in backticks, never a keyword.
+    Quoted(String),
+    String(String),
+    Number(IsarValue),
+    Symbol(&'static str),
+    End,
+}
+
+const SYMBOLS: [&str; 11] = ["==", "!=", "<>", ">=", "<=", "=", ">", "<", "(", ")", ","];
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens: Vec<(Token, usize)> = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let token = if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            Token::Ident(chars[start..i].iter().collect())
+        } else if c == '`' {
+            let (value, end) = read_quoted(&chars, i, column)?;
+            i = end;
+            Token::Quoted(value)
+        } else if c == '"' || c == '\'' {
+            let (value, end) = read_quoted(&chars, i, column)?;
+            i = end;
+            Token::String(value)
+        } else if c == '.' {
+            i += 1;
+            Token::Symbol(".")
+        } else if c.is_ascii_digit()
+            || (c == '-' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            // segments of a path like `tags.0.name` are always indexes
+            let after_dot = matches!(tokens.last(), Some((Token::Symbol("."), _)));
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let mut is_real = false;
+            if !after_dot
+                && chars.get(i) == Some(&'.')
+                && chars.get(i + 1).is_some_and(char::is_ascii_digit)
+            {
+                is_real = true;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let number = chars[start..i].iter().collect::<String>();
+            let value = if is_real {
+                number.parse().ok().map(IsarValue::Real)
+            } else {
+                number.parse().ok().map(IsarValue::Integer)
+            };
+            match value {
+                Some(value) => Token::Number(value),
+                None => return error(column, format!("Invalid number {}.", number)),
+            }
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| {
+            s.chars()
+                .enumerate()
+                .all(|(offset, c)| chars.get(i + offset) == Some(&c))
+        }) {
+            i += symbol.len();
+            Token::Symbol(symbol)
+        } else {
+            return error(column, format!("Unexpected character {}.", c));
+        };
+        tokens.push((token, column));
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+/// Reads a string or identifier enclosed by the quote at `start`. Returns the
+/// unescaped value and the position after the closing quote.
+fn read_quoted(chars: &[char], start: usize, column: usize) -> Result<(String, usize)> {
+    let quote = chars[start];
+    let mut value = String::new();
+    let mut i = start + 1;
+    while let Some(&c) = chars.get(i) {
+        i += 1;
+        if c == quote {
+            return Ok((value, i));
+        } else if c == '\\' {
+            let escaped = match chars.get(i) {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some(&c) => c,
+                None => break,
+            };
+            value.push(escaped);
+            i += 1;
+        } else {
+            value.push(c);
+        }
+    }
+    error(column, "Unterminated string.".to_string())
+}
+
+fn error<T>(column: usize, message: String) -> Result<T> {
+    Err(IsarError::QueryError {
+        message: format!("Column {}: {}", column, message),
+    })
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Ident(ident) => ident.clone(),
+        Token::Quoted(ident) => format!("`{}`", ident),
+        Token::String(string) => format!("\"{}\"", string),
+        Token::Number(IsarValue::Integer(number)) => number.to_string(),
+        Token::Number(IsarValue::Real(number)) => number.to_string(),
+        Token::Number(value) => format!("{:?}", value),
+        Token::Symbol(symbol) => symbol.to_string(),
+        Token::End => "end of query".to_string(),
+    }
+}
+
+/// Property path with the column of every segment.
+type Path = Vec<(String, usize)>;
+
+/// Object or object list property that a path descends into.
+enum Step {
+    Nested(String),
+    Elements(String),
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    schemas: &'a [IsarSchema],
+    schema: &'a IsarSchema,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn column(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn unexpected<T>(&self) -> Result<T> {
+        error(
+            self.column(),
+            format!("Unexpected {}.", describe(self.peek())),
+        )
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Ident(ident) if ident.eq_ignore_ascii_case(keyword))
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let is_keyword = self.is_keyword(keyword);
+        if is_keyword {
+            self.position += 1;
+        }
+        is_keyword
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.accept_keyword(keyword) {
+            Ok(())
+        } else {
+            error(
+                self.column(),
+                format!("Expected {} but found {}.", keyword, describe(self.peek())),
+            )
+        }
+    }
+
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        let is_symbol = matches!(self.peek(), Token::Symbol(s) if *s == symbol);
+        if is_symbol {
+            self.position += 1;
+        }
+        is_symbol
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if self.accept_symbol(symbol) {
+            Ok(())
+        } else {
+            error(
+                self.column(),
+                format!("Expected {} but found {}.", symbol, describe(self.peek())),
+            )
+        }
+    }
+
+    fn is_clause_start(&self) -> bool {
+        ["DISTINCT", "ORDER", "OFFSET", "LIMIT"]
+            .iter()
+            .any(|k| self.is_keyword(k))
+            || *self.peek() == Token::End
+    }
+
+    fn parse_query(&mut self) -> Result<QueryDescription> {
+        let mut description = QueryDescription::default();
+        if !self.is_clause_start() {
+            description.filter = Some(self.parse_or()?);
+        }
+        if self.accept_keyword("DISTINCT") {
+            self.expect_keyword("BY")?;
+            loop {
+                let property = self.parse_property()?;
+                let case_sensitive = !self.parse_ignore_case()?;
+                description.distinct.push(DistinctDescription {
+                    property,
+                    case_sensitive,
+                });
+                if !self.accept_symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.accept_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let property = self.parse_property()?;
+                let order = if self.accept_keyword("DESC") {
+                    Sort::Desc
+                } else {
+                    self.accept_keyword("ASC");
+                    Sort::Asc
+                };
+                let case_sensitive = !self.parse_ignore_case()?;
+                description.sort.push(SortDescription {
+                    property,
+                    order,
+                    case_sensitive,
+                });
+                if !self.accept_symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.accept_keyword("OFFSET") {
+            description.offset = Some(self.parse_count()?);
+        }
+        if self.accept_keyword("LIMIT") {
+            description.limit = Some(self.parse_count()?);
+        }
+        if *self.peek() != Token::End {
+            return self.unexpected();
+        }
+        Ok(description)
+    }
+
+    fn parse_or(&mut self) -> Result<FilterDescription> {
+        let mut filters = vec![self.parse_and()?];
+        while self.accept_keyword("OR") {
+            filters.push(self.parse_and()?);
+        }
+        if filters.len() == 1 {
+            Ok(filters.pop().unwrap())
+        } else {
+            Ok(FilterDescription::Or(filters))
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<FilterDescription> {
+        let mut filters = vec![self.parse_not()?];
+        while self.accept_keyword("AND") {
+            filters.push(self.parse_not()?);
+        }
+        if filters.len() == 1 {
+            Ok(filters.pop().unwrap())
+        } else {
+            Ok(FilterDescription::And(filters))
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<FilterDescription> {
+        if self.accept_keyword("NOT") {
+            Ok(FilterDescription::Not(Box::new(self.parse_not()?)))
+        } else if self.accept_symbol("(") {
+            let filter = self.parse_or()?;
+            self.expect_symbol(")")?;
+            Ok(filter)
+        } else {
+            self.parse_condition()
+        }
+    }
+
+    fn parse_path(&mut self) -> Result<Path> {
+        let mut path = vec![];
+        loop {
+            let column = self.column();
+            let segment = match self.next() {
+                Token::Ident(ident) | Token::Quoted(ident) => ident,
+                Token::Number(IsarValue::Integer(index)) if !path.is_empty() => index.to_string(),
+                token => {
+                    return error(
+                        column,
+                        format!("Expected property but found {}.", describe(&token)),
+                    );
+                }
+            };
+            path.push((segment, column));
+            if !self.accept_symbol(".") {
+                return Ok(path);
+            }
+        }
+    }
+
+    /// Top-level property of a sort or distinct clause.
+    fn parse_property(&mut self) -> Result<String> {
+        let mut path = self.parse_path()?;
+        if let Some((_, column)) = path.get(1) {
+            return error(
+                *column,
+                "Only top-level properties can be used here.".to_string(),
+            );
+        }
+        let (name, column) = path.pop().unwrap();
+        find_property(self.schema, &name).or_else(|e| at(e, column))?;
+        Ok(name)
+    }
+
+    fn parse_ignore_case(&mut self) -> Result<bool> {
+        if self.accept_keyword("IGNORE") {
+            self.expect_keyword("CASE")?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn parse_count(&mut self) -> Result<u32> {
+        let column = self.column();
+        match self.next() {
+            Token::Number(IsarValue::Integer(count)) if (0..=u32::MAX as i64).contains(&count) => {
+                Ok(count as u32)
+            }
+            token => error(
+                column,
+                format!("Expected a count but found {}.", describe(&token)),
+            ),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Option<IsarValue>> {
+        let column = self.column();
+        let value = match self.next() {
+            Token::String(string) => Some(IsarValue::String(string)),
+            Token::Number(number) => Some(number),
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("TRUE") => {
+                Some(IsarValue::Bool(true))
+            }
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("FALSE") => {
+                Some(IsarValue::Bool(false))
+            }
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("NULL") => None,
+            token => {
+                return error(
+                    column,
+                    format!("Expected a value but found {}.", describe(&token)),
+                );
+            }
+        };
+        Ok(value)
+    }
+
+    fn parse_condition(&mut self) -> Result<FilterDescription> {
+        let path = self.parse_path()?;
+
+        let (negate, condition_type, values) = if self.accept_keyword("IS") {
+            let negate = self.accept_keyword("NOT");
+            if self.accept_keyword("NULL") {
+                (negate, ConditionType::IsNull, vec![])
+            } else if self.accept_keyword("EMPTY") {
+                (negate, ConditionType::ListIsEmpty, vec![])
+            } else {
+                return error(
+                    self.column(),
+                    format!(
+                        "Expected NULL or EMPTY but found {}.",
+                        describe(self.peek())
+                    ),
+                );
+            }
+        } else {
+            let mut negate = self.accept_keyword("NOT");
+            let column = self.column();
+            let condition_type = match self.next() {
+                Token::Symbol("=" | "==") if !negate => ConditionType::Equal,
+                Token::Symbol("!=" | "<>") if !negate => {
+                    negate = true;
+                    ConditionType::Equal
+                }
+                Token::Symbol(">") if !negate => ConditionType::Greater,
+                Token::Symbol(">=") if !negate => ConditionType::GreaterOrEqual,
+                Token::Symbol("<") if !negate => ConditionType::Less,
+                Token::Symbol("<=") if !negate => ConditionType::LessOrEqual,
+                Token::Ident(ident) => match ident.to_ascii_uppercase().as_str() {
+                    "IN" => ConditionType::In,
+                    "BETWEEN" => ConditionType::Between,
+                    "STARTSWITH" => ConditionType::StringStartsWith,
+                    "ENDSWITH" => ConditionType::StringEndsWith,
+                    "CONTAINS" => ConditionType::StringContains,
+                    "MATCHES" => ConditionType::StringMatches,
+                    "REGEX" => ConditionType::StringRegex,
+                    _ => {
+                        return error(column, format!("Expected operator but found {}.", ident));
+                    }
+                },
+                token => {
+                    return error(
+                        column,
+                        format!("Expected operator but found {}.", describe(&token)),
+                    );
+                }
+            };
+            let values = match condition_type {
+                ConditionType::In => {
+                    self.expect_symbol("(")?;
+                    let mut values = vec![self.parse_value()?];
+                    while self.accept_symbol(",") {
+                        values.push(self.parse_value()?);
+                    }
+                    self.expect_symbol(")")?;
+                    values
+                }
+                ConditionType::Between => {
+                    let lower = self.parse_value()?;
+                    self.expect_keyword("AND")?;
+                    vec![lower, self.parse_value()?]
+                }
+                _ => vec![self.parse_value()?],
+            };
+            (negate, condition_type, values)
+        };
+        let normalization = if self.parse_ignore_case()? {
+            StringNormalization::CASE_INSENSITIVE
+        } else {
+            StringNormalization::NONE
+        };
+
+        let (steps, property, json_path) = self.resolve_path(&path)?;
+        let condition = FilterDescription::Condition(ConditionDescription {
+            property,
+            path: json_path,
+            condition_type,
+            values,
+            normalization,
+        });
+
+        let mut filter = if negate {
+            FilterDescription::Not(Box::new(condition))
+        } else {
+            condition
+        };
+        for step in steps.into_iter().rev() {
+            filter = match step {
+                Step::Nested(property) => FilterDescription::Nested {
+                    property,
+                    filter: Box::new(filter),
+                },
+                Step::Elements(property) => FilterDescription::Elements {
+                    property,
+                    quantifier: ListQuantifier::Any,
+                    filter: Box::new(filter),
+                },
+            };
+        }
+        Ok(filter)
+    }
+
+    /// Splits a path into the object properties it descends into, the name of
+    /// the compared property and the JSON path inside it.
+    fn resolve_path(&self, path: &Path) -> Result<(Vec<Step>, String, Vec<String>)> {
+        let mut steps = vec![];
+        let mut schema = self.schema;
+        for (i, (name, column)) in path.iter().enumerate() {
+            let (_, data_type, target) = find_property(schema, name).or_else(|e| at(e, *column))?;
+            let rest = &path[i + 1..];
+            let Some((next, next_column)) = rest.first() else {
+                break;
+            };
+            match data_type {
+                DataType::Object | DataType::ObjectList => {
+                    schema = find_embedded(self.schemas, target)?;
+                    if data_type == DataType::Object {
+                        steps.push(Step::Nested(name.clone()));
+                    } else {
+                        steps.push(Step::Elements(name.clone()));
+                    }
+                }
+                DataType::Json => {
+                    let json_path = rest.iter().map(|(segment, _)| segment.clone()).collect();
+                    return Ok((steps, name.clone(), json_path));
+                }
+                _ => {
+                    return error(*next_column, format!("{} has no property {}.", name, next));
+                }
+            }
+        }
+        let (name, _) = path.last().unwrap();
+        Ok((steps, name.clone(), vec![]))
+    }
+}
+
+/// Adds the column to query errors.
+fn at<T>(error: IsarError, column: usize) -> Result<T> {
+    match error {
+        IsarError::QueryError { message } => self::error(column, message),
+        error => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::PropertySchema;
+
+    fn schemas() -> Vec<IsarSchema> {
+        let person = IsarSchema::new(
+            "Person",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("age", DataType::Int, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+                PropertySchema::new("pets", DataType::ObjectList, Some("Pet")),
+                PropertySchema::new("data", DataType::Json, None),
+            ],
+            vec![],
+            false,
+        );
+        let address = IsarSchema::new(
+            "Address",
+            None,
+            vec![PropertySchema::new("city", DataType::String, None)],
+            vec![],
+            true,
+        );
+        let pet = IsarSchema::new(
+            "Pet",
+            None,
+            vec![PropertySchema::new("name", DataType::String, None)],
+            vec![],
+            true,
+        );
+        vec![person, address, pet]
+    }
+
+    fn parse(text: &str) -> Result<QueryDescription> {
+        let schemas = schemas();
+        parse_query(text, &schemas, &schemas[0])
+    }
+
+    fn condition(
+        property: &str,
+        condition_type: ConditionType,
+        values: Vec<Option<IsarValue>>,
+    ) -> FilterDescription {
+        FilterDescription::Condition(ConditionDescription {
+            property: property.to_string(),
+            path: vec![],
+            condition_type,
+            values,
+            normalization: StringNormalization::NONE,
+        })
+    }
+
+    fn string(value: &str) -> Option<IsarValue> {
+        Some(IsarValue::String(value.to_string()))
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let description = parse(
+            r#"age >= 18 AND name STARTSWITH "an" AND address.city = "Berlin"
+               ORDER BY age DESC LIMIT 10"#,
+        )
+        .unwrap();
+        assert_eq!(
+            description,
+            QueryDescription {
+                filter: Some(FilterDescription::And(vec![
+                    condition(
+                        "age",
+                        ConditionType::GreaterOrEqual,
+                        vec![Some(IsarValue::Integer(18))]
+                    ),
+                    condition("name", ConditionType::StringStartsWith, vec![string("an")]),
+                    FilterDescription::Nested {
+                        property: "address".to_string(),
+                        filter: Box::new(condition(
+                            "city",
+                            ConditionType::Equal,
+                            vec![string("Berlin")]
+                        )),
+                    },
+                ])),
+                sort: vec![SortDescription {
+                    property: "age".to_string(),
+                    order: Sort::Desc,
+                    case_sensitive: true,
+                }],
+                distinct: vec![],
+                offset: None,
+                limit: Some(10),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let description = parse(
+            "NOT (id IN (1, 2) OR age BETWEEN 1 AND 5) and name != 'a' \
+             and name is not null and age not between -1 and 2.0",
+        )
+        .unwrap();
+        assert_eq!(
+            description.filter,
+            Some(FilterDescription::And(vec![
+                FilterDescription::Not(Box::new(FilterDescription::Or(vec![
+                    condition(
+                        "id",
+                        ConditionType::In,
+                        vec![Some(IsarValue::Integer(1)), Some(IsarValue::Integer(2))]
+                    ),
+                    condition(
+                        "age",
+                        ConditionType::Between,
+                        vec![Some(IsarValue::Integer(1)), Some(IsarValue::Integer(5))]
+                    ),
+                ]))),
+                FilterDescription::Not(Box::new(condition(
+                    "name",
+                    ConditionType::Equal,
+                    vec![string("a")]
+                ))),
+                FilterDescription::Not(Box::new(condition("name", ConditionType::IsNull, vec![]))),
+                FilterDescription::Not(Box::new(condition(
+                    "age",
+                    ConditionType::Between,
+                    vec![Some(IsarValue::Integer(-1)), Some(IsarValue::Real(2.0))]
+                ))),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_paths_and_modifiers() {
+        let description = parse(
+            r#"pets.name = "Rex" IGNORE CASE AND data.`first tag`.0 CONTAINS "x"
+               DISTINCT BY name IGNORE CASE ORDER BY name, age ASC OFFSET 5"#,
+        )
+        .unwrap();
+        assert_eq!(
+            description,
+            QueryDescription {
+                filter: Some(FilterDescription::And(vec![
+                    FilterDescription::Elements {
+                        property: "pets".to_string(),
+                        quantifier: ListQuantifier::Any,
+                        filter: Box::new(FilterDescription::Condition(ConditionDescription {
+                            property: "name".to_string(),
+                            path: vec![],
+                            condition_type: ConditionType::Equal,
+                            values: vec![string("Rex")],
+                            normalization: StringNormalization::CASE_INSENSITIVE,
+                        })),
+                    },
+                    FilterDescription::Condition(ConditionDescription {
+                        property: "data".to_string(),
+                        path: vec!["first tag".to_string(), "0".to_string()],
+                        condition_type: ConditionType::StringContains,
+                        values: vec![string("x")],
+                        normalization: StringNormalization::NONE,
+                    }),
+                ])),
+                sort: vec![
+                    SortDescription {
+                        property: "name".to_string(),
+                        order: Sort::Asc,
+                        case_sensitive: true,
+                    },
+                    SortDescription {
+                        property: "age".to_string(),
+                        order: Sort::Asc,
+                        case_sensitive: true,
+                    },
+                ],
+                distinct: vec![DistinctDescription {
+                    property: "name".to_string(),
+                    case_sensitive: false,
+                }],
+                offset: Some(5),
+                limit: None,
+            }
+        );
+        assert_eq!(parse("LIMIT 1").unwrap().filter, None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let errors = [
+            (
+                "age >= ",
+                "Column 8: Expected a value but found end of query.",
+            ),
+            ("agee = 1", "Column 1: Unknown property agee of Person."),
+            (
+                "address.town = 1",
+                "Column 9: Unknown property town of Address.",
+            ),
+            ("name.first = 1", "Column 6: name has no property first."),
+            ("name = 'a", "Column 8: Unterminated string."),
+            (
+                "age = 1 AND",
+                "Column 12: Expected property but found end of query.",
+            ),
+            ("(age = 1", "Column 9: Expected ) but found end of query."),
+            ("age LIKE 1", "Column 5: Expected operator but found LIKE."),
+            (
+                "age = 1 LIMIT -1",
+                "Column 15: Expected a count but found -1.",
+            ),
+            (
+                "ORDER BY address.city",
+                "Column 18: Only top-level properties can be used here.",
+            ),
+            ("age = 1 ORDER age", "Column 15: Expected BY but found age."),
+            ("age = 1 age", "Column 9: Unexpected age."),
+            ("age # 1", "Column 5: Unexpected character #."),
+        ];
+        for (text, message) in errors {
+            assert_eq!(
+                parse(text),
+                Err(IsarError::QueryError {
+                    message: message.to_string()
+                }),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_values_are_checked_on_resolve() {
+        let schemas = schemas();
+        let filter = parse("age = \"a\"").unwrap().filter.unwrap();
+        assert_eq!(
+            filter.resolve(&schemas, &schemas[0]),
+            Err(IsarError::QueryError {
+                message: "Invalid value String(\"a\") for Int property age.".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use super::filter::ListQuantifier;
+use super::query_builder::Sort;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "backend", rename_all = "camelCase")]
+pub enum QueryPlan {
+    Native(NativeQueryPlan),
+    #[serde(rename = "sqlite")]
+    SQLite(SQLiteQueryPlan),
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct NativeQueryPlan {
+    pub collection: String,
+    pub scans: Vec<IndexScan>,
+    pub sort: Vec<SortPlan>,
+    #[serde(rename = "sortInMemory")]
+    pub sort_in_memory: bool,
+    pub distinct: DistinctStrategy,
+    pub filter: FilterPlan,
+}
+
+/// Node of the filter that is evaluated for every scanned object.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FilterPlan {
+    pub op: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantifier: Option<ListQuantifier>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<FilterPlan>,
+}
+
+impl FilterPlan {
+    pub fn new(op: &str, property: Option<String>, values: Vec<Value>) -> Self {
+        Self {
+            op: op.to_string(),
+            property,
+            values,
+            quantifier: None,
+            children: vec![],
+        }
+    }
+
+    pub fn with_quantifier(mut self, quantifier: ListQuantifier) -> Self {
+        self.quantifier = Some(quantifier);
+        self
+    }
+
+    pub fn with_children(mut self, children: Vec<FilterPlan>) -> Self {
+        self.children = children;
+        self
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum IndexScan {
+    Primary {
+        lower: i64,
+        upper: i64,
+    },
+    Secondary {
+        index: String,
+        lower: Vec<u8>,
+        upper: Vec<u8>,
+    },
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SortPlan {
+    pub property: String,
+    pub sort: Sort,
+    #[serde(rename = "caseSensitive")]
+    pub case_sensitive: bool,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "properties", rename_all = "camelCase")]
+pub enum DistinctStrategy {
+    None,
+    HashStreaming(Vec<String>),
+    HashAfterSort(Vec<String>),
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SQLiteQueryPlan {
+    pub sql: String,
+    pub rows: Vec<SQLitePlanRow>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SQLitePlanRow {
+    pub id: i32,
+    pub parent: i32,
+    pub detail: String,
+}
@@ -36,6 +36,8 @@ pub trait IsarReader {
 
     fn read_blob(&self, index: u32) -> Option<Cow<'_, [u8]>>;
 
+    fn read_uuid(&self, index: u32) -> Option<[u8; 16]>;
+
     fn read_object(&self, index: u32) -> Option<Self::ObjectReader<'_>>;
 
     fn read_list(&self, index: u32) -> Option<(Self::ListReader<'_>, u32)>;
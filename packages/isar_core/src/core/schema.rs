@@ -1,4 +1,5 @@
 use super::error::Result;
+use super::normalize::StringNormalization;
 use super::{data_type::DataType, error::IsarError};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -13,6 +14,33 @@ pub struct IsarSchema {
     pub properties: Vec<PropertySchema>,
     #[serde(default)]
     pub indexes: Vec<IndexSchema>,
+    /// String or UUID property that identifies objects instead of their id.
+    #[serde(rename = "keyName", default, skip_serializing_if = "Option::is_none")]
+    pub key_name: Option<String>,
+    /// Long property holding the timestamp (in microseconds since the epoch)
+    /// after which an object expires.
+    #[serde(
+        rename = "expiryName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expiry_name: Option<String>,
+    /// Long property holding the version of an object. It is incremented with
+    /// every write and writes that set it fail unless it matches the stored
+    /// version.
+    #[serde(
+        rename = "versionName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub version_name: Option<String>,
+    /// Whether deletions leave a tombstone that can be listed later.
+    #[serde(default)]
+    pub tombstones: bool,
+    /// Whether the last write of every field is stamped with a hybrid logical
+    /// clock timestamp so remote changes can be merged.
+    #[serde(rename = "fieldTimestamps", default)]
+    pub field_timestamps: bool,
     #[serde(default)]
     pub(crate) version: u8,
 }
@@ -31,10 +59,74 @@ impl IsarSchema {
             embedded,
             properties,
             indexes,
+            key_name: None,
+            expiry_name: None,
+            version_name: None,
+            tombstones: false,
+            field_timestamps: false,
             version: 0,
         }
     }
 
+    /// Makes `key_name` the string key of the collection and adds the unique
+    /// index that maps keys to ids.
+    pub fn with_key(mut self, key_name: &str) -> IsarSchema {
+        self.key_name = Some(key_name.to_string());
+        if self.key_index().is_none() {
+            self.indexes
+                .push(IndexSchema::new(key_name, vec![key_name], true, false));
+        }
+        self
+    }
+
+    pub fn key_index(&self) -> Option<&IndexSchema> {
+        let key_name = self.key_name.as_ref()?;
+        self.indexes.iter().find(|i| {
+            i.unique
+                && !i.hash
+                && !i.geo
+                && i.normalization.is_none()
+                && i.properties == [key_name.as_str()]
+        })
+    }
+
+    /// Makes `expiry_name` the expiry timestamp of the collection and adds the
+    /// index used to find expired objects.
+    pub fn with_expiry(mut self, expiry_name: &str) -> IsarSchema {
+        self.expiry_name = Some(expiry_name.to_string());
+        if self.expiry_index().is_none() {
+            self.indexes.push(IndexSchema::new(
+                expiry_name,
+                vec![expiry_name],
+                false,
+                false,
+            ));
+        }
+        self
+    }
+
+    pub fn expiry_index(&self) -> Option<&IndexSchema> {
+        let expiry_name = self.expiry_name.as_ref()?;
+        self.indexes
+            .iter()
+            .find(|i| !i.hash && !i.geo && i.properties == [expiry_name.as_str()])
+    }
+
+    pub fn with_version(mut self, version_name: &str) -> IsarSchema {
+        self.version_name = Some(version_name.to_string());
+        self
+    }
+
+    pub fn with_tombstones(mut self) -> IsarSchema {
+        self.tombstones = true;
+        self
+    }
+
+    pub fn with_field_timestamps(mut self) -> IsarSchema {
+        self.field_timestamps = true;
+        self
+    }
+
     pub fn from_json(json: &[u8]) -> Result<Vec<Self>> {
         if let Ok(collections) = serde_json::from_slice::<Vec<IsarSchema>>(json) {
             Ok(collections)
@@ -102,6 +194,8 @@ impl IsarSchema {
                 if index.unique != old_index.unique
                     || &index.properties != &old_index.properties
                     || index.hash != old_index.hash
+                    || index.geo != old_index.geo
+                    || index.normalization != old_index.normalization
                     || property_dropped
                 {
                     add_indexes.push(index);
@@ -158,6 +252,56 @@ impl IsarSchema {
             }
         }
 
+        if let Some(key_name) = &self.key_name {
+            let key_property = self
+                .properties
+                .iter()
+                .find(|p| p.name.as_ref() == Some(key_name));
+            if self.embedded {
+                return schema_error("Embedded objects must not have a key.");
+            } else if !key_property
+                .is_some_and(|p| p.data_type == DataType::String || p.data_type == DataType::Uuid)
+            {
+                return schema_error("The key must be a String or UUID property.");
+            } else if self.key_index().is_none() {
+                return schema_error("The key needs a unique index that is not hashed.");
+            }
+        }
+
+        if let Some(expiry_name) = &self.expiry_name {
+            let expiry_property = self
+                .properties
+                .iter()
+                .find(|p| p.name.as_ref() == Some(expiry_name));
+            if self.embedded {
+                return schema_error("Embedded objects must not expire.");
+            } else if !expiry_property.is_some_and(|p| p.data_type == DataType::Long) {
+                return schema_error("The expiry must be a Long property.");
+            } else if self.expiry_index().is_none() {
+                return schema_error("The expiry needs an index that is not hashed.");
+            }
+        }
+
+        if let Some(version_name) = &self.version_name {
+            let version_property = self
+                .properties
+                .iter()
+                .find(|p| p.name.as_ref() == Some(version_name));
+            if self.embedded {
+                return schema_error("Embedded objects must not have a version.");
+            } else if !version_property.is_some_and(|p| p.data_type == DataType::Long) {
+                return schema_error("The version must be a Long property.");
+            }
+        }
+
+        if self.embedded && self.tombstones {
+            return schema_error("Embedded objects must not have tombstones.");
+        }
+
+        if self.embedded && self.field_timestamps {
+            return schema_error("Embedded objects must not have field timestamps.");
+        }
+
         let unique_properties = self.properties.iter().unique_by(|p| &p.name);
         if unique_properties.count() != self.properties.len() {
             return schema_error("Duplicate property name")?;
@@ -173,6 +317,33 @@ impl IsarSchema {
                 return schema_error("At least one property needs to be added to a valid index");
             }
 
+            if index.geo {
+                let is_double = |name: &String| {
+                    self.properties
+                        .iter()
+                        .any(|p| p.name.as_ref() == Some(name) && p.data_type == DataType::Double)
+                };
+                if index.properties.len() != 2 || !index.properties.iter().all(is_double) {
+                    return schema_error(
+                        "Geo indexes need a latitude and a longitude Double property.",
+                    );
+                } else if index.unique || index.hash {
+                    return schema_error("Geo indexes cannot be unique or hashed.");
+                } else if !index.normalization.is_none() {
+                    return schema_error("Geo indexes cannot be normalized.");
+                }
+                continue;
+            }
+
+            let is_string = |name: &String| {
+                self.properties
+                    .iter()
+                    .any(|p| p.name.as_ref() == Some(name) && p.data_type == DataType::String)
+            };
+            if !index.normalization.is_none() && !index.properties.iter().any(is_string) {
+                return schema_error("Only indexes of String properties can be normalized.");
+            }
+
             for index_property in &index.properties {
                 let property = self
                     .properties
@@ -250,6 +421,13 @@ pub struct IndexSchema {
     pub properties: Vec<String>,
     pub unique: bool,
     pub hash: bool,
+    /// Indexes the geohash of a latitude and a longitude property.
+    #[serde(default)]
+    pub geo: bool,
+    /// Normalization of the indexed `String` values. Conditions with the same
+    /// normalization can use the index.
+    #[serde(default)]
+    pub normalization: StringNormalization,
 }
 
 impl IndexSchema {
@@ -259,8 +437,26 @@ impl IndexSchema {
             properties: properties.iter().map(|p| p.to_string()).collect(),
             unique,
             hash,
+            geo: false,
+            normalization: StringNormalization::NONE,
         }
     }
+
+    pub fn new_geo(name: &str, latitude: &str, longitude: &str) -> IndexSchema {
+        IndexSchema {
+            name: name.to_string(),
+            properties: vec![latitude.to_string(), longitude.to_string()],
+            unique: false,
+            hash: false,
+            geo: true,
+            normalization: StringNormalization::NONE,
+        }
+    }
+
+    pub fn with_normalization(mut self, normalization: StringNormalization) -> IndexSchema {
+        self.normalization = normalization;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -294,6 +490,22 @@ mod test {
         assert!(schema.verify(&[]).is_err());
     }
 
+    #[test]
+    fn test_verify_checks_index_normalization() {
+        let schema = |data_type| {
+            IsarSchema::new(
+                "test",
+                None,
+                vec![PropertySchema::new("value", data_type, None)],
+                vec![IndexSchema::new("value", vec!["value"], false, false)
+                    .with_normalization(StringNormalization::CASE_INSENSITIVE)],
+                false,
+            )
+        };
+        assert!(schema(DataType::String).verify(&[]).is_ok());
+        assert!(schema(DataType::Long).verify(&[]).is_err());
+    }
+
     #[test]
     fn test_verify_checks_property_name_is_unique() {
         let schema = IsarSchema::new(
@@ -564,4 +776,144 @@ mod test {
         );
         assert!(schema.verify(&[]).is_ok());
     }
+
+    #[test]
+    fn test_verify_checks_key() {
+        let properties = vec![
+            PropertySchema::new("key", DataType::String, None),
+            PropertySchema::new("count", DataType::Int, None),
+        ];
+        let schema = IsarSchema::new("test", Some("id"), properties.clone(), vec![], false);
+        let keyed = schema.clone().with_key("key");
+        assert!(keyed.verify(&[]).is_ok());
+        assert_eq!(
+            keyed.indexes,
+            vec![IndexSchema::new("key", vec!["key"], true, false)]
+        );
+        assert!(schema.clone().with_key("count").verify(&[]).is_err());
+        assert!(schema.clone().with_key("missing").verify(&[]).is_err());
+
+        let mut hashed = schema.clone();
+        hashed.key_name = Some("key".to_string());
+        hashed.indexes = vec![IndexSchema::new("key", vec!["key"], true, true)];
+        assert!(hashed.verify(&[]).is_err());
+
+        let json = r#"[{"name":"test","idName":"id","properties":[{"name":"key","type":"String"}],
+            "indexes":[{"name":"key","properties":["key"],"unique":true,"hash":false}],
+            "keyName":"key"}]"#;
+        let schemas = IsarSchema::from_json(json.as_bytes()).unwrap();
+        assert_eq!(schemas[0].key_name.as_deref(), Some("key"));
+        assert!(schemas[0].key_index().is_some());
+    }
+
+    #[test]
+    fn test_verify_checks_expiry() {
+        let properties = vec![
+            PropertySchema::new("expires", DataType::Long, None),
+            PropertySchema::new("count", DataType::Int, None),
+        ];
+        let schema = IsarSchema::new("test", Some("id"), properties, vec![], false);
+        let expiring = schema.clone().with_expiry("expires");
+        assert!(expiring.verify(&[]).is_ok());
+        assert_eq!(
+            expiring.indexes,
+            vec![IndexSchema::new("expires", vec!["expires"], false, false)]
+        );
+        assert!(schema.clone().with_expiry("count").verify(&[]).is_err());
+        assert!(schema.clone().with_expiry("missing").verify(&[]).is_err());
+
+        let mut hashed = schema.clone();
+        hashed.expiry_name = Some("expires".to_string());
+        hashed.indexes = vec![IndexSchema::new("expires", vec!["expires"], false, true)];
+        assert!(hashed.verify(&[]).is_err());
+    }
+
+    #[test]
+    fn test_verify_checks_version() {
+        let properties = vec![
+            PropertySchema::new("version", DataType::Long, None),
+            PropertySchema::new("count", DataType::Int, None),
+        ];
+        let schema = IsarSchema::new("test", Some("id"), properties.clone(), vec![], false);
+        assert!(schema.clone().with_version("version").verify(&[]).is_ok());
+        assert!(schema.clone().with_version("count").verify(&[]).is_err());
+        assert!(schema.with_version("missing").verify(&[]).is_err());
+
+        let embedded = IsarSchema::new("test", None, properties, vec![], true);
+        assert!(embedded.with_version("version").verify(&[]).is_err());
+
+        let json = r#"[{"name":"test","idName":"id","properties":[],"versionName":"v"}]"#;
+        let schemas = IsarSchema::from_json(json.as_bytes()).unwrap();
+        assert_eq!(schemas[0].version_name.as_deref(), Some("v"));
+    }
+
+    #[test]
+    fn test_verify_checks_tombstones() {
+        let properties = vec![PropertySchema::new("value", DataType::Int, None)];
+        let schema = IsarSchema::new("test", Some("id"), properties.clone(), vec![], false);
+        assert!(schema.with_tombstones().verify(&[]).is_ok());
+
+        let embedded = IsarSchema::new("test", None, properties, vec![], true);
+        assert!(embedded.with_tombstones().verify(&[]).is_err());
+
+        let json = r#"[{"name":"test","idName":"id","properties":[],"tombstones":true}]"#;
+        let schemas = IsarSchema::from_json(json.as_bytes()).unwrap();
+        assert!(schemas[0].tombstones);
+    }
+
+    #[test]
+    fn test_verify_checks_field_timestamps() {
+        let properties = vec![PropertySchema::new("value", DataType::Int, None)];
+        let schema = IsarSchema::new("test", Some("id"), properties.clone(), vec![], false);
+        assert!(schema.with_field_timestamps().verify(&[]).is_ok());
+
+        let embedded = IsarSchema::new("test", None, properties, vec![], true);
+        assert!(embedded.with_field_timestamps().verify(&[]).is_err());
+
+        let json = r#"[{"name":"test","idName":"id","properties":[],"fieldTimestamps":true}]"#;
+        let schemas = IsarSchema::from_json(json.as_bytes()).unwrap();
+        assert!(schemas[0].field_timestamps);
+    }
+
+    #[test]
+    fn test_verify_allows_uuid_in_any_index_position() {
+        let schema = IsarSchema::new(
+            "test",
+            None,
+            vec![
+                PropertySchema::new("prop1", DataType::Int, None),
+                PropertySchema::new("prop2", DataType::Uuid, None),
+            ],
+            vec![IndexSchema::new(
+                "index",
+                vec!["prop2", "prop1"],
+                false,
+                false,
+            )],
+            false,
+        );
+        assert!(schema.verify(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_verify_checks_geo_index_properties() {
+        let properties = vec![
+            PropertySchema::new("lat", DataType::Double, None),
+            PropertySchema::new("lng", DataType::Double, None),
+            PropertySchema::new("name", DataType::String, None),
+        ];
+
+        let index = IndexSchema::new_geo("location", "lat", "lng");
+        let schema = IsarSchema::new("test", None, properties.clone(), vec![index], false);
+        assert!(schema.verify(&[]).is_ok());
+
+        let index = IndexSchema::new_geo("location", "lat", "name");
+        let schema = IsarSchema::new("test", None, properties.clone(), vec![index], false);
+        assert!(schema.verify(&[]).is_err());
+
+        let mut index = IndexSchema::new_geo("location", "lat", "lng");
+        index.unique = true;
+        let schema = IsarSchema::new("test", None, properties, vec![index], false);
+        assert!(schema.verify(&[]).is_err());
+    }
 }
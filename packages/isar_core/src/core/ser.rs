@@ -1,3 +1,4 @@
+use super::uuid::format_uuid;
 use super::{data_type::DataType, reader::IsarReader};
 use serde::ser::{SerializeMap, SerializeSeq};
 use serde::Serialize;
@@ -29,101 +30,118 @@ impl<'a, R: IsarReader> Serialize for IsarObjectSerialize<'a, R> {
                 id_serialized = true;
             }
 
-            match data_type {
-                DataType::Bool => {
-                    match self.reader.read_bool(index as u32) {
-                        Some(value) => ser.serialize_entry(name, &value)?,
-                        None => ser.serialize_entry(name, &Value::Null)?,
-                    }
-                }
-                DataType::Byte => {
-                    if self.reader.is_null(index as u32) {
-                        ser.serialize_entry(name, &Value::Null)?;
-                    } else {
-                        let value = self.reader.read_byte(index as u32);
-                        ser.serialize_entry(name, &value)?;
-                    }
-                }
-                DataType::Int => {
-                    if self.reader.is_null(index as u32) {
-                        ser.serialize_entry(name, &Value::Null)?;
-                    } else {
-                        let value = self.reader.read_int(index as u32);
-                        ser.serialize_entry(name, &value)?;
-                    }
+            let property = IsarPropertySerialize::new(self.reader, index as u32, data_type);
+            ser.serialize_entry(name, &property)?;
+        }
+
+        if !id_serialized {
+            if let Some(id_name) = self.reader.id_name() {
+                ser.serialize_entry(id_name, &self.reader.read_id())?;
+            }
+        }
+
+        ser.end()
+    }
+}
+
+pub(super) struct IsarPropertySerialize<'a, R: IsarReader> {
+    reader: &'a R,
+    index: u32,
+    data_type: DataType,
+}
+
+impl<'a, R: IsarReader> IsarPropertySerialize<'a, R> {
+    pub fn new(reader: &'a R, index: u32, data_type: DataType) -> Self {
+        IsarPropertySerialize {
+            reader,
+            index,
+            data_type,
+        }
+    }
+}
+
+impl<'a, R: IsarReader> Serialize for IsarPropertySerialize<'a, R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let index = self.index;
+        match self.data_type {
+            DataType::Bool => match self.reader.read_bool(index) {
+                Some(value) => serializer.serialize_bool(value),
+                None => serializer.serialize_none(),
+            },
+            DataType::Byte => {
+                if self.reader.is_null(index) {
+                    serializer.serialize_none()
+                } else {
+                    serializer.serialize_u8(self.reader.read_byte(index))
                 }
-                DataType::Float => {
-                    if self.reader.is_null(index as u32) {
-                        ser.serialize_entry(name, &Value::Null)?;
-                    } else {
-                        let value = self.reader.read_float(index as u32);
-                        ser.serialize_entry(name, &value)?;
-                    }
+            }
+            DataType::Int => {
+                if self.reader.is_null(index) {
+                    serializer.serialize_none()
+                } else {
+                    serializer.serialize_i32(self.reader.read_int(index))
                 }
-                DataType::Long => {
-                    if self.reader.is_null(index as u32) {
-                        ser.serialize_entry(name, &Value::Null)?;
-                    } else {
-                        let value = self.reader.read_long(index as u32);
-                        ser.serialize_entry(name, &value)?;
-                    }
+            }
+            DataType::Float => {
+                if self.reader.is_null(index) {
+                    serializer.serialize_none()
+                } else {
+                    serializer.serialize_f32(self.reader.read_float(index))
                 }
-                DataType::Double => {
-                    if self.reader.is_null(index as u32) {
-                        ser.serialize_entry(name, &Value::Null)?;
-                    } else {
-                        let value = self.reader.read_double(index as u32);
-                        ser.serialize_entry(name, &value)?;
-                    }
+            }
+            DataType::Long => {
+                if self.reader.is_null(index) {
+                    serializer.serialize_none()
+                } else {
+                    serializer.serialize_i64(self.reader.read_long(index))
                 }
-                DataType::String => {
-                    if let Some(value) = self.reader.read_string(index as u32) {
-                        ser.serialize_entry(name, &value)?;
-                    } else {
-                        ser.serialize_entry(name, &Value::Null)?;
-                    }
+            }
+            DataType::Double => {
+                if self.reader.is_null(index) {
+                    serializer.serialize_none()
+                } else {
+                    serializer.serialize_f64(self.reader.read_double(index))
                 }
-                DataType::Json => {
-                    if let Some(value) = self.reader.read_string(index as u32) {
-                        match serde_json::from_str::<Value>(value) {
-                            Ok(parsed) => ser.serialize_entry(name, &parsed)?,
-                            Err(_) => ser.serialize_entry(name, &Value::Null)?,
-                        }
-                    } else {
-                        ser.serialize_entry(name, &Value::Null)?;
-                    }
+            }
+            DataType::String => match self.reader.read_string(index) {
+                Some(value) => serializer.serialize_str(value),
+                None => serializer.serialize_none(),
+            },
+            DataType::Json => {
+                if let Some(value) = self.reader.read_string(index) {
+                    match serde_json::from_str::<Value>(value) {
+                        Ok(parsed) => parsed.serialize(serializer),
+                        Err(_) => serializer.serialize_none(),
+                    }
+                } else {
+                    serializer.serialize_none()
                 }
-                DataType::Object => {
-                    if let Some(object) = self.reader.read_object(index as u32) {
-                        let reader = IsarObjectSerialize::new(&object);
-                        ser.serialize_entry(name, &reader)?;
-                    } else {
-                        ser.serialize_entry(name, &Value::Null)?;
-                    }
+            }
+            DataType::Uuid => match self.reader.read_uuid(index) {
+                Some(value) => serializer.serialize_str(&format_uuid(&value)),
+                None => serializer.serialize_none(),
+            },
+            DataType::Object => {
+                if let Some(object) = self.reader.read_object(index) {
+                    IsarObjectSerialize::new(&object).serialize(serializer)
+                } else {
+                    serializer.serialize_none()
                 }
-                _ => {
-                    let element_type = data_type.element_type();
-                    let list = self.reader.read_list(index as u32);
-                    match (element_type, list) {
-                        (Some(element_type), Some((list, length))) => {
-                            let reader = IsarListSerialize::new(element_type, &list, length);
-                            ser.serialize_entry(name, &reader)?;
-                        }
-                        _ => {
-                            ser.serialize_entry(name, &Value::Null)?;
-                        }
+            }
+            _ => {
+                let element_type = self.data_type.element_type();
+                let list = self.reader.read_list(index);
+                match (element_type, list) {
+                    (Some(element_type), Some((list, length))) => {
+                        IsarListSerialize::new(element_type, &list, length).serialize(serializer)
                     }
+                    _ => serializer.serialize_none(),
                 }
             }
         }
-
-        if !id_serialized {
-            if let Some(id_name) = self.reader.id_name() {
-                ser.serialize_entry(id_name, &self.reader.read_id())?;
-            }
-        }
-
-        ser.end()
     }
 }
 
@@ -0,0 +1,37 @@
+use super::insert::IsarInsert;
+use super::instance::IsarInstance;
+use super::schema::IsarSchema;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static NEXT_INSTANCE_ID: AtomicU32 = AtomicU32::new(1000);
+
+/// Opens an instance in a new temporary directory. Every instance gets its own
+/// id so tests can run in parallel.
+pub(crate) fn open_instance<I: IsarInstance>(name: &str, schemas: Vec<IsarSchema>) -> I::Instance {
+    let instance_id = NEXT_INSTANCE_ID.fetch_add(1, Ordering::Relaxed);
+    let dir =
+        std::env::temp_dir().join(format!("isar_test_{}_{}", std::process::id(), instance_id));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.to_str().unwrap();
+    I::open_instance(instance_id, name, dir, schemas, 10, None, None).unwrap()
+}
+
+/// Saves an object for each id in one transaction. `write` writes the
+/// properties of the object with the given id.
+pub(crate) fn insert_objects<'a, I: IsarInstance>(
+    instance: &'a I,
+    collection_index: u16,
+    ids: &[i64],
+    mut write: impl FnMut(&mut I::Insert<'a>, i64),
+) {
+    let txn = instance.begin_txn(true).unwrap();
+    let mut insert = instance
+        .insert(txn, collection_index, ids.len() as u32)
+        .unwrap();
+    for &id in ids {
+        write(&mut insert, id);
+        insert.save(id).unwrap();
+    }
+    instance.commit_txn(insert.finish().unwrap()).unwrap();
+}
@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+/// Record kept for a deleted object of a collection with tombstones.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tombstone {
+    pub id: i64,
+    /// Deletion time in microseconds since the epoch.
+    pub deleted_at: i64,
+    /// Increases with every deletion in the collection and is never reused.
+    pub sequence: i64,
+}
@@ -0,0 +1,334 @@
+use super::data_type::DataType;
+use super::error::{IsarError, Result};
+use super::uuid::parse_uuid;
+use super::value::IsarValue;
+use std::cmp::Ordering;
+
+/// Operation applied to a property by [update](crate::core::instance::IsarInstance::update)
+/// and [query_update](crate::core::instance::IsarInstance::query_update).
+///
+/// Operations are applied to the stored value so concurrent updates do not
+/// overwrite each other. A null number is treated as zero, a null string as
+/// empty and a null list as an empty list. Increments and decrements of
+/// integers saturate at the bounds of the property type. `Min` and `Max`
+/// compare strings by their UTF-8 bytes without any string normalization.
+#[derive(Clone, PartialEq, Debug)]
+pub enum UpdateOp {
+    Set(Option<IsarValue>),
+    Increment(IsarValue),
+    Decrement(IsarValue),
+    Min(IsarValue),
+    Max(IsarValue),
+    Concat(IsarValue),
+    Append(IsarValue),
+    /// Removes all elements equal to the value.
+    Remove(IsarValue),
+    /// Appends the value if the list does not contain it yet.
+    AddToSet(IsarValue),
+}
+
+impl UpdateOp {
+    pub fn is_list_op(&self) -> bool {
+        matches!(
+            self,
+            UpdateOp::Append(_) | UpdateOp::Remove(_) | UpdateOp::AddToSet(_)
+        )
+    }
+
+    /// Whether the operation can be applied to a property of `data_type`.
+    pub fn is_valid_for(&self, data_type: DataType) -> bool {
+        match self {
+            UpdateOp::Set(_) => true,
+            UpdateOp::Increment(value) | UpdateOp::Decrement(value) => {
+                // the amount is not stored so it may exceed the range of bytes
+                let amount_type = if data_type == DataType::Byte {
+                    DataType::Long
+                } else {
+                    data_type
+                };
+                is_numeric(data_type) && value_matches(value, amount_type)
+            }
+            UpdateOp::Min(value) | UpdateOp::Max(value) => {
+                (is_numeric(data_type) || data_type == DataType::String)
+                    && value_matches(value, data_type)
+            }
+            UpdateOp::Concat(value) => {
+                data_type == DataType::String && value_matches(value, data_type)
+            }
+            UpdateOp::Append(value) | UpdateOp::Remove(value) | UpdateOp::AddToSet(value) => {
+                match data_type.element_type() {
+                    Some(DataType::Object) | None => false,
+                    Some(element_type) => value_matches(value, element_type),
+                }
+            }
+        }
+    }
+
+    /// Returns the new value of a scalar property of `data_type` with the
+    /// `current` value.
+    pub fn apply(
+        &self,
+        current: Option<IsarValue>,
+        data_type: DataType,
+    ) -> Result<Option<IsarValue>> {
+        let value = match self {
+            UpdateOp::Set(value) => return Ok(value.clone()),
+            UpdateOp::Increment(value) | UpdateOp::Decrement(value) => {
+                let negate = matches!(self, UpdateOp::Decrement(_));
+                match (value, current) {
+                    (IsarValue::Integer(value), current) => {
+                        let (min, max) =
+                            integer_bounds(data_type).ok_or(IsarError::IllegalArgument {})?;
+                        let current = current.and_then(|c| c.i64()).unwrap_or(0) as i128;
+                        let result = if negate {
+                            current - *value as i128
+                        } else {
+                            current + *value as i128
+                        };
+                        IsarValue::Integer(result.clamp(min as i128, max as i128) as i64)
+                    }
+                    (IsarValue::Real(value), current) => {
+                        let current = current.and_then(|c| c.real()).unwrap_or(0.0);
+                        if negate {
+                            IsarValue::Real(current - value)
+                        } else {
+                            IsarValue::Real(current + value)
+                        }
+                    }
+                    _ => return Err(IsarError::IllegalArgument {}),
+                }
+            }
+            UpdateOp::Min(value) | UpdateOp::Max(value) => {
+                let Some(current) = current else {
+                    return Ok(Some(value.clone()));
+                };
+                let ordering = match (&current, value) {
+                    (IsarValue::Integer(a), IsarValue::Integer(b)) => a.cmp(b),
+                    (IsarValue::Real(a), IsarValue::Real(b)) => {
+                        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+                    }
+                    (IsarValue::String(a), IsarValue::String(b)) => a.cmp(b),
+                    _ => return Err(IsarError::IllegalArgument {}),
+                };
+                let keep_current = if matches!(self, UpdateOp::Min(_)) {
+                    ordering != Ordering::Greater
+                } else {
+                    ordering != Ordering::Less
+                };
+                if keep_current {
+                    current
+                } else {
+                    value.clone()
+                }
+            }
+            UpdateOp::Concat(IsarValue::String(value)) => {
+                let mut current = current
+                    .and_then(|c| c.string().map(|s| s.to_string()))
+                    .unwrap_or_default();
+                current.push_str(value);
+                IsarValue::String(current)
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        Ok(Some(value))
+    }
+
+    /// Applies a list operation to the elements of a list.
+    pub fn apply_list(&self, list: &mut Vec<Option<IsarValue>>) -> Result<()> {
+        match self {
+            UpdateOp::Append(value) => list.push(Some(value.clone())),
+            UpdateOp::Remove(value) => list.retain(|element| element.as_ref() != Some(value)),
+            UpdateOp::AddToSet(value) => {
+                if !list.iter().any(|element| element.as_ref() == Some(value)) {
+                    list.push(Some(value.clone()));
+                }
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        }
+        Ok(())
+    }
+}
+
+/// The smallest and largest value of an integer property. The minimum of
+/// ints and longs is reserved for null.
+pub(crate) fn integer_bounds(data_type: DataType) -> Option<(i64, i64)> {
+    match data_type {
+        DataType::Byte => Some((0, u8::MAX as i64)),
+        DataType::Int => Some((i32::MIN as i64 + 1, i32::MAX as i64)),
+        DataType::Long => Some((i64::MIN + 1, i64::MAX)),
+        _ => None,
+    }
+}
+
+fn is_numeric(data_type: DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Byte | DataType::Int | DataType::Long | DataType::Float | DataType::Double
+    )
+}
+
+fn value_matches(value: &IsarValue, data_type: DataType) -> bool {
+    match (value, data_type) {
+        (IsarValue::Integer(value), DataType::Byte) => (0..=u8::MAX as i64).contains(value),
+        (IsarValue::String(value), DataType::Uuid) => parse_uuid(value).is_some(),
+        _ => matches!(
+            (value, data_type),
+            (IsarValue::Bool(_), DataType::Bool)
+                | (IsarValue::Integer(_), DataType::Int | DataType::Long)
+                | (IsarValue::Real(_), DataType::Float | DataType::Double)
+                | (IsarValue::String(_), DataType::String | DataType::Json)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let int = |value| Some(IsarValue::Integer(value));
+        assert_eq!(
+            UpdateOp::Increment(IsarValue::Integer(2)).apply(int(3), DataType::Long),
+            Ok(int(5))
+        );
+        assert_eq!(
+            UpdateOp::Increment(IsarValue::Integer(2)).apply(None, DataType::Long),
+            Ok(int(2))
+        );
+        assert_eq!(
+            UpdateOp::Decrement(IsarValue::Integer(2)).apply(int(3), DataType::Long),
+            Ok(int(1))
+        );
+        assert_eq!(
+            UpdateOp::Decrement(IsarValue::Real(0.5))
+                .apply(Some(IsarValue::Real(2.0)), DataType::Double),
+            Ok(Some(IsarValue::Real(1.5)))
+        );
+        assert_eq!(
+            UpdateOp::Min(IsarValue::Integer(2)).apply(int(3), DataType::Long),
+            Ok(int(2))
+        );
+        assert_eq!(
+            UpdateOp::Min(IsarValue::Integer(4)).apply(int(3), DataType::Long),
+            Ok(int(3))
+        );
+        assert_eq!(
+            UpdateOp::Max(IsarValue::Integer(4)).apply(int(3), DataType::Long),
+            Ok(int(4))
+        );
+        assert_eq!(
+            UpdateOp::Max(IsarValue::Integer(4)).apply(None, DataType::Long),
+            Ok(int(4))
+        );
+        assert_eq!(
+            UpdateOp::Concat(IsarValue::String("b".to_string()))
+                .apply(Some(IsarValue::String("a".to_string())), DataType::String),
+            Ok(Some(IsarValue::String("ab".to_string())))
+        );
+        assert_eq!(
+            UpdateOp::Min(IsarValue::Integer(2)).apply(Some(IsarValue::Real(1.0)), DataType::Long),
+            Err(IsarError::IllegalArgument {})
+        );
+    }
+
+    #[test]
+    fn test_apply_saturates() {
+        let int = |value| Some(IsarValue::Integer(value));
+        let increment = |value| UpdateOp::Increment(IsarValue::Integer(value));
+        let decrement = |value| UpdateOp::Decrement(IsarValue::Integer(value));
+        assert_eq!(increment(10).apply(int(250), DataType::Byte), Ok(int(255)));
+        assert_eq!(decrement(10).apply(int(5), DataType::Byte), Ok(int(0)));
+        assert_eq!(
+            increment(1).apply(int(i32::MAX as i64), DataType::Int),
+            Ok(int(i32::MAX as i64))
+        );
+        assert_eq!(
+            decrement(i64::MAX).apply(int(i32::MIN as i64 + 1), DataType::Int),
+            Ok(int(i32::MIN as i64 + 1))
+        );
+        assert_eq!(
+            increment(i64::MAX).apply(int(i64::MAX), DataType::Long),
+            Ok(int(i64::MAX))
+        );
+        assert_eq!(
+            decrement(i64::MAX).apply(int(-5), DataType::Long),
+            Ok(int(i64::MIN + 1))
+        );
+        assert_eq!(
+            increment(1).apply(int(1), DataType::Double),
+            Err(IsarError::IllegalArgument {})
+        );
+    }
+
+    #[test]
+    fn test_apply_list() {
+        let mut list = vec![
+            Some(IsarValue::Integer(1)),
+            None,
+            Some(IsarValue::Integer(2)),
+            Some(IsarValue::Integer(1)),
+        ];
+        UpdateOp::Remove(IsarValue::Integer(1))
+            .apply_list(&mut list)
+            .unwrap();
+        assert_eq!(list, vec![None, Some(IsarValue::Integer(2))]);
+        UpdateOp::AddToSet(IsarValue::Integer(2))
+            .apply_list(&mut list)
+            .unwrap();
+        UpdateOp::AddToSet(IsarValue::Integer(3))
+            .apply_list(&mut list)
+            .unwrap();
+        UpdateOp::Append(IsarValue::Integer(3))
+            .apply_list(&mut list)
+            .unwrap();
+        assert_eq!(
+            list,
+            vec![
+                None,
+                Some(IsarValue::Integer(2)),
+                Some(IsarValue::Integer(3)),
+                Some(IsarValue::Integer(3))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_valid_for() {
+        let int = IsarValue::Integer(1);
+        assert!(UpdateOp::Increment(int.clone()).is_valid_for(DataType::Int));
+        assert!(!UpdateOp::Increment(int.clone()).is_valid_for(DataType::Double));
+        assert!(!UpdateOp::Increment(int.clone()).is_valid_for(DataType::LongList));
+        assert!(UpdateOp::Append(int.clone()).is_valid_for(DataType::LongList));
+        assert!(UpdateOp::Remove(int.clone()).is_valid_for(DataType::ByteList));
+        assert!(!UpdateOp::Append(int.clone()).is_valid_for(DataType::StringList));
+        assert!(!UpdateOp::Append(int).is_valid_for(DataType::ObjectList));
+        let string = IsarValue::String("a".to_string());
+        assert!(UpdateOp::Max(string.clone()).is_valid_for(DataType::String));
+        assert!(UpdateOp::Concat(string.clone()).is_valid_for(DataType::String));
+        assert!(!UpdateOp::Concat(string).is_valid_for(DataType::StringList));
+    }
+
+    #[test]
+    fn test_is_valid_for_bytes() {
+        let int = IsarValue::Integer;
+        assert!(UpdateOp::Max(int(255)).is_valid_for(DataType::Byte));
+        assert!(!UpdateOp::Max(int(300)).is_valid_for(DataType::Byte));
+        assert!(!UpdateOp::Min(int(-1)).is_valid_for(DataType::Byte));
+        assert!(UpdateOp::Append(int(0)).is_valid_for(DataType::ByteList));
+        assert!(!UpdateOp::Append(int(300)).is_valid_for(DataType::ByteList));
+        assert!(UpdateOp::Increment(int(300)).is_valid_for(DataType::Byte));
+        assert!(UpdateOp::Decrement(int(-1)).is_valid_for(DataType::Byte));
+    }
+
+    #[test]
+    fn test_value_matches_uuid() {
+        let uuid = IsarValue::String("0E58B3F4-0C4B-4E3C-9F64-2D6F3B6A1C7D".to_string());
+        assert!(value_matches(&uuid, DataType::Uuid));
+        assert!(!value_matches(
+            &IsarValue::String("a".to_string()),
+            DataType::Uuid
+        ));
+        assert!(!value_matches(&IsarValue::Integer(1), DataType::Uuid));
+    }
+}
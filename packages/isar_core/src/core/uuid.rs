@@ -0,0 +1,81 @@
+/// The nil UUID represents `null`.
+pub const NULL_UUID: [u8; 16] = [0; 16];
+
+/// Parses the canonical hyphenated form or 32 hex digits, case insensitive.
+pub fn parse_uuid(value: &str) -> Option<[u8; 16]> {
+    let bytes = value.as_bytes();
+    let digits: Vec<u8> = if bytes.len() == 36 {
+        for i in [8, 13, 18, 23] {
+            if bytes[i] != b'-' {
+                return None;
+            }
+        }
+        bytes.iter().copied().filter(|b| *b != b'-').collect()
+    } else {
+        bytes.to_vec()
+    };
+    if digits.len() != 32 {
+        return None;
+    }
+
+    let mut uuid = [0; 16];
+    for (i, pair) in digits.chunks_exact(2).enumerate() {
+        let high = (pair[0] as char).to_digit(16)?;
+        let low = (pair[1] as char).to_digit(16)?;
+        uuid[i] = (high << 4 | low) as u8;
+    }
+    Some(uuid)
+}
+
+/// Formats a UUID in the canonical lowercase hyphenated form.
+pub fn format_uuid(uuid: &[u8; 16]) -> String {
+    let mut value = String::with_capacity(36);
+    for (i, byte) in uuid.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            value.push('-');
+        }
+        value.push_str(&format!("{:02x}", byte));
+    }
+    value
+}
+
+/// Returns the canonical form of a UUID string or `None` if it is invalid.
+pub fn canonical_uuid(value: &str) -> Option<String> {
+    parse_uuid(value).map(|uuid| format_uuid(&uuid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: [u8; 16] = [
+        0x55, 0x0e, 0x84, 0x00, 0xe2, 0x9b, 0x41, 0xd4, 0xa7, 0x16, 0x44, 0x66, 0x55, 0x44, 0x00,
+        0x00,
+    ];
+
+    #[test]
+    fn test_parse_uuid() {
+        assert_eq!(
+            parse_uuid("550e8400-e29b-41d4-a716-446655440000"),
+            Some(UUID)
+        );
+        assert_eq!(
+            parse_uuid("550E8400-E29B-41D4-A716-446655440000"),
+            Some(UUID)
+        );
+        assert_eq!(parse_uuid("550e8400e29b41d4a716446655440000"), Some(UUID));
+        assert_eq!(parse_uuid("550e8400-e29b-41d4-a716-44665544000"), None);
+        assert_eq!(parse_uuid("550e8400-e29b-41d4-a716_446655440000"), None);
+        assert_eq!(parse_uuid("550e8400-e29b-41d4-a716-44665544000g"), None);
+        assert_eq!(parse_uuid(""), None);
+    }
+
+    #[test]
+    fn test_format_uuid() {
+        assert_eq!(format_uuid(&UUID), "550e8400-e29b-41d4-a716-446655440000");
+        assert_eq!(
+            canonical_uuid("550E8400E29B41D4A716446655440000").as_deref(),
+            Some("550e8400-e29b-41d4-a716-446655440000")
+        );
+    }
+}
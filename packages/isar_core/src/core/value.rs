@@ -1,9 +1,17 @@
-#[derive(PartialEq, Clone, Debug)]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(untagged)]
 pub enum IsarValue {
     Bool(bool),
     Integer(i64),
     Real(f64),
     String(String),
+    /// Elements of a list.
+    List(Vec<Option<IsarValue>>),
+    /// Property values of an embedded object by property index.
+    Object(BTreeMap<u16, Option<IsarValue>>),
 }
 
 impl IsarValue {
@@ -52,3 +60,52 @@ impl IsarValue {
 
 #[cfg(test)]
 impl Eq for IsarValue {}
+
+#[derive(Default)]
+pub(crate) struct IntegerSum {
+    sum: i64,
+    overflow: Option<f64>,
+}
+
+impl IntegerSum {
+    // Continues as f64 once the sum no longer fits into an i64.
+    pub fn add(&mut self, value: i64) {
+        if let Some(overflow) = &mut self.overflow {
+            *overflow += value as f64;
+        } else if let Some(sum) = self.sum.checked_add(value) {
+            self.sum = sum;
+        } else {
+            self.overflow = Some(self.sum as f64 + value as f64);
+        }
+    }
+
+    #[cfg(feature = "native")]
+    pub fn to_f64(&self) -> f64 {
+        self.overflow.unwrap_or(self.sum as f64)
+    }
+
+    pub fn to_value(&self) -> IsarValue {
+        if let Some(overflow) = self.overflow {
+            IsarValue::Real(overflow)
+        } else {
+            IsarValue::Integer(self.sum)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_sum_overflow() {
+        let mut sum = IntegerSum::default();
+        sum.add(i64::MAX - 1);
+        sum.add(1);
+        assert_eq!(sum.to_value(), IsarValue::Integer(i64::MAX));
+
+        sum.add(i64::MAX);
+        sum.add(-1);
+        assert_eq!(sum.to_value(), IsarValue::Real(i64::MAX as f64 * 2.0 - 1.0));
+    }
+}
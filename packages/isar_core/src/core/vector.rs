@@ -0,0 +1,158 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Distance function of a nearest neighbor query. Smaller distances are
+/// nearer for every metric.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum VectorMetric {
+    /// `1 - cos(a, b)` between 0 and 2.
+    Cosine,
+    /// Negated dot product so larger products are nearer.
+    DotProduct,
+    /// Euclidean distance.
+    L2,
+}
+
+impl VectorMetric {
+    /// Returns `None` if the dimensions differ or an element is null.
+    pub fn distance(&self, query: &[f64], vector: impl IntoIterator<Item = f64>) -> Option<f64> {
+        let mut dimensions = 0;
+        let mut dot = 0.0;
+        let mut query_norm = 0.0;
+        let mut vector_norm = 0.0;
+        let mut squared_distance = 0.0;
+        for value in vector {
+            let query_value = *query.get(dimensions)?;
+            if value.is_nan() {
+                return None;
+            }
+            dot += query_value * value;
+            query_norm += query_value * query_value;
+            vector_norm += value * value;
+            squared_distance += (query_value - value) * (query_value - value);
+            dimensions += 1;
+        }
+        if dimensions != query.len() {
+            return None;
+        }
+
+        let distance = match self {
+            VectorMetric::Cosine => {
+                if query_norm == 0.0 || vector_norm == 0.0 {
+                    return None;
+                }
+                1.0 - dot / (query_norm.sqrt() * vector_norm.sqrt())
+            }
+            VectorMetric::DotProduct => -dot,
+            VectorMetric::L2 => squared_distance.sqrt(),
+        };
+        Some(distance)
+    }
+}
+
+struct Neighbor {
+    id: i64,
+    distance: f64,
+}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Neighbor {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Neighbor {}
+
+/// Keeps the `k` nearest objects seen so far. The farthest one sits at the
+/// top of the heap so it can be replaced cheaply.
+pub(crate) struct NearestNeighbors {
+    k: usize,
+    heap: BinaryHeap<Neighbor>,
+}
+
+impl NearestNeighbors {
+    pub fn new(k: u32) -> Self {
+        Self {
+            k: k as usize,
+            heap: BinaryHeap::with_capacity((k as usize).min(1024)),
+        }
+    }
+
+    pub fn add(&mut self, id: i64, distance: f64) {
+        let neighbor = Neighbor { id, distance };
+        if self.heap.len() < self.k {
+            self.heap.push(neighbor);
+        } else if let Some(mut farthest) = self.heap.peek_mut() {
+            if neighbor < *farthest {
+                *farthest = neighbor;
+            }
+        }
+    }
+
+    /// Returns the ids and distances ordered from nearest to farthest.
+    pub fn into_sorted_vec(self) -> Vec<(i64, f64)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|neighbor| (neighbor.id, neighbor.distance))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        let query = [1.0, 0.0];
+        let cosine = VectorMetric::Cosine.distance(&query, [0.0, 2.0]).unwrap();
+        assert!((cosine - 1.0).abs() < 1e-9);
+        let cosine = VectorMetric::Cosine.distance(&query, [3.0, 0.0]).unwrap();
+        assert!(cosine.abs() < 1e-9);
+        assert_eq!(
+            VectorMetric::DotProduct.distance(&query, [3.0, 4.0]),
+            Some(-3.0)
+        );
+        assert_eq!(VectorMetric::L2.distance(&query, [4.0, 4.0]), Some(5.0));
+    }
+
+    #[test]
+    fn test_distance_invalid_vectors() {
+        let query = [1.0, 2.0];
+        assert_eq!(VectorMetric::L2.distance(&query, [1.0]), None);
+        assert_eq!(VectorMetric::L2.distance(&query, [1.0, 2.0, 3.0]), None);
+        assert_eq!(VectorMetric::L2.distance(&query, [1.0, f64::NAN]), None);
+        assert_eq!(VectorMetric::Cosine.distance(&query, [0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn test_nearest_neighbors() {
+        let mut neighbors = NearestNeighbors::new(3);
+        for (id, distance) in [(1, 0.5), (2, 0.1), (3, 0.9), (4, 0.3), (5, 0.1), (6, 0.7)] {
+            neighbors.add(id, distance);
+        }
+        assert_eq!(
+            neighbors.into_sorted_vec(),
+            vec![(2, 0.1), (5, 0.1), (4, 0.3)]
+        );
+
+        let mut neighbors = NearestNeighbors::new(0);
+        neighbors.add(1, 0.0);
+        assert!(neighbors.into_sorted_vec().is_empty());
+    }
+}
@@ -31,6 +31,9 @@ pub struct ChangeDetail {
     pub object_id: i64,
     pub field_changes: Vec<FieldChange>,
     pub full_document: String,
+    /// Whether the change was merged from another replica.
+    #[serde(default)]
+    pub remote: bool,
 }
 
 struct Watcher {
@@ -109,6 +112,7 @@ pub(crate) struct ChangeSet {
     changes: IntMap<Arc<Watcher>>,
     detailed_changes: Vec<ChangeDetail>,
     detailed_watchers: Vec<Arc<DetailedWatcher>>,
+    remote: bool,
 }
 
 impl ChangeSet {
@@ -117,9 +121,20 @@ impl ChangeSet {
             changes: IntMap::new(),
             detailed_changes: Vec::new(),
             detailed_watchers: Vec::new(),
+            remote: false,
         }
     }
 
+    /// Marks the detailed changes registered from now on as remote.
+    pub fn set_remote(&mut self, remote: bool) {
+        self.remote = remote;
+    }
+
+    /// Whether changes of another replica are currently applied.
+    pub fn is_remote(&self) -> bool {
+        self.remote
+    }
+
     #[allow(dead_code)]
     pub fn add_detailed_watcher(&mut self, watcher: Arc<DetailedWatcher>) {
         self.detailed_watchers.push(watcher);
@@ -130,7 +145,8 @@ impl ChangeSet {
         self.detailed_watchers.retain(|w| w.get_id() != watcher_id);
     }
 
-    pub fn register_detailed_change(&mut self, change: ChangeDetail) {
+    pub fn register_detailed_change(&mut self, mut change: ChangeDetail) {
+        change.remote = self.remote;
         self.detailed_changes.push(change);
     }
 
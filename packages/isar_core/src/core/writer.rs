@@ -1,4 +1,8 @@
 use super::data_type::DataType;
+use super::error::{IsarError, Result};
+use super::uuid::parse_uuid;
+use super::value::IsarValue;
+use itertools::Itertools;
 
 pub trait IsarWriter<'a> {
     type ObjectWriter: IsarWriter<'a>;
@@ -27,6 +31,8 @@ pub trait IsarWriter<'a> {
 
     fn write_byte_list(&mut self, index: u32, value: &[u8]);
 
+    fn write_uuid(&mut self, index: u32, value: &[u8; 16]);
+
     fn begin_object(&mut self, index: u32) -> Option<Self::ObjectWriter>;
 
     fn end_object(&mut self, writer: Self::ObjectWriter);
@@ -35,3 +41,79 @@ pub trait IsarWriter<'a> {
 
     fn end_list(&mut self, writer: Self::ListWriter);
 }
+
+/// Writes `value` to the property at `index` of `writer`. Lists and embedded
+/// objects are written with nested writers, unset object properties are null.
+pub fn write_value<'a>(
+    writer: &mut impl IsarWriter<'a>,
+    index: u32,
+    data_type: DataType,
+    value: Option<&IsarValue>,
+) -> Result<()> {
+    match (value, data_type) {
+        (None, _) => writer.write_null(index),
+        (Some(IsarValue::Bool(value)), DataType::Bool) => writer.write_bool(index, *value),
+        (Some(IsarValue::Integer(value)), DataType::Byte) => writer.write_byte(index, *value as u8),
+        (Some(IsarValue::Integer(value)), DataType::Int) => writer.write_int(index, *value as i32),
+        (Some(IsarValue::Integer(value)), DataType::Long) => writer.write_long(index, *value),
+        (Some(IsarValue::Real(value)), DataType::Float) => writer.write_float(index, *value as f32),
+        (Some(IsarValue::Real(value)), DataType::Double) => writer.write_double(index, *value),
+        (Some(IsarValue::String(value)), DataType::String | DataType::Json) => {
+            writer.write_string(index, value)
+        }
+        (Some(IsarValue::String(value)), DataType::Uuid) => {
+            let uuid = parse_uuid(value).ok_or(IsarError::IllegalArgument {})?;
+            writer.write_uuid(index, &uuid)
+        }
+        (Some(list @ IsarValue::List(_)), DataType::Json) => {
+            let json = serde_json::to_string(list).map_err(|e| IsarError::JsonError {
+                message: e.to_string(),
+            })?;
+            writer.write_string(index, &json)
+        }
+        (Some(IsarValue::List(list)), DataType::ByteList) => {
+            let bytes = list
+                .iter()
+                .map(|value| value.as_ref().and_then(|v| v.u8()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(IsarError::IllegalArgument {})?;
+            writer.write_byte_list(index, &bytes)
+        }
+        (Some(IsarValue::List(list)), _) if data_type.is_list() => {
+            let element_type = data_type.element_type().unwrap();
+            let mut list_writer = writer
+                .begin_list(index, list.len() as u32)
+                .ok_or(IsarError::IllegalArgument {})?;
+            let result = list.iter().enumerate().try_for_each(|(i, value)| {
+                write_value(&mut list_writer, i as u32, element_type, value.as_ref())
+            });
+            writer.end_list(list_writer);
+            result?
+        }
+        (Some(IsarValue::Object(values)), DataType::Object) => {
+            let mut object_writer = writer
+                .begin_object(index)
+                .ok_or(IsarError::IllegalArgument {})?;
+            let properties = object_writer.properties().map(|(_, t)| t).collect_vec();
+            let result = if values
+                .keys()
+                .any(|i| *i == 0 || *i as usize > properties.len())
+            {
+                Err(IsarError::IllegalArgument {})
+            } else {
+                properties
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(i, data_type)| {
+                        let index = i as u16 + 1;
+                        let value = values.get(&index).and_then(|v| v.as_ref());
+                        write_value(&mut object_writer, index as u32, *data_type, value)
+                    })
+            };
+            writer.end_object(object_writer);
+            result?
+        }
+        _ => return Err(IsarError::IllegalArgument {}),
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::core::uuid::NULL_UUID;
 use xxhash_rust::xxh3::xxh3_64;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -103,6 +104,15 @@ impl IndexKey {
         }
     }
 
+    pub fn add_uuid(&mut self, value: Option<[u8; 16]>) {
+        if let Some(value) = value {
+            self.bytes.extend_from_slice(&value);
+        } else {
+            self.contains_null = true;
+            self.bytes.extend_from_slice(&NULL_UUID);
+        }
+    }
+
     pub fn finish(mut self) -> (Vec<u8>, bool) {
         if self.bytes.len() > IndexKey::MAX_INDEX_SIZE {
             let hash = xxh3_64(&self.bytes);
@@ -330,6 +340,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_add_uuid() {
+        let mut index_key = IndexKey::min();
+        index_key.add_uuid(Some([1; 16]));
+        index_key.add_long(5);
+        let mut bytes = vec![1; 16];
+        bytes.extend_from_slice(&[128, 0, 0, 0, 0, 0, 0, 5]);
+        assert_eq!(index_key.finish(), (bytes, false));
+
+        let mut index_key = IndexKey::min();
+        index_key.add_uuid(None);
+        assert_eq!(index_key.finish(), (vec![0; 16], true));
+    }
+
     #[test]
     fn test_finish() {
         let long_str: String = (0..1030).map(|_| "I").collect::<String>();
@@ -1,5 +1,8 @@
 use super::{FALSE_BOOL, NULL_DOUBLE, NULL_FLOAT, NULL_INT, NULL_LONG, TRUE_BOOL};
 use crate::core::data_type::DataType;
+use crate::core::normalize::StringNormalization;
+use crate::core::uuid::{format_uuid, NULL_UUID};
+use crate::core::value::IsarValue;
 use byteorder::{ByteOrder, LittleEndian};
 use std::str::from_utf8_unchecked;
 use xxhash_rust::xxh3::xxh3_64_with_seed;
@@ -34,6 +37,7 @@ impl<'a> IsarDeserializer<'a> {
             DataType::Float => self.read_float(offset).is_nan(),
             DataType::Long => self.read_long(offset) == NULL_LONG,
             DataType::Double => self.read_double(offset).is_nan(),
+            DataType::Uuid => self.read_uuid(offset).is_none(),
             _ => self.get_offset_length(offset).is_none(),
         }
     }
@@ -96,6 +100,18 @@ impl<'a> IsarDeserializer<'a> {
         }
     }
 
+    #[inline]
+    pub fn read_uuid(&self, offset: u32) -> Option<[u8; 16]> {
+        if self.contains_offset(offset) {
+            let offset = offset as usize;
+            let uuid: [u8; 16] = self.bytes[offset..offset + 16].try_into().unwrap();
+            if uuid != NULL_UUID {
+                return Some(uuid);
+            }
+        }
+        None
+    }
+
     #[inline]
     fn get_offset(&self, offset: u32) -> Option<usize> {
         if self.contains_offset(offset) {
@@ -145,6 +161,24 @@ impl<'a> IsarDeserializer<'a> {
         Some((nested, length))
     }
 
+    pub fn read_value(&self, offset: u32, data_type: DataType) -> Option<IsarValue> {
+        if self.is_null(offset, data_type) {
+            return None;
+        }
+        let value = match data_type {
+            DataType::Bool => IsarValue::Bool(self.read_bool(offset)?),
+            DataType::Byte => IsarValue::Integer(self.read_byte(offset) as i64),
+            DataType::Int => IsarValue::Integer(self.read_int(offset) as i64),
+            DataType::Float => IsarValue::Real(self.read_float(offset) as f64),
+            DataType::Long => IsarValue::Integer(self.read_long(offset)),
+            DataType::Double => IsarValue::Real(self.read_double(offset)),
+            DataType::String => IsarValue::String(self.read_string(offset)?.to_string()),
+            DataType::Uuid => IsarValue::String(format_uuid(&self.read_uuid(offset)?)),
+            _ => return None,
+        };
+        Some(value)
+    }
+
     pub fn hash_property(
         &self,
         offset: u32,
@@ -183,14 +217,12 @@ impl<'a> IsarDeserializer<'a> {
                     xxh3_64_with_seed(&value.to_le_bytes(), seed)
                 }
             }
+            DataType::Uuid => xxh3_64_with_seed(&self.read_uuid(offset).unwrap_or(NULL_UUID), seed),
             DataType::String => {
                 if let Some(str) = self.read_string(offset) {
                     seed = xxh3_64_with_seed(&[1], seed);
-                    if case_sensitive {
-                        xxh3_64_with_seed(str.as_bytes(), seed)
-                    } else {
-                        xxh3_64_with_seed(str.to_lowercase().as_bytes(), seed)
-                    }
+                    let normalization = StringNormalization::from_case_sensitive(case_sensitive);
+                    xxh3_64_with_seed(normalization.normalize(str).as_bytes(), seed)
                 } else {
                     xxh3_64_with_seed(&[0], seed)
                 }
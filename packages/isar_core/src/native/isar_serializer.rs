@@ -1,5 +1,6 @@
 use super::{FALSE_BOOL, NULL_BOOL, NULL_DOUBLE, NULL_FLOAT, NULL_INT, NULL_LONG, TRUE_BOOL};
 use crate::core::data_type::DataType;
+use crate::core::uuid::NULL_UUID;
 use byteorder::{ByteOrder, LittleEndian};
 use std::cell::Cell;
 
@@ -29,12 +30,6 @@ impl IsarSerializer {
         self.buffer.get_mut()[offset..offset + bytes.len()].copy_from_slice(bytes);
     }
 
-    #[inline]
-    fn read_u24(&mut self, offset: u32) -> u32 {
-        let offset = (offset + self.offset) as usize;
-        LittleEndian::read_u24(&self.buffer.get_mut()[offset..]) as u32
-    }
-
     #[inline]
     fn write_u24(&mut self, offset: u32, value: u32) {
         let offset = (offset + self.offset) as usize;
@@ -71,6 +66,7 @@ impl IsarSerializer {
             DataType::Float => self.write_float(offset, NULL_FLOAT),
             DataType::Long => self.write_long(offset, NULL_LONG),
             DataType::Double => self.write_double(offset, NULL_DOUBLE),
+            DataType::Uuid => self.write_uuid(offset, &NULL_UUID),
             _ => self.write_u24_static_checked(offset, 0),
         }
     }
@@ -109,6 +105,11 @@ impl IsarSerializer {
         self.write_static_checked(offset, &value.to_le_bytes());
     }
 
+    #[inline]
+    pub fn write_uuid(&mut self, offset: u32, value: &[u8; 16]) {
+        self.write_static_checked(offset, value);
+    }
+
     pub fn write_dynamic(&mut self, offset: u32, value: &[u8]) {
         let buffer_len = self.buffer.get_mut().len() as u32;
         let dynamic_offset = buffer_len - self.offset;
@@ -121,20 +122,6 @@ impl IsarSerializer {
         self.write(dynamic_offset + 3, value);
     }
 
-    pub fn update_dynamic(&mut self, offset: u32, value: &[u8]) {
-        let existing_dynamic_offset = self.read_u24(offset);
-        if existing_dynamic_offset != 0 {
-            let existing_dynamic_len = self.read_u24(existing_dynamic_offset);
-            if existing_dynamic_len >= value.len() as u32 {
-                self.write_u24(existing_dynamic_offset, value.len() as u32);
-                self.write(existing_dynamic_offset + 3, value);
-                return;
-            }
-        }
-
-        self.write_dynamic(offset, value);
-    }
-
     pub fn begin_nested(&mut self, offset: u32, static_size: u32) -> Self {
         let nested_offset = self.buffer.get_mut().len() as u32;
         self.write_u24_static_checked(offset, nested_offset - self.offset);
@@ -230,6 +217,14 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_write_single_null_uuid() {
+            let mut serializer = IsarSerializer::new(Vec::new(), 0, 16);
+            serializer.write_uuid(0, &[1; 16]);
+            serializer.write_null(0, DataType::Uuid);
+            assert_eq!(serializer.finish(), concat!([16, 0, 0], [0; 16]));
+        }
+
         #[test]
         fn test_write_single_null_dynamic() {
             for dynamic_data_type in [
@@ -323,6 +318,14 @@ mod tests {
             }
         }
 
+        #[test]
+        fn test_write_single_uuid() {
+            let uuid: [u8; 16] = core::array::from_fn(|i| i as u8);
+            let mut serializer = IsarSerializer::new(Vec::new(), 0, 16);
+            serializer.write_uuid(0, &uuid);
+            assert_eq!(serializer.finish(), concat!([16, 0, 0], uuid));
+        }
+
         #[test]
         fn test_write_single_dynamic() {
             let mut serializer = IsarSerializer::new(Vec::new(), 0, 3);
@@ -11,6 +11,8 @@ pub mod native_instance;
 mod native_open;
 mod native_query_builder;
 mod native_reader;
+mod native_timestamps;
+mod native_tombstones;
 mod native_txn;
 mod native_verify;
 mod native_writer;
@@ -1,15 +1,25 @@
 use super::isar_deserializer::IsarDeserializer;
 use super::isar_serializer::IsarSerializer;
+use super::mdbx::cursor_iterator::CursorIterator;
 use super::mdbx::db::Db;
 use super::native_index::NativeIndex;
+use super::native_timestamps::NativeTimestamps;
+use super::native_tombstones::NativeTombstones;
 use super::native_txn::{NativeTxn, TxnCursor};
+use super::native_writer::{NativeObjectWriter, WriterImpl};
 use super::query::NativeQuery;
-use super::{BytesToId, IdToBytes};
+use super::{BytesToId, IdToBytes, NULL_LONG};
 use crate::core::change_detector::ChangeDetector;
+use crate::core::convert::copy_value;
 use crate::core::data_type::DataType;
 use crate::core::error::{IsarError, Result};
+use crate::core::expiry::now_micros;
+use crate::core::update::UpdateOp;
+use crate::core::uuid::format_uuid;
 use crate::core::value::IsarValue;
 use crate::core::watcher::{ChangeSet, CollectionWatchers};
+use crate::core::writer::write_value;
+use itertools::Itertools;
 use super::native_reader::NativeReader;
 use std::sync::atomic::{self, AtomicI64};
 use std::sync::Arc;
@@ -37,6 +47,14 @@ pub(crate) struct NativeCollection {
     pub id_name: Option<String>,
     pub properties: Vec<(String, NativeProperty)>,
     pub indexes: Vec<NativeIndex>,
+    /// Position of the unique index of the string key in `indexes`.
+    pub key_index: Option<usize>,
+    /// Position of the index of the expiry timestamp in `indexes`.
+    pub expiry_index: Option<usize>,
+    /// Index of the version property.
+    pub version_property: Option<u16>,
+    pub tombstones: Option<NativeTombstones>,
+    pub timestamps: Option<NativeTimestamps>,
     pub static_size: u32,
     pub watchers: Arc<CollectionWatchers<NativeQuery>>,
     auto_increment: AtomicI64,
@@ -44,12 +62,18 @@ pub(crate) struct NativeCollection {
 }
 
 impl NativeCollection {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         collection_index: u16,
         name: &str,
         id_name: Option<&str>,
         properties: Vec<(String, NativeProperty)>,
         indexes: Vec<NativeIndex>,
+        key_index: Option<usize>,
+        expiry_index: Option<usize>,
+        version_property: Option<u16>,
+        tombstones: Option<NativeTombstones>,
+        timestamps: Option<NativeTimestamps>,
         db: Option<Db>,
     ) -> Self {
         let static_size = properties
@@ -62,6 +86,11 @@ impl NativeCollection {
             id_name: id_name.map(|s| s.to_string()),
             properties,
             indexes,
+            key_index,
+            expiry_index,
+            version_property,
+            tombstones,
+            timestamps,
             static_size,
             watchers: CollectionWatchers::new(),
             auto_increment: AtomicI64::new(1),
@@ -92,7 +121,11 @@ impl NativeCollection {
         self.auto_increment.fetch_add(1, atomic::Ordering::AcqRel)
     }
 
-    fn update_auto_increment(&self, id: i64) {
+    pub fn next_auto_increment(&self) -> i64 {
+        self.auto_increment.load(atomic::Ordering::Acquire)
+    }
+
+    pub fn update_auto_increment(&self, id: i64) {
         self.auto_increment
             .fetch_max(id + 1, atomic::Ordering::AcqRel);
     }
@@ -108,6 +141,105 @@ impl NativeCollection {
         }
     }
 
+    /// Returns the string key of an object if the collection has one.
+    fn read_key(&self, object: IsarDeserializer) -> Option<String> {
+        let property = self.indexes.get(self.key_index?)?.properties[0];
+        match property.data_type {
+            DataType::String => object.read_string(property.offset).map(|s| s.to_string()),
+            DataType::Uuid => object.read_uuid(property.offset).map(|v| format_uuid(&v)),
+            _ => None,
+        }
+    }
+
+    pub fn get_id_by_key(&self, txn: &NativeTxn, key: &str) -> Result<Option<i64>> {
+        let index = self
+            .key_index
+            .and_then(|i| self.indexes.get(i))
+            .ok_or(IsarError::UnsupportedOperation {})?;
+        if let Some(key) = index.create_value_key(Some(&IsarValue::String(key.to_string()))) {
+            index.get_id(txn, &key)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the id of the object with the same key or a new id if the key
+    /// is unknown.
+    pub fn get_key_id(&self, txn: &NativeTxn, object: IsarDeserializer) -> Result<i64> {
+        let key = self
+            .read_key(object)
+            .ok_or_else(|| IsarError::KeyViolation {
+                message: "Missing key.".to_string(),
+            })?;
+        if let Some(id) = self.get_id_by_key(txn, &key)? {
+            Ok(id)
+        } else {
+            Ok(self.auto_increment())
+        }
+    }
+
+    fn verify_key(&self, txn: &NativeTxn, id: i64, object: IsarDeserializer) -> Result<()> {
+        let key = self
+            .read_key(object)
+            .ok_or_else(|| IsarError::KeyViolation {
+                message: "Missing key.".to_string(),
+            })?;
+        match self.get_id_by_key(txn, &key)? {
+            Some(key_id) if key_id != id => Err(IsarError::KeyViolation {
+                message: format!("Key '{}' already belongs to object {}.", key, key_id),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn expiry_property(&self) -> Option<&NativeProperty> {
+        let index = self.indexes.get(self.expiry_index?)?;
+        Some(&index.properties[0])
+    }
+
+    pub fn is_expired(&self, object: IsarDeserializer, now: i64) -> bool {
+        self.expiry_property().is_some_and(|property| {
+            let expiry = object.read_long(property.offset);
+            expiry != NULL_LONG && expiry <= now
+        })
+    }
+
+    /// Iterates the expiry index entries of objects that expired at `now`.
+    fn iter_expired<'a>(
+        &self,
+        txn: &'a NativeTxn,
+        now: i64,
+    ) -> Result<Option<CursorIterator<'a, TxnCursor<'a>>>> {
+        let Some(index) = self.expiry_index.and_then(|i| self.indexes.get(i)) else {
+            return Ok(None);
+        };
+        let (lower, upper) = index.create_long_keys(NULL_LONG + 1, now);
+        Ok(Some(index.iter_between(txn, lower, upper)?))
+    }
+
+    /// Deletes up to `limit` objects that expired at `now`, oldest first.
+    pub fn purge_expired<'a>(
+        &self,
+        txn: &'a NativeTxn,
+        change_set: &mut ChangeSet,
+        cursor: &mut TxnCursor<'a>,
+        now: i64,
+        limit: u32,
+        all_collections: &[NativeCollection],
+    ) -> Result<u32> {
+        let Some(expired) = self.iter_expired(txn, now)? else {
+            return Ok(0);
+        };
+        let ids = expired
+            .take(limit as usize)
+            .map(|(_, id_bytes)| id_bytes.to_id())
+            .collect_vec();
+        for id in &ids {
+            self.delete(txn, change_set, cursor, *id, all_collections)?;
+        }
+        Ok(ids.len() as u32)
+    }
+
     pub fn get_size(&self, txn: &NativeTxn, include_indexes: bool) -> Result<u64> {
         if let Some(db) = self.db {
             let mut size = txn.stat(db)?.1;
@@ -130,15 +262,55 @@ impl NativeCollection {
         }
     }
 
+    /// Verifies that the version of the object matches the stored version
+    /// unless it is null and increments it.
+    fn increment_version(
+        &self,
+        cursor: &mut TxnCursor,
+        id: i64,
+        bytes: &mut Vec<u8>,
+    ) -> Result<()> {
+        let Some(property) = self.version_property.and_then(|i| self.get_property(i)) else {
+            return Ok(());
+        };
+        let stored = cursor.move_to(&id.to_id_bytes())?.map_or(NULL_LONG, |(_, object)| {
+            IsarDeserializer::from_bytes(object).read_long(property.offset)
+        });
+        let stored = if stored != NULL_LONG { stored } else { 0 };
+        let version = IsarDeserializer::from_bytes(bytes).read_long(property.offset);
+        if version != NULL_LONG && version != stored {
+            return Err(IsarError::VersionConflict {});
+        }
+
+        let mut object = IsarSerializer::new(std::mem::take(bytes), 0, self.static_size);
+        object.write_long(property.offset, stored + 1);
+        *bytes = object.finish();
+        Ok(())
+    }
+
     pub fn put<'a>(
         &self,
         txn: &'a NativeTxn,
         change_set: &mut ChangeSet,
         cursor: &mut TxnCursor<'a>,
         id: i64,
-        bytes: &[u8],
+        bytes: &mut Vec<u8>,
+        keep_version: bool,
         all_collections: &[NativeCollection],
     ) -> Result<()> {
+        if !keep_version {
+            self.increment_version(cursor, id, bytes)?;
+        }
+        let bytes = bytes.as_slice();
+        if self.expiry_index.is_some() {
+            txn.mark_expiring(self.collection_index);
+        }
+        if self.key_index.is_some() {
+            self.verify_key(txn, id, IsarDeserializer::from_bytes(bytes))?;
+        }
+        if let Some(tombstones) = &self.tombstones {
+            tombstones.remove(txn, id)?;
+        }
         let id_bytes = id.to_id_bytes();
 
         // For detailed watchers, we need to capture both old and new objects
@@ -243,6 +415,12 @@ impl NativeCollection {
             }
 
             cursor.delete_current()?;
+            if let Some(tombstones) = &self.tombstones {
+                tombstones.put(txn, id, now_micros())?;
+            }
+            if let Some(timestamps) = &self.timestamps {
+                timestamps.remove(txn, id)?;
+            }
             Ok(true)
         } else {
             Ok(false)
@@ -255,20 +433,17 @@ impl NativeCollection {
         change_set: &mut ChangeSet,
         cursor: &mut TxnCursor<'a>,
         id: i64,
-        updates: &[(u16, Option<IsarValue>)],
+        updates: &[(u16, UpdateOp)],
         all_collections: &[NativeCollection],
     ) -> Result<bool> {
         if let Some((_, old_object)) = cursor.move_to(&id.to_id_bytes())? {
-            let mut buffer = txn.take_buffer();
-            buffer.extend_from_slice(&old_object);
-            let mut new_object = IsarSerializer::new(buffer, 0, self.static_size);
-
-            for (property_index, value) in updates {
-                self.write_value(&mut new_object, *property_index as u16, value.as_ref())?;
-            }
-
-            let buffer = new_object.finish();
-            self.put(txn, change_set, cursor, id, &buffer, all_collections)?;
+            let old_object = IsarDeserializer::from_bytes(&old_object);
+            let buffer = txn.take_buffer();
+            let mut buffer =
+                self.write_update(id, old_object, buffer, updates, all_collections)?;
+            // changes of other replicas already carry their version
+            let keep_version = change_set.is_remote();
+            self.put(txn, change_set, cursor, id, &mut buffer, keep_version, all_collections)?;
             txn.put_buffer(buffer);
 
             Ok(true)
@@ -277,43 +452,87 @@ impl NativeCollection {
         }
     }
 
-    fn write_value(
+    /// Writes `old_object` with `updates` applied to `buffer`. The object is
+    /// serialized again so replaced values do not leave unused bytes behind.
+    fn write_update(
         &self,
-        object: &mut IsarSerializer,
+        id: i64,
+        old_object: IsarDeserializer,
+        buffer: Vec<u8>,
+        updates: &[(u16, UpdateOp)],
+        all_collections: &[NativeCollection],
+    ) -> Result<Vec<u8>> {
+        let mut values = vec![None; self.properties.len()];
+        for (property_index, op) in updates {
+            self.apply_update(old_object, &mut values, *property_index, op)?;
+        }
+
+        let reader = NativeReader::new(id, old_object, self, all_collections);
+        let new_object = IsarSerializer::new(buffer, 0, self.static_size);
+        let mut writer = NativeObjectWriter::new(self, all_collections, new_object);
+        for ((_, p), (index, value)) in self.properties.iter().zip((1u32..).zip(values)) {
+            match value {
+                Some(value) => write_value(&mut writer, index, p.data_type, value.as_ref())?,
+                None => copy_value(&reader, index, &mut writer, index, p.data_type)?,
+            }
+        }
+        Ok(writer.get_serializer().finish())
+    }
+
+    /// Applies `op` to the value of the property and stores the result in
+    /// `values`. Properties without a value yet start from `old_object`.
+    fn apply_update(
+        &self,
+        old_object: IsarDeserializer,
+        values: &mut [Option<Option<IsarValue>>],
         property_index: u16,
-        value: Option<&IsarValue>,
+        op: &UpdateOp,
     ) -> Result<()> {
-        if let Some(p) = self.get_property(property_index) {
-            match (value, p.data_type) {
-                (None, _) => object.write_null(p.offset, p.data_type),
-                (Some(IsarValue::Bool(value)), DataType::Bool) => {
-                    object.write_bool(p.offset, *value)
-                }
-                (Some(IsarValue::Integer(value)), DataType::Byte) => {
-                    object.write_byte(p.offset, *value as u8)
-                }
-                (Some(IsarValue::Integer(value)), DataType::Int) => {
-                    object.write_int(p.offset, *value as i32)
-                }
-                (Some(IsarValue::Integer(value)), DataType::Long) => {
-                    object.write_long(p.offset, *value)
-                }
-                (Some(IsarValue::Real(value)), DataType::Float) => {
-                    object.write_float(p.offset, *value as f32)
-                }
-                (Some(IsarValue::Real(value)), DataType::Double) => {
-                    object.write_double(p.offset, *value)
-                }
-                (Some(IsarValue::String(value)), DataType::String)
-                | (Some(IsarValue::String(value)), DataType::Json) => {
-                    object.update_dynamic(p.offset, value.as_bytes())
-                }
-                _ => return Err(IsarError::IllegalArgument {}),
-            }
-            Ok(())
-        } else {
+        let p = self
+            .get_property(property_index)
+            .ok_or(IsarError::IllegalArgument {})?;
+        if self.version_property == Some(property_index) && !matches!(op, UpdateOp::Set(_)) {
+            // the version is managed by isar and can only be set to the
+            // expected stored version
+            return Err(IsarError::IllegalArgument {});
+        }
+        let value = &mut values[property_index as usize - 1];
+        if let UpdateOp::Set(new_value) = op {
+            *value = Some(new_value.clone());
+            return Ok(());
+        } else if !op.is_valid_for(p.data_type) {
             return Err(IsarError::IllegalArgument {});
         }
+
+        let current = match value.take() {
+            Some(current) => current,
+            None => Self::read_update_value(old_object, p),
+        };
+        let new_value = if p.data_type.element_type().is_some() {
+            let mut list = match current {
+                Some(IsarValue::List(list)) => list,
+                _ => vec![],
+            };
+            op.apply_list(&mut list)?;
+            Some(IsarValue::List(list))
+        } else {
+            op.apply(current, p.data_type)?
+        };
+        *value = Some(new_value);
+        Ok(())
+    }
+
+    fn read_update_value(object: IsarDeserializer, p: &NativeProperty) -> Option<IsarValue> {
+        if let Some(element_type) = p.data_type.element_type() {
+            let (elements, length) = object.read_list(p.offset, element_type)?;
+            let size = element_type.static_size() as u32;
+            let list = (0..length)
+                .map(|i| elements.read_value(i * size, element_type))
+                .collect();
+            Some(IsarValue::List(list))
+        } else {
+            object.read_value(p.offset, p.data_type)
+        }
     }
 
     pub fn clear(&self, txn: &NativeTxn, all_collections: &[NativeCollection]) -> Result<()> {
@@ -346,10 +565,25 @@ impl NativeCollection {
             }
         }
         
+        if let Some(tombstones) = &self.tombstones {
+            let now = now_micros();
+            let ids = self
+                .get_cursor(txn)?
+                .iter()?
+                .map(|(key, _)| key.to_id())
+                .collect_vec();
+            for id in ids {
+                tombstones.put(txn, id, now)?;
+            }
+        }
+
         txn.clear_db(db)?;
         for index in &self.indexes {
             index.clear(txn)?;
         }
+        if let Some(timestamps) = &self.timestamps {
+            timestamps.clear(txn)?;
+        }
         Ok(())
     }
 }
@@ -364,7 +598,415 @@ impl DataType {
             DataType::Bool | DataType::Byte => 1,
             DataType::Int | DataType::Float => 4,
             DataType::Long | DataType::Double => 8,
+            DataType::Uuid => 16,
             _ => 3,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cursor::IsarCursor;
+    use crate::core::expiry::set_test_now;
+    use crate::core::insert::IsarInsert;
+    use crate::core::instance::{Aggregation, IsarInstance};
+    use crate::core::merge::{now_millis, FieldUpdate, HlcTimestamp, MergeResult, ObjectChange};
+    use crate::core::query_builder::IsarQueryBuilder;
+    use crate::core::reader::IsarReader;
+    use crate::core::schema::{IsarSchema, PropertySchema};
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::core::writer::IsarWriter;
+    use crate::native::native_instance::NativeInstance;
+
+    fn collection() -> NativeCollection {
+        let properties = [
+            DataType::Long,
+            DataType::String,
+            DataType::LongList,
+            DataType::StringList,
+            DataType::Double,
+        ];
+        let mut offset = 0;
+        let properties = properties
+            .into_iter()
+            .map(|data_type| {
+                let property = NativeProperty::new(data_type, offset, None);
+                offset += data_type.static_size() as u32;
+                ("".to_string(), property)
+            })
+            .collect();
+        NativeCollection::new(
+            0,
+            "",
+            None,
+            properties,
+            vec![],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn update(
+        collection: &NativeCollection,
+        object: &[u8],
+        updates: &[(u16, UpdateOp)],
+    ) -> Result<Vec<u8>> {
+        let old_object = IsarDeserializer::from_bytes(object);
+        collection.write_update(1, old_object, vec![], updates, &[])
+    }
+
+    fn read_list(
+        collection: &NativeCollection,
+        object: &[u8],
+        property_index: u16,
+    ) -> Vec<Option<IsarValue>> {
+        let property = collection.get_property(property_index).unwrap();
+        let element_type = property.data_type.element_type().unwrap();
+        let object = IsarDeserializer::from_bytes(object);
+        let (list, length) = object.read_list(property.offset, element_type).unwrap();
+        (0..length)
+            .map(|i| list.read_value(i * element_type.static_size() as u32, element_type))
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let collection = collection();
+        let mut serializer = IsarSerializer::new(vec![], 0, collection.static_size);
+        serializer.write_long(0, 5);
+        serializer.write_dynamic(8, b"a");
+        let mut list = serializer.begin_nested(11, 16);
+        list.write_long(0, 1);
+        list.write_long(8, 2);
+        serializer.end_nested(list);
+        serializer.write_null(14, DataType::StringList);
+        serializer.write_null(17, DataType::Double);
+        let object = serializer.finish();
+
+        let object = update(
+            &collection,
+            &object,
+            &[
+                (1, UpdateOp::Increment(IsarValue::Integer(3))),
+                (2, UpdateOp::Concat(IsarValue::String("bc".to_string()))),
+                (3, UpdateOp::AddToSet(IsarValue::Integer(3))),
+                (4, UpdateOp::Append(IsarValue::String("x".to_string()))),
+                (5, UpdateOp::Decrement(IsarValue::Real(1.5))),
+            ],
+        )
+        .unwrap();
+        let reader = IsarDeserializer::from_bytes(&object);
+        assert_eq!(reader.read_long(0), 8);
+        assert_eq!(reader.read_string(8), Some("abc"));
+        assert_eq!(reader.read_double(17), -1.5);
+        assert_eq!(
+            read_list(&collection, &object, 3),
+            vec![
+                Some(IsarValue::Integer(1)),
+                Some(IsarValue::Integer(2)),
+                Some(IsarValue::Integer(3))
+            ]
+        );
+        assert_eq!(
+            read_list(&collection, &object, 4),
+            vec![Some(IsarValue::String("x".to_string()))]
+        );
+
+        let object = update(
+            &collection,
+            &object,
+            &[
+                (1, UpdateOp::Max(IsarValue::Integer(7))),
+                (3, UpdateOp::Remove(IsarValue::Integer(2))),
+                (4, UpdateOp::AddToSet(IsarValue::String("x".to_string()))),
+            ],
+        )
+        .unwrap();
+        assert_eq!(IsarDeserializer::from_bytes(&object).read_long(0), 8);
+        assert_eq!(
+            read_list(&collection, &object, 3),
+            vec![Some(IsarValue::Integer(1)), Some(IsarValue::Integer(3))]
+        );
+        assert_eq!(
+            read_list(&collection, &object, 4),
+            vec![Some(IsarValue::String("x".to_string()))]
+        );
+
+        let list = vec![None, Some(IsarValue::String("y".to_string()))];
+        let object = update(
+            &collection,
+            &object,
+            &[(4, UpdateOp::Set(Some(IsarValue::List(list.clone()))))],
+        )
+        .unwrap();
+        assert_eq!(read_list(&collection, &object, 4), list);
+        assert_eq!(
+            read_list(&collection, &object, 3),
+            vec![Some(IsarValue::Integer(1)), Some(IsarValue::Integer(3))]
+        );
+
+        assert_eq!(
+            update(
+                &collection,
+                &object,
+                &[(3, UpdateOp::Set(Some(IsarValue::List(list))))]
+            ),
+            Err(IsarError::IllegalArgument {})
+        );
+        assert_eq!(
+            update(
+                &collection,
+                &object,
+                &[(1, UpdateOp::Increment(IsarValue::Real(1.0)))]
+            ),
+            Err(IsarError::IllegalArgument {})
+        );
+        assert_eq!(
+            update(
+                &collection,
+                &object,
+                &[(3, UpdateOp::Concat(IsarValue::String("a".to_string())))]
+            ),
+            Err(IsarError::IllegalArgument {})
+        );
+    }
+
+    #[test]
+    fn test_apply_update_to_version() {
+        let mut collection = collection();
+        collection.version_property = Some(1);
+        let object = IsarSerializer::new(vec![], 0, collection.static_size).finish();
+
+        let object = update(
+            &collection,
+            &object,
+            &[(1, UpdateOp::Set(Some(IsarValue::Integer(3))))],
+        )
+        .unwrap();
+        assert_eq!(IsarDeserializer::from_bytes(&object).read_long(0), 3);
+        assert_eq!(
+            update(
+                &collection,
+                &object,
+                &[(1, UpdateOp::Increment(IsarValue::Integer(1)))]
+            ),
+            Err(IsarError::IllegalArgument {})
+        );
+    }
+
+    #[test]
+    fn expired_objects_are_hidden_and_purged() {
+        let schema = IsarSchema::new(
+            "Session",
+            Some("id"),
+            vec![
+                PropertySchema::new("expires", DataType::Long, None),
+                PropertySchema::new("value", DataType::Int, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_expiry("expires");
+        let instance = open_instance::<NativeInstance>(
+            "expired_objects_are_hidden_and_purged",
+            vec![schema],
+        );
+
+        let now = 1_700_000_000_000_000;
+        set_test_now(Some(now));
+        let mut insert = instance.insert(instance.begin_txn(true).unwrap(), 0, 4).unwrap();
+        for (id, expires) in [(1, Some(1)), (2, Some(now + 3_600_000_000)), (3, None)] {
+            match expires {
+                Some(expires) => insert.write_long(1, expires),
+                None => insert.write_null(1),
+            }
+            insert.write_int(2, id as i32);
+            insert.save(id).unwrap();
+        }
+        insert.write_long(1, now + 500_000);
+        insert.write_int(2, 4);
+        insert.save(4).unwrap();
+        let txn = insert.finish().unwrap();
+
+        let mut cursor = instance.cursor(&txn, 0).unwrap();
+        assert!(cursor.next(1).is_none());
+        assert!(cursor.next(2).is_some());
+        assert!(cursor.next(4).is_some());
+        drop(cursor);
+        // expired objects are counted until they are purged
+        assert_eq!(instance.count(&txn, 0), Ok(4));
+        let query = instance.query(0).unwrap().build().unwrap();
+        assert_eq!(
+            instance.query_aggregate(&txn, &query, Aggregation::Sum, Some(2)),
+            Ok(Some(IsarValue::Integer(9)))
+        );
+        assert_eq!(instance.purge_expired(&txn, 0, None), Ok(1));
+        assert_eq!(instance.purge_expired(&txn, 0, None), Ok(0));
+        assert_eq!(instance.count(&txn, 0), Ok(3));
+        instance.commit_txn(txn).unwrap();
+
+        set_test_now(Some(now + 500_000));
+        let txn = instance.begin_txn(false).unwrap();
+        assert_eq!(
+            instance.query_aggregate(&txn, &query, Aggregation::Sum, Some(2)),
+            Ok(Some(IsarValue::Integer(5)))
+        );
+        instance.abort_txn(txn);
+
+        // committing a write transaction that did not write to the collection
+        // does not purge it
+        let txn = instance.begin_txn(true).unwrap();
+        instance.commit_txn(txn).unwrap();
+        let txn = instance.begin_txn(false).unwrap();
+        assert_eq!(instance.count(&txn, 0), Ok(3));
+        instance.abort_txn(txn);
+
+        // writing to it purges the object that expired since
+        let mut insert = instance.insert(instance.begin_txn(true).unwrap(), 0, 1).unwrap();
+        insert.write_null(1);
+        insert.write_int(2, 5);
+        insert.save(5).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(instance.purge_expired(&txn, 0, None), Ok(0));
+        assert_eq!(instance.count(&txn, 0), Ok(3));
+        instance.abort_txn(txn);
+        set_test_now(None);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn rejects_writes_with_outdated_versions() {
+        let schema = IsarSchema::new(
+            "Note",
+            Some("id"),
+            vec![
+                PropertySchema::new("title", DataType::String, None),
+                PropertySchema::new("version", DataType::Long, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_version("version");
+        let instance =
+            open_instance::<NativeInstance>("rejects_writes_with_outdated_versions", vec![schema]);
+        let put = |title: &str, version: Option<i64>| {
+            let mut insert = instance.insert(instance.begin_txn(true).unwrap(), 0, 1).unwrap();
+            insert.write_string(1, title);
+            insert.write_long(2, version.unwrap_or(i64::MIN));
+            match insert.save(1) {
+                Ok(()) => instance.commit_txn(insert.finish().unwrap()),
+                Err(e) => {
+                    instance.abort_txn(insert.abort());
+                    Err(e)
+                }
+            }
+        };
+        let version = || {
+            let txn = instance.begin_txn(false).unwrap();
+            let version = instance
+                .cursor(&txn, 0)
+                .unwrap()
+                .next(1)
+                .map(|reader| reader.read_long(2));
+            instance.abort_txn(txn);
+            version
+        };
+
+        assert_eq!(put("a", None), Ok(()));
+        assert_eq!(version(), Some(1));
+        assert_eq!(put("b", Some(1)), Ok(()));
+        assert_eq!(version(), Some(2));
+        assert_eq!(put("c", Some(1)), Err(IsarError::VersionConflict {}));
+        assert_eq!(version(), Some(2));
+
+        let title = |value: &str| UpdateOp::Set(Some(IsarValue::String(value.to_string())));
+        let expect = |value| UpdateOp::Set(Some(IsarValue::Integer(value)));
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(1, title("d")), (2, expect(2))]),
+            Ok(true)
+        );
+        assert_eq!(instance.update(&txn, 0, 1, &[(1, title("e"))]), Ok(true));
+        let query = instance.query(0).unwrap().build().unwrap();
+        assert_eq!(
+            instance.query_update(&txn, &query, None, None, &[(1, title("f"))]),
+            Ok(1)
+        );
+        instance.commit_txn(txn).unwrap();
+        assert_eq!(version(), Some(5));
+
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(1, title("g")), (2, expect(4))]),
+            Err(IsarError::VersionConflict {})
+        );
+        instance.abort_txn(txn);
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(2, UpdateOp::Increment(IsarValue::Integer(1)))]),
+            Err(IsarError::IllegalArgument {})
+        );
+        instance.abort_txn(txn);
+        assert_eq!(version(), Some(5));
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn keeps_versions_of_remote_changes() {
+        let schema = IsarSchema::new(
+            "Note",
+            Some("id"),
+            vec![
+                PropertySchema::new("title", DataType::String, None),
+                PropertySchema::new("version", DataType::Long, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_version("version")
+        .with_field_timestamps();
+        let instance =
+            open_instance::<NativeInstance>("keeps_versions_of_remote_changes", vec![schema]);
+        insert_objects(instance.as_ref(), 0, &[1], |insert, _| {
+            insert.write_string(1, "a");
+            insert.write_null(2);
+        });
+        let txn = instance.begin_txn(true).unwrap();
+
+        let t = now_millis() + 60_000;
+        let change = |version: i64, millis: u64| ObjectChange {
+            id: 1,
+            updates: vec![FieldUpdate {
+                property_index: 2,
+                value: Some(IsarValue::Integer(version)),
+                timestamp: HlcTimestamp::new(t + millis, 0, 1),
+            }],
+        };
+        let version = |txn: &NativeTxn| {
+            instance.cursor(txn, 0).unwrap().next(1).map(|reader| reader.read_long(2))
+        };
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &[change(7, 1)], true),
+            Ok(MergeResult {
+                merged: 1,
+                missing: vec![]
+            })
+        );
+        assert_eq!(version(&txn), Some(7));
+        assert_eq!(
+            instance
+                .merge_changes(&txn, 0, &[change(7, 2)], false)
+                .map(|result| result.merged),
+            Ok(1)
+        );
+        assert_eq!(version(&txn), Some(8));
+        instance.commit_txn(txn).unwrap();
+        NativeInstance::close(instance, true);
+    }
+}
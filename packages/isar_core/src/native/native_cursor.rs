@@ -5,11 +5,13 @@ use super::native_txn::{NativeTxn, TxnCursor};
 use super::IdToBytes;
 use crate::core::cursor::IsarCursor;
 use crate::core::error::Result;
+use crate::core::expiry::now_micros;
 
 pub struct NativeCursor<'a> {
     cursor: TxnCursor<'a>,
     collection: &'a NativeCollection,
     collections: &'a Vec<NativeCollection>,
+    now: i64,
 }
 
 impl<'a> NativeCursor<'a> {
@@ -23,6 +25,7 @@ impl<'a> NativeCursor<'a> {
             cursor,
             collection,
             collections,
+            now: now_micros(),
         };
         Ok(nc)
     }
@@ -35,6 +38,9 @@ impl<'a> IsarCursor for NativeCursor<'a> {
     fn next(&mut self, id: i64) -> Option<Self::Reader<'_>> {
         if let Some((_, bytes)) = self.cursor.move_to(&id.to_id_bytes()).ok()? {
             let object = IsarDeserializer::from_bytes(bytes);
+            if self.collection.is_expired(object, self.now) {
+                return None;
+            }
             Some(NativeReader::new(
                 id,
                 object,
@@ -1,11 +1,16 @@
 use super::index_key::IndexKey;
 use super::isar_deserializer::IsarDeserializer;
+use super::mdbx::cursor_iterator::CursorIterator;
 use super::mdbx::db::Db;
 use super::native_collection::NativeProperty;
-use super::native_txn::NativeTxn;
-use super::{BytesToId, IdToBytes};
+use super::native_txn::{NativeTxn, TxnCursor};
+use super::{BytesToId, IdToBytes, NULL_INT, NULL_LONG};
 use crate::core::data_type::DataType;
 use crate::core::error::Result;
+use crate::core::geo::geohash;
+use crate::core::normalize::StringNormalization;
+use crate::core::uuid::parse_uuid;
+use crate::core::value::IsarValue;
 
 #[derive(Clone, Eq, PartialEq)]
 pub(crate) struct NativeIndex {
@@ -13,6 +18,8 @@ pub(crate) struct NativeIndex {
     pub properties: Vec<NativeProperty>,
     pub unique: bool,
     pub hash: bool,
+    pub geo: bool,
+    pub normalization: StringNormalization,
     db: Db,
 }
 
@@ -23,18 +30,29 @@ impl NativeIndex {
         properties: Vec<NativeProperty>,
         unique: bool,
         hash: bool,
+        geo: bool,
+        normalization: StringNormalization,
     ) -> Self {
         NativeIndex {
             name: name.to_string(),
             properties,
             unique,
             hash,
+            geo,
+            normalization,
             db,
         }
     }
 
     fn create_key(&self, object: IsarDeserializer, buffer: Vec<u8>) -> (Vec<u8>, bool) {
         let mut key = IndexKey::with_buffer(buffer);
+        if self.geo {
+            let latitude = object.read_double(self.properties[0].offset);
+            let longitude = object.read_double(self.properties[1].offset);
+            let hash = geohash(latitude, longitude).map_or(NULL_LONG, |hash| hash as i64);
+            key.add_long(hash);
+            return key.finish();
+        }
         for property in &self.properties {
             match property.data_type {
                 DataType::Bool => key.add_bool(object.read_bool(property.offset)),
@@ -43,7 +61,11 @@ impl NativeIndex {
                 DataType::Float => key.add_float(object.read_float(property.offset)),
                 DataType::Long => key.add_long(object.read_long(property.offset)),
                 DataType::Double => key.add_double(object.read_double(property.offset)),
-                DataType::String => key.add_string(object.read_string(property.offset)),
+                DataType::String => {
+                    let value = object.read_string(property.offset);
+                    key.add_string(value.map(|v| self.normalization.normalize(v)).as_deref())
+                }
+                DataType::Uuid => key.add_uuid(object.read_uuid(property.offset)),
                 _ => unreachable!(),
             }
         }
@@ -98,6 +120,81 @@ impl NativeIndex {
         Ok(key)
     }
 
+    /// Returns the id stored for a key of a unique index.
+    pub fn get_id(&self, txn: &NativeTxn, key: &[u8]) -> Result<Option<i64>> {
+        let mut cursor = txn.get_cursor(self.db)?;
+        Ok(cursor.move_to(key)?.map(|(_, id_bytes)| id_bytes.to_id()))
+    }
+
+    pub fn iter<'txn>(
+        &self,
+        txn: &'txn NativeTxn,
+    ) -> Result<CursorIterator<'txn, TxnCursor<'txn>>> {
+        txn.get_cursor(self.db)?.iter_between(
+            IndexKey::min().finish().0,
+            IndexKey::max().finish().0,
+            !self.unique,
+            false,
+        )
+    }
+
+    pub fn iter_between<'txn>(
+        &self,
+        txn: &'txn NativeTxn,
+        lower_key: Vec<u8>,
+        upper_key: Vec<u8>,
+    ) -> Result<CursorIterator<'txn, TxnCursor<'txn>>> {
+        txn.get_cursor(self.db)?
+            .iter_between(lower_key, upper_key, !self.unique, false)
+    }
+
+    /// Creates the key that a single property index stores for objects with the
+    /// given value. Returns `None` if the value cannot be looked up in this index.
+    pub fn create_value_key(&self, value: Option<&IsarValue>) -> Option<Vec<u8>> {
+        let [property] = self.properties.as_slice() else {
+            return None;
+        };
+        let mut key = IndexKey::min();
+        match property.data_type {
+            DataType::Bool => match value {
+                Some(value) => key.add_bool(Some(value.bool()?)),
+                None => key.add_bool(None),
+            },
+            DataType::Byte => key.add_byte(value?.u8()?),
+            DataType::Int => key.add_int(value.map_or(Some(NULL_INT), |v| v.i32())?),
+            DataType::Long => key.add_long(value.map_or(Some(NULL_LONG), |v| v.i64())?),
+            DataType::String => match value {
+                Some(value) => key.add_string(Some(&self.normalization.normalize(value.string()?))),
+                None => key.add_string(None),
+            },
+            DataType::Uuid => match value {
+                Some(value) => key.add_uuid(Some(parse_uuid(value.string()?)?)),
+                None => key.add_uuid(None),
+            },
+            _ => return None,
+        }
+
+        if self.hash {
+            Some(key.hash().to_be_bytes().to_vec())
+        } else {
+            Some(key.finish().0)
+        }
+    }
+
+    /// Creates the key range of geohashes for a geo index.
+    pub fn create_geohash_keys(&self, lower: u64, upper: u64) -> (Vec<u8>, Vec<u8>) {
+        self.create_long_keys(lower as i64, upper as i64)
+    }
+
+    /// Creates the key range of a single `Long` property index.
+    pub fn create_long_keys(&self, lower: i64, upper: i64) -> (Vec<u8>, Vec<u8>) {
+        let mut lower_key = IndexKey::min();
+        lower_key.add_long(lower);
+        let mut upper_key = IndexKey::min();
+        upper_key.add_long(upper);
+        (lower_key.finish().0, upper_key.finish().0)
+    }
+
     pub fn get_size(&self, txn: &NativeTxn) -> Result<u64> {
         Ok(txn.stat(self.db)?.1)
     }
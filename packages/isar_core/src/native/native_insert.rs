@@ -1,10 +1,15 @@
+use super::isar_deserializer::IsarDeserializer;
 use super::isar_serializer::IsarSerializer;
 use super::native_collection::NativeCollection;
+use super::native_reader::NativeReader;
 use super::native_txn::{NativeTxn, TxnCursor};
-use super::MAX_OBJ_SIZE;
+use super::{IdToBytes, MAX_OBJ_SIZE};
+use crate::core::convert::copy_value;
 use crate::core::error::{IsarError, Result};
 use crate::core::insert::IsarInsert;
 use crate::core::watcher::ChangeSet;
+use crate::core::writer::IsarWriter;
+use itertools::Itertools;
 use ouroboros::self_referencing;
 use std::cell::RefMut;
 
@@ -28,10 +33,41 @@ impl TxnWithCursor {
         )
     }
 
-    fn put(&mut self, collection: &NativeCollection, id: i64, bytes: &[u8], all_collections: &[NativeCollection]) -> Result<()> {
-        self.with_mut(|mut this| {
-            this.txn
-                .guard(|| collection.put(this.txn, &mut this.change_set, this.cursor, id, bytes, all_collections))
+    fn put(
+        &mut self,
+        collection: &NativeCollection,
+        id: i64,
+        bytes: &mut Vec<u8>,
+        keep_version: bool,
+        all_collections: &[NativeCollection],
+    ) -> Result<()> {
+        self.with_mut(|this| {
+            this.txn.guard(|| {
+                collection.put(
+                    this.txn,
+                    this.change_set,
+                    this.cursor,
+                    id,
+                    bytes,
+                    keep_version,
+                    all_collections,
+                )?;
+                match &collection.timestamps {
+                    // objects written as-is are not local writes
+                    Some(timestamps) if !keep_version => {
+                        let properties = collection.properties.len() as u16;
+                        timestamps.stamp(this.txn, id, 1..=properties)
+                    }
+                    _ => Ok(()),
+                }
+            })
+        })
+    }
+
+    fn get(&mut self, id: i64) -> Result<Option<Vec<u8>>> {
+        self.with_cursor_mut(|cursor| {
+            let object = cursor.move_to(&id.to_id_bytes())?;
+            Ok(object.map(|(_, bytes)| bytes.to_vec()))
         })
     }
 
@@ -47,6 +83,9 @@ pub struct NativeInsert<'a> {
 
     remaining: u32,
     pub(crate) object: IsarSerializer,
+    // Properties of the current object that have been written
+    pub(crate) written: Vec<bool>,
+    keep_versions: bool,
 }
 
 impl<'a> NativeInsert<'a> {
@@ -64,26 +103,85 @@ impl<'a> NativeInsert<'a> {
             all_collections,
             remaining: count,
             object: IsarSerializer::new(buffer, 0, collection.static_size),
+            written: vec![false; collection.properties.len() + 1],
+            keep_versions: false,
         };
         Ok(insert)
     }
 }
 
-impl<'a> IsarInsert<'a> for NativeInsert<'a> {
-    type Txn = NativeTxn;
-
-    fn save(&mut self, id: i64) -> Result<()> {
+impl<'a> NativeInsert<'a> {
+    fn save_object(&mut self, id: Option<i64>) -> Result<i64> {
         if self.remaining > 0 {
             let mut buffer = self.object.finish();
             if buffer.len() > MAX_OBJ_SIZE as usize {
                 return Result::Err(IsarError::ObjectLimitReached {});
             }
-            self.txn_cursor.put(self.collection, id, &buffer, self.all_collections)?;
+            let id = match id {
+                Some(id) => id,
+                None => self.collection.get_key_id(
+                    self.txn_cursor.borrow_txn(),
+                    IsarDeserializer::from_bytes(&buffer),
+                )?,
+            };
+            self.txn_cursor.put(
+                self.collection,
+                id,
+                &mut buffer,
+                self.keep_versions,
+                self.all_collections,
+            )?;
 
             self.remaining -= 1;
             buffer.clear();
             self.object = IsarSerializer::new(buffer, 0, self.collection.static_size);
-            Ok(())
+            self.written.fill(false);
+            Ok(id)
+        } else {
+            Err(IsarError::UnsupportedOperation {})
+        }
+    }
+}
+
+impl<'a> IsarInsert<'a> for NativeInsert<'a> {
+    type Txn = NativeTxn;
+
+    fn save(&mut self, id: i64) -> Result<()> {
+        self.save_object(Some(id))?;
+        Ok(())
+    }
+
+    fn save_merge(&mut self, id: i64) -> Result<()> {
+        let collection = self.collection;
+        let unwritten = collection
+            .properties
+            .iter()
+            .enumerate()
+            .map(|(i, (_, p))| (i as u32 + 1, p.data_type))
+            .filter(|(index, _)| !self.written[*index as usize])
+            .collect_vec();
+        if let Some(old_object) = self.txn_cursor.get(id)? {
+            let object = IsarDeserializer::from_bytes(&old_object);
+            let reader = NativeReader::new(id, object, collection, self.all_collections);
+            for (index, data_type) in unwritten {
+                copy_value(&reader, index, self, index, data_type)?;
+            }
+        } else {
+            for (index, _) in unwritten {
+                self.write_null(index);
+            }
+        }
+        self.save_object(Some(id))?;
+        Ok(())
+    }
+
+    fn keep_versions(&mut self) {
+        self.keep_versions = true;
+    }
+
+    fn save_by_key(&mut self) -> Result<i64> {
+        if self.collection.key_index.is_some() {
+            self.save_object(None)
         } else {
             Err(IsarError::UnsupportedOperation {})
         }
@@ -98,4 +196,77 @@ impl<'a> IsarInsert<'a> for NativeInsert<'a> {
             Ok(txn)
         }
     }
+
+    fn abort(self) -> Self::Txn {
+        self.txn_cursor.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cursor::IsarCursor;
+    use crate::core::data_type::DataType;
+    use crate::core::instance::IsarInstance;
+    use crate::core::reader::IsarReader;
+    use crate::core::schema::{IsarSchema, PropertySchema};
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::native::native_instance::NativeInstance;
+
+    #[test]
+    fn merges_written_properties_into_stored_objects() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("count", DataType::Long, None),
+                PropertySchema::new("tags", DataType::StringList, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance = open_instance::<NativeInstance>(
+            "merges_written_properties_into_stored_objects",
+            vec![schema],
+        );
+        insert_objects(instance.as_ref(), 0, &[1], |insert, _| {
+            insert.write_string(1, "a");
+            insert.write_long(2, 5);
+            let mut tags = insert.begin_list(3, 1).unwrap();
+            tags.write_string(0, "x");
+            insert.end_list(tags);
+        });
+
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 3)
+            .unwrap();
+        insert.write_long(2, 7);
+        insert.save_merge(1).unwrap();
+        insert.write_string(1, "b");
+        insert.save_merge(1).unwrap();
+        insert.write_string(1, "c");
+        insert.save_merge(2).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let txn = instance.begin_txn(false).unwrap();
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(1).unwrap();
+            assert_eq!(reader.read_string(1), Some("b"));
+            assert_eq!(reader.read_long(2), 7);
+            let (tags, length) = reader.read_list(3).unwrap();
+            assert_eq!(length, 1);
+            assert_eq!(tags.read_string(0), Some("x"));
+        }
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(2).unwrap();
+            assert_eq!(reader.read_string(1), Some("c"));
+            assert!(reader.is_null(2));
+            assert!(reader.is_null(3));
+        }
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
 }
@@ -2,16 +2,23 @@ use super::mdbx::env::Env;
 use super::native_collection::NativeCollection;
 use super::native_cursor::NativeCursor;
 use super::native_insert::NativeInsert;
-use super::native_open::{get_isar_path, open_native};
+use super::native_open::{get_isar_path, open_native, read_native_schemas};
 use super::native_query_builder::NativeQueryBuilder;
 use super::native_reader::NativeReader;
 use super::native_txn::NativeTxn;
 use super::native_verify::verify_native;
 use super::query::{NativeQuery, NativeQueryCursor};
+use crate::core::continuation::QueryContinuation;
 use crate::core::error::{IsarError, Result};
+use crate::core::expiry::{now_micros, PURGE_BATCH_SIZE};
 use crate::core::instance::{Aggregation, CompactCondition, IsarInstance};
+use crate::core::merge::{resolve_updates, MergeResult, ObjectChange};
+use crate::core::query_plan::QueryPlan;
 use crate::core::schema::IsarSchema;
+use crate::core::tombstone::Tombstone;
+use crate::core::update::UpdateOp;
 use crate::core::value::IsarValue;
+use crate::core::vector::VectorMetric;
 use crate::core::watcher::{WatchHandle, WatcherCallback, DetailedWatcherCallback};
 use intmap::IntMap;
 use parking_lot::Mutex;
@@ -25,6 +32,7 @@ pub struct NativeInstance {
     name: String,
     dir: String,
     instance_id: u32,
+    schemas: Vec<IsarSchema>,
     collections: Vec<NativeCollection>,
     env: Arc<Env>,
 }
@@ -34,6 +42,7 @@ impl NativeInstance {
         name: &str,
         dir: &str,
         instance_id: u32,
+        schemas: Vec<IsarSchema>,
         collections: Vec<NativeCollection>,
         env: Arc<Env>,
     ) -> Self {
@@ -41,11 +50,18 @@ impl NativeInstance {
             name: name.to_string(),
             dir: dir.to_string(),
             instance_id,
+            schemas,
             collections,
             env,
         }
     }
 
+    /// Reads the schemas stored in an existing instance so it can be opened
+    /// without knowing its schema.
+    pub fn read_schemas(name: &str, dir: &str, max_size_mib: u32) -> Result<Vec<IsarSchema>> {
+        read_native_schemas(name, dir, max_size_mib)
+    }
+
     pub(crate) fn verify_instance_id(&self, instance_id: u32) -> Result<()> {
         if self.instance_id != instance_id {
             Err(IsarError::InstanceMismatch {})
@@ -111,6 +127,14 @@ impl IsarInstance for NativeInstance {
         self.collections.iter().map(|c| c.name.as_str())
     }
 
+    fn get_schemas(&self) -> &[IsarSchema] {
+        &self.schemas
+    }
+
+    fn is_embedded(&self, collection_index: u16) -> bool {
+        self.get_collection(collection_index).is_ok_and(|c| c.is_embedded())
+    }
+
     fn open_instance(
         instance_id: u32,
         name: &str,
@@ -147,6 +171,11 @@ impl IsarInstance for NativeInstance {
 
     fn commit_txn(&self, txn: Self::Txn) -> Result<()> {
         self.verify_instance_id(txn.instance_id)?;
+        if txn.is_write() {
+            for collection_index in txn.take_expiring() {
+                self.purge_expired(&txn, collection_index, Some(PURGE_BATCH_SIZE))?;
+            }
+        }
         txn.commit()
     }
 
@@ -164,6 +193,20 @@ impl IsarInstance for NativeInstance {
         }
     }
 
+    fn next_auto_increment(&self, collection_index: u16) -> i64 {
+        if let Ok(collection) = self.get_collection(collection_index) {
+            collection.next_auto_increment()
+        } else {
+            0
+        }
+    }
+
+    fn update_auto_increment(&self, collection_index: u16, id: i64) {
+        if let Ok(collection) = self.get_collection(collection_index) {
+            collection.update_auto_increment(id);
+        }
+    }
+
     fn cursor<'a>(&'a self, txn: &'a Self::Txn, collection_index: u16) -> Result<Self::Cursor<'a>> {
         self.verify_instance_id(txn.instance_id)?;
         let collection = self.get_collection(collection_index)?;
@@ -186,12 +229,25 @@ impl IsarInstance for NativeInstance {
         txn: &Self::Txn,
         collection_index: u16,
         id: i64,
-        updates: &[(u16, Option<IsarValue>)],
+        updates: &[(u16, UpdateOp)],
     ) -> Result<bool> {
         self.verify_instance_id(txn.instance_id)?;
         let collection = self.get_collection(collection_index)?;
         let mut cursor = collection.get_cursor(txn)?;
-        txn.guard(|| collection.update(txn, &mut txn.get_change_set(), &mut cursor, id, updates, &self.collections))
+        txn.guard(|| {
+            let updated = collection.update(
+                txn,
+                &mut txn.get_change_set(),
+                &mut cursor,
+                id,
+                updates,
+                &self.collections,
+            )?;
+            if let Some(timestamps) = collection.timestamps.as_ref().filter(|_| updated) {
+                timestamps.stamp(txn, id, updates.iter().map(|(index, _)| *index))?;
+            }
+            Ok(updated)
+        })
     }
 
     fn delete<'a>(&'a self, txn: &'a Self::Txn, collection_index: u16, id: i64) -> Result<bool> {
@@ -201,9 +257,21 @@ impl IsarInstance for NativeInstance {
         txn.guard(|| collection.delete(txn, &mut txn.get_change_set(), &mut cursor, id, &self.collections))
     }
 
+    fn get_id_by_key(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        key: &str,
+    ) -> Result<Option<i64>> {
+        self.verify_instance_id(txn.instance_id)?;
+        let collection = self.get_collection(collection_index)?;
+        collection.get_id_by_key(txn, key)
+    }
+
     fn count(&self, txn: &Self::Txn, collection_index: u16) -> Result<u32> {
         self.verify_instance_id(txn.instance_id)?;
         let collection = self.get_collection(collection_index)?;
+        // expired objects are counted until they are purged
         collection.count(txn)
     }
 
@@ -242,8 +310,32 @@ impl IsarInstance for NativeInstance {
     ) -> Result<Self::QueryCursor<'a>> {
         self.verify_instance_id(txn.instance_id)?;
         self.verify_instance_id(query.instance_id)?;
-        let result = query.cursor(txn, &self.collections, offset, limit);
-        Ok(result)
+        query.cursor(txn, &self.collections, &[], None, offset, limit)
+    }
+
+    fn query_cursor_with_params<'a>(
+        &'a self,
+        txn: &'a Self::Txn,
+        query: &'a Self::Query,
+        params: &[Option<IsarValue>],
+        offset: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Self::QueryCursor<'a>> {
+        self.verify_instance_id(txn.instance_id)?;
+        self.verify_instance_id(query.instance_id)?;
+        query.cursor(txn, &self.collections, params, None, offset, limit)
+    }
+
+    fn query_cursor_after<'a>(
+        &'a self,
+        txn: &'a Self::Txn,
+        query: &'a Self::Query,
+        after: Option<&QueryContinuation>,
+        limit: Option<u32>,
+    ) -> Result<Self::QueryCursor<'a>> {
+        self.verify_instance_id(txn.instance_id)?;
+        self.verify_instance_id(query.instance_id)?;
+        query.cursor(txn, &self.collections, &[], after, None, limit)
     }
 
     fn query_aggregate(
@@ -255,8 +347,42 @@ impl IsarInstance for NativeInstance {
     ) -> Result<Option<IsarValue>> {
         self.verify_instance_id(txn.instance_id)?;
         self.verify_instance_id(query.instance_id)?;
-        let result = query.aggregate(txn, &self.collections, aggregation, property_index);
-        Ok(result)
+        query.verify_bound()?;
+        query.aggregate(txn, &self.collections, aggregation, property_index)
+    }
+
+    fn query_facets(
+        &self,
+        txn: &Self::Txn,
+        query: &Self::Query,
+        property_index: u16,
+        sort_by_count: bool,
+        limit: Option<u32>,
+    ) -> Result<Vec<(Option<IsarValue>, u32)>> {
+        self.verify_instance_id(txn.instance_id)?;
+        self.verify_instance_id(query.instance_id)?;
+        query.facets(txn, &self.collections, property_index, sort_by_count, limit)
+    }
+
+    fn query_nearest(
+        &self,
+        txn: &Self::Txn,
+        query: &Self::Query,
+        property_index: u16,
+        vector: &[f64],
+        metric: VectorMetric,
+        k: u32,
+    ) -> Result<Vec<(i64, f64)>> {
+        self.verify_instance_id(txn.instance_id)?;
+        self.verify_instance_id(query.instance_id)?;
+        query.nearest(txn, &self.collections, property_index, vector, metric, k)
+    }
+
+    fn explain(&self, txn: &Self::Txn, query: &Self::Query) -> Result<QueryPlan> {
+        self.verify_instance_id(txn.instance_id)?;
+        self.verify_instance_id(query.instance_id)?;
+        let collection = self.get_collection(query.collection_index)?;
+        Ok(QueryPlan::Native(query.explain(collection, &self.collections)))
     }
 
     fn query_update(
@@ -265,10 +391,11 @@ impl IsarInstance for NativeInstance {
         query: &Self::Query,
         offset: Option<u32>,
         limit: Option<u32>,
-        updates: &[(u16, Option<IsarValue>)],
+        updates: &[(u16, UpdateOp)],
     ) -> Result<u32> {
         self.verify_instance_id(txn.instance_id)?;
         self.verify_instance_id(query.instance_id)?;
+        query.verify_bound()?;
         let collection = self.get_collection(query.collection_index)?;
         let ids = query.get_matching_ids(txn, collection, offset, limit);
 
@@ -277,11 +404,114 @@ impl IsarInstance for NativeInstance {
             let mut cursor = collection.get_cursor(txn)?;
             for id in &ids {
                 collection.update(txn, change_set, &mut cursor, *id, updates, &self.collections)?;
+                if let Some(timestamps) = &collection.timestamps {
+                    timestamps.stamp(txn, *id, updates.iter().map(|(index, _)| *index))?;
+                }
             }
             Ok(ids.len() as u32)
         })
     }
 
+    fn purge_expired(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        limit: Option<u32>,
+    ) -> Result<u32> {
+        self.verify_instance_id(txn.instance_id)?;
+        let collection = self.get_collection(collection_index)?;
+        if collection.expiry_index.is_none() {
+            return Ok(0);
+        }
+        let mut cursor = collection.get_cursor(txn)?;
+        txn.guard(|| {
+            collection.purge_expired(
+                txn,
+                &mut txn.get_change_set(),
+                &mut cursor,
+                now_micros(),
+                limit.unwrap_or(u32::MAX),
+                &self.collections,
+            )
+        })
+    }
+
+    fn get_tombstones(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        after_sequence: i64,
+        limit: Option<u32>,
+    ) -> Result<Vec<Tombstone>> {
+        self.verify_instance_id(txn.instance_id)?;
+        let collection = self.get_collection(collection_index)?;
+        let tombstones = collection
+            .tombstones
+            .as_ref()
+            .ok_or(IsarError::UnsupportedOperation {})?;
+        tombstones.get_after(txn, after_sequence, limit.unwrap_or(u32::MAX))
+    }
+
+    fn purge_tombstones(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        deleted_before: i64,
+    ) -> Result<u32> {
+        self.verify_instance_id(txn.instance_id)?;
+        let collection = self.get_collection(collection_index)?;
+        let tombstones = collection
+            .tombstones
+            .as_ref()
+            .ok_or(IsarError::UnsupportedOperation {})?;
+        txn.guard(|| tombstones.purge(txn, deleted_before))
+    }
+
+    fn merge_changes(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        changes: &[ObjectChange],
+        remote: bool,
+    ) -> Result<MergeResult> {
+        self.verify_instance_id(txn.instance_id)?;
+        let collection = self.get_collection(collection_index)?;
+        let timestamps = collection
+            .timestamps
+            .as_ref()
+            .ok_or(IsarError::UnsupportedOperation {})?;
+
+        txn.guard(|| {
+            let change_set = &mut txn.get_change_set();
+            change_set.set_remote(remote);
+            let mut cursor = collection.get_cursor(txn)?;
+            let mut result = MergeResult::default();
+            for change in changes {
+                let mut field_timestamps = timestamps.get(txn, change.id)?;
+                let updates = resolve_updates(&mut field_timestamps, &change.updates);
+                if updates.is_empty() {
+                    continue;
+                }
+                let updated = collection.update(
+                    txn,
+                    change_set,
+                    &mut cursor,
+                    change.id,
+                    &updates,
+                    &self.collections,
+                )?;
+                if updated {
+                    timestamps.put(txn, change.id, &field_timestamps)?;
+                    result.merged += 1;
+                } else {
+                    result.missing.push(change.id);
+                }
+            }
+            change_set.set_remote(false);
+            Ok(result)
+        })
+    }
+
     fn query_delete(
         &self,
         txn: &Self::Txn,
@@ -291,6 +521,7 @@ impl IsarInstance for NativeInstance {
     ) -> Result<u32> {
         self.verify_instance_id(txn.instance_id)?;
         self.verify_instance_id(query.instance_id)?;
+        query.verify_bound()?;
         let collection = self.get_collection(query.collection_index)?;
         let ids = query.get_matching_ids(txn, collection, offset, limit);
 
@@ -363,3 +594,311 @@ impl IsarInstance for NativeInstance {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cursor::{IsarCursor, IsarQueryCursor};
+    use crate::core::data_type::DataType;
+    use crate::core::insert::IsarInsert;
+    use crate::core::query_description::QueryDescription;
+    use crate::core::reader::IsarReader;
+    use crate::core::normalize::StringNormalization;
+    use crate::core::query_builder::IsarQueryBuilder;
+    use crate::core::schema::{IndexSchema, PropertySchema};
+    use crate::core::uuid::format_uuid;
+    use crate::core::writer::IsarWriter;
+    use crate::native::IdToBytes;
+    use std::collections::BTreeMap;
+
+    fn open(instance_id: u32, name: &str, schemas: Vec<IsarSchema>) -> Arc<NativeInstance> {
+        let dir = std::env::temp_dir().join(format!("isar_native_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap();
+        NativeInstance::open_instance(instance_id, name, dir, schemas, 10, None, None).unwrap()
+    }
+
+    #[test]
+    fn keeps_property_order_of_schemas_after_migration() {
+        let schema = |properties: &[(&str, DataType)]| {
+            let properties = properties
+                .iter()
+                .map(|(name, data_type)| PropertySchema::new(name, *data_type, None))
+                .collect();
+            IsarSchema::new("Person", Some("id"), properties, vec![], false)
+        };
+        let name = "keeps_property_order_of_schemas_after_migration";
+        let schemas = vec![schema(&[("name", DataType::String), ("city", DataType::String)])];
+        let instance = open(4, name, schemas);
+        let mut insert = instance.insert(instance.begin_txn(true).unwrap(), 0, 2).unwrap();
+        for (id, name, city) in [(1, "a", "x"), (2, "b", "y")] {
+            insert.write_string(1, name);
+            insert.write_string(2, city);
+            insert.save(id).unwrap();
+        }
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+        NativeInstance::close(instance, false);
+
+        let schemas = vec![schema(&[
+            ("name", DataType::String),
+            ("age", DataType::Int),
+            ("city", DataType::String),
+        ])];
+        let dir = std::env::temp_dir().join(format!("isar_native_{}", name));
+        let dir = dir.to_str().unwrap();
+        let instance =
+            NativeInstance::open_instance(4, name, dir, schemas, 10, None, None).unwrap();
+        let names = instance.get_schemas()[0]
+            .properties
+            .iter()
+            .filter_map(|p| p.name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["name", "age", "city"]);
+
+        let description = QueryDescription::from_json(
+            r#"{"filter": {"condition": {"property": "city", "type": "equal", "values": ["y"]}}}"#,
+        )
+        .unwrap();
+        let (query, offset, limit) = description.build(instance.as_ref(), 0).unwrap();
+        let txn = instance.begin_txn(false).unwrap();
+        let mut cursor = instance.query_cursor(&txn, &query, offset, limit).unwrap();
+        let reader = cursor.next().unwrap();
+        assert_eq!(reader.read_string(1), Some("b"));
+        assert_eq!(reader.read_string(3), Some("y"));
+        assert!(cursor.next().is_none());
+        drop(cursor);
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn updates_uuids() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![PropertySchema::new("uuid", DataType::Uuid, None)],
+            vec![],
+            false,
+        );
+        let instance = open(6, "updates_uuids", vec![schema]);
+        let mut insert = instance.insert(instance.begin_txn(true).unwrap(), 0, 1).unwrap();
+        insert.write_uuid(1, &[1; 16]);
+        insert.save(1).unwrap();
+        let txn = insert.finish().unwrap();
+        let read = |txn: &NativeTxn| {
+            let mut cursor = instance.cursor(txn, 0).unwrap();
+            cursor.next(1).unwrap().read_uuid(1)
+        };
+
+        let set = |value: &str| UpdateOp::Set(Some(IsarValue::String(value.to_string())));
+        let update = (1, set("550E8400E29B41D4A716446655440000"));
+        assert_eq!(instance.update(&txn, 0, 1, &[update]), Ok(true));
+        assert_eq!(
+            read(&txn).map(|uuid| format_uuid(&uuid)).as_deref(),
+            Some("550e8400-e29b-41d4-a716-446655440000")
+        );
+        let update = (1, set("00000000-0000-0000-0000-000000000000"));
+        assert_eq!(instance.update(&txn, 0, 1, &[update]), Ok(true));
+        assert_eq!(read(&txn), None);
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(1, set("invalid"))]),
+            Err(IsarError::IllegalArgument {})
+        );
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn updates_lists_embedded_objects_and_json() {
+        let item = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("tags", DataType::LongList, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+                PropertySchema::new("meta", DataType::Json, None),
+                PropertySchema::new("bytes", DataType::ByteList, None),
+            ],
+            vec![],
+            false,
+        );
+        let address = IsarSchema::new(
+            "Address",
+            None,
+            vec![
+                PropertySchema::new("street", DataType::String, None),
+                PropertySchema::new("numbers", DataType::LongList, None),
+                PropertySchema::new("code", DataType::Uuid, None),
+            ],
+            vec![],
+            true,
+        );
+        let instance = open(7, "updates_lists_embedded_objects_and_json", vec![item, address]);
+        let mut insert = instance.insert(instance.begin_txn(true).unwrap(), 0, 1).unwrap();
+        for index in 1..=4 {
+            insert.write_null(index);
+        }
+        insert.save(1).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let int = |value| Some(IsarValue::Integer(value));
+        let txn = instance.begin_txn(true).unwrap();
+        let address = BTreeMap::from([
+            (1, Some(IsarValue::String("Main".to_string()))),
+            (2, Some(IsarValue::List(vec![int(1), int(2)]))),
+            (3, Some(IsarValue::String("550E8400E29B41D4A716446655440000".to_string()))),
+        ]);
+        let updated = instance.update(
+            &txn,
+            0,
+            1,
+            &[
+                (1, UpdateOp::Set(Some(IsarValue::List(vec![int(1), None, int(3)])))),
+                (2, UpdateOp::Set(Some(IsarValue::Object(address)))),
+                (3, UpdateOp::Set(Some(IsarValue::List(vec![int(1), None])))),
+                (4, UpdateOp::Set(Some(IsarValue::List(vec![int(4), int(5)])))),
+            ],
+        );
+        assert_eq!(updated, Ok(true));
+        instance.commit_txn(txn).unwrap();
+
+        let txn = instance.begin_txn(true).unwrap();
+        let invalid = BTreeMap::from([(4, int(1))]);
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(2, UpdateOp::Set(Some(IsarValue::Object(invalid))))]),
+            Err(IsarError::IllegalArgument {})
+        );
+        instance.abort_txn(txn);
+
+        let txn = instance.begin_txn(false).unwrap();
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(1).unwrap();
+            let (tags, length) = reader.read_list(1).unwrap();
+            assert_eq!(length, 3);
+            assert_eq!(tags.read_long(0), 1);
+            assert!(tags.is_null(1));
+            assert_eq!(tags.read_long(2), 3);
+            let address = reader.read_object(2).unwrap();
+            assert_eq!(address.read_string(1), Some("Main"));
+            let (numbers, length) = address.read_list(2).unwrap();
+            assert_eq!(length, 2);
+            assert_eq!(numbers.read_long(1), 2);
+            assert_eq!(
+                address.read_uuid(3).map(|uuid| format_uuid(&uuid)).as_deref(),
+                Some("550e8400-e29b-41d4-a716-446655440000")
+            );
+            assert_eq!(reader.read_string(3), Some("[1,null]"));
+            assert_eq!(reader.read_blob(4).unwrap().as_ref(), &[4, 5]);
+        }
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn updates_without_growing_objects_and_saturates_increments() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("tags", DataType::LongList, None),
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("count", DataType::Byte, None),
+                PropertySchema::new("total", DataType::Long, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance = open(8, "updates_without_growing_objects", vec![schema]);
+        let mut insert = instance.insert(instance.begin_txn(true).unwrap(), 0, 1).unwrap();
+        insert.write_null(1);
+        insert.write_string(2, "item");
+        insert.write_byte(3, 250);
+        insert.write_long(4, i64::MAX - 1);
+        insert.save(1).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let int = |value| IsarValue::Integer(value);
+        let object_size = |instance: &NativeInstance| {
+            let txn = instance.begin_txn(false).unwrap();
+            let mut cursor = instance.collections[0].get_cursor(&txn).unwrap();
+            let size = cursor.move_to(&1i64.to_id_bytes()).unwrap().unwrap().1.len();
+            drop(cursor);
+            instance.abort_txn(txn);
+            size
+        };
+
+        let txn = instance.begin_txn(true).unwrap();
+        let updates = [
+            (1, UpdateOp::Append(int(1))),
+            (1, UpdateOp::Append(int(2))),
+            (3, UpdateOp::Increment(int(10))),
+            (4, UpdateOp::Increment(int(5))),
+        ];
+        assert_eq!(instance.update(&txn, 0, 1, &updates), Ok(true));
+        instance.commit_txn(txn).unwrap();
+        let size = object_size(&instance);
+
+        let txn = instance.begin_txn(true).unwrap();
+        for i in 0..100 {
+            let updates = [
+                (1, UpdateOp::Remove(int(2))),
+                (1, UpdateOp::AddToSet(int(2))),
+                (2, UpdateOp::Set(Some(IsarValue::String(format!("item{}", i % 10))))),
+            ];
+            assert_eq!(instance.update(&txn, 0, 1, &updates), Ok(true));
+        }
+        let updates = [(3, UpdateOp::Decrement(int(i64::MAX)))];
+        assert_eq!(instance.update(&txn, 0, 1, &updates), Ok(true));
+        instance.commit_txn(txn).unwrap();
+        // only the one byte longer name remains
+        assert_eq!(object_size(&instance), size + 1);
+
+        let txn = instance.begin_txn(false).unwrap();
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(1).unwrap();
+            let (tags, length) = reader.read_list(1).unwrap();
+            assert_eq!(length, 2);
+            assert_eq!((tags.read_long(0), tags.read_long(1)), (1, 2));
+            assert_eq!(reader.read_string(2), Some("item9"));
+            assert_eq!(reader.read_byte(3), 0);
+            assert_eq!(reader.read_long(4), i64::MAX);
+        }
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn query_facets_ignores_normalized_indexes() {
+        let schema = IsarSchema::new(
+            "TestCol",
+            None,
+            vec![PropertySchema::new("value", DataType::String, None)],
+            vec![
+                IndexSchema::new("value", vec!["value"], false, false)
+                    .with_normalization(StringNormalization::CASE_INSENSITIVE),
+            ],
+            false,
+        );
+        let instance = open(17, "query_facets_ignores_normalized_indexes", vec![schema]);
+        let values = ["a", "A", "a", "b"];
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, values.len() as u32)
+            .unwrap();
+        for (id, value) in values.into_iter().enumerate() {
+            insert.write_string(1, value);
+            insert.save(id as i64 + 1).unwrap();
+        }
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let txn = instance.begin_txn(false).unwrap();
+        let query = instance.query(0).unwrap().build().unwrap();
+        let facets = instance.query_facets(&txn, &query, 1, false, None).unwrap();
+        let string = |s: &str| Some(IsarValue::String(s.to_string()));
+        assert_eq!(facets, vec![(string("A"), 1), (string("a"), 2), (string("b"), 1)]);
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+}
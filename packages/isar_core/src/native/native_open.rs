@@ -2,7 +2,7 @@ use super::mdbx::env::Env;
 use super::native_collection::NativeCollection;
 use super::native_instance::NativeInstance;
 use super::native_txn::NativeTxn;
-use super::schema_manager::perform_migration;
+use super::schema_manager::{perform_migration, read_schemas};
 use crate::core::error::{IsarError, Result};
 use crate::core::instance::CompactCondition;
 use crate::core::schema::IsarSchema;
@@ -27,15 +27,16 @@ pub(crate) fn open_native(
         None
     };
 
-    // _info + collections + indexes + 1 (to delete old dbs)
+    // _info + collections + indexes + tombstones and their sequences + timestamps + 1 (to
+    // delete old dbs)
     let db_count = schemas
         .iter()
         .filter(|c| !c.embedded)
-        .map(|c| c.indexes.len() as u32 + 1)
+        .map(|c| c.indexes.len() as u32 + 1 + 2 * c.tombstones as u32 + c.field_timestamps as u32)
         .sum::<u32>()
         + 2;
     let env = Env::create(&path, db_count, max_size_mib)?;
-    let collections = perform_migration(instance_id, &env, schemas)?;
+    let collections = perform_migration(instance_id, &env, &schemas)?;
 
     let env_collections = if let Some(compact_condition) = &compact_condition {
         compact_instance(env, collections, &path, compact_condition)?
@@ -44,7 +45,7 @@ pub(crate) fn open_native(
     };
 
     if let Some((env, collections)) = env_collections {
-        let instance = NativeInstance::new(name, dir, instance_id, collections, env);
+        let instance = NativeInstance::new(name, dir, instance_id, schemas, collections, env);
         Ok(instance)
     } else {
         open_native(
@@ -58,6 +59,24 @@ pub(crate) fn open_native(
     }
 }
 
+pub(crate) fn read_native_schemas(
+    name: &str,
+    dir: &str,
+    max_size_mib: u32,
+) -> Result<Vec<IsarSchema>> {
+    let path = get_isar_path(name, dir);
+    if !PathBuf::from(&path).exists() {
+        return Err(IsarError::PathError {});
+    }
+
+    // _info + 1 (unnamed db)
+    let env = Env::create(&path, 2, max_size_mib)?;
+    let txn = NativeTxn::new(0, &env, true)?;
+    let schemas = read_schemas(&txn);
+    txn.abort();
+    schemas
+}
+
 fn compact_instance(
     env: Arc<Env>,
     collections: Vec<NativeCollection>,
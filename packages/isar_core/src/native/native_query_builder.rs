@@ -2,9 +2,14 @@ use super::native_collection::{NativeCollection, NativeProperty};
 use super::query::native_filter::NativeFilter;
 use super::query::{NativeQuery, QueryIndex};
 use crate::core::data_type::DataType;
-use crate::core::filter::{ConditionType, Filter, FilterCondition};
+use crate::core::error::Result;
+use crate::core::filter::{ConditionType, Filter, FilterCondition, FilterElements, FilterGeo};
+use crate::core::filter_regex::FilterRegex;
+use crate::core::normalize::StringNormalization;
 use crate::core::query_builder::{IsarQueryBuilder, Sort};
+use crate::core::uuid::{format_uuid, parse_uuid};
 use crate::core::value::IsarValue;
+use itertools::Itertools;
 use std::hint::black_box;
 
 pub struct NativeQueryBuilder<'a> {
@@ -52,70 +57,219 @@ impl<'a> IsarQueryBuilder for NativeQueryBuilder<'a> {
         }
     }
 
-    fn build(self) -> Self::Query {
-        let filter = self
+    fn add_property(&mut self, _property_index: u16) {
+        // Native readers decode properties lazily, so unprojected properties are never read.
+    }
+
+    fn build(self) -> Result<Self::Query> {
+        let indexes = self
             .filter
-            .map(|f| filter_to_native(&f, self.collection, self.all_collections))
-            .unwrap_or(NativeFilter::stat(true));
+            .as_ref()
+            .and_then(|f| filter_to_indexes(f, self.collection))
+            .unwrap_or_else(|| vec![QueryIndex::Primary(i64::MIN, i64::MAX)]);
+        let filter = if let Some(filter) = &self.filter {
+            filter_to_native(filter, self.collection, self.all_collections)?
+        } else {
+            NativeFilter::stat(true)
+        };
 
-        black_box(NativeQuery::new(
+        Ok(black_box(NativeQuery::new(
             self.instance_id,
             self.collection.collection_index,
-            vec![QueryIndex::Primary(i64::MIN, i64::MAX)],
+            indexes,
             filter,
             self.sort,
             self.distinct,
-        ))
+        )))
+    }
+}
+
+/// Index ranges that contain every object matching the filter. The filter is
+/// still evaluated for each object so the ranges may contain more objects.
+fn filter_to_indexes(filter: &Filter, collection: &NativeCollection) -> Option<Vec<QueryIndex>> {
+    match filter {
+        Filter::Condition(condition) if condition.condition_type == ConditionType::In => {
+            in_to_indexes(condition, collection)
+        }
+        Filter::Geo(geo) => geo_to_indexes(geo, collection),
+        Filter::And(filters) => filters
+            .iter()
+            .find_map(|f| filter_to_indexes(f, collection)),
+        _ => None,
+    }
+}
+
+fn geo_to_indexes(geo: &FilterGeo, collection: &NativeCollection) -> Option<Vec<QueryIndex>> {
+    let latitude = collection.get_property(geo.latitude_index)?;
+    let longitude = collection.get_property(geo.longitude_index)?;
+    let (index_position, index) = collection.indexes.iter().enumerate().find(|(_, index)| {
+        index.geo
+            && index.properties[0].offset == latitude.offset
+            && index.properties[1].offset == longitude.offset
+    })?;
+    let indexes = geo
+        .shape
+        .geohash_ranges()
+        .into_iter()
+        .map(|(lower, upper)| {
+            let (lower, upper) = index.create_geohash_keys(lower, upper);
+            QueryIndex::Secondary(index_position, lower, upper)
+        })
+        .collect();
+    Some(indexes)
+}
+
+fn in_to_indexes(
+    condition: &FilterCondition,
+    collection: &NativeCollection,
+) -> Option<Vec<QueryIndex>> {
+    if condition.property_index == 0 {
+        let ids = condition
+            .values
+            .iter()
+            .flatten()
+            .filter_map(|v| v.i64())
+            .sorted_unstable()
+            .dedup()
+            .map(|id| QueryIndex::Primary(id, id))
+            .collect();
+        return Some(ids);
     }
+
+    let property = collection.get_property(condition.property_index)?;
+    let (index_position, index) = collection.indexes.iter().enumerate().find(|(_, index)| {
+        index.properties.len() == 1
+            && index.properties[0].offset == property.offset
+            && (property.data_type != DataType::String
+                || index.normalization == condition.string_normalization())
+    })?;
+    let keys: Option<Vec<_>> = condition
+        .values
+        .iter()
+        .map(|value| index.create_value_key(value.as_ref()))
+        .collect();
+    let indexes = keys?
+        .into_iter()
+        .sorted_unstable()
+        .dedup()
+        .map(|key| QueryIndex::Secondary(index_position, key.clone(), key))
+        .collect();
+    Some(indexes)
 }
 
 fn filter_to_native(
     filter: &Filter,
     collection: &NativeCollection,
     all_collections: &[NativeCollection],
-) -> NativeFilter {
-    match filter {
+) -> Result<NativeFilter> {
+    let filter = match filter {
         Filter::Condition(condition) => {
-            condition_to_native(condition, collection).unwrap_or(NativeFilter::stat(false))
+            condition_to_native(condition, collection, condition.regex()?)
+                .unwrap_or(NativeFilter::stat(false))
+        }
+        Filter::Param(param) => NativeFilter::param(param.clone(), collection.collection_index),
+        Filter::Json(json) => {
+            json.condition.verify()?;
+            if let Some(property) = collection.get_property(json.property_index) {
+                NativeFilter::json(property, json.condition.clone())
+            } else {
+                NativeFilter::stat(false)
+            }
         }
-        Filter::Json(_) => todo!(),
         Filter::Nested(nested) => {
             if let Some(property) = collection.get_property(nested.property_index) {
                 if let Some(embedded_collection_index) = property.embedded_collection_index {
                     let embedded_collection = &all_collections[embedded_collection_index as usize];
                     let filter =
-                        filter_to_native(&nested.filter, embedded_collection, all_collections);
-                    return NativeFilter::nested(property, filter);
+                        filter_to_native(&nested.filter, embedded_collection, all_collections)?;
+                    return Ok(NativeFilter::nested(property, filter));
                 }
             }
             NativeFilter::stat(false)
         }
+        Filter::Elements(elements) => elements_to_native(elements, collection, all_collections)?
+            .unwrap_or(NativeFilter::stat(false)),
+        Filter::Geo(geo) => {
+            let latitude = collection.get_property(geo.latitude_index);
+            let longitude = collection.get_property(geo.longitude_index);
+            if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+                NativeFilter::geo(latitude, longitude, geo.shape.clone())
+            } else {
+                NativeFilter::stat(false)
+            }
+        }
         Filter::And(filters) => {
             let filters = filters
                 .iter()
                 .map(|f| filter_to_native(f, collection, all_collections))
-                .collect();
+                .collect::<Result<_>>()?;
             NativeFilter::and(filters)
         }
         Filter::Or(filters) => {
             let filters = filters
                 .iter()
                 .map(|f| filter_to_native(f, collection, all_collections))
-                .collect();
+                .collect::<Result<_>>()?;
             NativeFilter::or(filters)
         }
         Filter::Not(filter) => {
-            let filter = filter_to_native(filter, collection, all_collections);
+            let filter = filter_to_native(filter, collection, all_collections)?;
             NativeFilter::not(filter)
         }
+    };
+    Ok(filter)
+}
+
+/// Binds `params` to the parameter conditions of a compiled filter.
+pub(crate) fn bind_filter(
+    filter: &NativeFilter,
+    params: &[Option<IsarValue>],
+    all_collections: &[NativeCollection],
+) -> Result<NativeFilter> {
+    filter.bind(&|param, collection_index| {
+        let condition = param.bind(params)?;
+        let collection = &all_collections[collection_index as usize];
+        let regex = condition.regex()?;
+        Ok(condition_to_native(&condition, collection, regex).unwrap_or(NativeFilter::stat(false)))
+    })
+}
+
+fn elements_to_native(
+    elements: &FilterElements,
+    collection: &NativeCollection,
+    all_collections: &[NativeCollection],
+) -> Result<Option<NativeFilter>> {
+    let Some(property) = collection.get_property(elements.property_index) else {
+        return Ok(None);
+    };
+    if let Some(embedded_collection_index) = property.embedded_collection_index {
+        let embedded_collection = &all_collections[embedded_collection_index as usize];
+        let filter = filter_to_native(&elements.filter, embedded_collection, all_collections)?;
+        Ok(Some(NativeFilter::object_elements(
+            property,
+            elements.quantifier,
+            filter,
+        )))
+    } else if let Filter::Condition(condition) = elements.filter.as_ref() {
+        if condition.property_index != elements.property_index {
+            return Ok(None);
+        }
+        let filter = condition_to_native(condition, collection, condition.regex()?);
+        Ok(filter.map(|f| f.quantified(elements.quantifier)))
+    } else {
+        Ok(None)
     }
 }
 
+/// Converts a condition to a native filter. `regex` is the compiled pattern of
+/// a `StringRegex` condition.
 fn condition_to_native(
     condition: &FilterCondition,
     collection: &NativeCollection,
+    regex: Option<FilterRegex>,
 ) -> Option<NativeFilter> {
     let property = collection.get_property(condition.property_index);
+    let normalization = condition.string_normalization();
     let filter = match condition.condition_type {
         ConditionType::IsNull => NativeFilter::is_null(property?),
         ConditionType::Equal => {
@@ -126,7 +280,7 @@ fn condition_to_native(
                 true,
                 value.as_ref(),
                 true,
-                condition.case_sensitive,
+                normalization,
             )?
         }
         ConditionType::Greater => native_between_filter(
@@ -135,7 +289,7 @@ fn condition_to_native(
             false,
             get_max(property).as_ref(),
             true,
-            condition.case_sensitive,
+            normalization,
         )?,
         ConditionType::GreaterOrEqual => native_between_filter(
             property,
@@ -143,7 +297,7 @@ fn condition_to_native(
             true,
             get_max(property).as_ref(),
             true,
-            condition.case_sensitive,
+            normalization,
         )?,
         ConditionType::Less => native_between_filter(
             property,
@@ -151,7 +305,7 @@ fn condition_to_native(
             true,
             condition.values.get(0)?.as_ref(),
             false,
-            condition.case_sensitive,
+            normalization,
         )?,
         ConditionType::LessOrEqual => native_between_filter(
             property,
@@ -159,7 +313,7 @@ fn condition_to_native(
             true,
             condition.values.get(0)?.as_ref(),
             true,
-            condition.case_sensitive,
+            normalization,
         )?,
         ConditionType::Between => native_between_filter(
             property,
@@ -167,30 +321,49 @@ fn condition_to_native(
             true,
             condition.values.get(1)?.as_ref(),
             true,
-            condition.case_sensitive,
+            normalization,
         )?,
+        ConditionType::In => {
+            if let Some(property) = property {
+                NativeFilter::is_in(property, &condition.values, normalization)
+            } else {
+                NativeFilter::id_in(condition.values.iter().flatten().filter_map(|v| v.i64()))
+            }
+        }
         ConditionType::StringStartsWith => {
             let lower = condition.values.get(0)?.as_ref()?.string()?;
             let upper = format!("{}{}", lower, IsarValue::MAX_STRING);
-            NativeFilter::string(
-                property?,
-                Some(lower),
-                Some(&upper),
-                condition.case_sensitive,
-            )
+            NativeFilter::string(property?, Some(lower), Some(&upper), normalization)
         }
         ConditionType::StringEndsWith => {
             let value = condition.values.get(0)?.as_ref()?.string()?;
-            NativeFilter::string_ends_with(property?, value, condition.case_sensitive)
+            NativeFilter::string_ends_with(property?, value, normalization)
         }
         ConditionType::StringContains => {
             let value = condition.values.get(0)?.as_ref()?.string()?;
-            NativeFilter::string_contains(property?, value, condition.case_sensitive)
+            NativeFilter::string_contains(property?, value, normalization)
         }
         ConditionType::StringMatches => {
             let value = condition.values.get(0)?.as_ref()?.string()?;
-            NativeFilter::string_matches(property?, value, condition.case_sensitive)
+            NativeFilter::string_matches(property?, value, normalization)
+        }
+        ConditionType::ListLength => {
+            let lower = condition
+                .values
+                .first()?
+                .as_ref()
+                .map_or(Some(0), |v| v.i64())?;
+            let upper = condition
+                .values
+                .get(1)?
+                .as_ref()
+                .map_or(Some(i64::MAX), |v| v.i64())?;
+            let lower = lower.clamp(0, u32::MAX as i64) as u32;
+            let upper = upper.clamp(0, u32::MAX as i64) as u32;
+            NativeFilter::list_length(property?, lower, upper)
         }
+        ConditionType::ListIsEmpty => NativeFilter::list_length(property?, 0, 0),
+        ConditionType::StringRegex => NativeFilter::string_regex(property?, regex?),
     };
     Some(filter)
 }
@@ -201,7 +374,7 @@ fn native_between_filter(
     include_lower: bool,
     upper: Option<&IsarValue>,
     include_upper: bool,
-    case_sensitive: bool,
+    normalization: StringNormalization,
 ) -> Option<NativeFilter> {
     let filter = if let Some(property) = property {
         match property.data_type {
@@ -339,7 +512,26 @@ fn native_between_filter(
                     None
                 };
 
-                NativeFilter::string(property, lower.as_deref(), upper.as_deref(), case_sensitive)
+                NativeFilter::string(property, lower.as_deref(), upper.as_deref(), normalization)
+            }
+            DataType::Uuid => {
+                let mut lower = if let Some(lower) = lower {
+                    u128::from_be_bytes(parse_uuid(lower.string()?)?)
+                } else {
+                    u128::MIN
+                };
+                if !include_lower {
+                    lower = lower.checked_add(1)?;
+                }
+                let mut upper = if let Some(upper) = upper {
+                    u128::from_be_bytes(parse_uuid(upper.string()?)?)
+                } else {
+                    u128::MIN
+                };
+                if !include_upper {
+                    upper = upper.checked_sub(1)?;
+                }
+                NativeFilter::uuid(property, lower, upper)
             }
             DataType::Object | DataType::ObjectList => return None,
         }
@@ -415,6 +607,7 @@ fn get_max(property: Option<&NativeProperty>) -> Option<IsarValue> {
             DataType::String | DataType::StringList | DataType::Json => {
                 IsarValue::String(IsarValue::MAX_STRING.to_string())
             }
+            DataType::Uuid => IsarValue::String(format_uuid(&[u8::MAX; 16])),
             DataType::Object | DataType::ObjectList => return None,
         }
     } else {
@@ -422,3 +615,188 @@ fn get_max(property: Option<&NativeProperty>) -> Option<IsarValue> {
     };
     Some(value)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cursor::IsarQueryCursor;
+    use crate::core::error::IsarError;
+    use crate::core::filter::{FilterJson, FilterParam, JsonCondition, ListQuantifier};
+    use crate::core::instance::IsarInstance;
+    use crate::core::query_plan::{IndexScan, QueryPlan};
+    use crate::core::reader::IsarReader;
+    use crate::core::schema::{IndexSchema, IsarSchema, PropertySchema};
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::core::writer::IsarWriter;
+    use crate::native::isar_deserializer::IsarDeserializer;
+    use crate::native::isar_serializer::IsarSerializer;
+    use crate::native::native_instance::NativeInstance;
+
+    #[test]
+    fn test_bind_filter() {
+        let collection = NativeCollection::new(
+            0,
+            "",
+            None,
+            vec![(
+                "value".to_string(),
+                NativeProperty::new(DataType::Long, 0, None),
+            )],
+            vec![],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let collections = [collection];
+        let param = Filter::Param(FilterParam::new(
+            1,
+            ConditionType::Between,
+            vec![0, 1],
+            StringNormalization::NONE,
+        ));
+        let filter = filter_to_native(
+            &Filter::Or(vec![
+                param,
+                Filter::Condition(FilterCondition::new(1, ConditionType::IsNull, vec![], true)),
+            ]),
+            &collections[0],
+            &collections,
+        )
+        .unwrap();
+        assert!(filter.has_params());
+
+        let mut serializer = IsarSerializer::new(Vec::new(), 0, 8);
+        serializer.write_long(0, 5);
+        let object = serializer.finish();
+        let matches =
+            |filter: &NativeFilter| filter.evaluate(1, IsarDeserializer::from_bytes(&object));
+        assert!(!matches(&filter));
+
+        let bound = bind_filter(
+            &filter,
+            &[Some(IsarValue::Integer(1)), Some(IsarValue::Integer(10))],
+            &collections,
+        )
+        .unwrap();
+        assert!(!bound.has_params());
+        assert!(matches(&bound));
+
+        let bound = bind_filter(
+            &filter,
+            &[Some(IsarValue::Integer(6)), Some(IsarValue::Integer(10))],
+            &collections,
+        )
+        .unwrap();
+        assert!(!matches(&bound));
+
+        assert!(bind_filter(&filter, &[Some(IsarValue::Integer(1))], &collections).is_err());
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let collection = NativeCollection::new(
+            0,
+            "",
+            None,
+            vec![
+                (
+                    "value".to_string(),
+                    NativeProperty::new(DataType::String, 0, None),
+                ),
+                (
+                    "json".to_string(),
+                    NativeProperty::new(DataType::Json, 4, None),
+                ),
+            ],
+            vec![],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let collections = [collection];
+        let pattern = Some(IsarValue::String("(a".to_string()));
+        let condition =
+            FilterCondition::new(1, ConditionType::StringRegex, vec![pattern.clone()], true);
+        let json = JsonCondition::new(
+            vec![],
+            ConditionType::StringRegex,
+            false,
+            vec![pattern],
+            true,
+        );
+        let filters = [
+            Filter::Condition(condition.clone()),
+            Filter::Json(FilterJson::new(2, json)),
+            Filter::Elements(FilterElements::new(
+                1,
+                ListQuantifier::Any,
+                Filter::Condition(condition),
+            )),
+        ];
+        for filter in filters {
+            let result = filter_to_native(&filter, &collections[0], &collections);
+            assert!(matches!(result, Err(IsarError::IllegalArgument {})));
+        }
+    }
+
+    #[test]
+    fn normalized_indexes_serve_conditions_with_the_same_normalization() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![PropertySchema::new("value", DataType::String, None)],
+            vec![IndexSchema::new("value", vec!["value"], false, false)
+                .with_normalization(StringNormalization::CASE_INSENSITIVE)],
+            false,
+        );
+        let instance = open_instance::<NativeInstance>(
+            "normalized_indexes_serve_conditions_with_the_same_normalization",
+            vec![schema],
+        );
+        let values = ["Ärger", "arger", "ärger"];
+        insert_objects(instance.as_ref(), 0, &[1, 2, 3], |insert, id| {
+            insert.write_string(1, values[id as usize - 1]);
+        });
+
+        let txn = instance.begin_txn(false).unwrap();
+        let query = |normalization| {
+            let mut qb = instance.query(0).unwrap();
+            qb.set_filter(Filter::Condition(FilterCondition::new_normalized(
+                1,
+                ConditionType::In,
+                vec![Some(IsarValue::String("ÄRGER".to_string()))],
+                normalization,
+            )));
+            qb.build().unwrap()
+        };
+        let read = |query: &NativeQuery| {
+            let mut cursor = instance.query_cursor(&txn, query, None, None).unwrap();
+            let mut ids = vec![];
+            while let Some(reader) = cursor.next() {
+                ids.push(reader.read_id());
+            }
+            ids
+        };
+        let uses_index = |query: &NativeQuery| {
+            let QueryPlan::Native(plan) = instance.explain(&txn, query).unwrap() else {
+                panic!("expected native plan");
+            };
+            matches!(plan.scans[..], [IndexScan::Secondary { .. }])
+        };
+
+        let folded = query(StringNormalization::CASE_INSENSITIVE);
+        assert!(uses_index(&folded));
+        assert_eq!(read(&folded), vec![1, 3]);
+        let exact = query(StringNormalization::NONE);
+        assert!(!uses_index(&exact));
+        assert_eq!(read(&exact), Vec::<i64>::new());
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+}
@@ -141,6 +141,12 @@ impl<'a> IsarReader for NativeReader<'a> {
         self.object.read_dynamic(offset).map(Cow::Borrowed)
     }
 
+    #[inline]
+    fn read_uuid(&self, index: u32) -> Option<[u8; 16]> {
+        let offset = self.get_offset(index, DataType::Uuid)?;
+        self.object.read_uuid(offset)
+    }
+
     fn read_object(&self, index: u32) -> Option<Self::ObjectReader<'_>> {
         let property = self.get_property(index)?;
         if property.data_type != DataType::Object {
@@ -277,6 +283,10 @@ impl<'a> IsarReader for NativeListReader<'a> {
         None // nested lists are not supported
     }
 
+    fn read_uuid(&self, _index: u32) -> Option<[u8; 16]> {
+        None // there are no UUID lists
+    }
+
     fn read_object(&self, index: u32) -> Option<Self::ObjectReader<'_>> {
         if self.data_type != DataType::Object {
             return None;
@@ -330,12 +340,36 @@ mod test {
             ));
             offset += prop_type.static_size() as u32;
         }
-        NativeCollection::new(0, "", None, properties, vec![], None)
+        NativeCollection::new(
+            0,
+            "",
+            None,
+            properties,
+            vec![],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
     }
 
     #[test]
     fn test_reader_id_name() {
-        let collection = NativeCollection::new(0, "", Some("myid"), vec![], vec![], None);
+        let collection = NativeCollection::new(
+            0,
+            "",
+            Some("myid"),
+            vec![],
+            vec![],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
         let reader = NativeReader::new(
             0,
             IsarDeserializer::from_bytes(&[0, 0, 0]),
@@ -359,6 +393,11 @@ mod test {
             ],
             vec![],
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
 
         let reader = NativeReader::new(
@@ -532,6 +571,23 @@ mod test {
         assert_eq!(reader.is_null(3), true);
     }
 
+    #[test]
+    fn test_reader_read_uuid() {
+        let col: NativeCollection = get_collection(vec![Uuid, Uuid, Byte]);
+        let uuid: [u8; 16] = core::array::from_fn(|i| i as u8 + 1);
+        let bytes = concat!([33, 0, 0], uuid, [0; 16], [1]);
+        let reader = NativeReader::new(0, IsarDeserializer::from_bytes(&bytes), &col, &[]);
+
+        assert_eq!(reader.read_uuid(1), Some(uuid));
+        assert_eq!(reader.read_uuid(2), None);
+        assert_eq!(reader.read_uuid(3), None);
+        assert_eq!(reader.read_uuid(4), None);
+
+        assert_eq!(reader.is_null(1), false);
+        assert_eq!(reader.is_null(2), true);
+        assert_eq!(reader.is_null(3), false);
+    }
+
     #[test]
     fn test_reader_read_object() {
         let col1: NativeCollection = get_collection(vec![Object, Bool, String]);
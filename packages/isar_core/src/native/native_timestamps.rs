@@ -0,0 +1,242 @@
+use super::mdbx::db::Db;
+use super::native_txn::NativeTxn;
+use super::IdToBytes;
+use crate::core::error::Result;
+use crate::core::merge::{
+    decode_timestamps, encode_timestamps, now_millis, stamp_local_write, FieldTimestamp,
+};
+
+/// Side db that maps object ids to the timestamps of the last write of their
+/// fields.
+pub(crate) struct NativeTimestamps {
+    db: Db,
+}
+
+impl NativeTimestamps {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+
+    pub fn get(&self, txn: &NativeTxn, id: i64) -> Result<Vec<FieldTimestamp>> {
+        let mut cursor = txn.get_cursor(self.db)?;
+        let timestamps = cursor
+            .move_to(&id.to_id_bytes())?
+            .map(|(_, bytes)| decode_timestamps(bytes))
+            .unwrap_or_default();
+        Ok(timestamps)
+    }
+
+    pub fn put(&self, txn: &NativeTxn, id: i64, timestamps: &[FieldTimestamp]) -> Result<()> {
+        let mut cursor = txn.get_cursor(self.db)?;
+        cursor.put(&id.to_id_bytes(), &encode_timestamps(timestamps))
+    }
+
+    /// Stamps the fields of a local write of the object.
+    pub fn stamp(
+        &self,
+        txn: &NativeTxn,
+        id: i64,
+        property_indexes: impl IntoIterator<Item = u16>,
+    ) -> Result<()> {
+        let mut timestamps = self.get(txn, id)?;
+        stamp_local_write(&mut timestamps, property_indexes, now_millis());
+        self.put(txn, id, &timestamps)
+    }
+
+    pub fn remove(&self, txn: &NativeTxn, id: i64) -> Result<()> {
+        let mut cursor = txn.get_cursor(self.db)?;
+        if cursor.move_to(&id.to_id_bytes())?.is_some() {
+            cursor.delete_current()?;
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self, txn: &NativeTxn) -> Result<()> {
+        txn.clear_db(self.db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cursor::IsarCursor;
+    use crate::core::data_type::DataType;
+    use crate::core::insert::IsarInsert;
+    use crate::core::instance::IsarInstance;
+    use crate::core::merge::{FieldUpdate, HlcTimestamp, MergeResult, ObjectChange};
+    use crate::core::query_builder::IsarQueryBuilder;
+    use crate::core::reader::IsarReader;
+    use crate::core::schema::{IsarSchema, PropertySchema};
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::core::update::UpdateOp;
+    use crate::core::value::IsarValue;
+    use crate::core::writer::IsarWriter;
+    use crate::native::native_instance::NativeInstance;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn merges_changes_by_field_timestamp() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![
+                PropertySchema::new("value", DataType::Int, None),
+                PropertySchema::new("name", DataType::String, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_field_timestamps();
+        let instance =
+            open_instance::<NativeInstance>("merges_changes_by_field_timestamp", vec![schema]);
+        let notified = Arc::new(AtomicU32::new(0));
+        let notified_clone = notified.clone();
+        let _handle = instance
+            .watch(
+                0,
+                Box::new(move || {
+                    notified_clone.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .unwrap();
+        let changes = Arc::new(Mutex::new(vec![]));
+        let changes_clone = changes.clone();
+        let _detailed_handle = instance
+            .watch_detailed(
+                0,
+                Box::new(move |change| {
+                    changes_clone
+                        .lock()
+                        .unwrap()
+                        .push((change.object_id, change.remote))
+                }),
+            )
+            .unwrap();
+        // remote changes are newer than the local writes
+        let t = now_millis() + 60_000;
+        let merged = |merged: u32, missing: Vec<i64>| MergeResult { merged, missing };
+        let change = |id: i64, updates: &[(u16, IsarValue, u64, u32)]| ObjectChange {
+            id,
+            updates: updates
+                .iter()
+                .map(|(property_index, value, millis, node)| FieldUpdate {
+                    property_index: *property_index,
+                    value: Some(value.clone()),
+                    timestamp: HlcTimestamp::new(t + *millis, 0, *node),
+                })
+                .collect(),
+        };
+        let read = |txn: &NativeTxn, id: i64| {
+            let mut cursor = instance.cursor(txn, 0).unwrap();
+            let reader = cursor.next(id).unwrap();
+            (reader.read_int(1), reader.read_string(2).map(String::from))
+        };
+
+        insert_objects(instance.as_ref(), 0, &[1], |insert, _| {
+            insert.write_int(1, 0);
+            insert.write_null(2);
+        });
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+        changes.lock().unwrap().clear();
+
+        let txn = instance.begin_txn(true).unwrap();
+        let remote = [
+            change(
+                1,
+                &[
+                    (1, IsarValue::Integer(10), 5, 1),
+                    (2, IsarValue::String("a".to_string()), 5, 1),
+                ],
+            ),
+            change(2, &[(1, IsarValue::Integer(10), 5, 1)]),
+        ];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &remote, true),
+            Ok(merged(1, vec![2]))
+        );
+        assert_eq!(read(&txn, 1), (10, Some("a".to_string())));
+        instance.commit_txn(txn).unwrap();
+        // merged remote changes notify the watchers too
+        assert_eq!(notified.load(Ordering::SeqCst), 2);
+
+        let txn = instance.begin_txn(true).unwrap();
+
+        let local = [change(1, &[(1, IsarValue::Integer(20), 7, 2)])];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &local, false),
+            Ok(merged(1, vec![]))
+        );
+
+        // the older remote value must not clobber the newer local edit
+        let remote = [change(
+            1,
+            &[
+                (1, IsarValue::Integer(30), 6, 1),
+                (2, IsarValue::String("b".to_string()), 6, 1),
+            ],
+        )];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &remote, true),
+            Ok(merged(1, vec![]))
+        );
+        assert_eq!(read(&txn, 1), (20, Some("b".to_string())));
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &remote, true),
+            Ok(merged(0, vec![]))
+        );
+        instance.commit_txn(txn).unwrap();
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![(1, true), (1, false), (1, true)]
+        );
+        assert_eq!(notified.load(Ordering::SeqCst), 3);
+
+        // local writes win over concurrent remote changes
+        let txn = instance.begin_txn(true).unwrap();
+        let update = (1, UpdateOp::Set(Some(IsarValue::Integer(50))));
+        assert_eq!(instance.update(&txn, 0, 1, &[update]), Ok(true));
+        let query = instance.query(0).unwrap().build().unwrap();
+        let update = (2, UpdateOp::Set(Some(IsarValue::String("c".to_string()))));
+        assert_eq!(
+            instance.query_update(&txn, &query, None, None, &[update]),
+            Ok(1)
+        );
+        let concurrent = [change(
+            1,
+            &[
+                (1, IsarValue::Integer(60), 7, 9),
+                (2, IsarValue::String("d".to_string()), 6, 9),
+            ],
+        )];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &concurrent, true),
+            Ok(merged(0, vec![]))
+        );
+        assert_eq!(read(&txn, 1), (50, Some("c".to_string())));
+        instance.abort_txn(txn);
+
+        // objects put locally win over remote changes of the past
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 1)
+            .unwrap();
+        insert.write_int(1, 1);
+        insert.write_null(2);
+        insert.save(3).unwrap();
+        let txn = insert.finish().unwrap();
+        let past = ObjectChange {
+            id: 3,
+            updates: vec![FieldUpdate {
+                property_index: 1,
+                value: Some(IsarValue::Integer(2)),
+                timestamp: HlcTimestamp::new(1, 0, 1),
+            }],
+        };
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &[past], true),
+            Ok(merged(0, vec![]))
+        );
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+}
@@ -0,0 +1,195 @@
+use super::mdbx::db::Db;
+use super::native_txn::NativeTxn;
+use super::{BytesToId, IdToBytes};
+use crate::core::error::Result;
+use crate::core::tombstone::Tombstone;
+use itertools::Itertools;
+use std::sync::atomic::{self, AtomicI64};
+
+/// Side db that maps the ids of deleted objects to their deletion time and
+/// sequence. A second db maps the sequences back to the ids so tombstones can
+/// be read in sequence order. The last sequence is stored in the info db so
+/// sequences are never reused, even after all tombstones have been purged.
+pub(crate) struct NativeTombstones {
+    db: Db,
+    sequence_db: Db,
+    info_db: Db,
+    sequence_key: String,
+    sequence: AtomicI64,
+}
+
+impl NativeTombstones {
+    pub fn new(db: Db, sequence_db: Db, info_db: Db, sequence_key: String) -> Self {
+        Self {
+            db,
+            sequence_db,
+            info_db,
+            sequence_key,
+            sequence: AtomicI64::new(0),
+        }
+    }
+
+    pub fn init_sequence(&self, txn: &NativeTxn) -> Result<()> {
+        let mut cursor = txn.get_cursor(self.info_db)?;
+        if let Some((_, bytes)) = cursor.move_to(self.sequence_key.as_bytes())? {
+            let sequence = i64::from_le_bytes(bytes.try_into().unwrap_or_default());
+            self.sequence.store(sequence, atomic::Ordering::Release);
+        }
+        Ok(())
+    }
+
+    pub fn put(&self, txn: &NativeTxn, id: i64, deleted_at: i64) -> Result<i64> {
+        self.remove(txn, id)?;
+        let sequence = self.sequence.fetch_add(1, atomic::Ordering::AcqRel) + 1;
+        let mut info_cursor = txn.get_cursor(self.info_db)?;
+        info_cursor.put(self.sequence_key.as_bytes(), &sequence.to_le_bytes())?;
+
+        let mut cursor = txn.get_cursor(self.db)?;
+        cursor.put(&id.to_id_bytes(), &Self::value(deleted_at, sequence))?;
+        let mut sequence_cursor = txn.get_cursor(self.sequence_db)?;
+        sequence_cursor.put(&sequence.to_id_bytes(), &Self::value(deleted_at, id))?;
+        Ok(sequence)
+    }
+
+    fn value(deleted_at: i64, other: i64) -> [u8; 16] {
+        let mut value = [0; 16];
+        value[..8].copy_from_slice(&deleted_at.to_le_bytes());
+        value[8..].copy_from_slice(&other.to_le_bytes());
+        value
+    }
+
+    pub fn remove(&self, txn: &NativeTxn, id: i64) -> Result<bool> {
+        let mut cursor = txn.get_cursor(self.db)?;
+        if let Some((_, value)) = cursor.move_to(&id.to_id_bytes())? {
+            let sequence = i64::from_le_bytes(value[8..16].try_into().unwrap());
+            cursor.delete_current()?;
+            let mut sequence_cursor = txn.get_cursor(self.sequence_db)?;
+            if sequence_cursor.move_to(&sequence.to_id_bytes())?.is_some() {
+                sequence_cursor.delete_current()?;
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Iterates the tombstones with a sequence greater than `after_sequence`
+    /// in sequence order.
+    fn iter_after<'txn>(
+        &self,
+        txn: &'txn NativeTxn,
+        after_sequence: i64,
+    ) -> Result<impl Iterator<Item = Tombstone> + 'txn> {
+        let cursor = txn.get_cursor(self.sequence_db)?;
+        let start = after_sequence.saturating_add(1);
+        let iterator =
+            cursor
+                .iter_between_ids(start, i64::MAX, false, false)?
+                .map(|(key, value)| Tombstone {
+                    id: i64::from_le_bytes(value[8..16].try_into().unwrap()),
+                    deleted_at: i64::from_le_bytes(value[..8].try_into().unwrap()),
+                    sequence: key.to_id(),
+                });
+        Ok(iterator)
+    }
+
+    pub fn get_after(
+        &self,
+        txn: &NativeTxn,
+        after_sequence: i64,
+        limit: u32,
+    ) -> Result<Vec<Tombstone>> {
+        let tombstones = self
+            .iter_after(txn, after_sequence)?
+            .take(limit as usize)
+            .collect();
+        Ok(tombstones)
+    }
+
+    /// Removes the tombstones deleted at or before `deleted_before`. Later
+    /// sequences are deleted later, so the oldest tombstones come first.
+    pub fn purge(&self, txn: &NativeTxn, deleted_before: i64) -> Result<u32> {
+        let ids = self
+            .iter_after(txn, i64::MIN)?
+            .take_while(|tombstone| tombstone.deleted_at <= deleted_before)
+            .map(|tombstone| tombstone.id)
+            .collect_vec();
+        for id in &ids {
+            self.remove(txn, *id)?;
+        }
+        Ok(ids.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data_type::DataType;
+    use crate::core::insert::IsarInsert;
+    use crate::core::instance::IsarInstance;
+    use crate::core::schema::{IsarSchema, PropertySchema};
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::core::writer::IsarWriter;
+    use crate::native::native_instance::NativeInstance;
+
+    #[test]
+    fn reads_and_purges_tombstones_in_sequence_order() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![PropertySchema::new("value", DataType::Long, None)],
+            vec![],
+            false,
+        )
+        .with_tombstones();
+        let instance = open_instance::<NativeInstance>(
+            "reads_and_purges_tombstones_in_sequence_order",
+            vec![schema],
+        );
+        insert_objects(instance.as_ref(), 0, &[1, 2, 3, 4], |insert, id| {
+            insert.write_long(1, id);
+        });
+        let txn = instance.begin_txn(true).unwrap();
+        for id in [3, 1, 4, 2] {
+            // distinct deletion times
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            assert_eq!(instance.delete(&txn, 0, id), Ok(true));
+        }
+        instance.commit_txn(txn).unwrap();
+
+        let txn = instance.begin_txn(true).unwrap();
+        let ids = |tombstones: Vec<Tombstone>| tombstones.iter().map(|t| t.id).collect_vec();
+        let tombstones = instance.get_tombstones(&txn, 0, 0, None).unwrap();
+        assert_eq!(ids(tombstones.clone()), vec![3, 1, 4, 2]);
+        let sequences = tombstones.iter().map(|t| t.sequence).collect_vec();
+        assert!(sequences.windows(2).all(|s| s[0] < s[1]));
+        let after = instance
+            .get_tombstones(&txn, 0, sequences[0], Some(2))
+            .unwrap();
+        assert_eq!(ids(after), vec![1, 4]);
+
+        // saving an object again removes its tombstone
+        let mut insert = instance.insert(txn, 0, 1).unwrap();
+        insert.write_long(1, 1);
+        insert.save(1).unwrap();
+        let txn = insert.finish().unwrap();
+        assert_eq!(
+            ids(instance.get_tombstones(&txn, 0, 0, None).unwrap()),
+            vec![3, 4, 2]
+        );
+
+        let deleted_at = tombstones[2].deleted_at;
+        assert_eq!(instance.purge_tombstones(&txn, 0, deleted_at), Ok(2));
+        assert_eq!(
+            ids(instance.get_tombstones(&txn, 0, 0, None).unwrap()),
+            vec![2]
+        );
+        assert_eq!(instance.purge_tombstones(&txn, 0, i64::MAX), Ok(1));
+        assert!(instance
+            .get_tombstones(&txn, 0, 0, None)
+            .unwrap()
+            .is_empty());
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+}
@@ -13,10 +13,13 @@ use std::sync::Arc;
 
 pub struct NativeTxn {
     pub(crate) instance_id: u32,
+    write: bool,
     txn: Txn,
     buffer: Cell<Option<Vec<u8>>>,
     change_set: RefCell<ChangeSet>,
     unbound_cursors: RefCell<Vec<UnboundCursor>>,
+    /// Collections with an expiry property that objects were written to.
+    expiring_collections: RefCell<Vec<u16>>,
 }
 
 impl NativeTxn {
@@ -24,14 +27,20 @@ impl NativeTxn {
         let txn = env.txn(write)?;
         let txn = Self {
             instance_id,
+            write,
             txn,
             buffer: Cell::new(None),
             change_set: RefCell::new(ChangeSet::new()),
             unbound_cursors: RefCell::new(Vec::new()),
+            expiring_collections: RefCell::new(Vec::new()),
         };
         Ok(txn)
     }
 
+    pub(crate) fn is_write(&self) -> bool {
+        self.write
+    }
+
     pub(crate) fn get_cursor<'txn>(&'txn self, db: Db) -> Result<TxnCursor<'txn>> {
         let unbound = self
             .unbound_cursors
@@ -50,6 +59,17 @@ impl NativeTxn {
         self.change_set.borrow_mut()
     }
 
+    pub(crate) fn mark_expiring(&self, collection_index: u16) {
+        let mut collections = self.expiring_collections.borrow_mut();
+        if !collections.contains(&collection_index) {
+            collections.push(collection_index);
+        }
+    }
+
+    pub(crate) fn take_expiring(&self) -> Vec<u16> {
+        self.expiring_collections.take()
+    }
+
     #[inline]
     pub(crate) fn guard<T, F>(&self, job: F) -> Result<T>
     where
@@ -11,6 +11,12 @@ pub(crate) fn verify_native(txn: &NativeTxn, collections: &[NativeCollection]) -
             for index in &col.indexes {
                 db_names.push(format!("_{}_{}", col.name, index.name));
             }
+            if col.tombstones.is_some() {
+                db_names.push(format!("_{}__tombstones", col.name));
+            }
+            if col.timestamps.is_some() {
+                db_names.push(format!("_{}__timestamps", col.name));
+            }
         }
     }
     let mut actual_db_names = txn.db_names()?;
@@ -26,6 +26,9 @@ pub(crate) trait WriterImpl<'a> {
     fn get_collections(&self) -> &'a [NativeCollection];
 
     fn get_serializer(&mut self) -> &mut IsarSerializer;
+
+    #[inline]
+    fn set_written(&mut self, _index: u32) {}
 }
 
 impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
@@ -45,6 +48,7 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     #[inline]
     fn write_null(&mut self, index: u32) {
         if let Some((data_type, offset, _)) = self.get_property(index) {
+            self.set_written(index);
             self.get_serializer().write_null(offset, data_type);
         }
     }
@@ -52,6 +56,7 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     #[inline]
     fn write_bool(&mut self, index: u32, value: bool) {
         if let Some(offset) = self.get_offset(index, DataType::Bool) {
+            self.set_written(index);
             self.get_serializer().write_bool(offset, value);
         }
     }
@@ -59,6 +64,7 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     #[inline]
     fn write_byte(&mut self, index: u32, value: u8) {
         if let Some(offset) = self.get_offset(index, DataType::Byte) {
+            self.set_written(index);
             self.get_serializer().write_byte(offset, value);
         }
     }
@@ -66,6 +72,7 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     #[inline]
     fn write_int(&mut self, index: u32, value: i32) {
         if let Some(offset) = self.get_offset(index, DataType::Int) {
+            self.set_written(index);
             self.get_serializer().write_int(offset, value);
         }
     }
@@ -73,6 +80,7 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     #[inline]
     fn write_float(&mut self, index: u32, value: f32) {
         if let Some(offset) = self.get_offset(index, DataType::Float) {
+            self.set_written(index);
             self.get_serializer().write_float(offset, value);
         }
     }
@@ -80,6 +88,7 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     #[inline]
     fn write_long(&mut self, index: u32, value: i64) {
         if let Some(offset) = self.get_offset(index, DataType::Long) {
+            self.set_written(index);
             self.get_serializer().write_long(offset, value);
         }
     }
@@ -87,15 +96,18 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     #[inline]
     fn write_double(&mut self, index: u32, value: f64) {
         if let Some(offset) = self.get_offset(index, DataType::Double) {
+            self.set_written(index);
             self.get_serializer().write_double(offset, value);
         }
     }
 
     #[inline]
     fn write_string(&mut self, index: u32, value: &str) {
-        if let Some((data_type, index, _)) = self.get_property(index) {
+        if let Some((data_type, offset, _)) = self.get_property(index) {
             if data_type == DataType::String || data_type == DataType::Json {
-                self.get_serializer().write_dynamic(index, value.as_bytes());
+                self.set_written(index);
+                self.get_serializer()
+                    .write_dynamic(offset, value.as_bytes());
             }
         }
     }
@@ -103,13 +115,23 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     #[inline]
     fn write_byte_list(&mut self, index: u32, value: &[u8]) {
         if let Some(offset) = self.get_offset(index, DataType::ByteList) {
+            self.set_written(index);
             self.get_serializer().write_dynamic(offset, value);
         }
     }
 
+    #[inline]
+    fn write_uuid(&mut self, index: u32, value: &[u8; 16]) {
+        if let Some(offset) = self.get_offset(index, DataType::Uuid) {
+            self.set_written(index);
+            self.get_serializer().write_uuid(offset, value);
+        }
+    }
+
     fn begin_object(&mut self, index: u32) -> Option<Self::ObjectWriter> {
         let (data_type, offset, collection_index) = self.get_property(index)?;
         if data_type == DataType::Object {
+            self.set_written(index);
             let collections = self.get_collections();
             let collection = &collections[collection_index? as usize];
 
@@ -130,6 +152,7 @@ impl<'a, T: WriterImpl<'a>> IsarWriter<'a> for T {
     fn begin_list(&mut self, index: u32, length: u32) -> Option<Self::ListWriter> {
         let (data_type, offset, embedded_collection_index) = self.get_property(index)?;
         if let Some(element_type) = data_type.element_type() {
+            self.set_written(index);
             let list = self
                 .get_serializer()
                 .begin_nested(offset, element_type.static_size() as u32 * length);
@@ -183,6 +206,11 @@ impl<'a> WriterImpl<'a> for NativeInsert<'a> {
     fn get_serializer(&mut self) -> &mut IsarSerializer {
         &mut self.object
     }
+
+    #[inline]
+    fn set_written(&mut self, index: u32) {
+        self.written[index as usize] = true;
+    }
 }
 
 pub struct NativeObjectWriter<'a> {
@@ -1,6 +1,8 @@
+use super::facet::facets_by_scan;
 use super::query_iterator::QueryIterator;
+use super::NativeQuery;
 use crate::core::data_type::DataType;
-use crate::core::value::IsarValue;
+use crate::core::value::{IntegerSum, IsarValue};
 use crate::native::native_collection::NativeProperty;
 use crate::native::{NULL_INT, NULL_LONG};
 use std::cmp::Ordering;
@@ -13,12 +15,12 @@ pub(crate) fn aggregate_sum_average<'a>(
     if let Some(property) = property {
         match property.data_type {
             DataType::Byte | DataType::Int | DataType::Long => {
-                let mut sum = 0i64;
+                let mut sum = IntegerSum::default();
                 let mut count = 0i64;
                 match property.data_type {
                     DataType::Byte => {
                         for (_, reader) in iterator {
-                            sum += reader.read_byte(property.offset) as i64;
+                            sum.add(reader.read_byte(property.offset) as i64);
                             count += 1;
                         }
                     }
@@ -26,7 +28,7 @@ pub(crate) fn aggregate_sum_average<'a>(
                         for (_, reader) in iterator {
                             let value = reader.read_int(property.offset);
                             if value != NULL_INT {
-                                sum += value as i64;
+                                sum.add(value as i64);
                                 count += 1;
                             }
                         }
@@ -35,7 +37,7 @@ pub(crate) fn aggregate_sum_average<'a>(
                         for (_, reader) in iterator {
                             let value = reader.read_long(property.offset);
                             if value != NULL_LONG {
-                                sum += value;
+                                sum.add(value);
                                 count += 1;
                             }
                         }
@@ -44,9 +46,9 @@ pub(crate) fn aggregate_sum_average<'a>(
                 }
 
                 if aggregate_sum {
-                    Some(IsarValue::Integer(sum))
+                    Some(sum.to_value())
                 } else if count > 0 {
-                    Some(IsarValue::Real(sum as f64 / count as f64))
+                    Some(IsarValue::Real(sum.to_f64() / count as f64))
                 } else {
                     Some(IsarValue::Real(f64::NAN))
                 }
@@ -87,18 +89,18 @@ pub(crate) fn aggregate_sum_average<'a>(
             _ => None,
         }
     } else {
-        let mut sum = 0i64;
+        let mut sum = IntegerSum::default();
         let mut count = 0i64;
         for (id, _) in iterator {
             if id != NULL_LONG {
-                sum += id;
+                sum.add(id);
                 count += 1;
             }
         }
         if aggregate_sum {
-            Some(IsarValue::Integer(sum))
+            Some(sum.to_value())
         } else if count > 0 {
-            Some(IsarValue::Real(sum as f64 / count as f64))
+            Some(IsarValue::Real(sum.to_f64() / count as f64))
         } else {
             Some(IsarValue::Real(f64::NAN))
         }
@@ -246,3 +248,100 @@ pub(crate) fn aggregate_min_max<'a>(
         }
     }
 }
+
+pub(crate) fn aggregate_count_distinct(
+    iterator: QueryIterator,
+    property: Option<&NativeProperty>,
+) -> Option<IsarValue> {
+    let count = if let Some(property) = property {
+        match property.data_type {
+            DataType::Bool
+            | DataType::Byte
+            | DataType::Int
+            | DataType::Float
+            | DataType::Long
+            | DataType::Double
+            | DataType::String => facets_by_scan(iterator, property)
+                .iter()
+                .filter(|(value, _)| value.is_some())
+                .count(),
+            _ => return None,
+        }
+    } else {
+        iterator.count()
+    };
+    Some(IsarValue::Integer(count as i64))
+}
+
+pub(crate) fn aggregate_percentile(
+    iterator: QueryIterator,
+    property: Option<&NativeProperty>,
+    percentile: f64,
+) -> Option<IsarValue> {
+    let mut values = numeric_values(iterator, property)?;
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+
+    let rank = percentile * (values.len() - 1) as f64;
+    let lower = rank.floor();
+    let lower_value = values[lower as usize];
+    let upper_value = values
+        .get(lower as usize + 1)
+        .copied()
+        .unwrap_or(lower_value);
+    Some(IsarValue::Real(
+        lower_value + (upper_value - lower_value) * (rank - lower),
+    ))
+}
+
+pub(crate) fn aggregate_variance(
+    iterator: QueryIterator,
+    property: Option<&NativeProperty>,
+    std_dev: bool,
+) -> Option<IsarValue> {
+    let values = numeric_values(iterator, property)?;
+    if values.is_empty() {
+        return None;
+    }
+
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean) * (value - mean))
+        .sum::<f64>()
+        / count;
+    if std_dev {
+        Some(IsarValue::Real(variance.sqrt()))
+    } else {
+        Some(IsarValue::Real(variance))
+    }
+}
+
+fn numeric_values(iterator: QueryIterator, property: Option<&NativeProperty>) -> Option<Vec<f64>> {
+    let mut values = vec![];
+    if let Some(property) = property {
+        match property.data_type {
+            DataType::Byte
+            | DataType::Int
+            | DataType::Float
+            | DataType::Long
+            | DataType::Double => {}
+            _ => return None,
+        }
+        for (_, reader) in iterator {
+            match NativeQuery::read_value(reader, property) {
+                Some(IsarValue::Integer(value)) => values.push(value as f64),
+                Some(IsarValue::Real(value)) => values.push(value),
+                _ => {}
+            }
+        }
+    } else {
+        for (id, _) in iterator {
+            values.push(id as f64);
+        }
+    }
+    Some(values)
+}
@@ -0,0 +1,104 @@
+use super::native_filter::NativeFilter;
+use super::query_iterator::QueryIterator;
+use super::NativeQuery;
+use crate::core::error::Result;
+use crate::core::value::IsarValue;
+use crate::native::isar_deserializer::IsarDeserializer;
+use crate::native::native_collection::{NativeCollection, NativeProperty};
+use crate::native::native_index::NativeIndex;
+use crate::native::native_txn::NativeTxn;
+use crate::native::BytesToId;
+use std::cmp::Ordering;
+
+pub(crate) fn facets_by_scan(
+    iterator: QueryIterator,
+    property: &NativeProperty,
+) -> Vec<(Option<IsarValue>, u32)> {
+    let mut values = vec![];
+    for (_, object) in iterator {
+        if let Some(element_type) = property.data_type.element_type() {
+            if let Some((list, length)) = object.read_list(property.offset, element_type) {
+                let size = element_type.static_size() as u32;
+                for i in 0..length {
+                    let element = NativeProperty::new(element_type, i * size, None);
+                    values.push(NativeQuery::read_value(list, &element));
+                }
+            }
+        } else {
+            values.push(NativeQuery::read_value(object, property));
+        }
+    }
+    values.sort_by(compare_facet_value);
+
+    let mut facets = vec![];
+    for value in values {
+        add_facet_value(&mut facets, value);
+    }
+    facets
+}
+
+// Index entries are ordered by key, so equal values are adjacent and can be
+// counted without collecting them first.
+pub(crate) fn facets_by_index(
+    txn: &NativeTxn,
+    collection: &NativeCollection,
+    index: &NativeIndex,
+    filter: &NativeFilter,
+) -> Result<Vec<(Option<IsarValue>, u32)>> {
+    let property = &index.properties[0];
+    let mut primary_cursor = collection.get_cursor(txn)?;
+    let mut facets = vec![];
+    for (_, id_bytes) in index.iter(txn)? {
+        if let Some((id, object)) = primary_cursor.move_to(id_bytes)? {
+            let object = IsarDeserializer::from_bytes(object);
+            if filter.evaluate(id.to_id(), object) {
+                add_facet_value(&mut facets, NativeQuery::read_value(object, property));
+            }
+        }
+    }
+    Ok(facets)
+}
+
+pub(crate) fn sort_facets(
+    facets: &mut Vec<(Option<IsarValue>, u32)>,
+    sort_by_count: bool,
+    limit: Option<u32>,
+) {
+    if sort_by_count {
+        facets.sort_by(|(value1, count1), (value2, count2)| {
+            count2
+                .cmp(count1)
+                .then_with(|| compare_facet_value(value1, value2))
+        });
+    } else {
+        facets.sort_by(|(value1, _), (value2, _)| compare_facet_value(value1, value2));
+    }
+    if let Some(limit) = limit {
+        facets.truncate(limit as usize);
+    }
+}
+
+fn add_facet_value(facets: &mut Vec<(Option<IsarValue>, u32)>, value: Option<IsarValue>) {
+    if let Some((last, count)) = facets.last_mut() {
+        if compare_facet_value(last, &value) == Ordering::Equal {
+            *count += 1;
+            return;
+        }
+    }
+    facets.push((value, 1));
+}
+
+fn compare_facet_value(value1: &Option<IsarValue>, value2: &Option<IsarValue>) -> Ordering {
+    match (value1, value2) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(IsarValue::Bool(v1)), Some(IsarValue::Bool(v2))) => v1.cmp(v2),
+        (Some(IsarValue::Integer(v1)), Some(IsarValue::Integer(v2))) => v1.cmp(v2),
+        (Some(IsarValue::Real(v1)), Some(IsarValue::Real(v2))) => {
+            v1.partial_cmp(v2).unwrap_or(Ordering::Equal)
+        }
+        (Some(IsarValue::String(v1)), Some(IsarValue::String(v2))) => v1.cmp(v2),
+        _ => Ordering::Equal,
+    }
+}
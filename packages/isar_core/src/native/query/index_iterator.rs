@@ -1,18 +1,32 @@
 use super::QueryIndex;
+use crate::core::expiry::now_micros;
 use crate::native::isar_deserializer::IsarDeserializer;
 use crate::native::mdbx::cursor_iterator::CursorIterator;
 use crate::native::native_collection::NativeCollection;
 use crate::native::native_txn::{NativeTxn, TxnCursor};
-use crate::native::BytesToId;
+use crate::native::{BytesToId, IdToBytes};
 
+/// Iterates the objects of the query ranges in id order. Secondary index ranges
+/// are resolved to the ids they contain first so that objects found with an
+/// index are returned in the same order as with a full scan. Iterators that do
+/// not need id order stream secondary index ranges instead, see
+/// [streaming](Self::streaming).
 pub(crate) struct IndexIterator<'a> {
     txn: &'a NativeTxn,
     collection: &'a NativeCollection,
-    // Either a primary or secondary index iterator. If primary_cursor is None, then iterator is a
-    // primary index iterator.
     iterator: Option<CursorIterator<'a, TxnCursor<'a>>>,
-    primary_cursor: Option<TxnCursor<'a>>,
-    indexes: Vec<QueryIndex>,
+    // Remaining id ranges in reverse order.
+    ranges: Vec<(i64, i64)>,
+    // Remaining streamed secondary index ranges in reverse order.
+    index_ranges: Vec<(usize, Vec<u8>, Vec<u8>)>,
+    index_iterator: Option<CursorIterator<'a, TxnCursor<'a>>>,
+    // Cursor to look up the objects of streamed index entries.
+    objects: Option<TxnCursor<'a>>,
+    has_duplicates: bool,
+    after: Option<i64>,
+    is_after: bool,
+    // Time at which objects count as expired, if the collection expires objects.
+    now: Option<i64>,
 }
 
 impl<'a> IndexIterator<'a> {
@@ -21,50 +35,174 @@ impl<'a> IndexIterator<'a> {
         collection: &'a NativeCollection,
         indexes: &[QueryIndex],
     ) -> Self {
-        let mut indexes = indexes.to_vec();
-        indexes.reverse();
-        if let Some((iterator, primary_cursor)) =
-            Self::next_iterator(txn, collection, None, &mut indexes)
-        {
-            Self {
-                txn,
-                collection,
-                iterator: Some(iterator),
-                primary_cursor,
-                indexes,
+        let ranges = Self::id_ranges(txn, collection, indexes);
+        Self::create(txn, collection, ranges, None)
+    }
+
+    /// Iterates the objects that follow the object with the id `after`.
+    pub fn after(
+        txn: &'a NativeTxn,
+        collection: &'a NativeCollection,
+        indexes: &[QueryIndex],
+        after: i64,
+    ) -> Self {
+        let ranges = Self::id_ranges(txn, collection, indexes)
+            .into_iter()
+            .filter_map(|(lower, upper)| {
+                let lower = lower.max(after.checked_add(1)?);
+                (lower <= upper).then_some((lower, upper))
+            })
+            .collect();
+        Self::create(txn, collection, ranges, None)
+    }
+
+    /// Iterates all objects like [new](Self::new) but tracks whether the
+    /// object with the id `after` has been passed. See [is_after](Self::is_after).
+    pub fn passing(
+        txn: &'a NativeTxn,
+        collection: &'a NativeCollection,
+        indexes: &[QueryIndex],
+        after: i64,
+    ) -> Self {
+        let ranges = Self::id_ranges(txn, collection, indexes);
+        Self::create(txn, collection, ranges, Some(after))
+    }
+
+    /// Iterates the objects of the query ranges without sorting them by id.
+    /// Secondary index ranges are streamed in index order after the primary
+    /// ranges. See [has_duplicates](Self::has_duplicates).
+    pub fn streaming(
+        txn: &'a NativeTxn,
+        collection: &'a NativeCollection,
+        indexes: &[QueryIndex],
+    ) -> Self {
+        let mut ranges = vec![];
+        let mut index_ranges = vec![];
+        for index in indexes {
+            match index {
+                QueryIndex::Primary(lower, upper) => ranges.push((*lower, *upper)),
+                QueryIndex::Secondary(index, lower, upper) => {
+                    index_ranges.push((*index, lower.clone(), upper.clone()))
+                }
             }
-        } else {
-            Self {
-                txn,
-                collection,
-                iterator: None,
-                primary_cursor: None,
-                indexes,
+        }
+        ranges.sort_unstable();
+
+        let mut iterator = Self::create(txn, collection, ranges, None);
+        index_ranges.reverse();
+        iterator.index_ranges = index_ranges;
+        // an index has a single entry per object but ranges may overlap
+        iterator.has_duplicates = indexes.len() > 1;
+        iterator
+    }
+
+    /// Whether the iterator can return the same object more than once.
+    pub fn has_duplicates(&self) -> bool {
+        self.has_duplicates
+    }
+
+    fn create(
+        txn: &'a NativeTxn,
+        collection: &'a NativeCollection,
+        mut ranges: Vec<(i64, i64)>,
+        after: Option<i64>,
+    ) -> Self {
+        ranges.reverse();
+        let iterator = Self::next_iterator(txn, collection, None, &mut ranges);
+        Self {
+            txn,
+            collection,
+            iterator,
+            ranges,
+            index_ranges: vec![],
+            index_iterator: None,
+            objects: None,
+            has_duplicates: false,
+            is_after: after.is_none(),
+            after,
+            now: collection.expiry_property().map(|_| now_micros()),
+        }
+    }
+
+    /// Whether the last returned object follows the object the query resumes
+    /// after.
+    pub fn is_after(&self) -> bool {
+        self.is_after
+    }
+
+    /// The sorted id ranges of the query. Secondary index ranges are replaced
+    /// by the ids of the objects they contain.
+    fn id_ranges(
+        txn: &NativeTxn,
+        collection: &NativeCollection,
+        indexes: &[QueryIndex],
+    ) -> Vec<(i64, i64)> {
+        let mut ranges = vec![];
+        let mut ids = vec![];
+        for index in indexes {
+            match index {
+                QueryIndex::Primary(lower, upper) => ranges.push((*lower, *upper)),
+                QueryIndex::Secondary(index, lower, upper) => {
+                    let iterator =
+                        collection.indexes[*index].iter_between(txn, lower.clone(), upper.clone());
+                    if let Ok(iterator) = iterator {
+                        ids.extend(iterator.map(|(_, id_bytes)| id_bytes.to_id()));
+                    }
+                }
             }
         }
+        ids.sort_unstable();
+        ids.dedup();
+        ranges.extend(ids.into_iter().map(|id| (id, id)));
+        ranges.sort_unstable();
+        ranges
     }
 
     fn next_iterator<'b>(
         txn: &'b NativeTxn,
         collection: &'b NativeCollection,
-        primary_cursor: Option<TxnCursor<'b>>,
-        indexes: &mut Vec<QueryIndex>,
-    ) -> Option<(CursorIterator<'b, TxnCursor<'b>>, Option<TxnCursor<'b>>)> {
-        let next_index = indexes.pop();
-        if let Some(QueryIndex::Primary(start, end)) = next_index {
-            let cursor = if let Some(primary_cursor) = primary_cursor {
-                primary_cursor
-            } else {
-                collection.get_cursor(txn).ok()?
-            };
-            let iterator = cursor.iter_between_ids(start, end, false, false).ok()?;
-            Some((iterator, None))
-        } else if let Some(QueryIndex::Secondary(_start, _end)) = next_index {
-            todo!()
+        cursor: Option<TxnCursor<'b>>,
+        ranges: &mut Vec<(i64, i64)>,
+    ) -> Option<CursorIterator<'b, TxnCursor<'b>>> {
+        let (start, end) = ranges.pop()?;
+        let cursor = if let Some(cursor) = cursor {
+            cursor
         } else {
-            None
+            collection.get_cursor(txn).ok()?
+        };
+        cursor.iter_between_ids(start, end, false, false).ok()
+    }
+
+    /// Returns the object of the next entry of the streamed index ranges.
+    fn next_streamed(&mut self) -> Option<(i64, IsarDeserializer<'a>)> {
+        loop {
+            if self.index_iterator.is_none() {
+                let (index, lower, upper) = self.index_ranges.pop()?;
+                let index = &self.collection.indexes[index];
+                self.index_iterator = index.iter_between(self.txn, lower, upper).ok();
+                continue;
+            }
+            let Some((_, id_bytes)) = self.index_iterator.as_mut()?.next() else {
+                self.index_iterator = None;
+                continue;
+            };
+            let id = id_bytes.to_id();
+            if self.objects.is_none() {
+                self.objects = self.collection.get_cursor(self.txn).ok();
+            }
+            if let Ok(Some((_, value))) = self.objects.as_mut()?.move_to(&id.to_id_bytes()) {
+                let object = IsarDeserializer::from_bytes(value);
+                if !self.is_expired(object) {
+                    return Some((id, object));
+                }
+            }
         }
     }
+
+    fn is_expired(&self, object: IsarDeserializer) -> bool {
+        self.now
+            .is_some_and(|now| self.collection.is_expired(object, now))
+    }
 }
 
 impl<'a> Iterator for IndexIterator<'a> {
@@ -72,25 +210,23 @@ impl<'a> Iterator for IndexIterator<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        let next = self.iterator.as_mut()?.next();
-        if let Some((key, value)) = next {
-            if let Some(primary_cursor) = &mut self.primary_cursor {
-                let (id, object) = primary_cursor.move_to(value).ok()??;
-                return Some((id.to_id(), IsarDeserializer::from_bytes(object)));
+        loop {
+            let Some(iterator) = self.iterator.as_mut() else {
+                return self.next_streamed();
+            };
+            if let Some((key, value)) = iterator.next() {
+                let id = key.to_id();
+                self.is_after = self.after.is_none_or(|after| id > after);
+                let object = IsarDeserializer::from_bytes(value);
+                if self.is_expired(object) {
+                    continue;
+                }
+                return Some((id, object));
             } else {
-                return Some((key.to_id(), IsarDeserializer::from_bytes(value)));
+                let cursor = self.iterator.take().map(|i| i.close());
+                self.iterator =
+                    Self::next_iterator(self.txn, self.collection, cursor, &mut self.ranges);
             }
-        } else {
-            let primary_cursor = if let Some(primary_cursor) = self.primary_cursor.take() {
-                Some(primary_cursor)
-            } else {
-                self.iterator.take().map(|i| i.close())
-            };
-            let (iterator, primary_cursor) =
-                Self::next_iterator(self.txn, self.collection, primary_cursor, &mut self.indexes)?;
-            self.iterator = Some(iterator);
-            self.primary_cursor = primary_cursor;
-            self.next()
         }
     }
 }
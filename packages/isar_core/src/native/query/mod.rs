@@ -1,18 +1,29 @@
-use self::aggregate::{aggregate_min_max, aggregate_sum_average};
-use self::native_filter::NativeFilter;
+use self::aggregate::{
+    aggregate_count_distinct, aggregate_min_max, aggregate_percentile, aggregate_sum_average,
+    aggregate_variance,
+};
+use self::facet::{facets_by_index, facets_by_scan, sort_facets};
+use self::native_filter::{NativeFilter, PropertyNames};
 use self::query_iterator::QueryIterator;
-use super::index_key::IndexKey;
 use super::isar_deserializer::IsarDeserializer;
 use super::native_collection::{NativeCollection, NativeProperty};
+use super::native_query_builder::bind_filter;
 use super::native_reader::NativeReader;
 use super::native_txn::NativeTxn;
+use crate::core::continuation::QueryContinuation;
 use crate::core::cursor::IsarQueryCursor;
+use crate::core::data_type::DataType;
+use crate::core::error::{IsarError, Result};
 use crate::core::instance::Aggregation;
 use crate::core::query_builder::Sort;
+use crate::core::query_plan::{DistinctStrategy, IndexScan, NativeQueryPlan, SortPlan};
 use crate::core::value::IsarValue;
+use crate::core::vector::{NearestNeighbors, VectorMetric};
 use crate::core::watcher::QueryMatches;
+use std::borrow::Cow;
 
 mod aggregate;
+mod facet;
 mod index_iterator;
 pub(crate) mod native_filter;
 mod query_iterator;
@@ -23,8 +34,8 @@ mod unsorted_query_iterator;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) enum QueryIndex {
     Primary(i64, i64),
-    #[allow(dead_code)]
-    Secondary(IndexKey, IndexKey),
+    /// Key range of the index at the given position in the collection.
+    Secondary(usize, Vec<u8>, Vec<u8>),
 }
 
 #[derive(Clone)]
@@ -56,23 +67,70 @@ impl NativeQuery {
         }
     }
 
+    /// Creates a cursor after binding `params` to the parameter conditions of
+    /// the filter. The compiled filter is reused and only the parameter
+    /// conditions are compiled again.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn cursor<'a>(
         &'a self,
         txn: &'a NativeTxn,
         all_collections: &'a [NativeCollection],
+        params: &[Option<IsarValue>],
+        after: Option<&QueryContinuation>,
         offset: Option<u32>,
         limit: Option<u32>,
-    ) -> NativeQueryCursor<'a> {
+    ) -> Result<NativeQueryCursor<'a>> {
         let collection = &all_collections[self.collection_index as usize];
+        let filter = if self.filter.has_params() {
+            Cow::Owned(bind_filter(&self.filter, params, all_collections)?)
+        } else {
+            Cow::Borrowed(&self.filter)
+        };
         let iterator = QueryIterator::new(
             txn,
             collection,
             self,
+            filter,
             false,
+            after,
             offset.unwrap_or(0),
             limit.unwrap_or(u32::MAX),
         );
-        NativeQueryCursor::new(iterator, collection, all_collections)
+        Ok(NativeQueryCursor::new(
+            iterator,
+            self,
+            collection,
+            all_collections,
+        ))
+    }
+
+    /// Fails if the query has parameter conditions. Only cursors bind
+    /// parameters, other operations would silently match nothing.
+    pub(crate) fn verify_bound(&self) -> Result<()> {
+        if self.filter.has_params() {
+            Err(IsarError::IllegalArgument {})
+        } else {
+            Ok(())
+        }
+    }
+
+    fn continuation(&self, id: i64, object: IsarDeserializer) -> QueryContinuation {
+        let values = self
+            .sort
+            .iter()
+            .map(|(property, _, _)| {
+                if let Some(property) = property {
+                    Self::read_value(object, property)
+                } else {
+                    Some(IsarValue::Integer(id))
+                }
+            })
+            .collect();
+        QueryContinuation::new(values, id)
+    }
+
+    fn read_value(object: IsarDeserializer, property: &NativeProperty) -> Option<IsarValue> {
+        object.read_value(property.offset, property.data_type)
     }
 
     pub(crate) fn aggregate(
@@ -81,7 +139,7 @@ impl NativeQuery {
         all_collections: &[NativeCollection],
         aggregation: Aggregation,
         property_index: Option<u16>,
-    ) -> Option<IsarValue> {
+    ) -> Result<Option<IsarValue>> {
         let collection = &all_collections[self.collection_index as usize];
         let property = if let Some(property_index) = property_index {
             collection.get_property(property_index)
@@ -89,8 +147,23 @@ impl NativeQuery {
             None
         };
 
-        let mut iterator = QueryIterator::new(txn, collection, self, true, 0, u32::MAX);
-        match aggregation {
+        if let Aggregation::Percentile(percentile) = aggregation {
+            if !(0.0..=1.0).contains(&percentile.value()) {
+                return Err(IsarError::IllegalArgument {});
+            }
+        }
+
+        let mut iterator = QueryIterator::new(
+            txn,
+            collection,
+            self,
+            Cow::Borrowed(&self.filter),
+            true,
+            None,
+            0,
+            u32::MAX,
+        );
+        let result = match aggregation {
             Aggregation::Min | Aggregation::Max => {
                 aggregate_min_max(iterator, property, aggregation == Aggregation::Min)
             }
@@ -99,6 +172,190 @@ impl NativeQuery {
             }
             Aggregation::Count => Some(IsarValue::Integer(iterator.count() as i64)),
             Aggregation::IsEmpty => Some(IsarValue::Bool(iterator.next().is_none())),
+            Aggregation::CountDistinct => aggregate_count_distinct(iterator, property),
+            Aggregation::Median => aggregate_percentile(iterator, property, 0.5),
+            Aggregation::Percentile(percentile) => {
+                aggregate_percentile(iterator, property, percentile.value())
+            }
+            Aggregation::Variance | Aggregation::StdDev => {
+                aggregate_variance(iterator, property, aggregation == Aggregation::StdDev)
+            }
+        };
+        Ok(result)
+    }
+
+    pub(crate) fn facets(
+        &self,
+        txn: &NativeTxn,
+        all_collections: &[NativeCollection],
+        property_index: u16,
+        sort_by_count: bool,
+        limit: Option<u32>,
+    ) -> Result<Vec<(Option<IsarValue>, u32)>> {
+        let collection = &all_collections[self.collection_index as usize];
+        let property = collection
+            .get_property(property_index)
+            .ok_or(IsarError::IllegalArgument {})?;
+        let data_type = property
+            .data_type
+            .element_type()
+            .unwrap_or(property.data_type);
+        if matches!(data_type, DataType::Object | DataType::Json) {
+            return Err(IsarError::IllegalArgument {});
+        }
+
+        let full_scan = self.distinct.is_empty()
+            && self.indexes == [QueryIndex::Primary(i64::MIN, i64::MAX)];
+        let index = collection.indexes.iter().find(|index| {
+            !index.unique
+                && !index.hash
+                && index.normalization.is_none()
+                && index.properties.len() == 1
+                && index.properties[0].offset == property.offset
+        });
+        let mut facets = match index {
+            Some(index) if full_scan => facets_by_index(txn, collection, index, &self.filter)?,
+            _ => {
+                let iterator = QueryIterator::new(
+                    txn,
+                    collection,
+                    self,
+                    Cow::Borrowed(&self.filter),
+                    true,
+                    None,
+                    0,
+                    u32::MAX,
+                );
+                facets_by_scan(iterator, property)
+            }
+        };
+        sort_facets(&mut facets, sort_by_count, limit);
+        Ok(facets)
+    }
+
+    pub(crate) fn nearest(
+        &self,
+        txn: &NativeTxn,
+        all_collections: &[NativeCollection],
+        property_index: u16,
+        vector: &[f64],
+        metric: VectorMetric,
+        k: u32,
+    ) -> Result<Vec<(i64, f64)>> {
+        let collection = &all_collections[self.collection_index as usize];
+        let property = collection
+            .get_property(property_index)
+            .ok_or(IsarError::IllegalArgument {})?;
+        let element_type = match property.data_type.element_type() {
+            Some(element_type @ (DataType::Float | DataType::Double)) if !vector.is_empty() => {
+                element_type
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+
+        let mut neighbors = NearestNeighbors::new(k);
+        let iterator = QueryIterator::new(
+            txn,
+            collection,
+            self,
+            Cow::Borrowed(&self.filter),
+            true,
+            None,
+            0,
+            u32::MAX,
+        );
+        for (id, object) in iterator {
+            let Some((list, length)) = object.read_list(property.offset, element_type) else {
+                continue;
+            };
+            let distance = if element_type == DataType::Float {
+                metric.distance(vector, (0..length).map(|i| list.read_float(i * 4) as f64))
+            } else {
+                metric.distance(vector, (0..length).map(|i| list.read_double(i * 8)))
+            };
+            if let Some(distance) = distance {
+                neighbors.add(id, distance);
+            }
+        }
+        Ok(neighbors.into_sorted_vec())
+    }
+
+    /// Whether the results are sorted in memory. A single scan of the primary
+    /// index already returns the objects in ascending id order.
+    pub(crate) fn sorts_in_memory(&self) -> bool {
+        match self.sort.as_slice() {
+            [] => false,
+            [(None, Sort::Asc, _)] => !matches!(self.indexes.as_slice(), [QueryIndex::Primary(..)]),
+            _ => true,
+        }
+    }
+
+    pub(crate) fn explain(
+        &self,
+        collection: &NativeCollection,
+        all_collections: &[NativeCollection],
+    ) -> NativeQueryPlan {
+        let property_name = |property: Option<&NativeProperty>| -> String {
+            if let Some(property) = property {
+                collection
+                    .properties
+                    .iter()
+                    .find(|(_, p)| p.offset == property.offset)
+                    .map(|(name, _)| name.clone())
+                    .unwrap_or_default()
+            } else {
+                collection.id_name.clone().unwrap_or_else(|| "id".to_string())
+            }
+        };
+
+        let scans = self
+            .indexes
+            .iter()
+            .map(|index| match index {
+                QueryIndex::Primary(lower, upper) => IndexScan::Primary {
+                    lower: *lower,
+                    upper: *upper,
+                },
+                QueryIndex::Secondary(index, lower, upper) => IndexScan::Secondary {
+                    index: collection.indexes[*index].name.clone(),
+                    lower: lower.clone(),
+                    upper: upper.clone(),
+                },
+            })
+            .collect();
+
+        let sort = self
+            .sort
+            .iter()
+            .map(|(property, sort, case_sensitive)| SortPlan {
+                property: property_name(property.as_ref()),
+                sort: *sort,
+                case_sensitive: *case_sensitive,
+            })
+            .collect();
+
+        let distinct_properties = self
+            .distinct
+            .iter()
+            .map(|(property, _)| property_name(Some(property)))
+            .collect();
+        let distinct = if self.distinct.is_empty() {
+            DistinctStrategy::None
+        } else if !self.sorts_in_memory() {
+            DistinctStrategy::HashStreaming(distinct_properties)
+        } else {
+            DistinctStrategy::HashAfterSort(distinct_properties)
+        };
+
+        NativeQueryPlan {
+            collection: collection.name.clone(),
+            scans,
+            sort,
+            sort_in_memory: self.sorts_in_memory(),
+            distinct,
+            filter: self
+                .filter
+                .describe(PropertyNames::new(collection, all_collections)),
         }
     }
 
@@ -113,7 +370,9 @@ impl NativeQuery {
             txn,
             collection,
             self,
+            Cow::Borrowed(&self.filter),
             false,
+            None,
             offset.unwrap_or(0),
             limit.unwrap_or(u32::MAX),
         );
@@ -131,20 +390,25 @@ impl QueryMatches for NativeQuery {
 
 pub struct NativeQueryCursor<'a> {
     iterator: QueryIterator<'a>,
+    query: &'a NativeQuery,
     collection: &'a NativeCollection,
     all_collections: &'a [NativeCollection],
+    last: Option<(i64, IsarDeserializer<'a>)>,
 }
 
 impl<'a> NativeQueryCursor<'a> {
     pub(crate) fn new(
         iterator: QueryIterator<'a>,
+        query: &'a NativeQuery,
         collection: &'a NativeCollection,
         all_collections: &'a [NativeCollection],
     ) -> Self {
         Self {
             iterator,
+            query,
             collection,
             all_collections,
+            last: None,
         }
     }
 }
@@ -155,6 +419,7 @@ impl<'a> IsarQueryCursor for NativeQueryCursor<'a> {
     #[inline]
     fn next(&mut self) -> Option<Self::Reader<'_>> {
         let (id, object) = self.iterator.next()?;
+        self.last = Some((id, object));
         Some(NativeReader::new(
             id,
             object,
@@ -162,4 +427,457 @@ impl<'a> IsarQueryCursor for NativeQueryCursor<'a> {
             self.all_collections,
         ))
     }
+
+    fn continuation(&self) -> Option<QueryContinuation> {
+        let (id, object) = self.last?;
+        Some(self.query.continuation(id, object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::{ConditionType, Filter, FilterCondition, FilterParam};
+    use crate::core::instance::IsarInstance;
+    use crate::core::normalize::StringNormalization;
+    use crate::core::query_builder::IsarQueryBuilder;
+    use crate::core::query_plan::QueryPlan;
+    use crate::core::schema::{IndexSchema, IsarSchema, PropertySchema};
+    use crate::core::reader::IsarReader;
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::core::update::UpdateOp;
+    use crate::core::writer::IsarWriter;
+    use crate::native::native_instance::NativeInstance;
+    use itertools::Itertools;
+
+    #[test]
+    fn explain_reports_index_usage() {
+        let schema = IsarSchema::new(
+            "TestCol",
+            None,
+            vec![PropertySchema::new("value", DataType::String, None)],
+            vec![IndexSchema::new("value", vec!["value"], false, false)],
+            false,
+        );
+        let instance = open_instance::<NativeInstance>("explain_reports_index_usage", vec![schema]);
+        let txn = instance.begin_txn(false).unwrap();
+
+        let query = instance.query(0).unwrap().build().unwrap();
+        let plan = instance.explain(&txn, &query).unwrap();
+        assert_eq!(serde_json::to_value(&plan).unwrap()["backend"], "native");
+        let QueryPlan::Native(plan) = plan else {
+            panic!("expected native plan");
+        };
+        assert_eq!(plan.collection, "TestCol");
+        assert_eq!(
+            plan.scans,
+            vec![IndexScan::Primary {
+                lower: i64::MIN,
+                upper: i64::MAX
+            }]
+        );
+        assert!(!plan.sort_in_memory);
+        assert_eq!(plan.distinct, DistinctStrategy::None);
+
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(Filter::Condition(FilterCondition::new(
+            1,
+            ConditionType::In,
+            vec![
+                Some(IsarValue::String("a".to_string())),
+                Some(IsarValue::String("b".to_string())),
+            ],
+            true,
+        )));
+        qb.add_sort(1, Sort::Desc, true);
+        qb.add_distinct(1, true);
+        let query = qb.build().unwrap();
+        let QueryPlan::Native(plan) = instance.explain(&txn, &query).unwrap() else {
+            panic!("expected native plan");
+        };
+        assert_eq!(plan.scans.len(), 2);
+        assert!(plan
+            .scans
+            .iter()
+            .all(|scan| matches!(scan, IndexScan::Secondary { index, .. } if index == "value")));
+        assert_eq!(
+            plan.sort,
+            vec![SortPlan {
+                property: "value".to_string(),
+                sort: Sort::Desc,
+                case_sensitive: true,
+            }]
+        );
+        assert!(plan.sort_in_memory);
+        assert_eq!(
+            plan.distinct,
+            DistinctStrategy::HashAfterSort(vec!["value".to_string()])
+        );
+        assert_eq!(
+            serde_json::to_value(&plan.filter).unwrap(),
+            serde_json::json!({"op": "in", "property": "value", "values": ["a", "b"]})
+        );
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_sort(0, Sort::Asc, true);
+        qb.add_distinct(1, true);
+        let query = qb.build().unwrap();
+        let QueryPlan::Native(plan) = instance.explain(&txn, &query).unwrap() else {
+            panic!("expected native plan");
+        };
+        assert!(!plan.sort_in_memory);
+        assert_eq!(
+            plan.distinct,
+            DistinctStrategy::HashStreaming(vec!["value".to_string()])
+        );
+        assert_eq!(plan.filter.op, "static");
+
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn projected_query_reads_selected_properties() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("value", DataType::String, None),
+                PropertySchema::new("count", DataType::Long, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance = open_instance::<NativeInstance>(
+            "projected_query_reads_selected_properties",
+            vec![schema],
+        );
+        insert_objects(instance.as_ref(), 0, &[1, 2, 3], |insert, id| {
+            match id {
+                2 => insert.write_null(1),
+                _ => insert.write_string(1, if id == 1 { "a" } else { "b" }),
+            }
+            insert.write_long(2, id * 10);
+        });
+
+        let txn = instance.begin_txn(false).unwrap();
+        let mut qb = instance.query(0).unwrap();
+        qb.add_property(1);
+        let query = qb.build().unwrap();
+        let mut cursor = instance.query_cursor(&txn, &query, None, None).unwrap();
+        let mut json = vec![];
+        cursor
+            .serialize_values(1, &mut serde_json::Serializer::new(&mut json))
+            .unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), r#"["a",null,"b"]"#);
+        drop(cursor);
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_property(0);
+        qb.add_sort(1, Sort::Desc, false);
+        let query = qb.build().unwrap();
+        let after = {
+            let mut cursor = instance
+                .query_cursor_after(&txn, &query, None, Some(1))
+                .unwrap();
+            let reader = cursor.next().unwrap();
+            assert_eq!(reader.read_id(), 3);
+            // native readers decode all properties
+            assert_eq!(reader.read_long(2), 30);
+            cursor.continuation().unwrap()
+        };
+        assert_eq!(after.values, vec![Some(IsarValue::String("b".to_string()))]);
+        let mut cursor = instance
+            .query_cursor_after(&txn, &query, Some(&after), None)
+            .unwrap();
+        assert_eq!(cursor.next().unwrap().read_id(), 1);
+        drop(cursor);
+
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn index_queries_return_and_resume_in_id_order() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![PropertySchema::new("value", DataType::String, None)],
+            vec![IndexSchema::new("value", vec!["value"], false, false)],
+            false,
+        );
+        let instance = open_instance::<NativeInstance>(
+            "index_queries_return_and_resume_in_id_order",
+            vec![schema],
+        );
+        let values = ["c", "a", "b", "a", "c", "d"];
+        insert_objects(instance.as_ref(), 0, &[1, 2, 3, 4, 5, 6], |insert, id| {
+            insert.write_string(1, values[id as usize - 1]);
+        });
+
+        let txn = instance.begin_txn(false).unwrap();
+        let read_pages = |query: &NativeQuery, limit: u32| {
+            let mut pages = vec![];
+            let mut after = None;
+            loop {
+                let mut cursor = instance
+                    .query_cursor_after(&txn, query, after.as_ref(), Some(limit))
+                    .unwrap();
+                let mut ids = vec![];
+                while let Some(reader) = cursor.next() {
+                    ids.push(reader.read_id());
+                }
+                if ids.is_empty() {
+                    return pages;
+                }
+                after = cursor.continuation();
+                pages.push(ids);
+            }
+        };
+        let string = |value: &str| Some(IsarValue::String(value.to_string()));
+        let filter = Filter::Condition(FilterCondition::new(
+            1,
+            ConditionType::In,
+            vec![string("a"), string("b"), string("c")],
+            true,
+        ));
+
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(filter.clone());
+        let query = qb.build().unwrap();
+        let QueryPlan::Native(plan) = instance.explain(&txn, &query).unwrap() else {
+            panic!("expected native plan");
+        };
+        assert_eq!(plan.scans.len(), 3);
+        assert!(matches!(plan.scans[0], IndexScan::Secondary { .. }));
+        // index ranges are returned in id order like a full scan
+        assert_eq!(read_pages(&query, 10), vec![vec![1, 2, 3, 4, 5]]);
+        assert_eq!(read_pages(&query, 2), vec![vec![1, 2], vec![3, 4], vec![5]]);
+
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(filter);
+        qb.add_distinct(1, true);
+        let query = qb.build().unwrap();
+        assert_eq!(read_pages(&query, 1), vec![vec![1], vec![2], vec![3]]);
+
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn sorted_queries_stream_index_ranges() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("value", DataType::String, None),
+                PropertySchema::new("rank", DataType::Long, None),
+            ],
+            vec![IndexSchema::new("value", vec!["value"], false, false)],
+            false,
+        );
+        let instance =
+            open_instance::<NativeInstance>("sorted_queries_stream_index_ranges", vec![schema]);
+        let values = [("a", 3), ("b", 5), ("a", 1), ("c", 4), ("b", 2)];
+        insert_objects(instance.as_ref(), 0, &[1, 2, 3, 4, 5], |insert, id| {
+            let (value, rank) = values[id as usize - 1];
+            insert.write_string(1, value);
+            insert.write_long(2, rank);
+        });
+        let txn = instance.begin_txn(false).unwrap();
+
+        let read = |values: &[&str], offset: Option<u32>, limit: Option<u32>| {
+            let mut qb = instance.query(0).unwrap();
+            qb.set_filter(Filter::Condition(FilterCondition::new(
+                1,
+                ConditionType::In,
+                values
+                    .iter()
+                    .map(|v| Some(IsarValue::String(v.to_string())))
+                    .collect(),
+                true,
+            )));
+            qb.add_sort(2, Sort::Desc, true);
+            let query = qb.build().unwrap();
+            let QueryPlan::Native(plan) = instance.explain(&txn, &query).unwrap() else {
+                panic!("expected native plan");
+            };
+            assert!(plan.sort_in_memory);
+            assert!(plan
+                .scans
+                .iter()
+                .all(|scan| matches!(scan, IndexScan::Secondary { .. })));
+            let mut cursor = instance.query_cursor(&txn, &query, offset, limit).unwrap();
+            let mut ids = vec![];
+            while let Some(reader) = cursor.next() {
+                ids.push(reader.read_id());
+            }
+            ids
+        };
+
+        assert_eq!(read(&["a"], None, None), vec![1, 3]);
+        assert_eq!(read(&["b", "a"], None, None), vec![2, 1, 5, 3]);
+        assert_eq!(read(&["a", "b", "c"], Some(1), Some(3)), vec![4, 1, 5]);
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn query_nearest_orders_by_distance() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![
+                PropertySchema::new("tag", DataType::String, None),
+                PropertySchema::new("embedding", DataType::FloatList, None),
+                PropertySchema::new("precise", DataType::DoubleList, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance =
+            open_instance::<NativeInstance>("query_nearest_orders_by_distance", vec![schema]);
+        let rows: [(&str, Option<&[Option<f64>]>); 7] = [
+            ("a", Some(&[Some(1.0), Some(0.0)])),
+            ("a", Some(&[Some(0.0), Some(1.0)])),
+            ("b", Some(&[Some(0.9), Some(0.1)])),
+            ("a", Some(&[Some(0.7), Some(0.7)])),
+            ("a", Some(&[Some(1.0), Some(0.0), Some(0.0)])),
+            ("a", Some(&[None, Some(1.0)])),
+            ("a", None),
+        ];
+        insert_objects(instance.as_ref(), 0, &[1, 2, 3, 4, 5, 6, 7], |insert, id| {
+            let (tag, embedding) = rows[id as usize - 1];
+            insert.write_string(1, tag);
+            if let Some(embedding) = embedding {
+                let mut floats = insert.begin_list(2, embedding.len() as u32).unwrap();
+                for (i, value) in embedding.iter().enumerate() {
+                    match value {
+                        Some(value) => floats.write_float(i as u32, *value as f32),
+                        None => floats.write_null(i as u32),
+                    }
+                }
+                insert.end_list(floats);
+                let mut doubles = insert.begin_list(3, embedding.len() as u32).unwrap();
+                for (i, value) in embedding.iter().enumerate() {
+                    match value {
+                        Some(value) => doubles.write_double(i as u32, *value),
+                        None => doubles.write_null(i as u32),
+                    }
+                }
+                insert.end_list(doubles);
+            } else {
+                insert.write_null(2);
+                insert.write_null(3);
+            }
+        });
+
+        let txn = instance.begin_txn(false).unwrap();
+        let nearest = |query: &NativeQuery, property: u16, metric: VectorMetric, k: u32| {
+            instance
+                .query_nearest(&txn, query, property, &[1.0, 0.0], metric, k)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect_vec()
+        };
+
+        let all = instance.query(0).unwrap().build().unwrap();
+        for property in [2, 3] {
+            assert_eq!(
+                nearest(&all, property, VectorMetric::Cosine, 10),
+                vec![1, 3, 4, 2]
+            );
+            assert_eq!(nearest(&all, property, VectorMetric::L2, 2), vec![1, 3]);
+            assert_eq!(
+                nearest(&all, property, VectorMetric::DotProduct, 2),
+                vec![1, 3]
+            );
+        }
+
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(Filter::Condition(FilterCondition::new(
+            1,
+            ConditionType::Equal,
+            vec![Some(IsarValue::String("a".to_string()))],
+            true,
+        )));
+        let filtered = qb.build().unwrap();
+        assert_eq!(nearest(&filtered, 2, VectorMetric::Cosine, 2), vec![1, 4]);
+
+        let distances = instance
+            .query_nearest(&txn, &all, 3, &[1.0, 0.0], VectorMetric::L2, 1)
+            .unwrap();
+        assert_eq!(distances, vec![(1, 0.0)]);
+        assert!(instance
+            .query_nearest(&txn, &all, 1, &[1.0, 0.0], VectorMetric::L2, 1)
+            .is_err());
+        assert!(instance
+            .query_nearest(&txn, &all, 2, &[], VectorMetric::L2, 1)
+            .is_err());
+
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
+
+    #[test]
+    fn binds_query_params_and_rejects_unbound_params() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![PropertySchema::new("value", DataType::String, None)],
+            vec![],
+            false,
+        );
+        let instance = open_instance::<NativeInstance>(
+            "binds_query_params_and_rejects_unbound_params",
+            vec![schema],
+        );
+        let values = ["a", "ab", "b"];
+        insert_objects(instance.as_ref(), 0, &[1, 2, 3], |insert, id| {
+            insert.write_string(1, values[id as usize - 1]);
+        });
+
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(Filter::Param(FilterParam::new(
+            1,
+            ConditionType::StringStartsWith,
+            vec![0],
+            StringNormalization::NONE,
+        )));
+        let query = qb.build().unwrap();
+
+        let txn = instance.begin_txn(true).unwrap();
+        let params = [Some(IsarValue::String("a".to_string()))];
+        let mut cursor = instance
+            .query_cursor_with_params(&txn, &query, &params, None, None)
+            .unwrap();
+        let mut ids = vec![];
+        while let Some(reader) = cursor.next() {
+            ids.push(reader.read_id());
+        }
+        drop(cursor);
+        assert_eq!(ids, vec![1, 2]);
+
+        assert!(matches!(
+            instance.query_cursor(&txn, &query, None, None),
+            Err(IsarError::IllegalArgument {})
+        ));
+        assert!(matches!(
+            instance.query_aggregate(&txn, &query, Aggregation::Count, None),
+            Err(IsarError::IllegalArgument {})
+        ));
+        let updates = [(1, UpdateOp::Set(Some(IsarValue::String("c".to_string()))))];
+        assert!(matches!(
+            instance.query_update(&txn, &query, None, None, &updates),
+            Err(IsarError::IllegalArgument {})
+        ));
+        assert!(matches!(
+            instance.query_delete(&txn, &query, None, None),
+            Err(IsarError::IllegalArgument {})
+        ));
+        assert_eq!(instance.count(&txn, 0), Ok(3));
+        instance.abort_txn(txn);
+        NativeInstance::close(instance, true);
+    }
 }
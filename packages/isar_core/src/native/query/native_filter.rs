@@ -1,10 +1,21 @@
 use crate::core::data_type::DataType;
+use crate::core::error::Result;
 use crate::core::fast_wild_match::fast_wild_match;
+use crate::core::filter::{FilterParam, JsonCondition, ListQuantifier};
+use crate::core::filter_regex::FilterRegex;
+use crate::core::geo::GeoShape;
+use crate::core::normalize::StringNormalization;
+use crate::core::query_plan::FilterPlan;
+use crate::core::uuid::{format_uuid, parse_uuid, NULL_UUID};
+use crate::core::value::IsarValue;
 use crate::native::isar_deserializer::IsarDeserializer;
-use crate::native::native_collection::NativeProperty;
+use crate::native::native_collection::{NativeCollection, NativeProperty};
+use crate::native::{NULL_INT, NULL_LONG};
 use enum_dispatch::enum_dispatch;
 use itertools::Itertools;
 use paste::paste;
+use serde_json::{json, Value};
+use std::collections::HashSet;
 
 #[macro_export]
 macro_rules! primitive_create {
@@ -22,6 +33,7 @@ macro_rules! primitive_create {
                         offset: $property.offset,
                         $lower,
                         $upper,
+                        quantifier: ListQuantifier::Any,
                     })
                 } else {
                     Filter::Static(StaticCond { value: false })
@@ -34,25 +46,22 @@ macro_rules! primitive_create {
 
 #[macro_export]
 macro_rules! string_filter_create {
-    ($name:ident, $property:expr, $value:expr, $case_sensitive:expr) => {
+    ($name:ident, $property:expr, $value:expr, $normalization:expr) => {
         paste! {
             {
-                let value = if $case_sensitive {
-                    $value.to_string()
-                } else {
-                    $value.to_lowercase()
-                };
+                let value = $normalization.normalize($value).into_owned();
                 let filter = if $property.data_type == DataType::String {
                     Filter::[<String $name>]([<String $name Cond>] {
                         offset: $property.offset,
                         value,
-                        $case_sensitive,
+                        $normalization,
                     })
                 } else if $property.data_type == DataType::StringList {
                     Filter::[<AnyString $name>]([<AnyString $name Cond>] {
                         offset: $property.offset,
                         value,
-                        $case_sensitive,
+                        $normalization,
+                        quantifier: ListQuantifier::Any,
                     })
                 } else {
                     Filter::Static(StaticCond { value: false })
@@ -80,6 +89,13 @@ impl NativeFilter {
         NativeFilter(filter)
     }
 
+    pub fn id_in(ids: impl IntoIterator<Item = i64>) -> NativeFilter {
+        let filter = Filter::IdIn(IdInCond {
+            ids: ids.into_iter().collect(),
+        });
+        NativeFilter(filter)
+    }
+
     pub fn bool(
         property: &NativeProperty,
         lower: Option<bool>,
@@ -108,35 +124,76 @@ impl NativeFilter {
         primitive_create!(Double, property, lower, upper)
     }
 
-    pub fn string_to_bytes(str: Option<&str>, case_sensitive: bool) -> Option<Vec<u8>> {
-        if case_sensitive {
-            str.map(|s| s.as_bytes().to_vec())
+    pub fn uuid(property: &NativeProperty, lower: u128, upper: u128) -> NativeFilter {
+        let filter = if property.data_type == DataType::Uuid {
+            Filter::UuidBetween(UuidBetweenCond {
+                offset: property.offset,
+                lower,
+                upper,
+            })
         } else {
-            str.map(|s| s.to_lowercase().as_bytes().to_vec())
-        }
+            Filter::Static(StaticCond { value: false })
+        };
+        NativeFilter(filter)
+    }
+
+    pub fn is_in(
+        property: &NativeProperty,
+        values: &[Option<IsarValue>],
+        normalization: StringNormalization,
+    ) -> NativeFilter {
+        let element_type = property
+            .data_type
+            .element_type()
+            .unwrap_or(property.data_type);
+        let filter = if let Some(set) = InSet::new(element_type, values, normalization) {
+            if property.data_type.is_list() {
+                Filter::AnyIn(AnyInCond {
+                    offset: property.offset,
+                    set,
+                    quantifier: ListQuantifier::Any,
+                })
+            } else {
+                Filter::In(InCond {
+                    offset: property.offset,
+                    set,
+                })
+            }
+        } else {
+            Filter::Static(StaticCond { value: false })
+        };
+        NativeFilter(filter)
+    }
+
+    pub fn string_to_bytes(
+        str: Option<&str>,
+        normalization: StringNormalization,
+    ) -> Option<Vec<u8>> {
+        str.map(|s| normalization.normalize(s).as_bytes().to_vec())
     }
 
     pub fn string(
         property: &NativeProperty,
         lower: Option<&str>,
         upper: Option<&str>,
-        case_sensitive: bool,
+        normalization: StringNormalization,
     ) -> NativeFilter {
-        let lower = Self::string_to_bytes(lower, case_sensitive);
-        let upper = Self::string_to_bytes(upper, case_sensitive);
+        let lower = Self::string_to_bytes(lower, normalization);
+        let upper = Self::string_to_bytes(upper, normalization);
         let filter = if property.data_type == DataType::String {
             Filter::StringBetween(StringBetweenCond {
                 offset: property.offset,
                 lower,
                 upper,
-                case_sensitive,
+                normalization,
             })
         } else if property.data_type == DataType::StringList {
             Filter::AnyStringBetween(AnyStringBetweenCond {
                 offset: property.offset,
                 lower,
                 upper,
-                case_sensitive,
+                normalization,
+                quantifier: ListQuantifier::Any,
             })
         } else {
             Filter::Static(StaticCond { value: false })
@@ -147,25 +204,135 @@ impl NativeFilter {
     pub fn string_ends_with(
         property: &NativeProperty,
         value: &str,
-        case_sensitive: bool,
+        normalization: StringNormalization,
     ) -> NativeFilter {
-        string_filter_create!(EndsWith, property, value, case_sensitive)
+        string_filter_create!(EndsWith, property, value, normalization)
     }
 
     pub fn string_contains(
         property: &NativeProperty,
         value: &str,
-        case_sensitive: bool,
+        normalization: StringNormalization,
     ) -> NativeFilter {
-        string_filter_create!(Contains, property, value, case_sensitive)
+        string_filter_create!(Contains, property, value, normalization)
     }
 
     pub fn string_matches(
         property: &NativeProperty,
         value: &str,
-        case_sensitive: bool,
+        normalization: StringNormalization,
     ) -> NativeFilter {
-        string_filter_create!(Matches, property, value, case_sensitive)
+        string_filter_create!(Matches, property, value, normalization)
+    }
+
+    pub fn string_regex(property: &NativeProperty, regex: FilterRegex) -> NativeFilter {
+        let filter = if property.data_type == DataType::String {
+            Filter::StringRegex(StringRegexCond {
+                offset: property.offset,
+                regex,
+            })
+        } else if property.data_type == DataType::StringList {
+            Filter::AnyStringRegex(AnyStringRegexCond {
+                offset: property.offset,
+                regex,
+                quantifier: ListQuantifier::Any,
+            })
+        } else {
+            Filter::Static(StaticCond { value: false })
+        };
+        NativeFilter(filter)
+    }
+
+    pub fn geo(
+        latitude: &NativeProperty,
+        longitude: &NativeProperty,
+        shape: GeoShape,
+    ) -> NativeFilter {
+        let filter =
+            if latitude.data_type == DataType::Double && longitude.data_type == DataType::Double {
+                Filter::Geo(GeoCond {
+                    latitude_offset: latitude.offset,
+                    longitude_offset: longitude.offset,
+                    shape,
+                })
+            } else {
+                Filter::Static(StaticCond { value: false })
+            };
+        NativeFilter(filter)
+    }
+
+    pub fn json(property: &NativeProperty, condition: JsonCondition) -> NativeFilter {
+        let filter = if property.data_type == DataType::Json {
+            Filter::Json(JsonCond {
+                offset: property.offset,
+                conditions: vec![condition],
+                all: true,
+            })
+        } else {
+            Filter::Static(StaticCond { value: false })
+        };
+        NativeFilter(filter)
+    }
+
+    /// Parameter condition on a property of the collection at
+    /// `collection_index` that never matches until it is bound.
+    pub fn param(param: FilterParam, collection_index: u16) -> NativeFilter {
+        NativeFilter(Filter::Param(ParamCond {
+            param,
+            collection_index,
+        }))
+    }
+
+    pub fn list_length(property: &NativeProperty, lower: u32, upper: u32) -> NativeFilter {
+        let filter = if let Some(element_type) = property.data_type.element_type() {
+            Filter::ListLength(ListLengthCond {
+                offset: property.offset,
+                element_type,
+                lower,
+                upper,
+            })
+        } else {
+            Filter::Static(StaticCond { value: false })
+        };
+        NativeFilter(filter)
+    }
+
+    /// Applies the quantifier to a list condition created by one of the other
+    /// constructors.
+    pub fn quantified(mut self, quantifier: ListQuantifier) -> NativeFilter {
+        match &mut self.0 {
+            Filter::AnyBoolBetween(cond) => cond.quantifier = quantifier,
+            Filter::AnyByteBetween(cond) => cond.quantifier = quantifier,
+            Filter::AnyIntBetween(cond) => cond.quantifier = quantifier,
+            Filter::AnyLongBetween(cond) => cond.quantifier = quantifier,
+            Filter::AnyFloatBetween(cond) => cond.quantifier = quantifier,
+            Filter::AnyDoubleBetween(cond) => cond.quantifier = quantifier,
+            Filter::AnyStringBetween(cond) => cond.quantifier = quantifier,
+            Filter::AnyStringEndsWith(cond) => cond.quantifier = quantifier,
+            Filter::AnyStringContains(cond) => cond.quantifier = quantifier,
+            Filter::AnyStringMatches(cond) => cond.quantifier = quantifier,
+            Filter::AnyStringRegex(cond) => cond.quantifier = quantifier,
+            Filter::AnyIn(cond) => cond.quantifier = quantifier,
+            _ => return NativeFilter::stat(false),
+        }
+        self
+    }
+
+    pub fn object_elements(
+        property: &NativeProperty,
+        quantifier: ListQuantifier,
+        filter: NativeFilter,
+    ) -> NativeFilter {
+        let filter = if property.data_type == DataType::ObjectList {
+            Filter::ObjectElements(ObjectElementsCond {
+                offset: property.offset,
+                quantifier,
+                filter: Box::new(filter.0),
+            })
+        } else {
+            Filter::Static(StaticCond { value: false })
+        };
+        NativeFilter(filter)
     }
 
     pub fn nested(property: &NativeProperty, filter: NativeFilter) -> NativeFilter {
@@ -181,13 +348,13 @@ impl NativeFilter {
     }
 
     pub fn and(filters: Vec<NativeFilter>) -> NativeFilter {
-        let filters = filters.into_iter().map(|f| f.0).collect_vec();
+        let filters = JsonCond::merge(filters, true);
         let filter_cond = Filter::And(AndCond { filters });
         NativeFilter(filter_cond)
     }
 
     pub fn or(filters: Vec<NativeFilter>) -> NativeFilter {
-        let filters = filters.into_iter().map(|f| f.0).collect_vec();
+        let filters = JsonCond::merge(filters, false);
         let filter_cond = Filter::Or(OrCond { filters });
         NativeFilter(filter_cond)
     }
@@ -204,9 +371,71 @@ impl NativeFilter {
         NativeFilter(filter_cond)
     }
 
+    pub(crate) fn has_params(&self) -> bool {
+        self.0.has_params()
+    }
+
+    /// Replaces the parameter conditions with the filters returned by `bind`
+    /// for the parameter and the index of its collection.
+    pub(crate) fn bind(
+        &self,
+        bind: &impl Fn(&FilterParam, u16) -> Result<NativeFilter>,
+    ) -> Result<NativeFilter> {
+        Ok(NativeFilter(self.0.bind(bind)?))
+    }
+
     pub(crate) fn evaluate(&self, id: i64, object: IsarDeserializer) -> bool {
         self.0.evaluate(id, object)
     }
+
+    pub(crate) fn describe(&self, names: PropertyNames) -> FilterPlan {
+        self.0.describe(names)
+    }
+}
+
+/// Resolves the property offsets of a filter to names for query plans.
+#[derive(Clone, Copy)]
+pub(crate) struct PropertyNames<'a> {
+    collection: &'a NativeCollection,
+    all_collections: &'a [NativeCollection],
+}
+
+impl<'a> PropertyNames<'a> {
+    pub fn new(collection: &'a NativeCollection, all_collections: &'a [NativeCollection]) -> Self {
+        Self {
+            collection,
+            all_collections,
+        }
+    }
+
+    fn property(&self, offset: u32) -> Option<&'a (String, NativeProperty)> {
+        self.collection
+            .properties
+            .iter()
+            .find(|(_, p)| p.offset == offset)
+    }
+
+    fn name(&self, offset: u32) -> Option<String> {
+        self.property(offset).map(|(name, _)| name.clone())
+    }
+
+    fn id(&self) -> Option<String> {
+        let id_name = self.collection.id_name.as_deref().unwrap_or("id");
+        Some(id_name.to_string())
+    }
+
+    /// The names of the embedded collection of the object property at
+    /// `offset`.
+    fn embedded(&self, offset: u32) -> Self {
+        let collection = self
+            .property(offset)
+            .and_then(|(_, p)| p.embedded_collection_index)
+            .and_then(|index| self.all_collections.get(index as usize));
+        Self {
+            collection: collection.unwrap_or(self.collection),
+            ..*self
+        }
+    }
 }
 
 #[enum_dispatch]
@@ -215,6 +444,7 @@ enum Filter {
     IsNull(IsNullCond),
 
     IdBetween(IdBetweenCond),
+    IdIn(IdInCond),
     BoolBetween(BoolBetweenCond),
     ByteBetween(ByteBetweenCond),
     IntBetween(IntBetweenCond),
@@ -226,6 +456,9 @@ enum Filter {
     StringEndsWith(StringEndsWithCond),
     StringContains(StringContainsCond),
     StringMatches(StringMatchesCond),
+    StringRegex(StringRegexCond),
+    UuidBetween(UuidBetweenCond),
+    In(InCond),
 
     AnyByteBetween(AnyByteBetweenCond),
     AnyBoolBetween(AnyBoolBetweenCond),
@@ -238,6 +471,14 @@ enum Filter {
     AnyStringEndsWith(AnyStringEndsWithCond),
     AnyStringContains(AnyStringContainsCond),
     AnyStringMatches(AnyStringMatchesCond),
+    AnyStringRegex(AnyStringRegexCond),
+    AnyIn(AnyInCond),
+
+    ListLength(ListLengthCond),
+    ObjectElements(ObjectElementsCond),
+    Geo(GeoCond),
+    Json(JsonCond),
+    Param(ParamCond),
 
     Nested(NestedCond),
     And(AndCond),
@@ -246,6 +487,209 @@ enum Filter {
     Static(StaticCond),
 }
 
+impl Filter {
+    fn has_params(&self) -> bool {
+        match self {
+            Filter::Param(_) => true,
+            Filter::Nested(cond) => cond.filter.has_params(),
+            Filter::ObjectElements(cond) => cond.filter.has_params(),
+            Filter::And(cond) => cond.filters.iter().any(|f| f.has_params()),
+            Filter::Or(cond) => cond.filters.iter().any(|f| f.has_params()),
+            Filter::Not(cond) => cond.filter.has_params(),
+            _ => false,
+        }
+    }
+
+    fn bind(&self, bind: &impl Fn(&FilterParam, u16) -> Result<NativeFilter>) -> Result<Filter> {
+        let bind_all = |filters: &[Filter]| {
+            filters
+                .iter()
+                .map(|f| f.bind(bind))
+                .collect::<Result<Vec<_>>>()
+        };
+        let filter = match self {
+            Filter::Param(cond) => bind(&cond.param, cond.collection_index)?.0,
+            Filter::Nested(cond) => Filter::Nested(NestedCond {
+                offset: cond.offset,
+                filter: Box::new(cond.filter.bind(bind)?),
+            }),
+            Filter::ObjectElements(cond) => Filter::ObjectElements(ObjectElementsCond {
+                offset: cond.offset,
+                quantifier: cond.quantifier,
+                filter: Box::new(cond.filter.bind(bind)?),
+            }),
+            Filter::And(cond) => Filter::And(AndCond {
+                filters: bind_all(&cond.filters)?,
+            }),
+            Filter::Or(cond) => Filter::Or(OrCond {
+                filters: bind_all(&cond.filters)?,
+            }),
+            Filter::Not(cond) => Filter::Not(NotCond {
+                filter: Box::new(cond.filter.bind(bind)?),
+            }),
+            _ => self.clone(),
+        };
+        Ok(filter)
+    }
+
+    fn describe(&self, names: PropertyNames) -> FilterPlan {
+        let node = |op: &str, offset: u32, values: Vec<Value>| {
+            FilterPlan::new(op, names.name(offset), values)
+        };
+        let describe_all = |filters: &[Filter]| filters.iter().map(|f| f.describe(names)).collect();
+        let string_bound = |bound: &Option<Vec<u8>>| {
+            json!(bound
+                .as_ref()
+                .map(|b| String::from_utf8_lossy(b).into_owned()))
+        };
+        let uuid = |value: u128| json!(format_uuid(&value.to_be_bytes()));
+        match self {
+            Filter::IsNull(c) => node("isNull", c.offset, vec![]),
+            Filter::IdBetween(c) => {
+                FilterPlan::new("between", names.id(), vec![json!(c.lower), json!(c.upper)])
+            }
+            Filter::IdIn(c) => {
+                let ids = c.ids.iter().sorted().map(|id| json!(id)).collect();
+                FilterPlan::new("in", names.id(), ids)
+            }
+            Filter::BoolBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+            }
+            Filter::ByteBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+            }
+            Filter::IntBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+            }
+            Filter::LongBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+            }
+            Filter::FloatBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+            }
+            Filter::DoubleBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+            }
+            Filter::StringBetween(c) => node(
+                "between",
+                c.offset,
+                vec![string_bound(&c.lower), string_bound(&c.upper)],
+            ),
+            Filter::StringEndsWith(c) => node("endsWith", c.offset, vec![json!(c.value)]),
+            Filter::StringContains(c) => node("contains", c.offset, vec![json!(c.value)]),
+            Filter::StringMatches(c) => node("matches", c.offset, vec![json!(c.value)]),
+            Filter::StringRegex(c) => node("regex", c.offset, vec![json!(c.regex.as_str())]),
+            Filter::UuidBetween(c) => node("between", c.offset, vec![uuid(c.lower), uuid(c.upper)]),
+            Filter::In(c) => node("in", c.offset, c.set.describe()),
+            Filter::AnyByteBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+                    .with_quantifier(c.quantifier)
+            }
+            Filter::AnyBoolBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+                    .with_quantifier(c.quantifier)
+            }
+            Filter::AnyIntBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+                    .with_quantifier(c.quantifier)
+            }
+            Filter::AnyLongBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+                    .with_quantifier(c.quantifier)
+            }
+            Filter::AnyFloatBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+                    .with_quantifier(c.quantifier)
+            }
+            Filter::AnyDoubleBetween(c) => {
+                node("between", c.offset, vec![json!(c.lower), json!(c.upper)])
+                    .with_quantifier(c.quantifier)
+            }
+            Filter::AnyStringBetween(c) => node(
+                "between",
+                c.offset,
+                vec![string_bound(&c.lower), string_bound(&c.upper)],
+            )
+            .with_quantifier(c.quantifier),
+            Filter::AnyStringEndsWith(c) => {
+                node("endsWith", c.offset, vec![json!(c.value)]).with_quantifier(c.quantifier)
+            }
+            Filter::AnyStringContains(c) => {
+                node("contains", c.offset, vec![json!(c.value)]).with_quantifier(c.quantifier)
+            }
+            Filter::AnyStringMatches(c) => {
+                node("matches", c.offset, vec![json!(c.value)]).with_quantifier(c.quantifier)
+            }
+            Filter::AnyStringRegex(c) => {
+                node("regex", c.offset, vec![json!(c.regex.as_str())]).with_quantifier(c.quantifier)
+            }
+            Filter::AnyIn(c) => {
+                node("in", c.offset, c.set.describe()).with_quantifier(c.quantifier)
+            }
+            Filter::ListLength(c) => {
+                node("listLength", c.offset, vec![json!(c.lower), json!(c.upper)])
+            }
+            Filter::ObjectElements(c) => node("elements", c.offset, vec![])
+                .with_quantifier(c.quantifier)
+                .with_children(vec![c.filter.describe(names.embedded(c.offset))]),
+            Filter::Geo(c) => {
+                let property = names
+                    .name(c.latitude_offset)
+                    .zip(names.name(c.longitude_offset))
+                    .map(|(latitude, longitude)| format!("{}, {}", latitude, longitude));
+                FilterPlan::new("geo", property, vec![json!(c.shape)])
+            }
+            Filter::Json(c) => {
+                let conditions = c
+                    .conditions
+                    .iter()
+                    .map(|condition| {
+                        let op = json!(condition.condition_type);
+                        let values = condition.values.iter().map(|v| json!(v)).collect();
+                        let plan = FilterPlan::new(
+                            op.as_str().unwrap_or_default(),
+                            Some(condition.path.join(".")),
+                            values,
+                        );
+                        if condition.is_list {
+                            plan.with_quantifier(condition.quantifier)
+                        } else {
+                            plan
+                        }
+                    })
+                    .collect_vec();
+                let children = if conditions.len() == 1 {
+                    conditions
+                } else {
+                    let op = if c.all { "and" } else { "or" };
+                    vec![FilterPlan::new(op, None, vec![]).with_children(conditions)]
+                };
+                node("json", c.offset, vec![]).with_children(children)
+            }
+            Filter::Param(c) => {
+                let property = match names.collection.get_property(c.param.property_index) {
+                    Some(property) => names.name(property.offset),
+                    None => names.id(),
+                };
+                let params = c.param.params.iter().map(|p| json!(p)).collect();
+                FilterPlan::new("param", property, params)
+            }
+            Filter::Nested(c) => node("nested", c.offset, vec![])
+                .with_children(vec![c.filter.describe(names.embedded(c.offset))]),
+            Filter::And(c) => {
+                FilterPlan::new("and", None, vec![]).with_children(describe_all(&c.filters))
+            }
+            Filter::Or(c) => {
+                FilterPlan::new("or", None, vec![]).with_children(describe_all(&c.filters))
+            }
+            Filter::Not(c) => {
+                FilterPlan::new("not", None, vec![]).with_children(vec![c.filter.describe(names)])
+            }
+            Filter::Static(c) => FilterPlan::new("static", None, vec![json!(c.value)]),
+        }
+    }
+}
+
 #[enum_dispatch(Filter)]
 trait Condition {
     fn evaluate(&self, id: i64, object: IsarDeserializer) -> bool;
@@ -276,6 +720,310 @@ impl Condition for IdBetweenCond {
     }
 }
 
+#[derive(Clone, Debug)]
+struct IdInCond {
+    ids: HashSet<i64>,
+}
+
+impl Condition for IdInCond {
+    #[inline]
+    fn evaluate(&self, id: i64, _object: IsarDeserializer) -> bool {
+        self.ids.contains(&id)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum InValues {
+    Integer(HashSet<i64>),
+    Real(HashSet<u64>),
+    String(HashSet<String>),
+    Uuid(HashSet<[u8; 16]>),
+}
+
+/// Hashed values of an `In` condition so membership does not depend on the
+/// number of values.
+#[derive(Clone, Debug)]
+struct InSet {
+    data_type: DataType,
+    values: InValues,
+    null: bool,
+    normalization: StringNormalization,
+}
+
+impl InSet {
+    fn new(
+        data_type: DataType,
+        values: &[Option<IsarValue>],
+        normalization: StringNormalization,
+    ) -> Option<Self> {
+        let null = values.iter().any(|value| value.is_none());
+        let values = values.iter().flatten();
+        let values = match data_type {
+            DataType::Bool => {
+                InValues::Integer(values.filter_map(|v| v.bool()).map(|v| v as i64).collect())
+            }
+            DataType::Byte | DataType::Int | DataType::Long => {
+                InValues::Integer(values.filter_map(|v| v.i64()).collect())
+            }
+            DataType::Float => InValues::Real(
+                values
+                    .filter_map(|v| v.real())
+                    .map(|v| Self::real_key(v as f32 as f64))
+                    .collect(),
+            ),
+            DataType::Double => InValues::Real(
+                values
+                    .filter_map(|v| v.real())
+                    .map(Self::real_key)
+                    .collect(),
+            ),
+            DataType::String => InValues::String(
+                values
+                    .filter_map(|v| v.string())
+                    .map(|v| normalization.normalize(v).into_owned())
+                    .collect(),
+            ),
+            DataType::Uuid => InValues::Uuid(
+                values
+                    .filter_map(|v| v.string())
+                    .filter_map(parse_uuid)
+                    .collect(),
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            data_type,
+            values,
+            null,
+            normalization,
+        })
+    }
+
+    /// The values of the set in ascending order, null first.
+    fn describe(&self) -> Vec<Value> {
+        let mut values: Vec<Value> = match &self.values {
+            InValues::Integer(values) if self.data_type == DataType::Bool => {
+                values.iter().sorted().map(|v| json!(*v != 0)).collect()
+            }
+            InValues::Integer(values) => values.iter().sorted().map(|v| json!(v)).collect(),
+            InValues::Real(values) => values
+                .iter()
+                .map(|v| f64::from_bits(*v))
+                .sorted_by(|a, b| a.total_cmp(b))
+                .map(|v| json!(v))
+                .collect(),
+            InValues::String(values) => values.iter().sorted().map(|v| json!(v)).collect(),
+            InValues::Uuid(values) => values
+                .iter()
+                .sorted()
+                .map(|v| json!(format_uuid(v)))
+                .collect(),
+        };
+        if self.null {
+            values.insert(0, Value::Null);
+        }
+        values
+    }
+
+    fn real_key(value: f64) -> u64 {
+        // 0.0 and -0.0 are equal
+        if value == 0.0 {
+            0
+        } else {
+            value.to_bits()
+        }
+    }
+
+    fn contains(&self, object: IsarDeserializer, offset: u32) -> bool {
+        match (&self.values, self.data_type) {
+            (InValues::Integer(values), DataType::Bool) => match object.read_bool(offset) {
+                Some(value) => values.contains(&(value as i64)),
+                None => self.null,
+            },
+            (InValues::Integer(values), DataType::Byte) => {
+                values.contains(&(object.read_byte(offset) as i64))
+            }
+            (InValues::Integer(values), DataType::Int) => match object.read_int(offset) {
+                NULL_INT => self.null,
+                value => values.contains(&(value as i64)),
+            },
+            (InValues::Integer(values), DataType::Long) => match object.read_long(offset) {
+                NULL_LONG => self.null,
+                value => values.contains(&value),
+            },
+            (InValues::Real(values), DataType::Float) => {
+                let value = object.read_float(offset);
+                if value.is_nan() {
+                    self.null
+                } else {
+                    values.contains(&Self::real_key(value as f64))
+                }
+            }
+            (InValues::Real(values), DataType::Double) => {
+                let value = object.read_double(offset);
+                if value.is_nan() {
+                    self.null
+                } else {
+                    values.contains(&Self::real_key(value))
+                }
+            }
+            (InValues::String(values), _) => match object.read_string(offset) {
+                Some(value) => values.contains(self.normalization.normalize(value).as_ref()),
+                None => self.null,
+            },
+            (InValues::Uuid(values), _) => match object.read_uuid(offset) {
+                Some(value) => values.contains(&value),
+                None => self.null,
+            },
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct InCond {
+    offset: u32,
+    set: InSet,
+}
+
+impl Condition for InCond {
+    #[inline]
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        self.set.contains(object, self.offset)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AnyInCond {
+    offset: u32,
+    set: InSet,
+    quantifier: ListQuantifier,
+}
+
+impl Condition for AnyInCond {
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        quantify_list(
+            object,
+            self.offset,
+            self.set.data_type,
+            self.quantifier,
+            |list, offset| self.set.contains(list, offset),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+struct UuidBetweenCond {
+    offset: u32,
+    lower: u128,
+    upper: u128,
+}
+
+impl Condition for UuidBetweenCond {
+    #[inline]
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        let value = u128::from_be_bytes(object.read_uuid(self.offset).unwrap_or(NULL_UUID));
+        self.lower <= value && value <= self.upper
+    }
+}
+
+#[derive(Clone, Debug)]
+struct GeoCond {
+    latitude_offset: u32,
+    longitude_offset: u32,
+    shape: GeoShape,
+}
+
+impl Condition for GeoCond {
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        let latitude = object.read_double(self.latitude_offset);
+        let longitude = object.read_double(self.longitude_offset);
+        self.shape.contains(latitude, longitude)
+    }
+}
+
+/// Conditions on the same JSON property that share one parsed value. `all`
+/// decides whether all or any of the conditions have to match.
+#[derive(Clone, Debug)]
+struct JsonCond {
+    offset: u32,
+    conditions: Vec<JsonCondition>,
+    all: bool,
+}
+
+impl JsonCond {
+    // Combines the JSON conditions of an AND or OR on the same property so the
+    // JSON is only parsed once per object.
+    fn merge(filters: Vec<NativeFilter>, all: bool) -> Vec<Filter> {
+        let mut merged: Vec<Filter> = Vec::with_capacity(filters.len());
+        for filter in filters {
+            match filter.0 {
+                Filter::Json(cond) if cond.all == all || cond.conditions.len() == 1 => {
+                    let existing = merged.iter_mut().find_map(|f| match f {
+                        Filter::Json(existing)
+                            if existing.offset == cond.offset
+                                && (existing.all == all || existing.conditions.len() == 1) =>
+                        {
+                            Some(existing)
+                        }
+                        _ => None,
+                    });
+                    if let Some(existing) = existing {
+                        existing.conditions.extend(cond.conditions);
+                        existing.all = all;
+                    } else {
+                        merged.push(Filter::Json(cond));
+                    }
+                }
+                filter => merged.push(filter),
+            }
+        }
+        merged
+    }
+}
+
+impl Condition for JsonCond {
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        let value = object
+            .read_string(self.offset)
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or(serde_json::Value::Null);
+        if self.all {
+            self.conditions.iter().all(|c| c.matches(&value))
+        } else {
+            self.conditions.iter().any(|c| c.matches(&value))
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ParamCond {
+    param: FilterParam,
+    collection_index: u16,
+}
+
+impl Condition for ParamCond {
+    fn evaluate(&self, _id: i64, _object: IsarDeserializer) -> bool {
+        false
+    }
+}
+
+fn quantify_list<'a>(
+    object: IsarDeserializer<'a>,
+    offset: u32,
+    element_type: DataType,
+    quantifier: ListQuantifier,
+    matches: impl Fn(IsarDeserializer<'a>, u32) -> bool,
+) -> bool {
+    if let Some((list, length)) = object.read_list(offset, element_type) {
+        let element_size = element_type.static_size() as u32;
+        quantifier.evaluate((0..length).map(|i| matches(list, i * element_size)))
+    } else {
+        // null lists have no elements
+        quantifier.evaluate(std::iter::empty())
+    }
+}
+
 #[macro_export]
 macro_rules! filter_between {
     ($type:ty, $data_type:ident, $prop_accessor:ident) => {
@@ -302,20 +1050,16 @@ macro_rules! filter_between {
                 upper: $type,
                 lower: $type,
                 offset: u32,
+                quantifier: ListQuantifier,
             }
 
 
             impl Condition for [<Any $data_type BetweenCond>] {
                 fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
-                    if let Some((list, length)) = object.read_list(self.offset, DataType::$data_type) {
-                        for i in 0..length {
-                            let val = list.$prop_accessor(i * DataType::$data_type.static_size() as u32);
-                            if filter_between!(eval val, self, $data_type) {
-                                return true;
-                            }
-                        }
-                    }
-                    false
+                    quantify_list(object, self.offset, DataType::$data_type, self.quantifier, |list, offset| {
+                        let val = list.$prop_accessor(offset);
+                        filter_between!(eval val, self, $data_type)
+                    })
                 }
             }
         }
@@ -345,30 +1089,19 @@ fn string_between(
     value: Option<&str>,
     lower: Option<&[u8]>,
     upper: Option<&[u8]>,
-    case_sensitive: bool,
+    normalization: StringNormalization,
 ) -> bool {
     if let Some(obj_str) = value {
+        let obj_str = normalization.normalize(obj_str);
         let mut matches = true;
-        if case_sensitive {
-            if let Some(lower) = lower {
-                matches = lower <= obj_str.as_bytes();
-            }
-            matches &= if let Some(upper) = upper {
-                upper >= obj_str.as_bytes()
-            } else {
-                false
-            };
-        } else {
-            let obj_str = obj_str.to_lowercase();
-            if let Some(lower) = lower {
-                matches = lower <= obj_str.as_bytes();
-            }
-            matches &= if let Some(upper) = upper {
-                upper >= obj_str.as_bytes()
-            } else {
-                false
-            };
+        if let Some(lower) = lower {
+            matches = lower <= obj_str.as_bytes();
         }
+        matches &= if let Some(upper) = upper {
+            upper >= obj_str.as_bytes()
+        } else {
+            false
+        };
         matches
     } else {
         lower.is_none()
@@ -380,7 +1113,7 @@ struct StringBetweenCond {
     upper: Option<Vec<u8>>,
     lower: Option<Vec<u8>>,
     offset: u32,
-    case_sensitive: bool,
+    normalization: StringNormalization,
 }
 
 impl Condition for StringBetweenCond {
@@ -391,7 +1124,7 @@ impl Condition for StringBetweenCond {
             value,
             self.lower.as_deref(),
             self.upper.as_deref(),
-            self.case_sensitive,
+            self.normalization,
         )
     }
 }
@@ -401,38 +1134,39 @@ struct AnyStringBetweenCond {
     upper: Option<Vec<u8>>,
     lower: Option<Vec<u8>>,
     offset: u32,
-    case_sensitive: bool,
+    normalization: StringNormalization,
+    quantifier: ListQuantifier,
 }
 
 impl Condition for AnyStringBetweenCond {
     fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
-        if let Some((list, length)) = object.read_list(self.offset, DataType::String) {
-            for i in 0..length {
-                let value = list.read_string(i * DataType::String.static_size() as u32);
-                let result = string_between(
-                    value,
+        quantify_list(
+            object,
+            self.offset,
+            DataType::String,
+            self.quantifier,
+            |list, offset| {
+                string_between(
+                    list.read_string(offset),
                     self.lower.as_deref(),
                     self.upper.as_deref(),
-                    self.case_sensitive,
-                );
-                if result {
-                    return true;
-                }
-            }
-        }
-        false
+                    self.normalization,
+                )
+            },
+        )
     }
 }
 
 #[macro_export]
 macro_rules! string_filter_struct {
-    ($name:ident) => {
+    ($name:ident $(, $field:ident: $field_type:ty)*) => {
         paste! {
             #[derive(Clone, Debug)]
             struct [<$name Cond>] {
                 offset: u32,
                 value: String,
-                case_sensitive: bool,
+                normalization: StringNormalization,
+                $($field: $field_type,)*
             }
         }
     };
@@ -450,18 +1184,13 @@ macro_rules! string_filter {
                 }
             }
 
-            string_filter_struct!([<Any $name>]);
+            string_filter_struct!([<Any $name>], quantifier: ListQuantifier);
             impl Condition for [<Any $name Cond>] {
                 fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
-                    if let Some((list, length)) = object.read_list(self.offset, DataType::String) {
-                        for i in 0..length {
-                            let value = list.read_string(i * DataType::String.static_size() as u32);
-                            if string_filter!(eval $name, self, value) {
-                                return true;
-                            }
-                        }
-                    }
-                    false
+                    quantify_list(object, self.offset, DataType::String, self.quantifier, |list, offset| {
+                        let value = list.read_string(offset);
+                        string_filter!(eval $name, self, value)
+                    })
                 }
             }
         }
@@ -469,13 +1198,9 @@ macro_rules! string_filter {
 
     (eval $name:tt, $filter:expr, $value:expr) => {
         if let Some(other_str) = $value {
-            if $filter.case_sensitive {
-                string_filter!($name & $filter.value, other_str)
-            } else {
-                let lowercase_string = other_str.to_lowercase();
-                let lowercase_str = &lowercase_string;
-                string_filter!($name & $filter.value, lowercase_str)
-            }
+            let normalized = $filter.normalization.normalize(other_str);
+            let normalized_str = normalized.as_ref();
+            string_filter!($name & $filter.value, normalized_str)
         } else {
             false
         }
@@ -498,6 +1223,84 @@ string_filter!(StringEndsWith);
 string_filter!(StringContains);
 string_filter!(StringMatches);
 
+#[derive(Clone, Debug)]
+struct StringRegexCond {
+    offset: u32,
+    regex: FilterRegex,
+}
+
+impl Condition for StringRegexCond {
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        if let Some(value) = object.read_string(self.offset) {
+            self.regex.is_match(value)
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AnyStringRegexCond {
+    offset: u32,
+    regex: FilterRegex,
+    quantifier: ListQuantifier,
+}
+
+impl Condition for AnyStringRegexCond {
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        quantify_list(
+            object,
+            self.offset,
+            DataType::String,
+            self.quantifier,
+            |list, offset| {
+                list.read_string(offset)
+                    .is_some_and(|value| self.regex.is_match(value))
+            },
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ListLengthCond {
+    offset: u32,
+    element_type: DataType,
+    lower: u32,
+    upper: u32,
+}
+
+impl Condition for ListLengthCond {
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        if let Some((_, length)) = object.read_list(self.offset, self.element_type) {
+            self.lower <= length && length <= self.upper
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ObjectElementsCond {
+    offset: u32,
+    quantifier: ListQuantifier,
+    filter: Box<Filter>,
+}
+
+impl Condition for ObjectElementsCond {
+    fn evaluate(&self, _id: i64, object: IsarDeserializer) -> bool {
+        quantify_list(
+            object,
+            self.offset,
+            DataType::Object,
+            self.quantifier,
+            |list, offset| {
+                list.read_nested(offset)
+                    .is_some_and(|object| self.filter.evaluate(i64::MIN, object))
+            },
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 struct NestedCond {
     offset: u32,
@@ -570,3 +1373,353 @@ impl Condition for StaticCond {
         self.value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::ConditionType;
+    use crate::native::isar_serializer::IsarSerializer;
+
+    fn list_object() -> Vec<u8> {
+        let mut serializer = IsarSerializer::new(Vec::new(), 0, 9);
+        let mut list = serializer.begin_nested(0, 12);
+        for (i, value) in [1, 2, 3].into_iter().enumerate() {
+            list.write_int(i as u32 * 4, value);
+        }
+        serializer.end_nested(list);
+
+        let mut list = serializer.begin_nested(3, 6);
+        for (i, value) in [5, 7].into_iter().enumerate() {
+            let mut object = list.begin_nested(i as u32 * 3, 4);
+            object.write_int(0, value);
+            list.end_nested(object);
+        }
+        serializer.end_nested(list);
+
+        serializer.write_null(6, DataType::StringList);
+        serializer.finish()
+    }
+
+    #[test]
+    fn test_list_quantifiers() {
+        let bytes = list_object();
+        let object = IsarDeserializer::from_bytes(&bytes);
+        let matches = |filter: NativeFilter| filter.evaluate(1, object);
+        let ints = NativeProperty::new(DataType::IntList, 0, None);
+        let strings = NativeProperty::new(DataType::StringList, 6, None);
+
+        assert!(matches(NativeFilter::int(&ints, 2, i32::MAX)));
+        assert!(matches(
+            NativeFilter::int(&ints, 1, i32::MAX).quantified(ListQuantifier::All)
+        ));
+        assert!(!matches(
+            NativeFilter::int(&ints, 2, i32::MAX).quantified(ListQuantifier::All)
+        ));
+        assert!(!matches(
+            NativeFilter::int(&ints, 2, i32::MAX).quantified(ListQuantifier::None)
+        ));
+        assert!(matches(
+            NativeFilter::int(&ints, 4, 10).quantified(ListQuantifier::None)
+        ));
+
+        let contains = || NativeFilter::string_contains(&strings, "a", StringNormalization::NONE);
+        assert!(!matches(contains()));
+        assert!(matches(contains().quantified(ListQuantifier::All)));
+        assert!(matches(contains().quantified(ListQuantifier::None)));
+        assert!(!matches(
+            NativeFilter::is_null(&ints).quantified(ListQuantifier::All)
+        ));
+    }
+
+    #[test]
+    fn test_in() {
+        let mut serializer = IsarSerializer::new(Vec::new(), 0, 11);
+        serializer.write_int(0, 5);
+        serializer.write_dynamic(4, "Straße".as_bytes());
+        let mut list = serializer.begin_nested(7, 12);
+        for (i, value) in [1, 2, 3].into_iter().enumerate() {
+            list.write_int(i as u32 * 4, value);
+        }
+        serializer.end_nested(list);
+        let bytes = serializer.finish();
+        let object = IsarDeserializer::from_bytes(&bytes);
+        let matches = |filter: NativeFilter| filter.evaluate(7, object);
+
+        let int = NativeProperty::new(DataType::Int, 0, None);
+        let string = NativeProperty::new(DataType::String, 4, None);
+        let ints = NativeProperty::new(DataType::IntList, 7, None);
+        let values = |values: &[i64]| {
+            values
+                .iter()
+                .map(|v| Some(IsarValue::Integer(*v)))
+                .collect_vec()
+        };
+
+        assert!(matches(NativeFilter::is_in(
+            &int,
+            &values(&[1, 5]),
+            StringNormalization::NONE
+        )));
+        assert!(!matches(NativeFilter::is_in(
+            &int,
+            &values(&[1, 6]),
+            StringNormalization::NONE
+        )));
+        assert!(!matches(NativeFilter::is_in(
+            &int,
+            &[None],
+            StringNormalization::NONE
+        )));
+        assert!(!matches(NativeFilter::is_in(
+            &int,
+            &[],
+            StringNormalization::NONE
+        )));
+
+        let strings = [Some(IsarValue::String("STRASSE".to_string()))];
+        assert!(!matches(NativeFilter::is_in(
+            &string,
+            &strings,
+            StringNormalization::NONE
+        )));
+        assert!(matches(NativeFilter::is_in(
+            &string,
+            &strings,
+            StringNormalization::CASE_INSENSITIVE
+        )));
+
+        assert!(matches(NativeFilter::is_in(
+            &ints,
+            &values(&[3, 4]),
+            StringNormalization::NONE
+        )));
+        assert!(!matches(
+            NativeFilter::is_in(&ints, &values(&[3, 4]), StringNormalization::NONE)
+                .quantified(ListQuantifier::All)
+        ));
+        assert!(matches(
+            NativeFilter::is_in(&ints, &values(&[1, 2, 3]), StringNormalization::NONE)
+                .quantified(ListQuantifier::All)
+        ));
+
+        assert!(matches(NativeFilter::id_in([1, 7])));
+        assert!(!matches(NativeFilter::id_in([1, 8])));
+    }
+
+    #[test]
+    fn test_geo() {
+        let object = |latitude: Option<f64>, longitude: f64| {
+            let mut serializer = IsarSerializer::new(Vec::new(), 0, 20);
+            if let Some(latitude) = latitude {
+                serializer.write_double(0, latitude);
+            } else {
+                serializer.write_null(0, DataType::Double);
+            }
+            serializer.write_double(8, longitude);
+            serializer.write_int(16, 1);
+            serializer.finish()
+        };
+        let latitude = NativeProperty::new(DataType::Double, 0, None);
+        let longitude = NativeProperty::new(DataType::Double, 8, None);
+        let int = NativeProperty::new(DataType::Int, 16, None);
+        let radius = GeoShape::Radius {
+            latitude: 52.52,
+            longitude: 13.405,
+            meters: 1000.0,
+        };
+
+        let matches = |bytes: &[u8], filter: NativeFilter| {
+            filter.evaluate(1, IsarDeserializer::from_bytes(bytes))
+        };
+        let berlin = object(Some(52.521), 13.41);
+        let hamburg = object(Some(53.55), 9.99);
+        let unknown = object(None, 13.41);
+        let filter = || NativeFilter::geo(&latitude, &longitude, radius.clone());
+        assert!(matches(&berlin, filter()));
+        assert!(!matches(&hamburg, filter()));
+        assert!(!matches(&unknown, filter()));
+        assert!(!matches(
+            &berlin,
+            NativeFilter::geo(&latitude, &int, radius.clone())
+        ));
+    }
+
+    #[test]
+    fn test_json() {
+        let object = |json: Option<&str>| {
+            let mut serializer = IsarSerializer::new(Vec::new(), 0, 3);
+            if let Some(json) = json {
+                serializer.write_dynamic(0, json.as_bytes());
+            } else {
+                serializer.write_null(0, DataType::Json);
+            }
+            serializer.finish()
+        };
+        let json = NativeProperty::new(DataType::Json, 0, None);
+        let string = NativeProperty::new(DataType::String, 0, None);
+        let condition = |path: &[&str], condition_type, value| {
+            JsonCondition::new(
+                path.iter().map(|p| p.to_string()).collect(),
+                condition_type,
+                false,
+                vec![value],
+                false,
+            )
+        };
+
+        let matches = |bytes: &[u8], filter: NativeFilter| {
+            filter.evaluate(1, IsarDeserializer::from_bytes(bytes))
+        };
+        let person = object(Some(r#"{"name": "Anna", "tags": ["a", "b"]}"#));
+        let name_condition = condition(
+            &["name"],
+            ConditionType::StringStartsWith,
+            Some(IsarValue::String("an".to_string())),
+        );
+        assert!(matches(
+            &person,
+            NativeFilter::json(&json, name_condition.clone())
+        ));
+        assert!(matches(
+            &person,
+            NativeFilter::json(
+                &json,
+                condition(
+                    &["tags", "1"],
+                    ConditionType::Equal,
+                    Some(IsarValue::String("b".to_string()))
+                )
+            )
+        ));
+        assert!(!matches(
+            &object(Some("[1]")),
+            NativeFilter::json(&json, name_condition.clone())
+        ));
+        assert!(!matches(
+            &object(None),
+            NativeFilter::json(&json, name_condition.clone())
+        ));
+        assert!(!matches(
+            &person,
+            NativeFilter::json(&string, name_condition.clone())
+        ));
+
+        let tag_condition = |tag: &str| {
+            condition(
+                &["tags", "0"],
+                ConditionType::Equal,
+                Some(IsarValue::String(tag.to_string())),
+            )
+        };
+        let and = |tag: &str| {
+            NativeFilter::and(vec![
+                NativeFilter::json(&json, name_condition.clone()),
+                NativeFilter::json(&json, tag_condition(tag)),
+            ])
+        };
+        let or = |tag: &str| {
+            NativeFilter::or(vec![
+                NativeFilter::json(&json, tag_condition(tag)),
+                and("b"),
+            ])
+        };
+        let collection = NativeCollection::new(
+            0,
+            "",
+            None,
+            vec![("json".to_string(), json)],
+            vec![],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let json_nodes = |filter: NativeFilter| {
+            fn count(plan: &FilterPlan) -> usize {
+                (plan.op == "json") as usize + plan.children.iter().map(count).sum::<usize>()
+            }
+            count(&filter.describe(PropertyNames::new(&collection, &[])))
+        };
+        assert_eq!(json_nodes(and("a")), 1);
+        assert!(matches(&person, and("a")));
+        assert!(!matches(&person, and("b")));
+        assert_eq!(json_nodes(or("a")), 2);
+        assert!(matches(&person, or("a")));
+        assert!(!matches(&person, or("b")));
+    }
+
+    #[test]
+    fn test_uuid() {
+        let object = |uuid: Option<u128>| {
+            let mut serializer = IsarSerializer::new(Vec::new(), 0, 16);
+            if let Some(uuid) = uuid {
+                serializer.write_uuid(0, &uuid.to_be_bytes());
+            } else {
+                serializer.write_null(0, DataType::Uuid);
+            }
+            serializer.finish()
+        };
+        let property = NativeProperty::new(DataType::Uuid, 0, None);
+        let matches = |bytes: &[u8], filter: NativeFilter| {
+            filter.evaluate(1, IsarDeserializer::from_bytes(bytes))
+        };
+
+        let low = object(Some(1));
+        let high = object(Some(u128::MAX));
+        let null = object(None);
+        assert!(matches(&low, NativeFilter::uuid(&property, 1, 1)));
+        assert!(!matches(&high, NativeFilter::uuid(&property, 1, 10)));
+        assert!(matches(&high, NativeFilter::uuid(&property, 10, u128::MAX)));
+        assert!(matches(&null, NativeFilter::uuid(&property, 0, 0)));
+        assert!(!matches(&null, NativeFilter::uuid(&property, 1, u128::MAX)));
+
+        let values = [
+            Some(IsarValue::String(
+                "00000000000000000000000000000001".to_string(),
+            )),
+            Some(IsarValue::String("invalid".to_string())),
+        ];
+        let filter = || NativeFilter::is_in(&property, &values, StringNormalization::NONE);
+        assert!(matches(&low, filter()));
+        assert!(!matches(&high, filter()));
+        assert!(!matches(&null, filter()));
+    }
+
+    #[test]
+    fn test_list_length() {
+        let bytes = list_object();
+        let object = IsarDeserializer::from_bytes(&bytes);
+        let matches = |filter: NativeFilter| filter.evaluate(1, object);
+        let ints = NativeProperty::new(DataType::IntList, 0, None);
+        let objects = NativeProperty::new(DataType::ObjectList, 3, Some(1));
+        let strings = NativeProperty::new(DataType::StringList, 6, None);
+
+        assert!(matches(NativeFilter::list_length(&ints, 3, 3)));
+        assert!(!matches(NativeFilter::list_length(&ints, 0, 2)));
+        assert!(matches(NativeFilter::list_length(&objects, 1, 2)));
+        assert!(!matches(NativeFilter::list_length(&strings, 0, 0)));
+        let int = NativeProperty::new(DataType::Int, 0, None);
+        assert!(!matches(NativeFilter::list_length(&int, 0, 10)));
+    }
+
+    #[test]
+    fn test_object_elements() {
+        let bytes = list_object();
+        let object = IsarDeserializer::from_bytes(&bytes);
+        let matches = |quantifier: ListQuantifier, lower: i32, upper: i32| {
+            let objects = NativeProperty::new(DataType::ObjectList, 3, Some(1));
+            let value = NativeProperty::new(DataType::Int, 0, None);
+            let filter = NativeFilter::int(&value, lower, upper);
+            NativeFilter::object_elements(&objects, quantifier, filter).evaluate(1, object)
+        };
+
+        assert!(matches(ListQuantifier::Any, 7, 7));
+        assert!(!matches(ListQuantifier::Any, 6, 6));
+        assert!(matches(ListQuantifier::All, 5, 7));
+        assert!(!matches(ListQuantifier::All, 6, 7));
+        assert!(matches(ListQuantifier::None, 6, 6));
+        assert!(!matches(ListQuantifier::None, 5, 5));
+    }
+}
@@ -1,11 +1,14 @@
 use super::index_iterator::IndexIterator;
+use super::native_filter::NativeFilter;
 use super::sorted_query_iterator::SortedQueryIterator;
 use super::unsorted_distinct_query_iterator::UnsortedDistinctQueryIterator;
 use super::unsorted_query_iterator::UnsortedQueryIterator;
 use super::NativeQuery;
+use crate::core::continuation::QueryContinuation;
 use crate::native::isar_deserializer::IsarDeserializer;
 use crate::native::native_collection::NativeCollection;
 use crate::native::native_txn::NativeTxn;
+use std::borrow::Cow;
 
 pub(crate) enum QueryIterator<'a> {
     Unsorted(UnsortedQueryIterator<'a>),
@@ -14,38 +17,54 @@ pub(crate) enum QueryIterator<'a> {
 }
 
 impl<'a> QueryIterator<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         txn: &'a NativeTxn,
         collection: &'a NativeCollection,
         query: &'a NativeQuery,
+        filter: Cow<'a, NativeFilter>,
         ignore_sort: bool,
+        after: Option<&QueryContinuation>,
         offset: u32,
         limit: u32,
     ) -> Self {
-        let index_iterator = IndexIterator::new(txn, collection, &query.indexes);
-        if !query.sort.is_empty() && !ignore_sort {
+        if query.sorts_in_memory() && !ignore_sort {
+            // the results are sorted anyway so the index ranges are streamed
+            let index_iterator = IndexIterator::streaming(txn, collection, &query.indexes);
+            let has_duplicates = index_iterator.has_duplicates();
             QueryIterator::Sorted(SortedQueryIterator::new(
                 index_iterator,
-                false,
-                &query.filter,
+                has_duplicates,
+                &filter,
                 &query.sort,
                 &query.distinct,
+                after,
                 offset,
                 limit,
             ))
         } else if !query.distinct.is_empty() {
+            let index_iterator = if let Some(after) = after {
+                IndexIterator::passing(txn, collection, &query.indexes, after.id)
+            } else {
+                IndexIterator::new(txn, collection, &query.indexes)
+            };
             QueryIterator::UnsortedDistinct(UnsortedDistinctQueryIterator::new(
                 index_iterator,
-                &query.filter,
+                filter,
                 &query.distinct,
                 offset,
                 limit,
             ))
         } else {
+            let index_iterator = if let Some(after) = after {
+                IndexIterator::after(txn, collection, &query.indexes, after.id)
+            } else {
+                IndexIterator::new(txn, collection, &query.indexes)
+            };
             QueryIterator::Unsorted(UnsortedQueryIterator::new(
                 index_iterator,
                 false,
-                &query.filter,
+                filter,
                 offset,
                 limit,
             ))
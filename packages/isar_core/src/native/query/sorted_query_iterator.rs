@@ -1,11 +1,15 @@
 use super::index_iterator::IndexIterator;
 use super::native_filter::NativeFilter;
+use crate::core::continuation::QueryContinuation;
 use crate::core::data_type::DataType;
+use crate::core::normalize::StringNormalization;
 use crate::core::query_builder::Sort;
+use crate::core::uuid::parse_uuid;
+use crate::core::value::IsarValue;
 use crate::native::isar_deserializer::IsarDeserializer;
 use crate::native::native_collection::NativeProperty;
+use crate::native::{NULL_DOUBLE, NULL_FLOAT, NULL_INT, NULL_LONG};
 use intmap::IntMap;
-use itertools::Itertools;
 use std::cmp::Ordering;
 use std::iter::{Skip, Take};
 use std::vec::IntoIter;
@@ -21,6 +25,7 @@ impl<'a> SortedQueryIterator<'a> {
         filter: &NativeFilter,
         sort: &[(Option<NativeProperty>, Sort, bool)],
         distinct: &[(NativeProperty, bool)],
+        after: Option<&QueryContinuation>,
         offset: u32,
         limit: u32,
     ) -> SortedQueryIterator<'a> {
@@ -57,37 +62,108 @@ impl<'a> SortedQueryIterator<'a> {
                     };
                 }
             }
-            Ordering::Equal
+            id1.cmp(id2)
         });
 
         if !distinct.is_empty() {
             let mut hashes = IntMap::new();
-            let results = results
+            results.retain(|(_, object)| {
+                let hash = distinct.iter().fold(0, |hash, (property, case_sensitive)| {
+                    object.hash_property(property.offset, property.data_type, *case_sensitive, hash)
+                });
+                hashes.insert_checked(hash, ())
+            });
+        }
+
+        if let Some(after) = after {
+            let start = results.partition_point(|(id, object)| {
+                Self::compare_continuation(*id, object, sort, after) != Ordering::Greater
+            });
+            results.drain(..start);
+        }
+
+        SortedQueryIterator {
+            iterator: results
                 .into_iter()
-                .filter(|(_, object)| {
-                    let hash = distinct.iter().fold(0, |hash, (property, case_sensitive)| {
-                        object.hash_property(
-                            property.offset,
-                            property.data_type,
-                            *case_sensitive,
-                            hash,
-                        )
-                    });
-                    hashes.insert_checked(hash, ())
-                })
                 .skip(offset as usize)
-                .take(limit as usize)
-                .collect_vec();
-            SortedQueryIterator {
-                iterator: results.into_iter().skip(0).take(usize::MAX),
+                .take(limit as usize),
+        }
+    }
+
+    fn compare_continuation(
+        id: i64,
+        object: &IsarDeserializer,
+        sort: &[(Option<NativeProperty>, Sort, bool)],
+        after: &QueryContinuation,
+    ) -> Ordering {
+        for ((p, sort, case_sensitive), value) in sort.iter().zip(&after.values) {
+            let ord = if let Some(p) = p {
+                Self::compare_value(
+                    object,
+                    p.offset,
+                    p.data_type,
+                    value.as_ref(),
+                    *case_sensitive,
+                )
+            } else {
+                id.cmp(&value.as_ref().and_then(|v| v.i64()).unwrap_or(i64::MIN))
+            };
+            if ord != Ordering::Equal {
+                return if *sort == Sort::Asc {
+                    ord
+                } else {
+                    ord.reverse()
+                };
             }
-        } else {
-            SortedQueryIterator {
-                iterator: results
-                    .into_iter()
-                    .skip(offset as usize)
-                    .take(limit as usize),
+        }
+        id.cmp(&after.id)
+    }
+
+    fn compare_value(
+        object: &IsarDeserializer,
+        offset: u32,
+        data_type: DataType,
+        value: Option<&IsarValue>,
+        case_sensitive: bool,
+    ) -> Ordering {
+        match data_type {
+            DataType::Bool => object.read_bool(offset).cmp(&value.and_then(|v| v.bool())),
+            DataType::Byte => object
+                .read_byte(offset)
+                .cmp(&value.and_then(|v| v.u8()).unwrap_or(0)),
+            DataType::Int => object
+                .read_int(offset)
+                .cmp(&value.and_then(|v| v.i32()).unwrap_or(NULL_INT)),
+            DataType::Float => object.read_float(offset).total_cmp(
+                &value
+                    .and_then(|v| v.real())
+                    .map_or(NULL_FLOAT, |v| v as f32),
+            ),
+            DataType::Long => object
+                .read_long(offset)
+                .cmp(&value.and_then(|v| v.i64()).unwrap_or(NULL_LONG)),
+            DataType::Double => object
+                .read_double(offset)
+                .total_cmp(&value.and_then(|v| v.real()).unwrap_or(NULL_DOUBLE)),
+            DataType::String => {
+                let s1 = object.read_string(offset);
+                let s2 = value.and_then(|v| v.string());
+                Self::compare_string(s1, s2, case_sensitive)
             }
+            DataType::Uuid => object
+                .read_uuid(offset)
+                .cmp(&value.and_then(|v| v.string()).and_then(parse_uuid)),
+            _ => Ordering::Equal,
+        }
+    }
+
+    fn compare_string(s1: Option<&str>, s2: Option<&str>, case_sensitive: bool) -> Ordering {
+        match (s1, s2) {
+            (Some(s1), Some(s2)) => {
+                StringNormalization::from_case_sensitive(case_sensitive).compare(s1, s2)
+            }
+            // null sorts first
+            _ => s1.is_some().cmp(&s2.is_some()),
         }
     }
 
@@ -108,13 +184,9 @@ impl<'a> SortedQueryIterator<'a> {
             DataType::String => {
                 let s1 = o1.read_string(offset);
                 let s2 = o2.read_string(offset);
-                if case_sensitive {
-                    s1.cmp(&s2)
-                } else {
-                    s1.map(|s| s.to_lowercase())
-                        .cmp(&s2.map(|s| s.to_lowercase()))
-                }
+                Self::compare_string(s1, s2, case_sensitive)
             }
+            DataType::Uuid => o1.read_uuid(offset).cmp(&o2.read_uuid(offset)),
             _ => Ordering::Equal,
         }
     }
@@ -3,10 +3,11 @@ use super::native_filter::NativeFilter;
 use crate::native::isar_deserializer::IsarDeserializer;
 use crate::native::native_collection::NativeProperty;
 use intmap::IntMap;
+use std::borrow::Cow;
 
 pub(crate) struct UnsortedDistinctQueryIterator<'a> {
     iterator: IndexIterator<'a>,
-    filter: &'a NativeFilter,
+    filter: Cow<'a, NativeFilter>,
     properties: &'a [(NativeProperty, bool)],
     hashes: IntMap<()>,
     skip: u32,
@@ -16,7 +17,7 @@ pub(crate) struct UnsortedDistinctQueryIterator<'a> {
 impl<'a> UnsortedDistinctQueryIterator<'a> {
     pub fn new(
         iterator: IndexIterator<'a>,
-        filter: &'a NativeFilter,
+        filter: Cow<'a, NativeFilter>,
         properties: &'a [(NativeProperty, bool)],
         offset: u32,
         limit: u32,
@@ -50,7 +51,9 @@ impl<'a> Iterator for UnsortedDistinctQueryIterator<'a> {
                             hash,
                         )
                     });
-                if self.hashes.insert(hash, ()).is_none() {
+                // objects before a continuation are only hashed so their values
+                // are not returned again
+                if self.hashes.insert(hash, ()).is_none() && self.iterator.is_after() {
                     if self.skip > 0 {
                         self.skip -= 1;
                     } else if self.take > 0 {
@@ -2,6 +2,8 @@ use super::mdbx::db::Db;
 use super::mdbx::env::Env;
 use super::native_collection::{NativeCollection, NativeProperty};
 use super::native_index::NativeIndex;
+use super::native_timestamps::NativeTimestamps;
+use super::native_tombstones::NativeTombstones;
 use super::native_txn::NativeTxn;
 use crate::core::error::{IsarError, Result};
 use crate::core::schema::{IsarSchema, PropertySchema};
@@ -14,7 +16,7 @@ const ISAR_FILE_VERSION: u8 = 3;
 pub(crate) fn perform_migration(
     instance_id: u32,
     env: &Arc<Env>,
-    mut schemas: Vec<IsarSchema>,
+    schemas: &[IsarSchema],
 ) -> Result<Vec<NativeCollection>> {
     let txn = NativeTxn::new(instance_id, env, true)?;
     let info_db = open_info_db(&txn)?;
@@ -24,7 +26,9 @@ pub(crate) fn perform_migration(
     let schema_names = schemas.iter().map(|c| c.name.to_string()).collect_vec();
 
     let mut collections = vec![];
-    for schema in schemas.iter_mut() {
+    for schema in schemas {
+        // the caller's schema keeps its property order, only the stored schema is merged
+        let mut schema = schema.clone();
         let existing_schema_index = existing_schemas.iter().position(|c| c.name == schema.name);
 
         let txn = NativeTxn::new(instance_id, env, true)?;
@@ -32,6 +36,13 @@ pub(crate) fn perform_migration(
             let existing_schema = &existing_schemas[existing_schema_index];
 
             let merged_properties = migrate_collection(&txn, &schema, existing_schema)?;
+            if existing_schema.tombstones && !schema.tombstones {
+                drop_tombstones(&txn, info_db, &schema.name)?;
+            }
+            if existing_schema.field_timestamps && !schema.field_timestamps {
+                let timestamps_db = open_timestamps_db(&txn, &schema.name)?;
+                txn.drop_db(timestamps_db)?;
+            }
             Cow::Owned(merged_properties)
         } else {
             Cow::Borrowed(&schema.properties)
@@ -74,23 +85,68 @@ pub(crate) fn perform_migration(
                         .clone()
                 })
                 .collect_vec();
-            let index =
-                NativeIndex::new(&index.name, index_db, properties, index.unique, index.hash);
+            let index = NativeIndex::new(
+                &index.name,
+                index_db,
+                properties,
+                index.unique,
+                index.hash,
+                index.geo,
+                index.normalization,
+            );
             indexes.push(index);
         }
 
+        let key_index = schema
+            .key_index()
+            .and_then(|key_index| schema.indexes.iter().position(|i| i == key_index));
+        let expiry_index = schema
+            .expiry_index()
+            .and_then(|expiry_index| schema.indexes.iter().position(|i| i == expiry_index));
+        let version_property = schema.version_name.as_ref().and_then(|version_name| {
+            let position = properties
+                .iter()
+                .position(|(name, _)| name == version_name)?;
+            Some(position as u16 + 1)
+        });
+        let tombstones = if schema.tombstones {
+            let tombstones_db = open_tombstones_db(&txn, &schema.name)?;
+            let sequences_db = open_tombstone_sequences_db(&txn, &schema.name)?;
+            Some(NativeTombstones::new(
+                tombstones_db,
+                sequences_db,
+                info_db,
+                tombstones_sequence_key(&schema.name),
+            ))
+        } else {
+            None
+        };
+        let timestamps = if schema.field_timestamps {
+            let timestamps_db = open_timestamps_db(&txn, &schema.name)?;
+            Some(NativeTimestamps::new(timestamps_db))
+        } else {
+            None
+        };
         let col = NativeCollection::new(
             collections.len() as u16,
             &schema.name,
             schema.id_name.as_deref(),
             properties,
             indexes,
+            key_index,
+            expiry_index,
+            version_property,
+            tombstones,
+            timestamps,
             db,
         );
 
         if !col.is_embedded() {
             col.init_auto_increment(&txn)?;
         }
+        if let Some(tombstones) = &col.tombstones {
+            tombstones.init_sequence(&txn)?;
+        }
         txn.commit()?;
 
         collections.push(col);
@@ -107,10 +163,27 @@ pub(crate) fn perform_migration(
     Ok(collections)
 }
 
+/// Reads the schemas stored in the `_info` db of an existing instance.
+pub(crate) fn read_schemas(txn: &NativeTxn) -> Result<Vec<IsarSchema>> {
+    let info_db = open_info_db(txn)?;
+    let mut schemas = get_schemas(txn, info_db)?;
+    for schema in &mut schemas {
+        // the id name is not stored
+        if !schema.embedded {
+            schema.id_name = Some("id".to_string());
+        }
+    }
+    Ok(schemas)
+}
+
 fn get_schemas(txn: &NativeTxn, info_db: Db) -> Result<Vec<IsarSchema>> {
     let info_cursor = txn.get_cursor(info_db)?;
     let mut schemas = vec![];
-    for (_, bytes) in info_cursor.iter()? {
+    for (key, bytes) in info_cursor.iter()? {
+        // schema names never start with an underscore
+        if key.starts_with(b"_") {
+            continue;
+        }
         let col =
             serde_json::from_slice::<IsarSchema>(bytes).map_err(|_| IsarError::SchemaError {
                 message: "Could not deserialize existing schema.".to_string(),
@@ -138,6 +211,40 @@ fn open_index_db(txn: &NativeTxn, col_name: &str, index_name: &str) -> Result<Db
     txn.open_db(&db_name, false, true)
 }
 
+fn open_tombstones_db(txn: &NativeTxn, col_name: &str) -> Result<Db> {
+    let db_name = format!("_{}__tombstones", col_name);
+    txn.open_db(&db_name, true, false)
+}
+
+fn open_tombstone_sequences_db(txn: &NativeTxn, col_name: &str) -> Result<Db> {
+    let db_name = format!("_{}__tombstone_sequences", col_name);
+    txn.open_db(&db_name, true, false)
+}
+
+fn open_timestamps_db(txn: &NativeTxn, col_name: &str) -> Result<Db> {
+    let db_name = format!("_{}__timestamps", col_name);
+    txn.open_db(&db_name, true, false)
+}
+
+fn tombstones_sequence_key(col_name: &str) -> String {
+    format!("_{}__tombstones", col_name)
+}
+
+fn drop_tombstones(txn: &NativeTxn, info_db: Db, col_name: &str) -> Result<()> {
+    let tombstones_db = open_tombstones_db(txn, col_name)?;
+    txn.drop_db(tombstones_db)?;
+    let sequences_db = open_tombstone_sequences_db(txn, col_name)?;
+    txn.drop_db(sequences_db)?;
+    let mut info_cursor = txn.get_cursor(info_db)?;
+    if info_cursor
+        .move_to(tombstones_sequence_key(col_name).as_bytes())?
+        .is_some()
+    {
+        info_cursor.delete_current()?;
+    }
+    Ok(())
+}
+
 fn delete_collection(txn: &NativeTxn, info_db: Db, schema: &IsarSchema) -> Result<()> {
     let db = txn.open_db(&schema.name, true, false)?;
     txn.drop_db(db)?;
@@ -145,6 +252,13 @@ fn delete_collection(txn: &NativeTxn, info_db: Db, schema: &IsarSchema) -> Resul
         let index_db = open_index_db(txn, &schema.name, &index.name)?;
         txn.drop_db(index_db)?;
     }
+    if schema.tombstones {
+        drop_tombstones(txn, info_db, &schema.name)?;
+    }
+    if schema.field_timestamps {
+        let timestamps_db = open_timestamps_db(txn, &schema.name)?;
+        txn.drop_db(timestamps_db)?;
+    }
 
     let mut info_cursor = txn.get_cursor(info_db)?;
     if info_cursor.move_to(&schema.name.as_bytes())?.is_some() {
@@ -1,13 +1,17 @@
 use super::sql::{
-    add_column_sql, create_index_sql, create_table_sql, drop_column_sql, drop_index_sql,
-    sql_data_type,
+    add_column_sql, create_embedded_schemas_sql, create_index_sql, create_table_sql,
+    create_timestamps_sql, create_tombstones_sql, drop_column_sql, drop_index_sql,
+    drop_timestamps_trigger_sql, drop_tombstone_triggers_sql, sql_data_type, timestamps_table_name,
+    tombstones_table_name, EMBEDDED_SCHEMAS_TABLE,
 };
 use super::sqlite3::SQLite3;
 use super::sqlite_txn::SQLiteTxn;
+use crate::core::data_type::DataType;
 use crate::core::error::{IsarError, Result};
 use crate::core::schema::{IndexSchema, IsarSchema, PropertySchema};
 use crate::sqlite::sqlite_collection::SQLiteProperty;
 use itertools::Itertools;
+use serde_json::{Map, Value};
 
 pub(crate) fn perform_migration(txn: &SQLiteTxn, schemas: &[IsarSchema]) -> Result<()> {
     txn.guard(|| {
@@ -22,15 +26,44 @@ pub(crate) fn perform_migration(txn: &SQLiteTxn, schemas: &[IsarSchema]) -> Resu
                     let sql = create_table_sql(collection);
                     sqlite.prepare(&sql)?.step()?;
                     for index in &collection.indexes {
-                        let sql = create_index_sql(&collection.name, index);
+                        let sql = create_index_sql(collection, index);
                         sqlite.prepare(&sql)?.step()?;
                     }
                 }
+
+                let tombstones_sql = if collection.tombstones {
+                    create_tombstones_sql(&collection.name)
+                } else {
+                    drop_tombstone_triggers_sql(&collection.name)
+                };
+                for sql in tombstones_sql {
+                    sqlite.prepare(&sql)?.step()?;
+                }
+
+                let timestamps_sql = if collection.field_timestamps {
+                    create_timestamps_sql(&collection.name)
+                } else {
+                    vec![drop_timestamps_trigger_sql(&collection.name)]
+                };
+                for sql in timestamps_sql {
+                    sqlite.prepare(&sql)?.step()?;
+                }
             }
         }
 
+        save_embedded_schemas(sqlite, schemas)?;
+
         for table in table_names {
-            if !schemas.iter().any(|c| c.name == table && !c.embedded) {
+            if table == EMBEDDED_SCHEMAS_TABLE {
+                continue;
+            }
+            let used = schemas.iter().any(|c| {
+                !c.embedded
+                    && (c.name == table
+                        || (c.tombstones && tombstones_table_name(&c.name) == table)
+                        || (c.field_timestamps && timestamps_table_name(&c.name) == table))
+            });
+            if !used {
                 let sql = format!("DROP TABLE {}", table);
                 sqlite.prepare(&sql)?.step()?;
             }
@@ -40,6 +73,199 @@ pub(crate) fn perform_migration(txn: &SQLiteTxn, schemas: &[IsarSchema]) -> Resu
     })
 }
 
+fn save_embedded_schemas(sqlite: &SQLite3, schemas: &[IsarSchema]) -> Result<()> {
+    sqlite.prepare(&create_embedded_schemas_sql())?.step()?;
+    let sql = format!("DELETE FROM {}", EMBEDDED_SCHEMAS_TABLE);
+    sqlite.prepare(&sql)?.step()?;
+
+    let sql = format!(
+        "INSERT INTO {} (name, schema) VALUES (?, ?)",
+        EMBEDDED_SCHEMAS_TABLE
+    );
+    for schema in schemas.iter().filter(|s| s.embedded) {
+        let json = serde_json::to_string(schema).map_err(|_| IsarError::SchemaError {
+            message: "Could not serialize schema.".to_string(),
+        })?;
+        let mut stmt = sqlite.prepare(&sql)?;
+        stmt.bind_text(0, &schema.name)?;
+        stmt.bind_text(1, &json)?;
+        stmt.step()?;
+    }
+    Ok(())
+}
+
+fn read_embedded_schemas(sqlite: &SQLite3) -> Result<Vec<IsarSchema>> {
+    let sql = format!("SELECT schema FROM {}", EMBEDDED_SCHEMAS_TABLE);
+    let mut stmt = sqlite.prepare(&sql)?;
+    let mut schemas = vec![];
+    while stmt.step()? {
+        let schema = serde_json::from_str::<IsarSchema>(stmt.get_text(0)).map_err(|_| {
+            IsarError::SchemaError {
+                message: "Could not deserialize existing schema.".to_string(),
+            }
+        })?;
+        schemas.push(schema);
+    }
+    Ok(schemas)
+}
+
+type EmbeddedSamples = Vec<(String, Vec<Map<String, Value>>)>;
+
+/// Reads the schemas of all collection tables and the stored embedded schemas.
+/// Databases written before embedded schemas were stored have their embedded
+/// properties inferred from the objects that use them.
+pub(crate) fn read_schemas(txn: &SQLiteTxn) -> Result<Vec<IsarSchema>> {
+    let sqlite = txn.get_sqlite(false)?;
+    let table_names = sqlite.get_table_names()?;
+    let stored_embedded = table_names.iter().any(|t| t == EMBEDDED_SCHEMAS_TABLE);
+
+    let mut schemas = vec![];
+    let mut embedded: EmbeddedSamples = vec![];
+    for name in &table_names {
+        // tombstone and timestamp tables start with an underscore
+        if name.starts_with('_') {
+            continue;
+        }
+
+        let mut schema = read_col_schema(sqlite, name)?;
+        schema.id_name = Some("id".to_string());
+        schema.tombstones = table_names.contains(&tombstones_table_name(name));
+        schema.field_timestamps = table_names.contains(&timestamps_table_name(name));
+        if stored_embedded {
+            schemas.push(schema);
+            continue;
+        }
+
+        for property in &schema.properties {
+            if let (Some(property_name), Some(target)) = (&property.name, &property.collection) {
+                let sql = format!(
+                    "SELECT {} FROM {} WHERE {} IS NOT NULL",
+                    property_name, name, property_name
+                );
+                let mut stmt = sqlite.prepare(&sql)?;
+                let mut objects = vec![];
+                while stmt.step()? {
+                    if let Ok(value) = serde_json::from_str(stmt.get_text(0)) {
+                        collect_objects(value, &mut objects);
+                    }
+                }
+                add_samples(&mut embedded, target, objects);
+            }
+        }
+        schemas.push(schema);
+    }
+
+    if stored_embedded {
+        schemas.extend(read_embedded_schemas(sqlite)?);
+        return Ok(schemas);
+    }
+
+    let mut i = 0;
+    while i < embedded.len() {
+        let name = embedded[i].0.clone();
+        let objects = std::mem::take(&mut embedded[i].1);
+        let properties = infer_properties(&name, &objects, &mut embedded);
+        schemas.push(IsarSchema::new(&name, None, properties, vec![], true));
+        i += 1;
+    }
+
+    Ok(schemas)
+}
+
+fn add_samples(embedded: &mut EmbeddedSamples, name: &str, objects: Vec<Map<String, Value>>) {
+    if let Some((_, samples)) = embedded.iter_mut().find(|(n, _)| n == name) {
+        samples.extend(objects);
+    } else {
+        embedded.push((name.to_string(), objects));
+    }
+}
+
+fn collect_objects(value: Value, objects: &mut Vec<Map<String, Value>>) {
+    match value {
+        Value::Object(object) => objects.push(object),
+        Value::Array(list) => {
+            for value in list {
+                if let Value::Object(object) = value {
+                    objects.push(object);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Infers the properties of an embedded schema from sample objects. The
+/// property order is not stored, so properties are sorted by name. Nested
+/// objects get a schema named after their parent and property. Properties
+/// that are always null or empty are skipped because their type is unknown.
+fn infer_properties(
+    name: &str,
+    objects: &[Map<String, Value>],
+    embedded: &mut EmbeddedSamples,
+) -> Vec<PropertySchema> {
+    let keys = objects.iter().flat_map(|o| o.keys()).unique().collect_vec();
+    let mut properties = vec![];
+    for key in keys {
+        let values = objects.iter().filter_map(|o| o.get(key)).collect_vec();
+        let data_type = values
+            .iter()
+            .filter_map(|v| json_data_type(v))
+            .reduce(merge_data_types);
+        let Some(data_type) = data_type else {
+            continue;
+        };
+
+        let target = if data_type == DataType::Object || data_type == DataType::ObjectList {
+            let target = format!("{}_{}", name, key);
+            let mut nested = vec![];
+            for value in values {
+                collect_objects(value.clone(), &mut nested);
+            }
+            add_samples(embedded, &target, nested);
+            Some(target)
+        } else {
+            None
+        };
+        properties.push(PropertySchema::new(key, data_type, target.as_deref()));
+    }
+    properties
+}
+
+fn json_data_type(value: &Value) -> Option<DataType> {
+    match value {
+        Value::Bool(_) => Some(DataType::Bool),
+        Value::Number(n) if n.is_f64() => Some(DataType::Double),
+        Value::Number(_) => Some(DataType::Long),
+        Value::String(_) => Some(DataType::String),
+        Value::Object(_) => Some(DataType::Object),
+        Value::Array(list) => {
+            let element_type = list
+                .iter()
+                .filter_map(json_data_type)
+                .reduce(merge_data_types)?;
+            match element_type {
+                DataType::Bool => Some(DataType::BoolList),
+                DataType::Long => Some(DataType::LongList),
+                DataType::Double => Some(DataType::DoubleList),
+                DataType::String => Some(DataType::StringList),
+                DataType::Object => Some(DataType::ObjectList),
+                _ => None,
+            }
+        }
+        Value::Null => None,
+    }
+}
+
+fn merge_data_types(a: DataType, b: DataType) -> DataType {
+    match (a, b) {
+        (DataType::Long, DataType::Double) | (DataType::Double, DataType::Long) => DataType::Double,
+        (DataType::LongList, DataType::DoubleList) | (DataType::DoubleList, DataType::LongList) => {
+            DataType::DoubleList
+        }
+        _ => a,
+    }
+}
+
 fn read_col_schema(sqlite: &SQLite3, name: &str) -> Result<IsarSchema> {
     let columns = sqlite.get_table_columns(name)?;
     let indexes = sqlite.get_table_indexes(name)?;
@@ -65,10 +291,14 @@ fn read_col_schema(sqlite: &SQLite3, name: &str) -> Result<IsarSchema> {
 
     let indexes = indexes
         .iter()
-        .map(|(name, unique, cols)| {
+        .map(|(name, unique, cols, geo, normalization)| {
             let name = name.split('_').last().unwrap();
-            let cols = cols.iter().map(|c| c.as_str()).collect();
-            IndexSchema::new(name, cols, *unique, false)
+            if *geo {
+                IndexSchema::new_geo(name, &cols[0], &cols[1])
+            } else {
+                let cols = cols.iter().map(|c| c.as_str()).collect();
+                IndexSchema::new(name, cols, *unique, false).with_normalization(*normalization)
+            }
         })
         .collect();
 
@@ -96,7 +326,7 @@ fn update_table(sqlite: &SQLite3, collection: &IsarSchema) -> Result<()> {
     }
 
     for index in &add_indexes {
-        let sql = create_index_sql(&collection.name, index);
+        let sql = create_index_sql(collection, index);
         sqlite.prepare(&sql)?.step()?;
     }
 
@@ -1,10 +1,18 @@
 use super::sqlite3::{SQLite3, SQLiteFnContext};
 use super::sqlite_collection::{SQLiteCollection, SQLiteProperty};
 use super::sqlite_query::QueryParam;
+use super::sqlite_writer::SQLiteObjectWriter;
 use crate::core::data_type::DataType;
-use crate::core::error::Result;
-use crate::core::filter::{ConditionType, Filter, FilterCondition, JsonCondition};
+use crate::core::error::{IsarError, Result};
+use crate::core::filter::{
+    ConditionType, Filter, FilterCondition, FilterGeo, FilterJson, JsonCondition, ListQuantifier,
+};
+use crate::core::filter_regex::FilterRegex;
+use crate::core::geo::{geohash, GeoShape};
+use crate::core::normalize::StringNormalization;
 use crate::core::schema::{IndexSchema, IsarSchema, PropertySchema};
+use crate::core::update::{integer_bounds, UpdateOp};
+use crate::core::uuid::{canonical_uuid, format_uuid, parse_uuid, NULL_UUID};
 use crate::core::value::IsarValue;
 use itertools::Itertools;
 use serde_json::Value;
@@ -51,20 +59,145 @@ pub(crate) fn index_name(table_name: &str, index_name: &str) -> String {
     format!("{table_name}_{index_name}")
 }
 
-pub(crate) fn create_index_sql(table_name: &str, index: &IndexSchema) -> String {
+pub(crate) fn create_index_sql(collection: &IsarSchema, index: &IndexSchema) -> String {
+    let columns = if index.geo {
+        format!("{}({})", FN_GEOHASH_NAME, index.properties.join(", "))
+    } else {
+        index
+            .properties
+            .iter()
+            .map(|name| {
+                // the collation of other types would prevent SQLite from using the index
+                let is_string = collection
+                    .properties
+                    .iter()
+                    .any(|p| p.name.as_ref() == Some(name) && p.data_type == DataType::String);
+                if is_string && !index.normalization.is_none() {
+                    format!("{} COLLATE {}", name, collation_name(index.normalization))
+                } else {
+                    name.clone()
+                }
+            })
+            .join(", ")
+    };
     format!(
         "CREATE {} INDEX {} ON {} ({})",
         if index.unique { "UNIQUE" } else { "" },
-        index_name(table_name, &index.name),
-        table_name,
-        index.properties.join(", ")
+        index_name(&collection.name, &index.name),
+        collection.name,
+        columns
     )
 }
 
+/// The normalization of an index column with the given collation.
+pub(crate) fn collation_normalization(collation: &str) -> Option<StringNormalization> {
+    let flags = COLLATION_NAMES
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(collation))?;
+    Some(StringNormalization::from_flags(flags as u8))
+}
+
+/// Extracts the latitude and longitude property of a geo index from its SQL.
+pub(crate) fn geo_index_properties(index_sql: &str) -> Option<Vec<String>> {
+    let start = index_sql.find(&format!("{}(", FN_GEOHASH_NAME))? + FN_GEOHASH_NAME.len() + 1;
+    let end = start + index_sql[start..].find(')')?;
+    let properties = index_sql[start..end]
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .collect_vec();
+    if properties.len() == 2 {
+        Some(properties)
+    } else {
+        None
+    }
+}
+
 pub(crate) fn drop_index_sql(table_name: &str, idx_name: &str) -> String {
     format!("DROP INDEX {}", index_name(table_name, idx_name))
 }
 
+/// Table with the JSON of the embedded schemas, they have no table of their own.
+pub(crate) const EMBEDDED_SCHEMAS_TABLE: &str = "_embedded_schemas";
+
+pub(crate) fn create_embedded_schemas_sql() -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (name TEXT PRIMARY KEY, schema TEXT NOT NULL)",
+        EMBEDDED_SCHEMAS_TABLE
+    )
+}
+
+pub(crate) fn tombstones_table_name(table_name: &str) -> String {
+    format!("_{}__tombstones", table_name)
+}
+
+/// Creates the tombstone table of a collection and the triggers that record
+/// deletions and remove the tombstone when an object is inserted again.
+pub(crate) fn create_tombstones_sql(table_name: &str) -> Vec<String> {
+    let tombstones = tombstones_table_name(table_name);
+    vec![
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (sequence INTEGER PRIMARY KEY AUTOINCREMENT, \
+            id INTEGER NOT NULL UNIQUE, deleted_at INTEGER NOT NULL)",
+            tombstones
+        ),
+        format!(
+            "CREATE TRIGGER IF NOT EXISTS {0}_delete AFTER DELETE ON {1} BEGIN \
+            INSERT OR REPLACE INTO {0} (id, deleted_at) VALUES (old.{2}, {3}); END",
+            tombstones,
+            table_name,
+            SQLiteProperty::ID_NAME,
+            NOW_MICROS_SQL
+        ),
+        format!(
+            "CREATE TRIGGER IF NOT EXISTS {0}_insert AFTER INSERT ON {1} BEGIN \
+            DELETE FROM {0} WHERE id = new.{2}; END",
+            tombstones,
+            table_name,
+            SQLiteProperty::ID_NAME
+        ),
+    ]
+}
+
+/// Drops the tombstone triggers. The table itself is dropped like any other
+/// unused table.
+pub(crate) fn drop_tombstone_triggers_sql(table_name: &str) -> Vec<String> {
+    let tombstones = tombstones_table_name(table_name);
+    vec![
+        format!("DROP TRIGGER IF EXISTS {}_delete", tombstones),
+        format!("DROP TRIGGER IF EXISTS {}_insert", tombstones),
+    ]
+}
+
+pub(crate) fn timestamps_table_name(table_name: &str) -> String {
+    format!("_{}__timestamps", table_name)
+}
+
+/// Creates the table with the field timestamps of a collection and the trigger
+/// that removes them when an object is deleted.
+pub(crate) fn create_timestamps_sql(table_name: &str) -> Vec<String> {
+    let timestamps = timestamps_table_name(table_name);
+    vec![
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (id INTEGER PRIMARY KEY, timestamps BLOB NOT NULL)",
+            timestamps
+        ),
+        format!(
+            "CREATE TRIGGER IF NOT EXISTS {0}_delete AFTER DELETE ON {1} BEGIN \
+            DELETE FROM {0} WHERE id = old.{2}; END",
+            timestamps,
+            table_name,
+            SQLiteProperty::ID_NAME
+        ),
+    ]
+}
+
+pub(crate) fn drop_timestamps_trigger_sql(table_name: &str) -> String {
+    format!(
+        "DROP TRIGGER IF EXISTS {}_delete",
+        timestamps_table_name(table_name)
+    )
+}
+
 pub(crate) fn select_properties_sql(collection: &SQLiteCollection) -> String {
     let mut sql = String::new();
     sql.push_str(SQLiteProperty::ID_NAME);
@@ -75,6 +208,58 @@ pub(crate) fn select_properties_sql(collection: &SQLiteCollection) -> String {
     sql
 }
 
+/// Current time in microseconds since the epoch. SQLite reads the clock itself so
+/// this also works on platforms where Rust cannot.
+#[cfg(not(test))]
+const NOW_MICROS_SQL: &str = "CAST((julianday('now') - 2440587.5) * 86400000000 AS INTEGER)";
+
+/// Tests read the clock of [now_micros](crate::core::expiry::now_micros) so
+/// they can set the time.
+#[cfg(test)]
+const NOW_MICROS_SQL: &str = "isar_now_micros()";
+
+#[cfg(test)]
+pub(crate) const FN_NOW_MICROS_NAME: &str = "isar_now_micros";
+#[cfg(test)]
+pub(crate) fn sql_fn_now_micros(ctx: &mut SQLiteFnContext) -> Result<()> {
+    ctx.set_int_result(crate::core::expiry::now_micros());
+    Ok(())
+}
+
+/// Condition that matches expired objects or, if `expired` is false, objects
+/// that did not expire yet. Returns `None` if objects of the collection do not
+/// expire.
+pub(crate) fn expiry_sql(collection: &SQLiteCollection, expired: bool) -> Option<String> {
+    let property = collection.get_property(collection.expiry_property?)?;
+    if expired {
+        Some(format!("{} <= {}", property.name, NOW_MICROS_SQL))
+    } else {
+        Some(format!(
+            "({0} IS NULL OR {0} > {1})",
+            property.name, NOW_MICROS_SQL
+        ))
+    }
+}
+
+pub(crate) fn select_projected_properties_sql(
+    collection: &SQLiteCollection,
+    properties: &[u16],
+) -> String {
+    let mut sql = String::new();
+    sql.push_str(SQLiteProperty::ID_NAME);
+    for (i, prop) in collection.properties.iter().enumerate() {
+        sql.push(',');
+        if properties.contains(&(i as u16 + 1)) {
+            sql.push_str(&prop.name);
+        } else {
+            sql.push_str("NULL");
+        }
+    }
+    sql
+}
+
+/// Inserts `count` objects. Rows without an id are skipped so a batch can be
+/// written before all of its rows are bound.
 pub(crate) fn insert_sql(name: &str, properties: &[SQLiteProperty], count: u32) -> (u32, String) {
     let mut sql = String::new();
     sql.push_str("INSERT OR REPLACE INTO ");
@@ -87,7 +272,7 @@ pub(crate) fn insert_sql(name: &str, properties: &[SQLiteProperty], count: u32)
         sql.push_str(&property.name);
     }
 
-    sql.push_str(") VALUES ");
+    sql.push_str(") SELECT * FROM (VALUES ");
 
     let mut batch = String::new();
     batch.push_str("(?");
@@ -105,31 +290,187 @@ pub(crate) fn insert_sql(name: &str, properties: &[SQLiteProperty], count: u32)
         sql.push_str(",");
         sql.push_str(&batch);
     }
+    sql.push_str(") WHERE column1 IS NOT NULL");
 
     (batch_size, sql)
 }
 
+/// Returns the assignment adding `delta` to an integer column. Like on
+/// native, the result saturates at the bounds of the property type.
+fn saturating_add_sql(name: &str, data_type: DataType, delta: i128) -> Result<String> {
+    let (min, max) = integer_bounds(data_type).ok_or(IsarError::IllegalArgument {})?;
+    let (min, max) = (min as i128, max as i128);
+    let current = format!("IFNULL({}, 0)", name);
+    // a delta of 2^63 does not fit into a literal and is added in two steps
+    let sum = if delta > i64::MAX as i128 {
+        format!("{} + {} + {}", current, i64::MAX, delta - i64::MAX as i128)
+    } else {
+        format!("{} + {}", current, delta)
+    };
+    let sql = if delta >= 0 && max - delta < min {
+        format!("={}", max)
+    } else if delta >= 0 {
+        format!(
+            "=CASE WHEN {} > {} THEN {} ELSE {} END",
+            current,
+            max - delta,
+            max,
+            sum
+        )
+    } else if min - delta > max {
+        format!("={}", min)
+    } else {
+        format!(
+            "=CASE WHEN {} < {} THEN {} ELSE {} END",
+            current,
+            min - delta,
+            min,
+            sum
+        )
+    };
+    Ok(sql)
+}
+
+/// Returns the SET clause, its parameters and the condition on the stored
+/// version. With `keep_version` the version is written as is.
 pub(crate) fn update_properties_sql(
     collection: &SQLiteCollection,
-    updates: &[(u16, Option<IsarValue>)],
-) -> (String, Vec<QueryParam>) {
+    all_collections: &[SQLiteCollection],
+    updates: &[(u16, UpdateOp)],
+    keep_version: bool,
+) -> Result<(String, Vec<QueryParam>, Option<String>)> {
     let mut sql = String::new();
     let mut params = vec![];
-    for (prop, change) in updates.iter() {
+    let mut version_sql = None;
+    for (prop, op) in updates.iter() {
+        if collection.version_property == Some(*prop) && !matches!(op, UpdateOp::Set(_)) {
+            return Err(IsarError::IllegalArgument {});
+        } else if collection.version_property == Some(*prop) && !keep_version {
+            // the version can only be set to the expected stored version
+            let name = collection.get_property_name(*prop);
+            match op {
+                UpdateOp::Set(Some(IsarValue::Integer(version))) => {
+                    version_sql = Some(format!("IFNULL({}, 0) = {}", name, version));
+                }
+                UpdateOp::Set(None) => {}
+                _ => return Err(IsarError::IllegalArgument {}),
+            }
+            continue;
+        }
         if let Some(property) = collection.get_property(*prop) {
+            if !op.is_valid_for(property.data_type) {
+                return Err(IsarError::IllegalArgument {});
+            }
             if !sql.is_empty() {
                 sql.push(',');
             }
-            sql.push_str(&property.name);
-            if let Some(value) = change {
-                sql.push_str("=?");
-                params.push(QueryParam::Value(value.clone()));
-            } else {
-                sql.push_str("=NULL");
+            let name = &property.name;
+            sql.push_str(name);
+            match op {
+                UpdateOp::Set(Some(IsarValue::List(list)))
+                    if property.data_type == DataType::ByteList =>
+                {
+                    let bytes = list
+                        .iter()
+                        .map(|value| value.as_ref().and_then(|v| v.u8()))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or(IsarError::IllegalArgument {})?;
+                    sql.push_str("=?");
+                    params.push(QueryParam::Blob(bytes));
+                }
+                UpdateOp::Set(Some(value @ (IsarValue::List(_) | IsarValue::Object(_)))) => {
+                    let value = SQLiteObjectWriter::encode_property(
+                        collection,
+                        all_collections,
+                        *prop,
+                        value,
+                    )?;
+                    match value {
+                        Some(Value::String(value)) => {
+                            sql.push_str("=?");
+                            params.push(QueryParam::Value(IsarValue::String(value)));
+                        }
+                        Some(value) => {
+                            sql.push_str("=?");
+                            params.push(QueryParam::Value(IsarValue::String(value.to_string())));
+                        }
+                        None => sql.push_str("=NULL"),
+                    }
+                }
+                UpdateOp::Set(Some(value)) if property.data_type == DataType::Uuid => {
+                    // UUIDs are stored in canonical form and the nil UUID is null
+                    let uuid = value
+                        .string()
+                        .and_then(parse_uuid)
+                        .ok_or(IsarError::IllegalArgument {})?;
+                    if uuid == NULL_UUID {
+                        sql.push_str("=NULL");
+                    } else {
+                        sql.push_str("=?");
+                        params.push(QueryParam::Value(IsarValue::String(format_uuid(&uuid))));
+                    }
+                }
+                UpdateOp::Set(Some(value)) => {
+                    sql.push_str("=?");
+                    params.push(QueryParam::Value(value.clone()));
+                }
+                UpdateOp::Set(None) => sql.push_str("=NULL"),
+                UpdateOp::Increment(IsarValue::Integer(value))
+                | UpdateOp::Decrement(IsarValue::Integer(value)) => {
+                    let delta = if matches!(op, UpdateOp::Increment(_)) {
+                        *value as i128
+                    } else {
+                        -(*value as i128)
+                    };
+                    sql.push_str(&saturating_add_sql(name, property.data_type, delta)?);
+                }
+                UpdateOp::Increment(value) | UpdateOp::Decrement(value) => {
+                    let operator = if matches!(op, UpdateOp::Increment(_)) {
+                        '+'
+                    } else {
+                        '-'
+                    };
+                    sql.push_str(&format!("=IFNULL({}, 0) {} ?", name, operator));
+                    params.push(QueryParam::Value(value.clone()));
+                }
+                UpdateOp::Min(value) | UpdateOp::Max(value) => {
+                    let function = if matches!(op, UpdateOp::Min(_)) {
+                        "MIN"
+                    } else {
+                        "MAX"
+                    };
+                    sql.push_str(&format!("={}(IFNULL({}, ?), ?)", function, name));
+                    params.push(QueryParam::Value(value.clone()));
+                    params.push(QueryParam::Value(value.clone()));
+                }
+                UpdateOp::Concat(value) => {
+                    sql.push_str(&format!("=IFNULL({}, '') || ?", name));
+                    params.push(QueryParam::Value(value.clone()));
+                }
+                UpdateOp::Append(_) | UpdateOp::Remove(_) | UpdateOp::AddToSet(_) => {
+                    // byte lists are stored as blobs, all other lists as json
+                    let function = if property.data_type == DataType::ByteList {
+                        FN_UPDATE_BYTES_NAME
+                    } else {
+                        FN_UPDATE_LIST_NAME
+                    };
+                    sql.push_str(&format!("={}({}, ?)", function, name));
+                    params.push(QueryParam::Update(op.clone()));
+                }
             }
         }
     }
-    (sql, params)
+    if let Some(property) = collection
+        .version_property
+        .filter(|_| !keep_version)
+        .and_then(|p| collection.get_property(p))
+    {
+        if !sql.is_empty() {
+            sql.push(',');
+        }
+        sql.push_str(&format!("{0}=IFNULL({0}, 0) + 1", property.name));
+    }
+    Ok((sql, params, version_sql))
 }
 
 pub(crate) fn offset_limit_sql(offset: Option<u32>, limit: Option<u32>) -> String {
@@ -146,7 +487,7 @@ pub(crate) fn filter_sql(
     collection_index: u16,
     all_collections: &[SQLiteCollection],
     filter: Filter,
-) -> (String, Vec<QueryParam>) {
+) -> Result<(String, Vec<QueryParam>)> {
     filter_sql_path(collection_index, all_collections, filter, vec![])
 }
 
@@ -155,41 +496,14 @@ fn filter_sql_path(
     all_collections: &[SQLiteCollection],
     filter: Filter,
     mut path: Vec<String>,
-) -> (String, Vec<QueryParam>) {
+) -> Result<(String, Vec<QueryParam>)> {
     let collection = &all_collections[collection_index as usize];
-    match filter {
+    let sql = match filter {
         Filter::Condition(condition) => {
-            let is_list = collection
-                .get_property(condition.property_index)
-                .map_or(false, |p| p.data_type.is_list());
-            let property_name = collection.get_property_name(condition.property_index);
-            if !path.is_empty() {
-                let first_path_part = path.remove(0);
-                path.push(property_name.to_string());
-                let sql = format!("{}({}, ?)", FN_FILTER_JSON_NAME, first_path_part);
-                let condition = JsonCondition::new(
-                    path,
-                    condition.condition_type,
-                    is_list,
-                    condition.values,
-                    condition.case_sensitive,
-                );
-                (sql, vec![QueryParam::JsonCondition(condition)])
-            } else if is_list {
-                let sql = format!("{}({}, ?)", FN_FILTER_JSON_NAME, property_name);
-                let condition = JsonCondition::new(
-                    vec![],
-                    condition.condition_type,
-                    true,
-                    condition.values,
-                    condition.case_sensitive,
-                );
-                (sql, vec![QueryParam::JsonCondition(condition)])
-            } else {
-                condition_sql(collection, &condition).unwrap_or(("FALSE".to_string(), vec![]))
-            }
+            condition_path_sql(collection, condition, path, ListQuantifier::Any)?
         }
-        Filter::Json(_) => todo!(),
+        Filter::Json(json) => json_sql(collection, json, path)?,
+        Filter::Param(_) => ("FALSE".to_string(), vec![]),
         Filter::Nested(nested) => {
             if let Some(property) = collection.get_property(nested.property_index) {
                 if property.data_type == DataType::Object {
@@ -204,6 +518,54 @@ fn filter_sql_path(
             }
             ("FALSE".to_string(), vec![])
         }
+        Filter::Elements(elements) => {
+            if let Some(property) = collection.get_property(elements.property_index) {
+                if property.data_type == DataType::ObjectList {
+                    let source = if path.is_empty() {
+                        property.name.clone()
+                    } else {
+                        let column = path.remove(0);
+                        path.push(property.name.clone());
+                        format!("{}, '$.{}'", column, path.join("."))
+                    };
+                    let alias = format!("{}_element", property.name);
+                    let (sql, params) = filter_sql_path(
+                        property.collection_index.unwrap(),
+                        all_collections,
+                        *elements.filter,
+                        vec![format!("{}.value", alias)],
+                    )?;
+                    let sql = match elements.quantifier {
+                        ListQuantifier::Any => format!(
+                            "EXISTS (SELECT 1 FROM json_each({}) AS {} WHERE {})",
+                            source, alias, sql
+                        ),
+                        ListQuantifier::All => format!(
+                            "NOT EXISTS (SELECT 1 FROM json_each({}) AS {} WHERE NOT ({}))",
+                            source, alias, sql
+                        ),
+                        ListQuantifier::None => format!(
+                            "NOT EXISTS (SELECT 1 FROM json_each({}) AS {} WHERE {})",
+                            source, alias, sql
+                        ),
+                    };
+                    return Ok((sql, params));
+                } else if let Filter::Condition(condition) = *elements.filter {
+                    if property.data_type.is_list()
+                        && condition.property_index == elements.property_index
+                    {
+                        return condition_path_sql(
+                            collection,
+                            condition,
+                            path,
+                            elements.quantifier,
+                        );
+                    }
+                }
+            }
+            ("FALSE".to_string(), vec![])
+        }
+        Filter::Geo(geo) => geo_sql(collection, geo, path),
         Filter::And(filters) => {
             let mut sql = String::new();
             let mut params = vec![];
@@ -212,7 +574,7 @@ fn filter_sql_path(
                     sql.push_str(" AND ");
                 }
                 let (filter_sql, filter_params) =
-                    filter_sql_path(collection_index, all_collections, filter, path.clone());
+                    filter_sql_path(collection_index, all_collections, filter, path.clone())?;
                 sql.push_str(&filter_sql);
                 params.extend(filter_params.into_iter());
             }
@@ -226,29 +588,192 @@ fn filter_sql_path(
                     sql.push_str(" OR ");
                 }
                 let (filter_sql, filter_params) =
-                    filter_sql_path(collection_index, all_collections, filter, path.clone());
+                    filter_sql_path(collection_index, all_collections, filter, path.clone())?;
                 sql.push_str(&filter_sql);
                 params.extend(filter_params.into_iter());
             }
             (format!("({})", sql), params)
         }
         Filter::Not(filter) => {
-            let (sql, params) = filter_sql_path(collection_index, all_collections, *filter, path);
+            let (sql, params) = filter_sql_path(collection_index, all_collections, *filter, path)?;
             (format!("NOT ({})", sql), params)
         }
+    };
+    Ok(sql)
+}
+
+fn json_sql(
+    collection: &SQLiteCollection,
+    json: FilterJson,
+    path: Vec<String>,
+) -> Result<(String, Vec<QueryParam>)> {
+    json.condition.verify()?;
+    let Some(property) = collection.get_property(json.property_index) else {
+        return Ok(("FALSE".to_string(), vec![]));
+    };
+    if property.data_type != DataType::Json {
+        return Ok(("FALSE".to_string(), vec![]));
     }
+
+    // JSON properties of embedded objects are stored as JSON strings
+    let column = if let Some((first, rest)) = path.split_first() {
+        let json_path = rest
+            .iter()
+            .map(|p| p.as_str())
+            .chain([property.name.as_str()])
+            .join(".");
+        format!("json_extract({}, '$.{}')", first, json_path)
+    } else {
+        property.name.clone()
+    };
+    let sql = format!("{}({}, ?)", FN_FILTER_JSON_NAME, column);
+    Ok((sql, vec![QueryParam::JsonCondition(json.condition)]))
 }
 
+fn geo_sql(
+    collection: &SQLiteCollection,
+    geo: FilterGeo,
+    path: Vec<String>,
+) -> (String, Vec<QueryParam>) {
+    let latitude = collection.get_property(geo.latitude_index);
+    let longitude = collection.get_property(geo.longitude_index);
+    let (Some(latitude), Some(longitude)) = (latitude, longitude) else {
+        return ("FALSE".to_string(), vec![]);
+    };
+    if latitude.data_type != DataType::Double || longitude.data_type != DataType::Double {
+        return ("FALSE".to_string(), vec![]);
+    }
+
+    let column = |name: &str| {
+        if let Some((first, rest)) = path.split_first() {
+            let json_path = rest.iter().map(|p| p.as_str()).chain([name]).join(".");
+            format!("json_extract({}, '$.{}')", first, json_path)
+        } else {
+            name.to_string()
+        }
+    };
+    let latitude_sql = column(&latitude.name);
+    let longitude_sql = column(&longitude.name);
+    let sql = format!(
+        "{}({}, {}, ?)",
+        FN_GEO_WITHIN_NAME, latitude_sql, longitude_sql
+    );
+    let params = vec![QueryParam::Geo(geo.shape.clone())];
+
+    let has_index = path.is_empty()
+        && collection.indexes.iter().any(|index| {
+            index.geo && index.properties == [latitude.name.clone(), longitude.name.clone()]
+        });
+    if !has_index {
+        return (sql, params);
+    }
+
+    // the geohash ranges allow SQLite to use the expression index
+    let ranges = geo.shape.geohash_ranges();
+    if ranges.is_empty() {
+        return ("FALSE".to_string(), vec![]);
+    }
+    let hash_sql = format!("{}({}, {})", FN_GEOHASH_NAME, latitude_sql, longitude_sql);
+    let ranges_sql = ranges
+        .iter()
+        .map(|_| format!("{} BETWEEN ? AND ?", hash_sql))
+        .join(" OR ");
+    let mut range_params = ranges
+        .into_iter()
+        .flat_map(|(lower, upper)| {
+            [
+                QueryParam::Value(IsarValue::Integer(lower as i64)),
+                QueryParam::Value(IsarValue::Integer(upper as i64)),
+            ]
+        })
+        .collect_vec();
+    range_params.extend(params);
+    (format!("({}) AND {}", ranges_sql, sql), range_params)
+}
+
+fn condition_path_sql(
+    collection: &SQLiteCollection,
+    mut condition: FilterCondition,
+    mut path: Vec<String>,
+    quantifier: ListQuantifier,
+) -> Result<(String, Vec<QueryParam>)> {
+    // UUIDs are stored in canonical form so equivalent spellings match.
+    if collection
+        .get_property(condition.property_index)
+        .is_some_and(|p| p.data_type == DataType::Uuid)
+    {
+        for value in condition.values.iter_mut().flatten() {
+            if let Some(uuid) = value.string().and_then(canonical_uuid) {
+                *value = IsarValue::String(uuid);
+            }
+        }
+    }
+    let is_list = collection
+        .get_property(condition.property_index)
+        .is_some_and(|p| p.data_type.is_list());
+    let is_list_length = matches!(
+        condition.condition_type,
+        ConditionType::ListLength | ConditionType::ListIsEmpty
+    );
+    let property_name = collection.get_property_name(condition.property_index);
+    let normalization = condition.string_normalization();
+    if !path.is_empty() {
+        let first_path_part = path.remove(0);
+        path.push(property_name.to_string());
+        let sql = format!("{}({}, ?)", FN_FILTER_JSON_NAME, first_path_part);
+        let condition = JsonCondition::new_normalized(
+            path,
+            condition.condition_type,
+            is_list,
+            condition.values,
+            normalization,
+        )
+        .with_quantifier(quantifier);
+        condition.verify()?;
+        Ok((sql, vec![QueryParam::JsonCondition(condition)]))
+    } else if is_list && !is_list_length {
+        let sql = format!("{}({}, ?)", FN_FILTER_JSON_NAME, property_name);
+        let condition = JsonCondition::new_normalized(
+            vec![],
+            condition.condition_type,
+            true,
+            condition.values,
+            normalization,
+        )
+        .with_quantifier(quantifier);
+        condition.verify()?;
+        Ok((sql, vec![QueryParam::JsonCondition(condition)]))
+    } else {
+        let regex = condition.regex()?;
+        Ok(condition_sql(collection, &condition, regex).unwrap_or(("FALSE".to_string(), vec![])))
+    }
+}
+
+/// The SQL of a condition on a column. `regex` is the compiled pattern of a
+/// `StringRegex` condition.
 fn condition_sql(
     collection: &SQLiteCollection,
     condition: &FilterCondition,
+    regex: Option<FilterRegex>,
 ) -> Option<(String, Vec<QueryParam>)> {
     let property_name = collection.get_property_name(condition.property_index);
-    let collate = if condition.case_sensitive {
-        ""
+    let normalization = condition.string_normalization();
+    let collate = if normalization.is_none() {
+        String::new()
+    } else {
+        format!(" COLLATE {}", collation_name(normalization))
+    };
+    let normalized_name = if normalization.is_none() {
+        property_name.to_string()
     } else {
-        " COLLATE NOCASE"
+        format!(
+            "{}({}, {})",
+            FN_NORMALIZE_NAME,
+            property_name,
+            normalization.flags()
+        )
     };
+    let like_value = |value: &str| escape_wildcard(&normalization.normalize(value));
 
     let mut values = vec![];
     let sql = match condition.condition_type {
@@ -326,37 +851,74 @@ fn condition_sql(
                 format!("{} IS NULL", property_name)
             }
         }
+        ConditionType::In => {
+            let includes_null = condition.values.iter().any(|v| v.is_none());
+            values.extend(condition.values.iter().flatten().cloned());
+            let placeholders = vec!["?"; values.len()].join(", ");
+            match (values.is_empty(), includes_null) {
+                (true, true) => format!("{} IS NULL", property_name),
+                (true, false) => "FALSE".to_string(),
+                (false, true) => format!(
+                    "({}{} IN ({}) OR {} IS NULL)",
+                    property_name, collate, placeholders, property_name
+                ),
+                (false, false) => format!("{}{} IN ({})", property_name, collate, placeholders),
+            }
+        }
         ConditionType::StringStartsWith => {
             if let Some(IsarValue::String(prefix)) = condition.values.get(0)? {
-                values.push(IsarValue::String(format!("{}%", escape_wildcard(prefix))));
-                format!("{} LIKE ? ESCAPE '\\'", property_name)
+                values.push(IsarValue::String(format!("{}%", like_value(prefix))));
+                format!("{} LIKE ? ESCAPE '\\'", normalized_name)
             } else {
                 "FALSE".to_string()
             }
         }
         ConditionType::StringEndsWith => {
             if let Some(IsarValue::String(postfix)) = condition.values.get(0)? {
-                values.push(IsarValue::String(format!("%{}", escape_wildcard(postfix))));
-                format!("{} LIKE ? ESCAPE '\\'", property_name)
+                values.push(IsarValue::String(format!("%{}", like_value(postfix))));
+                format!("{} LIKE ? ESCAPE '\\'", normalized_name)
             } else {
                 "FALSE".to_string()
             }
         }
         ConditionType::StringContains => {
             if let Some(IsarValue::String(needle)) = condition.values.get(0)? {
-                values.push(IsarValue::String(format!("%{}%", escape_wildcard(needle))));
-                format!("{} LIKE ? ESCAPE '\\'", property_name)
+                values.push(IsarValue::String(format!("%{}%", like_value(needle))));
+                format!("{} LIKE ? ESCAPE '\\'", normalized_name)
+            } else {
+                "FALSE".to_string()
+            }
+        }
+        ConditionType::ListLength | ConditionType::ListIsEmpty => {
+            let is_list = collection
+                .get_property(condition.property_index)
+                .is_some_and(|p| p.data_type.is_list());
+            if !is_list {
+                return None;
+            }
+            if condition.condition_type == ConditionType::ListIsEmpty {
+                format!("json_array_length({}) = 0", property_name)
+            } else {
+                let lower = condition.values.first()?;
+                let upper = condition.values.get(1)?;
+                values.push(lower.clone().unwrap_or(IsarValue::Integer(0)));
+                values.push(upper.clone().unwrap_or(IsarValue::Integer(i64::MAX)));
+                format!("json_array_length({}) BETWEEN ? AND ?", property_name)
+            }
+        }
+        ConditionType::StringRegex => {
+            if let Some(regex) = regex {
+                let sql = format!("{} REGEXP ?", property_name);
+                return Some((sql, vec![QueryParam::Regex(regex)]));
             } else {
                 "FALSE".to_string()
             }
         }
         ConditionType::StringMatches => {
             if let Some(IsarValue::String(wildcard)) = condition.values.get(0)? {
-                let wildcard = escape_wildcard(wildcard)
-                    .replace("*", "%")
-                    .replace("?", "_");
+                let wildcard = like_value(wildcard).replace("*", "%").replace("?", "_");
                 values.push(IsarValue::String(wildcard));
-                format!("{} LIKE ? ESCAPE '\\'", property_name)
+                format!("{} LIKE ? ESCAPE '\\'", normalized_name)
             } else {
                 "FALSE".to_string()
             }
@@ -384,6 +946,7 @@ pub(crate) fn data_type_sql(property: &PropertySchema) -> Cow<'_, str> {
         DataType::Double => Cow::Borrowed("f64"),
         DataType::String => Cow::Borrowed("TEXT"),
         DataType::Json => Cow::Borrowed("JSON_TEXT"),
+        DataType::Uuid => Cow::Borrowed("UUID_TEXT"),
         DataType::Object => Cow::Owned(format!("{}_TEXT", property.collection.as_ref().unwrap())),
         DataType::BoolList => Cow::Borrowed("bool[]_TEXT"),
         DataType::ByteList => Cow::Borrowed("u8[]_TEXT"),
@@ -415,6 +978,7 @@ pub(crate) fn sql_data_type(sqlite_type: &str) -> (DataType, Option<&str>) {
         "f64" => (DataType::Double, None),
         "str" | "text" => (DataType::String, None),
         "json" => (DataType::Json, None),
+        "uuid" => (DataType::Uuid, None),
         "bool[]" => (DataType::BoolList, None),
         "u8[]" => (DataType::ByteList, None),
         "i32[]" => (DataType::IntList, None),
@@ -441,9 +1005,438 @@ pub(crate) fn sql_fn_filter_json(ctx: &mut SQLiteFnContext) -> Result<()> {
     let condition = ctx.get_object::<JsonCondition>(1, FN_FILTER_JSON_COND_PTR_TYPE);
 
     if let Some(condition) = condition {
-        let result = condition.matches(value);
+        let result = condition.matches(&value);
         ctx.set_int_result(if result { 1 } else { 0 });
     }
 
     Ok(())
 }
+
+pub(crate) const FN_REGEXP_NAME: &str = "regexp";
+pub(crate) const FN_REGEXP_PTR_TYPE: &[u8] = b"regex_ptr\0";
+pub(crate) fn sql_fn_regexp(ctx: &mut SQLiteFnContext) -> Result<()> {
+    if ctx.is_null(1) {
+        return Ok(());
+    }
+    let value = ctx.get_str(1);
+    let result = if let Some(regex) = ctx.get_object::<FilterRegex>(0, FN_REGEXP_PTR_TYPE) {
+        regex.is_match(value)
+    } else {
+        FilterRegex::new(ctx.get_str(0), StringNormalization::NONE)?.is_match(value)
+    };
+    ctx.set_int_result(if result { 1 } else { 0 });
+    Ok(())
+}
+
+pub(crate) const FN_GEOHASH_NAME: &str = "isar_geohash";
+pub(crate) fn sql_fn_geohash(ctx: &mut SQLiteFnContext) -> Result<()> {
+    if !ctx.is_null(0) && !ctx.is_null(1) {
+        if let Some(hash) = geohash(ctx.get_double(0), ctx.get_double(1)) {
+            ctx.set_int_result(hash as i64);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) const FN_GEO_WITHIN_NAME: &str = "isar_geo_within";
+pub(crate) const FN_GEO_SHAPE_PTR_TYPE: &[u8] = b"geo_shape_ptr\0";
+pub(crate) fn sql_fn_geo_within(ctx: &mut SQLiteFnContext) -> Result<()> {
+    let shape = ctx.get_object::<GeoShape>(2, FN_GEO_SHAPE_PTR_TYPE);
+    let result = if let Some(shape) = shape {
+        !ctx.is_null(0) && !ctx.is_null(1) && shape.contains(ctx.get_double(0), ctx.get_double(1))
+    } else {
+        false
+    };
+    ctx.set_int_result(if result { 1 } else { 0 });
+    Ok(())
+}
+
+pub(crate) const FN_UPDATE_LIST_NAME: &str = "isar_update_list";
+pub(crate) const FN_UPDATE_BYTES_NAME: &str = "isar_update_bytes";
+pub(crate) const FN_UPDATE_OP_PTR_TYPE: &[u8] = b"update_op_ptr\0";
+pub(crate) fn sql_fn_update_list(ctx: &mut SQLiteFnContext) -> Result<()> {
+    let mut list = if ctx.is_null(0) {
+        vec![]
+    } else {
+        serde_json::from_str::<Vec<Option<IsarValue>>>(ctx.get_str(0)).map_err(|e| {
+            IsarError::JsonError {
+                message: e.to_string(),
+            }
+        })?
+    };
+    if let Some(op) = ctx.get_object::<UpdateOp>(1, FN_UPDATE_OP_PTR_TYPE) {
+        op.apply_list(&mut list)?;
+        let json = serde_json::to_string(&list).map_err(|e| IsarError::JsonError {
+            message: e.to_string(),
+        })?;
+        ctx.set_str_result(&json);
+    }
+    Ok(())
+}
+
+pub(crate) fn sql_fn_update_bytes(ctx: &mut SQLiteFnContext) -> Result<()> {
+    let mut list = if ctx.is_null(0) {
+        vec![]
+    } else {
+        ctx.get_blob(0)
+            .iter()
+            .map(|byte| Some(IsarValue::Integer(*byte as i64)))
+            .collect()
+    };
+    if let Some(op) = ctx.get_object::<UpdateOp>(1, FN_UPDATE_OP_PTR_TYPE) {
+        op.apply_list(&mut list)?;
+        let bytes = list
+            .iter()
+            .map(|value| {
+                let byte = value.as_ref().and_then(|v| v.i64());
+                byte.and_then(|v| u8::try_from(v).ok()).ok_or(IsarError::IllegalArgument {})
+            })
+            .collect::<Result<Vec<_>>>()?;
+        ctx.set_blob_result(&bytes);
+    }
+    Ok(())
+}
+
+pub(crate) const COLLATION_NAMES: [&str; 8] = [
+    "BINARY",
+    "ISAR_FOLD",
+    "ISAR_NOMARK",
+    "ISAR_FOLD_NOMARK",
+    "ISAR_NFKC",
+    "ISAR_FOLD_NFKC",
+    "ISAR_NOMARK_NFKC",
+    "ISAR_FOLD_NOMARK_NFKC",
+];
+
+pub(crate) fn collation_name(normalization: StringNormalization) -> &'static str {
+    COLLATION_NAMES[normalization.flags() as usize]
+}
+
+pub(crate) const FN_NORMALIZE_NAME: &str = "isar_normalize";
+pub(crate) fn sql_fn_normalize(ctx: &mut SQLiteFnContext) -> Result<()> {
+    if !ctx.is_null(0) {
+        let normalization = StringNormalization::from_flags(ctx.get_int(1) as u8);
+        let value = normalization.normalize(ctx.get_str(0));
+        ctx.set_str_result(&value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cursor::IsarCursor;
+    use crate::core::expiry::{now_micros, set_test_now};
+    use crate::core::insert::IsarInsert;
+    use crate::core::instance::{Aggregation, IsarInstance};
+    use crate::core::merge::{now_millis, FieldUpdate, HlcTimestamp, MergeResult, ObjectChange};
+    use crate::core::query_builder::IsarQueryBuilder;
+    use crate::core::reader::IsarReader;
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::core::tombstone::Tombstone;
+    use crate::core::writer::IsarWriter;
+    use crate::sqlite::sqlite_instance::SQLiteInstance;
+    use crate::sqlite::sqlite_txn::SQLiteTxn;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn expired_objects_are_hidden_and_purged() {
+        let schema = IsarSchema::new(
+            "Session",
+            Some("id"),
+            vec![
+                PropertySchema::new("expires", DataType::Long, None),
+                PropertySchema::new("value", DataType::Int, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_expiry("expires");
+        let instance =
+            open_instance::<SQLiteInstance>("expired_objects_are_hidden_and_purged", vec![schema]);
+        let changes = Arc::new(AtomicU32::new(0));
+        let counter = changes.clone();
+        let _handle = instance
+            .watch(
+                0,
+                Box::new(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .unwrap();
+
+        let now = 1_700_000_000_000_000;
+        set_test_now(Some(now));
+        let txn = instance.begin_txn(true).unwrap();
+        let mut insert = instance.insert(txn, 0, 4).unwrap();
+        for (id, expires) in [(1, Some(1)), (2, Some(now + 3_600_000_000)), (3, None)] {
+            match expires {
+                Some(expires) => insert.write_long(1, expires),
+                None => insert.write_null(1),
+            }
+            insert.write_int(2, id as i32);
+            insert.save(id).unwrap();
+        }
+        insert.write_long(1, now + 500_000);
+        insert.write_int(2, 4);
+        insert.save(4).unwrap();
+        let txn = insert.finish().unwrap();
+
+        let mut cursor = instance.cursor(&txn, 0).unwrap();
+        assert!(cursor.next(1).is_none());
+        assert!(cursor.next(2).is_some());
+        assert!(cursor.next(3).is_some());
+        drop(cursor);
+        assert_eq!(instance.count(&txn, 0), Ok(3));
+        assert_eq!(instance.purge_expired(&txn, 0, None), Ok(1));
+        assert_eq!(instance.purge_expired(&txn, 0, None), Ok(0));
+        instance.commit_txn(txn).unwrap();
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+        set_test_now(Some(now + 500_000));
+        let txn = instance.begin_txn(false).unwrap();
+        assert_eq!(instance.count(&txn, 0), Ok(2));
+        let query = instance.query(0).unwrap().build().unwrap();
+        assert_eq!(
+            instance.query_aggregate(&txn, &query, Aggregation::Sum, Some(2)),
+            Ok(Some(IsarValue::Integer(5)))
+        );
+        instance.abort_txn(txn);
+
+        // committing a write transaction purges the object that expired since
+        let txn = instance.begin_txn(true).unwrap();
+        instance.commit_txn(txn).unwrap();
+        assert_eq!(changes.load(Ordering::SeqCst), 2);
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(instance.purge_expired(&txn, 0, None), Ok(0));
+        instance.abort_txn(txn);
+        set_test_now(None);
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn deletes_leave_tombstones() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![PropertySchema::new("value", DataType::Int, None)],
+            vec![],
+            false,
+        )
+        .with_tombstones();
+        let instance =
+            open_instance::<SQLiteInstance>("deletes_leave_tombstones", vec![schema]);
+        let insert = |txn: SQLiteTxn, ids: &[i64]| {
+            let mut insert = instance.insert(txn, 0, ids.len() as u32).unwrap();
+            for id in ids {
+                insert.write_int(1, *id as i32);
+                insert.save(*id).unwrap();
+            }
+            insert.finish().unwrap()
+        };
+        let ids = |tombstones: Vec<Tombstone>| tombstones.iter().map(|t| t.id).collect_vec();
+
+        let txn = insert(instance.begin_txn(true).unwrap(), &[1, 2, 3]);
+        assert_eq!(instance.delete(&txn, 0, 1), Ok(true));
+        assert_eq!(instance.delete(&txn, 0, 2), Ok(true));
+        assert_eq!(instance.delete(&txn, 0, 2), Ok(false));
+        instance.verify(&txn).unwrap();
+        instance.commit_txn(txn).unwrap();
+
+        let txn = instance.begin_txn(false).unwrap();
+        let tombstones = instance.get_tombstones(&txn, 0, 0, None).unwrap();
+        assert_eq!(ids(tombstones.clone()), vec![1, 2]);
+        assert_eq!(
+            tombstones.iter().map(|t| t.sequence).collect_vec(),
+            vec![1, 2]
+        );
+        assert!(tombstones.iter().all(|t| t.deleted_at > 0));
+        assert_eq!(
+            ids(instance.get_tombstones(&txn, 0, 1, None).unwrap()),
+            vec![2]
+        );
+        assert_eq!(instance.count(&txn, 0), Ok(1));
+        instance.abort_txn(txn);
+
+        // inserting an object again removes its tombstone
+        let txn = insert(instance.begin_txn(true).unwrap(), &[1]);
+        assert_eq!(
+            ids(instance.get_tombstones(&txn, 0, 0, None).unwrap()),
+            vec![2]
+        );
+        instance.clear(&txn, 0).unwrap();
+        let tombstones = instance.get_tombstones(&txn, 0, 2, None).unwrap();
+        assert_eq!(
+            ids(tombstones).into_iter().sorted().collect_vec(),
+            vec![1, 3]
+        );
+
+        assert_eq!(instance.purge_tombstones(&txn, 0, now_micros()), Ok(3));
+        assert_eq!(instance.get_tombstones(&txn, 0, 0, None), Ok(vec![]));
+        let txn = insert(txn, &[4]);
+        instance.delete(&txn, 0, 4).unwrap();
+        let tombstones = instance.get_tombstones(&txn, 0, 0, None).unwrap();
+        assert_eq!(tombstones[0].sequence, 5);
+        instance.commit_txn(txn).unwrap();
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn merges_changes_by_field_timestamp() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![
+                PropertySchema::new("value", DataType::Int, None),
+                PropertySchema::new("name", DataType::String, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_field_timestamps();
+        let instance =
+            open_instance::<SQLiteInstance>("merges_changes_by_field_timestamp", vec![schema]);
+        let notified = Arc::new(AtomicU32::new(0));
+        let notified_clone = notified.clone();
+        let _handle = instance
+            .watch(
+                0,
+                Box::new(move || {
+                    notified_clone.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .unwrap();
+        let changes = Arc::new(Mutex::new(vec![]));
+        let changes_clone = changes.clone();
+        let _handle = instance
+            .watch_detailed(
+                0,
+                Box::new(move |change| {
+                    changes_clone
+                        .lock()
+                        .unwrap()
+                        .push((change.object_id, change.remote))
+                }),
+            )
+            .unwrap();
+        // remote changes are newer than the local writes
+        let t = now_millis() + 60_000;
+        let merged = |merged: u32, missing: Vec<i64>| MergeResult { merged, missing };
+        let change = |id: i64, updates: &[(u16, IsarValue, u64, u32)]| ObjectChange {
+            id,
+            updates: updates
+                .iter()
+                .map(|(property_index, value, millis, node)| FieldUpdate {
+                    property_index: *property_index,
+                    value: Some(value.clone()),
+                    timestamp: HlcTimestamp::new(t + *millis, 0, *node),
+                })
+                .collect(),
+        };
+        let read = |txn: &SQLiteTxn, id: i64| {
+            let mut cursor = instance.cursor(txn, 0).unwrap();
+            let reader = cursor.next(id).unwrap();
+            (reader.read_int(1), reader.read_string(2).map(String::from))
+        };
+
+        insert_objects(&instance, 0, &[1], |insert, _| {
+            insert.write_int(1, 0);
+            insert.write_null(2);
+        });
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+        changes.lock().unwrap().clear();
+
+        let txn = instance.begin_txn(true).unwrap();
+        let remote = [
+            change(
+                1,
+                &[
+                    (1, IsarValue::Integer(10), 5, 1),
+                    (2, IsarValue::String("a".to_string()), 5, 1),
+                ],
+            ),
+            change(2, &[(1, IsarValue::Integer(10), 5, 1)]),
+        ];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &remote, true),
+            Ok(merged(1, vec![2]))
+        );
+        assert_eq!(read(&txn, 1), (10, Some("a".to_string())));
+        instance.commit_txn(txn).unwrap();
+        // merged remote changes notify the watchers too
+        assert_eq!(notified.load(Ordering::SeqCst), 2);
+
+        let txn = instance.begin_txn(true).unwrap();
+
+        let local = [change(1, &[(1, IsarValue::Integer(20), 7, 2)])];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &local, false),
+            Ok(merged(1, vec![]))
+        );
+
+        // the older remote value must not clobber the newer local edit
+        let remote = [change(
+            1,
+            &[
+                (1, IsarValue::Integer(30), 6, 1),
+                (2, IsarValue::String("b".to_string()), 6, 1),
+            ],
+        )];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &remote, true),
+            Ok(merged(1, vec![]))
+        );
+        assert_eq!(read(&txn, 1), (20, Some("b".to_string())));
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &remote, true),
+            Ok(merged(0, vec![]))
+        );
+        instance.verify(&txn).unwrap();
+        instance.commit_txn(txn).unwrap();
+        assert_eq!(*changes.lock().unwrap(), vec![(1, true), (1, false), (1, true)]);
+        assert_eq!(notified.load(Ordering::SeqCst), 3);
+
+        // local writes win over concurrent remote changes
+        let txn = instance.begin_txn(true).unwrap();
+        let update = (1, UpdateOp::Set(Some(IsarValue::Integer(50))));
+        assert_eq!(instance.update(&txn, 0, 1, &[update]), Ok(true));
+        let query = instance.query(0).unwrap().build().unwrap();
+        let update = (2, UpdateOp::Set(Some(IsarValue::String("c".to_string()))));
+        assert_eq!(instance.query_update(&txn, &query, None, None, &[update]), Ok(1));
+        let concurrent = [change(
+            1,
+            &[
+                (1, IsarValue::Integer(60), 7, 9),
+                (2, IsarValue::String("d".to_string()), 6, 9),
+            ],
+        )];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &concurrent, true),
+            Ok(merged(0, vec![]))
+        );
+        assert_eq!(read(&txn, 1), (50, Some("c".to_string())));
+        instance.abort_txn(txn);
+
+        // deleting an object drops its timestamps
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(instance.delete(&txn, 0, 1), Ok(true));
+        let old = [change(1, &[(1, IsarValue::Integer(40), 1, 0)])];
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &old, true),
+            Ok(merged(0, vec![1]))
+        );
+        let mut insert = instance.insert(txn, 0, 1).unwrap();
+        insert.write_int(1, 0);
+        insert.write_null(2);
+        insert.save(1).unwrap();
+        let txn = insert.finish().unwrap();
+        assert_eq!(
+            instance.merge_changes(&txn, 0, &old, true),
+            Ok(merged(1, vec![]))
+        );
+        assert_eq!(read(&txn, 1), (40, None));
+        instance.commit_txn(txn).unwrap();
+        SQLiteInstance::close(instance, true);
+    }
+}
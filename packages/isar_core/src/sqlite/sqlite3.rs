@@ -1,21 +1,34 @@
 use crate::core::error::{IsarError, Result};
+use crate::core::normalize::StringNormalization;
 use ffi::sqlite3_busy_timeout;
 use libsqlite3_sys as ffi;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
 use std::ffi::{c_char, c_int, c_void, CStr, CString};
 use std::{ptr, slice};
 
-use super::sql::{sql_fn_filter_json, FN_FILTER_JSON_NAME};
+use super::sql::{
+    collation_name, collation_normalization, geo_index_properties, sql_fn_filter_json,
+    sql_fn_geo_within, sql_fn_geohash, sql_fn_normalize, sql_fn_regexp, sql_fn_update_bytes,
+    sql_fn_update_list, COLLATION_NAMES, FN_FILTER_JSON_NAME, FN_GEOHASH_NAME, FN_GEO_WITHIN_NAME,
+    FN_NORMALIZE_NAME, FN_REGEXP_NAME, FN_UPDATE_BYTES_NAME, FN_UPDATE_LIST_NAME,
+};
+
+/// Name, uniqueness, columns, whether the index is a geo index and its normalization.
+pub(crate) type TableIndex = (String, bool, Vec<String>, bool, StringNormalization);
 
 pub(crate) struct SQLite3 {
     db: *mut ffi::sqlite3,
     free_update_hook: Cell<Option<unsafe extern "C" fn(*mut std::os::raw::c_void)>>,
+    /// Reset statements by SQL, the most recently used last.
+    statement_cache: RefCell<Vec<(String, *mut ffi::sqlite3_stmt)>>,
 }
 
 unsafe impl Send for SQLite3 {}
 
 impl SQLite3 {
     pub(crate) const MAX_PARAM_COUNT: u32 = 999;
+    const STATEMENT_CACHE_SIZE: usize = 16;
 
     pub fn open(path: &str, encryption_key: Option<&str>) -> Result<SQLite3> {
         let flags = ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE | ffi::SQLITE_OPEN_NOMUTEX;
@@ -27,6 +40,7 @@ impl SQLite3 {
                 let sqlite = SQLite3 {
                     db,
                     free_update_hook: Cell::new(None),
+                    statement_cache: RefCell::new(vec![]),
                 };
                 if let Some(encryption_key) = encryption_key {
                     sqlite
@@ -61,7 +75,27 @@ impl SQLite3 {
             sqlite3_busy_timeout(self.db, 5000);
         }
         self.prepare("PRAGMA case_sensitive_like = true")?.step()?;
-        self.create_function(FN_FILTER_JSON_NAME, 2, sql_fn_filter_json)?;
+        self.create_function(FN_FILTER_JSON_NAME, 2, false, sql_fn_filter_json)?;
+        self.create_function(FN_NORMALIZE_NAME, 2, false, sql_fn_normalize)?;
+        self.create_function(FN_REGEXP_NAME, 2, false, sql_fn_regexp)?;
+        // geo indexes are expression indexes which require a deterministic function
+        self.create_function(FN_GEOHASH_NAME, 2, true, sql_fn_geohash)?;
+        self.create_function(FN_GEO_WITHIN_NAME, 3, false, sql_fn_geo_within)?;
+        self.create_function(FN_UPDATE_LIST_NAME, 2, false, sql_fn_update_list)?;
+        self.create_function(FN_UPDATE_BYTES_NAME, 2, false, sql_fn_update_bytes)?;
+        #[cfg(test)]
+        self.create_function(
+            super::sql::FN_NOW_MICROS_NAME,
+            0,
+            false,
+            super::sql::sql_fn_now_micros,
+        )?;
+        for flags in 1..COLLATION_NAMES.len() as u8 {
+            let normalization = StringNormalization::from_flags(flags);
+            self.create_collation(collation_name(normalization), move |s1, s2| {
+                normalization.compare(s1, s2)
+            })?;
+        }
         Ok(())
     }
 
@@ -77,13 +111,46 @@ impl SQLite3 {
                 &mut c_tail as *mut *const c_char,
             );
             if r == ffi::SQLITE_OK {
-                Ok(SQLiteStatement { stmt, sqlite: self })
+                Ok(SQLiteStatement {
+                    stmt,
+                    sqlite: self,
+                    cache_key: None,
+                })
             } else {
                 Err(sqlite_err(self.db, r))
             }
         }
     }
 
+    /// Prepares a statement that is reset and kept for the next call with the
+    /// same SQL when it is dropped.
+    pub fn prepare_cached(&self, sql: &str) -> Result<SQLiteStatement<'_>> {
+        let mut cache = self.statement_cache.borrow_mut();
+        if let Some(position) = cache.iter().position(|(cached, _)| cached == sql) {
+            let (key, stmt) = cache.remove(position);
+            return Ok(SQLiteStatement {
+                stmt,
+                sqlite: self,
+                cache_key: Some(key),
+            });
+        }
+        drop(cache);
+        let mut stmt = self.prepare(sql)?;
+        stmt.cache_key = Some(sql.to_string());
+        Ok(stmt)
+    }
+
+    fn cache_statement(&self, sql: String, stmt: *mut ffi::sqlite3_stmt) {
+        let mut cache = self.statement_cache.borrow_mut();
+        if cache.len() >= Self::STATEMENT_CACHE_SIZE {
+            let (_, evicted) = cache.remove(0);
+            unsafe {
+                ffi::sqlite3_finalize(evicted);
+            }
+        }
+        cache.push((sql, stmt));
+    }
+
     pub fn get_table_names(&self) -> Result<Vec<String>> {
         let mut stmt = self.prepare("PRAGMA table_list")?;
         let mut names = vec![];
@@ -110,7 +177,7 @@ impl SQLite3 {
         Ok(cols)
     }
 
-    pub fn get_table_indexes(&self, table_name: &str) -> Result<Vec<(String, bool, Vec<String>)>> {
+    pub fn get_table_indexes(&self, table_name: &str) -> Result<Vec<TableIndex>> {
         let mut stmt = self.prepare(&format!("PRAGMA index_list({})", table_name))?;
         let mut index_names_unique = vec![];
         while stmt.step()? {
@@ -122,12 +189,41 @@ impl SQLite3 {
         }
         let mut indexes = vec![];
         for (index_name, unique) in index_names_unique {
-            let mut stmt = self.prepare(&format!("PRAGMA index_info({})", index_name))?;
+            let mut stmt = self.prepare(&format!("PRAGMA index_xinfo({})", index_name))?;
             let mut cols = vec![];
+            let mut is_expression = false;
+            let mut normalization = StringNormalization::NONE;
             while stmt.step()? {
-                cols.push(stmt.get_text(2).to_string());
+                // the rowid is stored as an auxiliary column
+                if stmt.get_int(5) == 0 {
+                    continue;
+                }
+                // expression columns have no name
+                if stmt.is_null(2) {
+                    is_expression = true;
+                } else {
+                    cols.push(stmt.get_text(2).to_string());
+                }
+                if let Some(column_normalization) = collation_normalization(stmt.get_text(4)) {
+                    if !column_normalization.is_none() {
+                        normalization = column_normalization;
+                    }
+                }
+            }
+            let mut geo = false;
+            if is_expression {
+                let mut stmt = self.prepare(&format!(
+                    "SELECT sql FROM sqlite_master WHERE type = 'index' AND name = '{}'",
+                    index_name
+                ))?;
+                if stmt.step()? {
+                    if let Some(properties) = geo_index_properties(stmt.get_text(0)) {
+                        cols = properties;
+                        geo = true;
+                    }
+                }
             }
-            indexes.push((index_name, unique, cols));
+            indexes.push((index_name, unique, cols, geo, normalization));
         }
         Ok(indexes)
     }
@@ -136,7 +232,13 @@ impl SQLite3 {
         unsafe { ffi::sqlite3_changes(self.db) }
     }
 
-    pub fn create_function<F>(&self, name: &str, args: u32, func: F) -> Result<()>
+    pub fn create_function<F>(
+        &self,
+        name: &str,
+        args: u32,
+        deterministic: bool,
+        func: F,
+    ) -> Result<()>
     where
         F: FnMut(&mut SQLiteFnContext<'_>) -> Result<()> + Send + 'static,
     {
@@ -170,7 +272,11 @@ impl SQLite3 {
                 self.db,
                 c_name.as_ptr(),
                 args as i32,
-                ffi::SQLITE_UTF8,
+                if deterministic {
+                    ffi::SQLITE_UTF8 | ffi::SQLITE_DETERMINISTIC
+                } else {
+                    ffi::SQLITE_UTF8
+                },
                 boxed_f.cast(),
                 Some(call_boxed_closure::<F>),
                 None,
@@ -186,6 +292,47 @@ impl SQLite3 {
         }
     }
 
+    pub fn create_collation<F>(&self, name: &str, func: F) -> Result<()>
+    where
+        F: Fn(&str, &str) -> Ordering + Send + 'static,
+    {
+        unsafe extern "C" fn call_boxed_closure<F>(
+            func: *mut c_void,
+            len1: c_int,
+            str1: *const c_void,
+            len2: c_int,
+            str2: *const c_void,
+        ) -> c_int
+        where
+            F: Fn(&str, &str) -> Ordering,
+        {
+            let s1 = slice::from_raw_parts(str1.cast::<u8>(), len1 as usize);
+            let s2 = slice::from_raw_parts(str2.cast::<u8>(), len2 as usize);
+            let boxed_f = func.cast::<F>();
+            let ordering = (*boxed_f)(&String::from_utf8_lossy(s1), &String::from_utf8_lossy(s2));
+            ordering as c_int
+        }
+
+        let boxed_f = Box::into_raw(Box::new(func));
+        let c_name = CString::new(name).unwrap();
+        let r = unsafe {
+            ffi::sqlite3_create_collation_v2(
+                self.db,
+                c_name.as_ptr(),
+                ffi::SQLITE_UTF8,
+                boxed_f.cast(),
+                Some(call_boxed_closure::<F>),
+                Some(free_boxed_value::<F>),
+            )
+        };
+
+        if r == ffi::SQLITE_OK {
+            Ok(())
+        } else {
+            Err(sqlite_err(self.db, r))
+        }
+    }
+
     pub fn set_update_hook<F>(&self, func: F)
     where
         F: FnMut(i64) + 'static,
@@ -194,11 +341,15 @@ impl SQLite3 {
             func: *mut c_void,
             _: i32,
             _: *const c_char,
-            _: *const c_char,
+            table: *const c_char,
             id: i64,
         ) where
             F: FnMut(i64) -> (),
         {
+            // side tables like tombstones start with an underscore and are not reported
+            if CStr::from_ptr(table).to_bytes().starts_with(b"_") {
+                return;
+            }
             let boxed_f = func.cast::<F>();
             (*boxed_f)(id);
         }
@@ -224,6 +375,9 @@ impl SQLite3 {
 impl Drop for SQLite3 {
     fn drop(&mut self) {
         unsafe {
+            for (_, stmt) in self.statement_cache.get_mut().drain(..) {
+                ffi::sqlite3_finalize(stmt);
+            }
             ffi::sqlite3_close(self.db);
         }
     }
@@ -240,6 +394,10 @@ pub(crate) struct SQLiteFnContext<'a> {
 
 #[allow(dead_code)]
 impl<'a> SQLiteFnContext<'a> {
+    pub fn is_null(&self, index: usize) -> bool {
+        unsafe { ffi::sqlite3_value_type(self.args[index]) == ffi::SQLITE_NULL }
+    }
+
     pub fn get_int(&self, index: usize) -> i64 {
         unsafe { ffi::sqlite3_value_int64(self.args[index]) }
     }
@@ -261,7 +419,12 @@ impl<'a> SQLiteFnContext<'a> {
         unsafe {
             let blob = ffi::sqlite3_value_blob(self.args[index]);
             let num = ffi::sqlite3_value_bytes(self.args[index]);
-            std::slice::from_raw_parts(blob as *const u8, num as usize)
+            if blob.is_null() {
+                // empty blobs have no data pointer
+                &[]
+            } else {
+                std::slice::from_raw_parts(blob as *const u8, num as usize)
+            }
         }
     }
 
@@ -322,6 +485,7 @@ impl<'a> SQLiteFnContext<'a> {
 pub(crate) struct SQLiteStatement<'sqlite> {
     stmt: *mut ffi::sqlite3_stmt,
     sqlite: &'sqlite SQLite3,
+    cache_key: Option<String>,
 }
 
 impl<'sqlite> SQLiteStatement<'sqlite> {
@@ -378,6 +542,10 @@ impl<'sqlite> SQLiteStatement<'sqlite> {
     pub fn get_blob(&self, col: u32) -> &[u8] {
         unsafe {
             let blob = ffi::sqlite3_column_blob(self.stmt, col as i32);
+            // NULL values and empty blobs return a null pointer
+            if blob.is_null() {
+                return &[];
+            }
             let num = ffi::sqlite3_column_bytes(self.stmt, col as i32);
             std::slice::from_raw_parts(blob as *const u8, num as usize)
         }
@@ -483,7 +651,14 @@ impl<'sqlite> SQLiteStatement<'sqlite> {
 impl Drop for SQLiteStatement<'_> {
     fn drop(&mut self) {
         unsafe {
-            ffi::sqlite3_finalize(self.stmt);
+            if let Some(sql) = self.cache_key.take() {
+                // clearing the bindings frees the bound objects
+                ffi::sqlite3_reset(self.stmt);
+                ffi::sqlite3_clear_bindings(self.stmt);
+                self.sqlite.cache_statement(sql, self.stmt);
+            } else {
+                ffi::sqlite3_finalize(self.stmt);
+            }
         }
     }
 }
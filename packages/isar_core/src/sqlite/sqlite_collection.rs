@@ -1,11 +1,18 @@
+use std::borrow::Cow;
 use std::sync::atomic::{self, AtomicI64};
 use std::sync::Arc;
 
+use super::sql::timestamps_table_name;
+use super::sqlite3::SQLite3;
 use super::sqlite_query::SQLiteQuery;
 use super::sqlite_txn::SQLiteTxn;
 use crate::core::data_type::DataType;
-use crate::core::error::Result;
+use crate::core::error::{IsarError, Result};
+use crate::core::merge::{
+    decode_timestamps, encode_timestamps, now_millis, stamp_local_write, FieldTimestamp,
+};
 use crate::core::schema::IndexSchema;
+use crate::core::uuid::canonical_uuid;
 use crate::core::watcher::CollectionWatchers;
 
 #[derive(Debug)]
@@ -32,6 +39,14 @@ pub(crate) struct SQLiteCollection {
     pub name: String,
     pub id_name: Option<String>,
     pub properties: Vec<SQLiteProperty>,
+    /// Index of the string key property.
+    pub key_property: Option<u16>,
+    /// Index of the expiry timestamp property.
+    pub expiry_property: Option<u16>,
+    /// Index of the version property.
+    pub version_property: Option<u16>,
+    pub tombstones: bool,
+    pub field_timestamps: bool,
     pub watchers: Arc<CollectionWatchers<SQLiteQuery>>,
     auto_increment: AtomicI64,
 
@@ -40,16 +55,27 @@ pub(crate) struct SQLiteCollection {
 }
 
 impl SQLiteCollection {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         id_name: Option<String>,
         properties: Vec<SQLiteProperty>,
         indexes: Vec<IndexSchema>,
+        key_property: Option<u16>,
+        expiry_property: Option<u16>,
+        version_property: Option<u16>,
+        tombstones: bool,
+        field_timestamps: bool,
     ) -> Self {
         Self {
             name,
             id_name,
             properties,
+            key_property,
+            expiry_property,
+            version_property,
+            tombstones,
+            field_timestamps,
             watchers: CollectionWatchers::new(),
             auto_increment: AtomicI64::new(0),
             indexes,
@@ -78,11 +104,85 @@ impl SQLiteCollection {
         self.auto_increment.fetch_add(1, atomic::Ordering::AcqRel)
     }
 
+    pub fn next_auto_increment(&self) -> i64 {
+        self.auto_increment.load(atomic::Ordering::Acquire)
+    }
+
     pub fn update_auto_increment(&self, id: i64) {
         self.auto_increment
             .fetch_max(id + 1, atomic::Ordering::AcqRel);
     }
 
+    pub fn get_id_by_key(&self, sqlite: &SQLite3, key: &str) -> Result<Option<i64>> {
+        let key_property = self
+            .key_property
+            .and_then(|p| self.get_property(p))
+            .ok_or(IsarError::UnsupportedOperation {})?;
+        let key = if key_property.data_type == DataType::Uuid {
+            match canonical_uuid(key) {
+                Some(key) => Cow::Owned(key),
+                None => return Ok(None),
+            }
+        } else {
+            Cow::Borrowed(key)
+        };
+
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            SQLiteProperty::ID_NAME,
+            self.name,
+            key_property.name
+        );
+        let mut stmt = sqlite.prepare(&sql)?;
+        stmt.bind_text(0, &key)?;
+        if stmt.step()? {
+            Ok(Some(stmt.get_long(0)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn get_timestamps(&self, sqlite: &SQLite3, id: i64) -> Result<Vec<FieldTimestamp>> {
+        let mut stmt = sqlite.prepare_cached(&format!(
+            "SELECT timestamps FROM {} WHERE id = ?",
+            timestamps_table_name(&self.name)
+        ))?;
+        stmt.bind_long(0, id)?;
+        if stmt.step()? {
+            Ok(decode_timestamps(stmt.get_blob(0)))
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    pub fn put_timestamps(
+        &self,
+        sqlite: &SQLite3,
+        id: i64,
+        timestamps: &[FieldTimestamp],
+    ) -> Result<()> {
+        let mut stmt = sqlite.prepare_cached(&format!(
+            "INSERT OR REPLACE INTO {} (id, timestamps) VALUES (?, ?)",
+            timestamps_table_name(&self.name)
+        ))?;
+        stmt.bind_long(0, id)?;
+        stmt.bind_blob(1, &encode_timestamps(timestamps))?;
+        stmt.step()?;
+        Ok(())
+    }
+
+    /// Stamps the fields of a local write of the object.
+    pub fn stamp_fields(
+        &self,
+        sqlite: &SQLite3,
+        id: i64,
+        property_indexes: impl IntoIterator<Item = u16>,
+    ) -> Result<()> {
+        let mut timestamps = self.get_timestamps(sqlite, id)?;
+        stamp_local_write(&mut timestamps, property_indexes, now_millis());
+        self.put_timestamps(sqlite, id, &timestamps)
+    }
+
     pub fn get_property(&self, property_index: u16) -> Option<&SQLiteProperty> {
         if property_index != 0 {
             self.properties.get(property_index as usize - 1)
@@ -1,4 +1,4 @@
-use super::sql::select_properties_sql;
+use super::sql::{expiry_sql, select_properties_sql};
 use super::sqlite3::SQLiteStatement;
 use super::sqlite_collection::{SQLiteCollection, SQLiteProperty};
 use super::sqlite_reader::SQLiteReader;
@@ -19,12 +19,16 @@ impl<'a> SQLiteCursor<'a> {
         collection: &'a SQLiteCollection,
         collections: &'a Vec<SQLiteCollection>,
     ) -> Result<Self> {
-        let sql = format!(
+        let mut sql = format!(
             "SELECT {} FROM {} WHERE {} = ?",
             select_properties_sql(collection),
             collection.name,
             SQLiteProperty::ID_NAME,
         );
+        if let Some(not_expired_sql) = expiry_sql(collection, false) {
+            sql.push_str(" AND ");
+            sql.push_str(&not_expired_sql);
+        }
         let stmt = txn.get_sqlite(false)?.prepare(&sql)?;
         let cursor = Self {
             stmt,
@@ -1,12 +1,21 @@
-use super::sql::insert_sql;
+use super::sql::{insert_sql, select_properties_sql};
 use super::sqlite3::SQLiteStatement;
-use super::sqlite_collection::SQLiteCollection;
+use super::sqlite_collection::{SQLiteCollection, SQLiteProperty};
+use super::sqlite_instance::SQLiteInstance;
+use super::sqlite_reader::{SQLiteObjectReader, SQLiteReader};
 use super::sqlite_txn::SQLiteTxn;
+use super::sqlite_writer::SQLiteObjectWriter;
+use crate::core::convert::copy_value;
+use crate::core::data_type::DataType;
 use crate::core::error::{IsarError, Result};
 use crate::core::insert::IsarInsert;
+use crate::core::instance::IsarInstance;
+use itertools::Itertools;
 use ouroboros::self_referencing;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
 use std::cell::Cell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[self_referencing]
 struct TxnWithStatement {
@@ -21,11 +30,12 @@ impl TxnWithStatement {
         txn: SQLiteTxn,
         collection: &SQLiteCollection,
         count: u32,
-    ) -> Result<(u32, TxnWithStatement)> {
+    ) -> std::result::Result<(u32, TxnWithStatement), (IsarError, SQLiteTxn)> {
         let (batch_size, sql) = insert_sql(&collection.name, &collection.properties, count);
-        let txn_stmt = Self::try_new(txn, |txn| {
+        let txn_stmt = Self::try_new_or_recover(txn, |txn| {
             Ok(Cell::new(txn.get_sqlite(true)?.prepare(&sql)?))
-        })?;
+        })
+        .map_err(|(e, heads)| (e, heads.txn))?;
         Ok((batch_size, txn_stmt))
     }
 
@@ -41,13 +51,29 @@ impl TxnWithStatement {
         Ok(batch_size)
     }
 
-    fn finish(mut self) -> Result<SQLiteTxn> {
-        self.with_mut(|s| s.txn.guard(|| s.statement.get_mut().step()))?;
-        Ok(self.into_heads().txn)
+    /// Writes the rows that are bound so far and keeps the bindings.
+    fn flush(&mut self) -> Result<()> {
+        self.with_mut(|s| {
+            s.txn.guard(|| {
+                let statement = s.statement.get_mut();
+                statement.step()?;
+                statement.reset()
+            })
+        })
+    }
+
+    fn finish(mut self) -> std::result::Result<SQLiteTxn, (IsarError, SQLiteTxn)> {
+        let result = self.with_mut(|s| s.txn.guard(|| s.statement.get_mut().step()));
+        let txn = self.into_heads().txn;
+        match result {
+            Ok(_) => Ok(txn),
+            Err(e) => Err((e, txn)),
+        }
     }
 }
 
 pub struct SQLiteInsert<'a> {
+    instance: &'a SQLiteInstance,
     pub(crate) collection: &'a SQLiteCollection,
     pub(crate) all_collections: &'a Vec<SQLiteCollection>,
 
@@ -60,17 +86,35 @@ pub struct SQLiteInsert<'a> {
     // Track inserted IDs for detailed change detection
     inserted_ids: Vec<i64>,
     before_states: HashMap<i64, serde_json::Value>,
+
+    // String key of the current object and the keys saved by this insert
+    // which might not have been written yet
+    pub(crate) key: Option<String>,
+    key_ids: HashMap<String, i64>,
+    id_keys: HashMap<i64, String>,
+
+    // Ids of the objects in the current batch which is not written yet
+    pending_ids: HashSet<i64>,
+
+    // Version of the current object that has to match the stored version
+    pub(crate) version: Option<i64>,
+    keep_versions: bool,
+
+    // Properties of the current object that have been written
+    pub(crate) written: Vec<bool>,
 }
 
 impl<'a> SQLiteInsert<'a> {
     pub(crate) fn new(
+        instance: &'a SQLiteInstance,
         txn: SQLiteTxn,
         collection: &'a SQLiteCollection,
         all_collections: &'a Vec<SQLiteCollection>,
         count: u32,
-    ) -> Result<Self> {
+    ) -> std::result::Result<Self, (IsarError, SQLiteTxn)> {
         let (batch_size, txn_stmt) = TxnWithStatement::open(txn, collection, count)?;
         let insert = Self {
+            instance,
             collection,
             all_collections,
             txn_stmt,
@@ -79,10 +123,172 @@ impl<'a> SQLiteInsert<'a> {
             batch_remaining: batch_size,
             inserted_ids: Vec::new(),
             before_states: HashMap::new(),
+            key: None,
+            key_ids: HashMap::new(),
+            id_keys: HashMap::new(),
+            pending_ids: HashSet::new(),
+            version: None,
+            keep_versions: false,
+            written: vec![false; collection.properties.len() + 1],
         };
         Ok(insert)
     }
 
+    fn save_object(&mut self, id: i64) -> Result<()> {
+        if let Some(version_property) = self
+            .collection
+            .version_property
+            .filter(|_| !self.keep_versions)
+        {
+            if self.pending_ids.contains(&id) {
+                self.flush_pending()?;
+            }
+            let stored = self.stored_version(id, version_property)?;
+            if self.version.take().is_some_and(|version| version != stored) {
+                return Err(IsarError::VersionConflict {});
+            }
+            let col = self.property_index(version_property as u32);
+            self.with_stmt(|stmt| stmt.bind_long(col, stored + 1))?;
+        }
+        self.collection.update_auto_increment(id);
+
+        let id_property = (self.batch_size - self.batch_remaining)
+            * (self.collection.properties.len() as u32 + 1);
+        self.with_stmt(|stmt| stmt.bind_long(id_property, id))?;
+        self.pending_ids.insert(id);
+        // objects written as-is are not local writes
+        if self.collection.field_timestamps && !self.keep_versions {
+            let sqlite = self.txn_stmt.borrow_txn().get_sqlite(true)?;
+            let properties = self.collection.properties.len() as u16;
+            self.collection.stamp_fields(sqlite, id, 1..=properties)?;
+        }
+
+        self.batch_remaining -= 1;
+        self.written.fill(false);
+
+        // Store ID for detailed change detection
+        if self.collection.watchers.has_detailed_watchers() {
+            self.inserted_ids.push(id);
+
+            // Fetch "before" state for upsert detection
+            let select_sql = format!(
+                "SELECT * FROM {} WHERE {} = ?",
+                self.collection.name,
+                super::sqlite_collection::SQLiteProperty::ID_NAME
+            );
+            if let Ok(sqlite) = self.txn_stmt.borrow_txn().get_sqlite(false) {
+                if let Ok(mut stmt) = sqlite.prepare(&select_sql) {
+                    if stmt.bind_long(0, id).is_ok() && stmt.step().unwrap_or(false) {
+                        use super::sqlite_reader::SQLiteReader;
+                        let reader = SQLiteReader::new(
+                            std::borrow::Cow::Borrowed(&stmt),
+                            self.collection,
+                            self.all_collections,
+                        );
+                        self.before_states.insert(id, reader.to_json());
+                    }
+                }
+            }
+        }
+
+        if self.batch_remaining == 0 && self.remaining > 0 {
+            let batch_size = self.txn_stmt.next(self.collection, self.remaining)?;
+            self.pending_ids.clear();
+            self.remaining -= batch_size;
+            self.batch_size = batch_size;
+            self.batch_remaining = batch_size;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the id that the current object is saved under and remembers its
+    /// key.
+    fn resolve_key(&mut self, id: Option<i64>) -> Result<i64> {
+        let key = self.key.take().ok_or_else(|| IsarError::KeyViolation {
+            message: "Missing key.".to_string(),
+        })?;
+        let key_id = if let Some(key_id) = self.key_ids.get(&key) {
+            Some(*key_id)
+        } else {
+            let sqlite = self.txn_stmt.borrow_txn().get_sqlite(false)?;
+            // Objects saved by this insert might have changed their key.
+            self.collection
+                .get_id_by_key(sqlite, &key)?
+                .filter(|key_id| !self.id_keys.contains_key(key_id))
+        };
+
+        let id = match (id, key_id) {
+            (Some(id), Some(key_id)) if id != key_id => {
+                return Err(IsarError::KeyViolation {
+                    message: format!("Key '{}' already belongs to object {}.", key, key_id),
+                });
+            }
+            (Some(id), _) => id,
+            (None, Some(key_id)) => key_id,
+            (None, None) => self.collection.auto_increment(),
+        };
+        if let Some(old_key) = self.id_keys.insert(id, key.clone()) {
+            self.key_ids.remove(&old_key);
+        }
+        self.key_ids.insert(key, id);
+        Ok(id)
+    }
+
+    /// Writes the objects of the current batch that were saved so far. Their
+    /// ids are unbound afterwards so they are not written again.
+    fn flush_pending(&mut self) -> Result<()> {
+        self.txn_stmt.flush()?;
+        let width = self.collection.properties.len() as u32 + 1;
+        for row in 0..self.batch_size - self.batch_remaining {
+            self.with_stmt(|stmt| stmt.bind_null(row * width))?;
+        }
+        self.pending_ids.clear();
+        Ok(())
+    }
+
+    /// Returns the stored version of the object with the given id.
+    fn stored_version(&self, id: i64, version_property: u16) -> Result<i64> {
+        let sqlite = self.txn_stmt.borrow_txn().get_sqlite(false)?;
+        let mut stmt = sqlite.prepare_cached(&format!(
+            "SELECT IFNULL({}, 0) FROM {} WHERE {} = ?",
+            self.collection.get_property_name(version_property),
+            self.collection.name,
+            SQLiteProperty::ID_NAME
+        ))?;
+        stmt.bind_long(0, id)?;
+        if stmt.step()? {
+            Ok(stmt.get_long(0))
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Reads the properties of the stored object with the given id.
+    fn read_stored(
+        &self,
+        id: i64,
+        properties: &[(u32, DataType)],
+    ) -> Result<Option<Map<String, Value>>> {
+        let sqlite = self.txn_stmt.borrow_txn().get_sqlite(false)?;
+        let mut stmt = sqlite.prepare_cached(&format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            select_properties_sql(self.collection),
+            self.collection.name,
+            SQLiteProperty::ID_NAME
+        ))?;
+        stmt.bind_long(0, id)?;
+        if !stmt.step()? {
+            return Ok(None);
+        }
+        let reader = SQLiteReader::new(Cow::Borrowed(&stmt), self.collection, self.all_collections);
+        let mut writer = SQLiteObjectWriter::new(self.collection, self.all_collections, 0);
+        for (index, data_type) in properties {
+            copy_value(&reader, *index, &mut writer, *index, *data_type)?;
+        }
+        Ok(Some(writer.map))
+    }
+
     #[inline]
     pub(crate) fn with_stmt<T>(&mut self, callback: impl FnOnce(&mut SQLiteStatement) -> T) -> T {
         self.txn_stmt
@@ -95,54 +301,60 @@ impl<'a> IsarInsert<'a> for SQLiteInsert<'a> {
 
     fn save(&mut self, id: i64) -> Result<()> {
         if self.batch_remaining > 0 {
-            self.collection.update_auto_increment(id);
-
-            let id_property = (self.batch_size - self.batch_remaining)
-                * (self.collection.properties.len() as u32 + 1);
-            self.with_stmt(|stmt| stmt.bind_long(id_property, id))?;
-
-            self.batch_remaining -= 1;
-
-            // Store ID for detailed change detection
-            if self.collection.watchers.has_detailed_watchers() {
-                self.inserted_ids.push(id);
+            let id = if self.collection.key_property.is_some() {
+                self.resolve_key(Some(id))?
+            } else {
+                id
+            };
+            self.save_object(id)
+        } else {
+            Err(IsarError::UnsupportedOperation {})
+        }
+    }
 
-                // Fetch "before" state for upsert detection
-                let select_sql = format!(
-                    "SELECT * FROM {} WHERE {} = ?",
-                    self.collection.name,
-                    super::sqlite_collection::SQLiteProperty::ID_NAME
-                );
-                if let Ok(sqlite) = self.txn_stmt.borrow_txn().get_sqlite(false) {
-                    if let Ok(mut stmt) = sqlite.prepare(&select_sql) {
-                        if stmt.bind_long(0, id).is_ok() && stmt.step().unwrap_or(false) {
-                            use super::sqlite_reader::SQLiteReader;
-                            let reader = SQLiteReader::new(
-                                std::borrow::Cow::Borrowed(&stmt),
-                                self.collection,
-                                self.all_collections,
-                            );
-                            self.before_states.insert(id, reader.to_json());
-                        }
-                    }
-                }
+    fn save_merge(&mut self, id: i64) -> Result<()> {
+        let unwritten = self
+            .collection
+            .properties
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i as u32 + 1, p.data_type))
+            .filter(|(index, _)| !self.written[*index as usize])
+            .collect_vec();
+        // objects saved by this insert have to be written before they are read
+        if self.pending_ids.contains(&id) {
+            self.flush_pending()?;
+        }
+        if let Some(stored) = self.read_stored(id, &unwritten)? {
+            let reader = SQLiteObjectReader::new(&stored, self.collection, self.all_collections);
+            for (index, data_type) in unwritten {
+                copy_value(&reader, index, self, index, data_type)?;
             }
+        }
+        self.save(id)
+    }
 
-            if self.batch_remaining == 0 && self.remaining > 0 {
-                let batch_size = self.txn_stmt.next(self.collection, self.remaining)?;
-                self.remaining -= batch_size;
-                self.batch_size = batch_size;
-                self.batch_remaining = batch_size;
-            }
+    fn keep_versions(&mut self) {
+        self.keep_versions = true;
+    }
 
-            Ok(())
+    fn save_by_key(&mut self) -> Result<i64> {
+        if self.batch_remaining > 0 && self.collection.key_property.is_some() {
+            let id = self.resolve_key(None)?;
+            self.save_object(id)?;
+            Ok(id)
         } else {
             Err(IsarError::UnsupportedOperation {})
         }
     }
 
     fn finish(self) -> Result<Self::Txn> {
-        let txn = self.txn_stmt.finish()?;
+        // a failed insert cannot be continued so the transaction is aborted
+        let instance = self.instance;
+        let txn = self.txn_stmt.finish().map_err(|(e, txn)| {
+            instance.abort_txn(txn);
+            e
+        })?;
 
         // Generate detailed changes for inserted objects
         if self.collection.watchers.has_detailed_watchers() && !self.inserted_ids.is_empty() {
@@ -189,4 +401,273 @@ impl<'a> IsarInsert<'a> for SQLiteInsert<'a> {
 
         Ok(txn)
     }
+
+    fn abort(self) -> Self::Txn {
+        self.txn_stmt.into_heads().txn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cursor::IsarCursor;
+    use crate::core::query_builder::IsarQueryBuilder;
+    use crate::core::reader::IsarReader;
+    use crate::core::schema::{IsarSchema, PropertySchema};
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::core::update::UpdateOp;
+    use crate::core::value::IsarValue;
+    use crate::core::writer::IsarWriter;
+
+    #[test]
+    fn merges_written_properties_into_stored_objects() {
+        let item = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("count", DataType::Long, None),
+                PropertySchema::new("tags", DataType::StringList, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+            ],
+            vec![],
+            false,
+        );
+        let address = IsarSchema::new(
+            "Address",
+            None,
+            vec![PropertySchema::new("street", DataType::String, None)],
+            vec![],
+            true,
+        );
+        let instance = open_instance::<SQLiteInstance>(
+            "merges_written_properties_into_stored_objects",
+            vec![item, address],
+        );
+        insert_objects(&instance, 0, &[1], |insert, _| {
+            insert.write_string(1, "a");
+            insert.write_long(2, 5);
+            let mut tags = insert.begin_list(3, 1).unwrap();
+            tags.write_string(0, "x");
+            insert.end_list(tags);
+            let mut address = insert.begin_object(4).unwrap();
+            address.write_string(1, "Main");
+            insert.end_object(address);
+        });
+
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 2)
+            .unwrap();
+        insert.write_long(2, 7);
+        insert.save_merge(1).unwrap();
+        insert.write_string(1, "b");
+        insert.save_merge(2).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let txn = instance.begin_txn(false).unwrap();
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(1).unwrap();
+            assert_eq!(reader.read_string(1), Some("a"));
+            assert_eq!(reader.read_long(2), 7);
+            let (tags, length) = reader.read_list(3).unwrap();
+            assert_eq!(length, 1);
+            assert_eq!(tags.read_string(0), Some("x"));
+            let address = reader.read_object(4).unwrap();
+            assert_eq!(address.read_string(1), Some("Main"));
+        }
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(2).unwrap();
+            assert_eq!(reader.read_string(1), Some("b"));
+            assert!(reader.is_null(2));
+            assert!(reader.is_null(3));
+            assert!(reader.is_null(4));
+        }
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn merges_objects_saved_in_the_same_batch() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("count", DataType::Long, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance =
+            open_instance::<SQLiteInstance>("merges_objects_saved_in_the_same_batch", vec![schema]);
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 3)
+            .unwrap();
+        insert.write_string(1, "a");
+        insert.save_merge(1).unwrap();
+        insert.write_long(2, 7);
+        insert.save_merge(1).unwrap();
+        insert.write_string(1, "b");
+        insert.save_merge(2).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let txn = instance.begin_txn(false).unwrap();
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(1).unwrap();
+            assert_eq!(reader.read_string(1), Some("a"));
+            assert_eq!(reader.read_long(2), 7);
+        }
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(2).unwrap();
+            assert_eq!(reader.read_string(1), Some("b"));
+            assert!(reader.is_null(2));
+        }
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn rejects_writes_with_outdated_versions() {
+        let schema = IsarSchema::new(
+            "Draft",
+            Some("id"),
+            vec![
+                PropertySchema::new("title", DataType::String, None),
+                PropertySchema::new("version", DataType::Long, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_version("version");
+        let instance =
+            open_instance::<SQLiteInstance>("rejects_writes_with_outdated_versions", vec![schema]);
+        let put = |title: &str, version: Option<i64>| {
+            let mut insert = instance
+                .insert(instance.begin_txn(true).unwrap(), 0, 1)
+                .unwrap();
+            insert.write_string(1, title);
+            insert.write_long(2, version.unwrap_or(i64::MIN));
+            let result = insert.save(1);
+            let txn = insert.finish().unwrap();
+            if result.is_ok() {
+                instance.commit_txn(txn).unwrap();
+            } else {
+                instance.abort_txn(txn);
+            }
+            result
+        };
+        let version = || {
+            let txn = instance.begin_txn(false).unwrap();
+            let version = instance
+                .cursor(&txn, 0)
+                .unwrap()
+                .next(1)
+                .map(|reader| reader.read_long(2));
+            instance.abort_txn(txn);
+            version
+        };
+
+        assert_eq!(put("a", None), Ok(()));
+        assert_eq!(version(), Some(1));
+        assert_eq!(put("b", Some(1)), Ok(()));
+        assert_eq!(version(), Some(2));
+        assert_eq!(put("c", Some(1)), Err(IsarError::VersionConflict {}));
+        assert_eq!(version(), Some(2));
+
+        let title = |value: &str| UpdateOp::Set(Some(IsarValue::String(value.to_string())));
+        let expect = |value| UpdateOp::Set(Some(IsarValue::Integer(value)));
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(1, title("d")), (2, expect(2))]),
+            Ok(true)
+        );
+        assert_eq!(instance.update(&txn, 0, 1, &[(1, title("e"))]), Ok(true));
+        let query = instance.query(0).unwrap().build().unwrap();
+        assert_eq!(
+            instance.query_update(&txn, &query, None, None, &[(1, title("f"))]),
+            Ok(1)
+        );
+        instance.commit_txn(txn).unwrap();
+        assert_eq!(version(), Some(5));
+
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(1, title("g")), (2, expect(4))]),
+            Err(IsarError::VersionConflict {})
+        );
+        instance.abort_txn(txn);
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(
+            instance.update(
+                &txn,
+                0,
+                1,
+                &[(2, UpdateOp::Increment(IsarValue::Integer(1)))]
+            ),
+            Err(IsarError::IllegalArgument {})
+        );
+        instance.abort_txn(txn);
+        assert_eq!(version(), Some(5));
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn rolls_back_partial_version_updates() {
+        let schema = IsarSchema::new(
+            "Note",
+            Some("id"),
+            vec![
+                PropertySchema::new("title", DataType::String, None),
+                PropertySchema::new("version", DataType::Long, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_version("version");
+        let instance =
+            open_instance::<SQLiteInstance>("rolls_back_partial_version_updates", vec![schema]);
+        let titles = ["a", "b"];
+        insert_objects(&instance, 0, &[1, 2], |insert, id| {
+            insert.write_string(1, titles[id as usize - 1]);
+            insert.write_null(2);
+        });
+        let txn = instance.begin_txn(true).unwrap();
+        instance.update(&txn, 0, 2, &[]).unwrap();
+        instance.commit_txn(txn).unwrap();
+
+        // only the first object has the expected version
+        let txn = instance.begin_txn(true).unwrap();
+        let query = instance.query(0).unwrap().build().unwrap();
+        let updates = [
+            (1, UpdateOp::Set(Some(IsarValue::String("c".to_string())))),
+            (2, UpdateOp::Set(Some(IsarValue::Integer(1)))),
+        ];
+        assert_eq!(
+            instance.query_update(&txn, &query, None, None, &updates),
+            Err(IsarError::VersionConflict {})
+        );
+        // the failed update closed the transaction so it cannot be committed
+        assert_eq!(
+            instance.commit_txn(txn),
+            Err(IsarError::TransactionClosed {})
+        );
+
+        let txn = instance.begin_txn(false).unwrap();
+        let mut cursor = instance.cursor(&txn, 0).unwrap();
+        let first = cursor
+            .next(1)
+            .map(|r| (r.read_string(1).map(String::from), r.read_long(2)));
+        assert_eq!(first, Some((Some("a".to_string()), 1)));
+        let second = cursor
+            .next(2)
+            .map(|r| (r.read_string(1).map(String::from), r.read_long(2)));
+        assert_eq!(second, Some((Some("b".to_string()), 2)));
+        drop(cursor);
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
 }
@@ -3,18 +3,25 @@ use super::sqlite3::SQLite3;
 use super::sqlite_collection::SQLiteCollection;
 use super::sqlite_cursor::SQLiteCursor;
 use super::sqlite_insert::SQLiteInsert;
-use super::sqlite_open::{close_instance, get_instance, open_instance};
+use super::sqlite_open::{close_instance, get_instance, open_instance, read_sqlite_schemas};
 use super::sqlite_query::{SQLiteQuery, SQLiteQueryCursor};
 use super::sqlite_query_builder::SQLiteQueryBuilder;
 use super::sqlite_reader::SQLiteReader;
 use super::sqlite_txn::SQLiteTxn;
 use super::sqlite_verify::verify_sqlite;
+use crate::core::continuation::QueryContinuation;
 use crate::core::error::{IsarError, Result};
+use crate::core::expiry::PURGE_BATCH_SIZE;
 use crate::core::filter::{ConditionType, Filter, FilterCondition};
 use crate::core::instance::{Aggregation, CompactCondition, IsarInstance};
+use crate::core::merge::{resolve_updates, MergeResult, ObjectChange};
 use crate::core::query_builder::IsarQueryBuilder;
+use crate::core::query_plan::QueryPlan;
 use crate::core::schema::IsarSchema;
+use crate::core::tombstone::Tombstone;
+use crate::core::update::UpdateOp;
 use crate::core::value::IsarValue;
+use crate::core::vector::VectorMetric;
 use crate::core::watcher::{DetailedWatcherCallback, WatchHandle, WatcherCallback};
 use itertools::Itertools;
 use parking_lot::lock_api::RawMutex;
@@ -30,6 +37,7 @@ pub(crate) struct SQLiteInstanceInfo {
     pub(crate) path: String,
     pub(crate) encryption_key: Option<String>,
 
+    schemas: Vec<IsarSchema>,
     collections: Vec<SQLiteCollection>,
     write_mutex: parking_lot::RawMutex,
 }
@@ -41,6 +49,7 @@ impl SQLiteInstanceInfo {
         dir: &str,
         path: &str,
         encryption_key: Option<&str>,
+        schemas: Vec<IsarSchema>,
         collections: Vec<SQLiteCollection>,
     ) -> Self {
         Self {
@@ -49,6 +58,7 @@ impl SQLiteInstanceInfo {
             dir: dir.to_string(),
             path: path.to_string(),
             encryption_key: encryption_key.map(|s| s.to_string()),
+            schemas,
             collections,
             write_mutex: RawMutex::INIT,
         }
@@ -62,6 +72,17 @@ pub struct SQLiteInstance {
 }
 
 impl SQLiteInstance {
+    /// Reads the schemas of an existing database so it can be opened without
+    /// knowing its schema. Embedded schemas of databases written before they
+    /// were stored are inferred from the stored objects.
+    pub fn read_schemas(
+        name: &str,
+        dir: &str,
+        encryption_key: Option<&str>,
+    ) -> Result<Vec<IsarSchema>> {
+        read_sqlite_schemas(name, dir, encryption_key)
+    }
+
     fn get_collection(&self, collection_index: u16) -> Result<&SQLiteCollection> {
         if let Some(collection) = self.info.collections.get(collection_index as usize) {
             Ok(collection)
@@ -69,6 +90,51 @@ impl SQLiteInstance {
             Err(IsarError::IllegalArgument {})
         }
     }
+
+    fn id_query(&self, collection_index: u16, id: i64) -> Result<SQLiteQuery> {
+        let mut qb = self.query(collection_index)?;
+        qb.set_filter(Filter::Condition(FilterCondition::new(
+            0,
+            ConditionType::Equal,
+            vec![Some(IsarValue::Integer(id))],
+            false,
+        )));
+        qb.build()
+    }
+
+    /// Updates the objects matched by the query. Local writes stamp the
+    /// timestamps of the updated fields, merged changes bring their own.
+    fn update_matching(
+        &self,
+        txn: &SQLiteTxn,
+        query: &SQLiteQuery,
+        offset: Option<u32>,
+        limit: Option<u32>,
+        updates: &[(u16, UpdateOp)],
+        stamp: bool,
+    ) -> Result<u32> {
+        query.verify_bound()?;
+        let collection = self.get_collection(query.collection_index)?;
+        txn.monitor_changes(
+            &collection.watchers,
+            &self.info.collections[query.collection_index as usize].name,
+        );
+        let result = txn.guard(|| {
+            let ids = if stamp && collection.field_timestamps {
+                query.ids(txn, &self.info.collections, offset, limit)?
+            } else {
+                vec![]
+            };
+            let count = query.update(txn, &self.info.collections, offset, limit, updates)?;
+            for id in ids {
+                let properties = updates.iter().map(|(index, _)| *index);
+                collection.stamp_fields(txn.get_sqlite(true)?, id, properties)?;
+            }
+            Ok(count)
+        })?;
+        txn.stop_monitor_changes();
+        Ok(result)
+    }
 }
 
 impl IsarInstance for SQLiteInstance {
@@ -106,6 +172,14 @@ impl IsarInstance for SQLiteInstance {
         self.info.collections.iter().map(|c| c.name.as_str())
     }
 
+    fn get_schemas(&self) -> &[IsarSchema] {
+        &self.info.schemas
+    }
+
+    fn is_embedded(&self, collection_index: u16) -> bool {
+        self.get_collection(collection_index).is_ok_and(|c| c.is_embedded())
+    }
+
     fn get_instance(instance_id: u32) -> Option<Self::Instance> {
         let (info, sqlite) = get_instance(instance_id)?;
         Some(Self {
@@ -158,8 +232,16 @@ impl IsarInstance for SQLiteInstance {
     }
 
     fn commit_txn(&self, txn: SQLiteTxn) -> Result<()> {
-        self.txn_active.replace(false);
         let write = txn.is_write();
+        if write {
+            for collection_index in 0..self.info.collections.len() as u16 {
+                if let Err(e) = self.purge_expired(&txn, collection_index, Some(PURGE_BATCH_SIZE)) {
+                    self.abort_txn(txn);
+                    return Err(e);
+                }
+            }
+        }
+        self.txn_active.replace(false);
         let result = txn.commit();
         if write {
             unsafe { self.info.write_mutex.unlock() };
@@ -184,6 +266,20 @@ impl IsarInstance for SQLiteInstance {
         }
     }
 
+    fn next_auto_increment(&self, collection_index: u16) -> i64 {
+        if let Ok(collection) = self.get_collection(collection_index) {
+            collection.next_auto_increment()
+        } else {
+            0
+        }
+    }
+
+    fn update_auto_increment(&self, collection_index: u16, id: i64) {
+        if let Ok(collection) = self.get_collection(collection_index) {
+            collection.update_auto_increment(id);
+        }
+    }
+
     fn cursor<'a>(&'a self, txn: &'a Self::Txn, collection_index: u16) -> Result<Self::Cursor<'a>> {
         let collection = self.get_collection(collection_index)?;
         SQLiteCursor::new(txn, collection, &self.info.collections)
@@ -195,13 +291,23 @@ impl IsarInstance for SQLiteInstance {
         collection_index: u16,
         count: u32,
     ) -> Result<Self::Insert<'a>> {
-        let collection = self.get_collection(collection_index)?;
+        // the transaction is consumed so it has to be aborted on failure
+        let collection = match self.get_collection(collection_index) {
+            Ok(collection) => collection,
+            Err(e) => {
+                self.abort_txn(txn);
+                return Err(e);
+            }
+        };
         txn.monitor_changes(
             &collection.watchers,
             &self.info.collections[collection_index as usize].name,
         );
 
-        SQLiteInsert::new(txn, collection, &self.info.collections, count)
+        SQLiteInsert::new(self, txn, collection, &self.info.collections, count).map_err(|(e, txn)| {
+            self.abort_txn(txn);
+            e
+        })
     }
 
     fn update(
@@ -209,35 +315,31 @@ impl IsarInstance for SQLiteInstance {
         txn: &Self::Txn,
         collection_index: u16,
         id: i64,
-        updates: &[(u16, Option<IsarValue>)],
+        updates: &[(u16, UpdateOp)],
     ) -> Result<bool> {
-        let mut qb = self.query(collection_index)?;
-        qb.set_filter(Filter::Condition(FilterCondition::new(
-            0,
-            ConditionType::Equal,
-            vec![Some(IsarValue::Integer(id))],
-            false,
-        )));
-        let q = qb.build();
-        let count = self.query_update(txn, &q, None, None, updates)?;
+        let query = self.id_query(collection_index, id)?;
+        let count = self.query_update(txn, &query, None, None, updates)?;
         Ok(count > 0)
     }
 
     fn delete<'a>(&'a self, txn: &'a Self::Txn, collection_index: u16, id: i64) -> Result<bool> {
-        let mut qb = self.query(collection_index)?;
-        qb.set_filter(Filter::Condition(FilterCondition::new(
-            0,
-            ConditionType::Equal,
-            vec![Some(IsarValue::Integer(id))],
-            false,
-        )));
-        let q = qb.build();
-        let count = self.query_delete(txn, &q, None, None)?;
+        let query = self.id_query(collection_index, id)?;
+        let count = self.query_delete(txn, &query, None, None)?;
         Ok(count > 0)
     }
 
+    fn get_id_by_key(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        key: &str,
+    ) -> Result<Option<i64>> {
+        let collection = self.get_collection(collection_index)?;
+        collection.get_id_by_key(txn.get_sqlite(false)?, key)
+    }
+
     fn count(&self, txn: &Self::Txn, collection_index: u16) -> Result<u32> {
-        let q = self.query(collection_index)?.build();
+        let q = self.query(collection_index)?.build()?;
         let result = self.query_aggregate(txn, &q, Aggregation::Count, None)?;
         if let Some(IsarValue::Integer(count)) = result {
             Ok(count as u32)
@@ -247,8 +349,9 @@ impl IsarInstance for SQLiteInstance {
     }
 
     fn clear(&self, txn: &Self::Txn, collection_index: u16) -> Result<()> {
-        let q = self.query(collection_index)?.build();
+        let q = self.query(collection_index)?.build()?;
         self.query_delete(txn, &q, None, None)?;
+        self.purge_expired(txn, collection_index, None)?;
         Ok(())
     }
 
@@ -295,7 +398,28 @@ impl IsarInstance for SQLiteInstance {
         offset: Option<u32>,
         limit: Option<u32>,
     ) -> Result<Self::QueryCursor<'a>> {
-        query.cursor(txn, &self.info.collections, offset, limit)
+        query.cursor(txn, &self.info.collections, &[], offset, limit)
+    }
+
+    fn query_cursor_with_params<'a>(
+        &'a self,
+        txn: &'a Self::Txn,
+        query: &'a Self::Query,
+        params: &[Option<IsarValue>],
+        offset: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Self::QueryCursor<'a>> {
+        query.cursor(txn, &self.info.collections, params, offset, limit)
+    }
+
+    fn query_cursor_after<'a>(
+        &'a self,
+        txn: &'a Self::Txn,
+        query: &'a Self::Query,
+        after: Option<&QueryContinuation>,
+        limit: Option<u32>,
+    ) -> Result<Self::QueryCursor<'a>> {
+        query.cursor_after(txn, &self.info.collections, after, limit)
     }
 
     fn query_aggregate(
@@ -305,26 +429,60 @@ impl IsarInstance for SQLiteInstance {
         aggregation: Aggregation,
         property_index: Option<u16>,
     ) -> Result<Option<IsarValue>> {
+        query.verify_bound()?;
         query.aggregate(txn, &self.info.collections, aggregation, property_index)
     }
 
+    fn query_facets(
+        &self,
+        txn: &Self::Txn,
+        query: &Self::Query,
+        property_index: u16,
+        sort_by_count: bool,
+        limit: Option<u32>,
+    ) -> Result<Vec<(Option<IsarValue>, u32)>> {
+        query.facets(
+            txn,
+            &self.info.collections,
+            property_index,
+            sort_by_count,
+            limit,
+        )
+    }
+
+    fn query_nearest(
+        &self,
+        txn: &Self::Txn,
+        query: &Self::Query,
+        property_index: u16,
+        vector: &[f64],
+        metric: VectorMetric,
+        k: u32,
+    ) -> Result<Vec<(i64, f64)>> {
+        query.nearest(
+            txn,
+            &self.info.collections,
+            property_index,
+            vector,
+            metric,
+            k,
+        )
+    }
+
+    fn explain(&self, txn: &Self::Txn, query: &Self::Query) -> Result<QueryPlan> {
+        let plan = query.explain(txn, &self.info.collections)?;
+        Ok(QueryPlan::SQLite(plan))
+    }
+
     fn query_update(
         &self,
         txn: &Self::Txn,
         query: &Self::Query,
         offset: Option<u32>,
         limit: Option<u32>,
-        updates: &[(u16, Option<IsarValue>)],
+        updates: &[(u16, UpdateOp)],
     ) -> Result<u32> {
-        let collection = self.get_collection(query.collection_index)?;
-        txn.monitor_changes(
-            &collection.watchers,
-            &self.info.collections[query.collection_index as usize].name,
-        );
-        let result =
-            txn.guard(|| query.update(txn, &self.info.collections, offset, limit, updates))?;
-        txn.stop_monitor_changes();
-        Ok(result)
+        self.update_matching(txn, query, offset, limit, updates, true)
     }
 
     fn query_delete(
@@ -334,6 +492,7 @@ impl IsarInstance for SQLiteInstance {
         offset: Option<u32>,
         limit: Option<u32>,
     ) -> Result<u32> {
+        query.verify_bound()?;
         let collection = self.get_collection(query.collection_index)?;
         txn.monitor_changes(
             &collection.watchers,
@@ -344,6 +503,117 @@ impl IsarInstance for SQLiteInstance {
         Ok(result)
     }
 
+    fn purge_expired(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        limit: Option<u32>,
+    ) -> Result<u32> {
+        let collection = self.get_collection(collection_index)?;
+        if let Some(expired_sql) = sql::expiry_sql(collection, true) {
+            let query = SQLiteQuery::new(
+                collection_index,
+                Some(expired_sql),
+                String::new(),
+                String::new(),
+                vec![],
+                vec![],
+                vec![],
+            );
+            self.query_delete(txn, &query, None, limit)
+        } else {
+            Ok(0)
+        }
+    }
+
+    fn get_tombstones(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        after_sequence: i64,
+        limit: Option<u32>,
+    ) -> Result<Vec<Tombstone>> {
+        let collection = self.get_collection(collection_index)?;
+        if !collection.tombstones {
+            return Err(IsarError::UnsupportedOperation {});
+        }
+        let sql = format!(
+            "SELECT id, deleted_at, sequence FROM {} WHERE sequence > ? ORDER BY sequence LIMIT ?",
+            sql::tombstones_table_name(&collection.name)
+        );
+        let sqlite = txn.get_sqlite(false)?;
+        let mut stmt = sqlite.prepare(&sql)?;
+        stmt.bind_long(0, after_sequence)?;
+        stmt.bind_long(1, limit.map_or(-1, |l| l as i64))?;
+        let mut tombstones = vec![];
+        while stmt.step()? {
+            tombstones.push(Tombstone {
+                id: stmt.get_long(0),
+                deleted_at: stmt.get_long(1),
+                sequence: stmt.get_long(2),
+            });
+        }
+        Ok(tombstones)
+    }
+
+    fn purge_tombstones(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        deleted_before: i64,
+    ) -> Result<u32> {
+        let collection = self.get_collection(collection_index)?;
+        if !collection.tombstones {
+            return Err(IsarError::UnsupportedOperation {});
+        }
+        let sql = format!(
+            "DELETE FROM {} WHERE deleted_at <= ?",
+            sql::tombstones_table_name(&collection.name)
+        );
+        txn.guard(|| {
+            let sqlite = txn.get_sqlite(true)?;
+            let mut stmt = sqlite.prepare(&sql)?;
+            stmt.bind_long(0, deleted_before)?;
+            stmt.step()?;
+            Ok(sqlite.count_changes() as u32)
+        })
+    }
+
+    fn merge_changes(
+        &self,
+        txn: &Self::Txn,
+        collection_index: u16,
+        changes: &[ObjectChange],
+        remote: bool,
+    ) -> Result<MergeResult> {
+        let collection = self.get_collection(collection_index)?;
+        if !collection.field_timestamps {
+            return Err(IsarError::UnsupportedOperation {});
+        }
+
+        txn.change_set.borrow_mut().set_remote(remote);
+        let mut result = MergeResult::default();
+        for change in changes {
+            let mut field_timestamps =
+                txn.guard(|| collection.get_timestamps(txn.get_sqlite(true)?, change.id))?;
+            let updates = resolve_updates(&mut field_timestamps, &change.updates);
+            if updates.is_empty() {
+                continue;
+            }
+            let query = self.id_query(collection_index, change.id)?;
+            if self.update_matching(txn, &query, None, None, &updates, false)? == 0 {
+                result.missing.push(change.id);
+                continue;
+            }
+            txn.guard(|| {
+                collection.put_timestamps(txn.get_sqlite(true)?, change.id, &field_timestamps)
+            })?;
+            result.merged += 1;
+        }
+        txn.change_set.borrow_mut().set_remote(false);
+        Ok(result)
+    }
+
     fn watch(&self, collection_index: u16, callback: WatcherCallback) -> Result<WatchHandle> {
         let collection = self.get_collection(collection_index)?;
         let handle = collection.watchers.watch(callback);
@@ -399,11 +669,28 @@ impl IsarInstance for SQLiteInstance {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::convert::{convert, CONVERT_BATCH_SIZE};
     use crate::core::data_type::DataType;
+    use crate::core::cursor::{IsarCursor, IsarQueryCursor};
+    use crate::core::filter::FilterGeo;
+    use crate::core::geo::GeoShape;
+    use crate::core::insert::IsarInsert;
+    use crate::core::query_builder::Sort;
+    use crate::core::query_description::QueryDescription;
+    use crate::core::query_parser::parse_query;
+    use crate::core::reader::IsarReader;
     use crate::core::schema::{IndexSchema, PropertySchema};
+    use crate::core::update::UpdateOp;
+    use crate::core::uuid::NULL_UUID;
+    use crate::core::writer::IsarWriter;
     use crate::SQLITE_MEMORY_DIR;
+    use std::collections::BTreeMap;
 
     fn open(name: &str) -> SQLiteInstance {
+        open_with_id(1, name)
+    }
+
+    fn open_with_id(instance_id: u32, name: &str) -> SQLiteInstance {
         let schema = IsarSchema::new(
             "TestCol",
             None,
@@ -411,7 +698,7 @@ mod tests {
             vec![IndexSchema::new("value", vec!["value"], false, false)],
             false,
         );
-        SQLiteInstance::open_instance(1, name, SQLITE_MEMORY_DIR, vec![schema], 0, None, None)
+        SQLiteInstance::open_instance(instance_id, name, SQLITE_MEMORY_DIR, vec![schema], 0, None, None)
             .unwrap()
     }
 
@@ -446,4 +733,1129 @@ mod tests {
         assert!((size_without_indexes as usize) >= row_count * payload.len());
         assert!(size_with_indexes > size_without_indexes);
     }
+
+    #[test]
+    fn query_cursor_after_pages_by_sort_key_and_id() {
+        let instance = open_with_id(3, "query_cursor_after_pages_by_sort_key_and_id");
+        for value in ["NULL", "'b'", "'a'", "'b'", "'c'"] {
+            instance
+                .sqlite
+                .prepare(&format!("INSERT INTO TestCol (value) VALUES ({value})"))
+                .unwrap()
+                .step()
+                .unwrap();
+        }
+        let txn = instance.begin_txn(false).unwrap();
+
+        let read_page = |query: &SQLiteQuery, after: Option<&QueryContinuation>| {
+            let mut cursor = instance
+                .query_cursor_after(&txn, query, after, Some(2))
+                .unwrap();
+            let mut ids = vec![];
+            while let Some(reader) = cursor.next() {
+                ids.push(reader.read_id());
+            }
+            (ids, cursor.continuation())
+        };
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_sort(1, Sort::Asc, false);
+        let query = qb.build().unwrap();
+        let (ids, after) = read_page(&query, None);
+        assert_eq!(ids, vec![1, 3]);
+        let token = after.unwrap().to_token();
+        let after = QueryContinuation::from_token(&token).unwrap();
+        let (ids, after) = read_page(&query, Some(&after));
+        assert_eq!(ids, vec![2, 4]);
+        let (ids, _) = read_page(&query, after.as_ref());
+        assert_eq!(ids, vec![5]);
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_sort(1, Sort::Desc, false);
+        let query = qb.build().unwrap();
+        let (ids, after) = read_page(&query, None);
+        assert_eq!(ids, vec![5, 2]);
+        let (ids, after) = read_page(&query, after.as_ref());
+        assert_eq!(ids, vec![4, 3]);
+        let (ids, after) = read_page(&query, after.as_ref());
+        assert_eq!(ids, vec![1]);
+        let (ids, _) = read_page(&query, after.as_ref());
+        assert!(ids.is_empty());
+
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn query_facets_counts_distinct_values() {
+        let instance = open_with_id(5, "query_facets_counts_distinct_values");
+        for value in ["'b'", "'a'", "'b'", "NULL", "'b'", "'a'"] {
+            instance
+                .sqlite
+                .prepare(&format!("INSERT INTO TestCol (value) VALUES ({value})"))
+                .unwrap()
+                .step()
+                .unwrap();
+        }
+        let txn = instance.begin_txn(false).unwrap();
+        let string = |value: &str| Some(IsarValue::String(value.to_string()));
+
+        let query = instance.query(0).unwrap().build().unwrap();
+        let facets = instance.query_facets(&txn, &query, 1, false, None).unwrap();
+        assert_eq!(facets, vec![(None, 1), (string("a"), 2), (string("b"), 3)]);
+
+        let facets = instance.query_facets(&txn, &query, 1, true, Some(2)).unwrap();
+        assert_eq!(facets, vec![(string("b"), 3), (string("a"), 2)]);
+
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(Filter::Condition(FilterCondition::new(
+            0,
+            ConditionType::Greater,
+            vec![Some(IsarValue::Integer(3))],
+            true,
+        )));
+        let query = qb.build().unwrap();
+        let facets = instance.query_facets(&txn, &query, 1, true, None).unwrap();
+        assert_eq!(facets, vec![(None, 1), (string("a"), 1), (string("b"), 1)]);
+
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn query_aggregate_statistics() {
+        let instance = open_with_id(6, "query_aggregate_statistics");
+        for value in ["'a'", "'b'", "'a'", "NULL"] {
+            instance
+                .sqlite
+                .prepare(&format!("INSERT INTO TestCol (value) VALUES ({value})"))
+                .unwrap()
+                .step()
+                .unwrap();
+        }
+        let txn = instance.begin_txn(false).unwrap();
+        let query = instance.query(0).unwrap().build().unwrap();
+        let aggregate = |aggregation: Aggregation, property_index: Option<u16>| {
+            instance
+                .query_aggregate(&txn, &query, aggregation, property_index)
+                .unwrap()
+        };
+
+        assert_eq!(
+            aggregate(Aggregation::CountDistinct, Some(1)),
+            Some(IsarValue::Integer(2))
+        );
+        assert_eq!(
+            aggregate(Aggregation::Median, None),
+            Some(IsarValue::Real(2.5))
+        );
+        assert_eq!(
+            aggregate(Aggregation::Percentile(0.25.into()), None),
+            Some(IsarValue::Real(1.75))
+        );
+        assert_eq!(
+            aggregate(Aggregation::Variance, None),
+            Some(IsarValue::Real(1.25))
+        );
+        assert_eq!(
+            aggregate(Aggregation::StdDev, None),
+            Some(IsarValue::Real(1.25f64.sqrt()))
+        );
+        assert_eq!(aggregate(Aggregation::Median, Some(1)), None);
+        assert!(instance
+            .query_aggregate(&txn, &query, Aggregation::Percentile(1.5.into()), None)
+            .is_err());
+
+        instance
+            .sqlite
+            .prepare(&format!(
+                "INSERT INTO TestCol (_rowid_, value) VALUES ({}, NULL)",
+                i64::MAX
+            ))
+            .unwrap()
+            .step()
+            .unwrap();
+        assert_eq!(
+            aggregate(Aggregation::Sum, None),
+            Some(IsarValue::Real(i64::MAX as f64 + 10.0))
+        );
+
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn query_filters_by_regex() {
+        let instance = open_with_id(8, "query_filters_by_regex");
+        for value in ["'abc'", "'ABC'", "'xyz'", "NULL"] {
+            instance
+                .sqlite
+                .prepare(&format!("INSERT INTO TestCol (value) VALUES ({value})"))
+                .unwrap()
+                .step()
+                .unwrap();
+        }
+        let txn = instance.begin_txn(false).unwrap();
+        let count = |pattern: &str, case_sensitive: bool| {
+            let mut qb = instance.query(0).unwrap();
+            qb.set_filter(Filter::Condition(FilterCondition::new(
+                1,
+                ConditionType::StringRegex,
+                vec![Some(IsarValue::String(pattern.to_string()))],
+                case_sensitive,
+            )));
+            let query = qb.build()?;
+            instance.query_aggregate(&txn, &query, Aggregation::Count, None)
+        };
+
+        assert_eq!(count("^ab", true).unwrap(), Some(IsarValue::Integer(1)));
+        assert_eq!(count("^ab", false).unwrap(), Some(IsarValue::Integer(2)));
+        assert_eq!(count("^[a-z]{3}$", true).unwrap(), Some(IsarValue::Integer(2)));
+        assert!(matches!(count("(ab", true), Err(IsarError::IllegalArgument {})));
+
+        let mut stmt = instance
+            .sqlite
+            .prepare("SELECT COUNT(*) FROM TestCol WHERE value REGEXP 'y'")
+            .unwrap();
+        stmt.step().unwrap();
+        assert_eq!(stmt.get_long(0), 1);
+
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn query_filters_with_geo() {
+        let schema = IsarSchema::new(
+            "Place",
+            Some("id"),
+            vec![
+                PropertySchema::new("lat", DataType::Double, None),
+                PropertySchema::new("lng", DataType::Double, None),
+            ],
+            vec![IndexSchema::new_geo("position", "lat", "lng")],
+            false,
+        );
+        let instance = SQLiteInstance::open_instance(
+            10,
+            "query_filters_with_geo",
+            SQLITE_MEMORY_DIR,
+            vec![schema],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        // Berlin, Potsdam, Hamburg and a place without coordinates
+        for (lat, lng) in [
+            ("52.52", "13.405"),
+            ("52.39", "13.065"),
+            ("53.55", "9.99"),
+            ("NULL", "NULL"),
+        ] {
+            instance
+                .sqlite
+                .prepare(&format!(
+                    "INSERT INTO Place (lat, lng) VALUES ({lat}, {lng})"
+                ))
+                .unwrap()
+                .step()
+                .unwrap();
+        }
+        let txn = instance.begin_txn(false).unwrap();
+        instance.verify(&txn).unwrap();
+
+        let build = |shape: GeoShape| {
+            let mut qb = instance.query(0).unwrap();
+            qb.set_filter(Filter::Geo(FilterGeo::new(1, 2, shape)));
+            qb.build().unwrap()
+        };
+        let count = |shape: GeoShape| {
+            let query = build(shape);
+            instance
+                .query_aggregate(&txn, &query, Aggregation::Count, None)
+                .unwrap()
+        };
+
+        let radius = |meters: f64| GeoShape::Radius {
+            latitude: 52.52,
+            longitude: 13.405,
+            meters,
+        };
+        assert_eq!(count(radius(1_000.0)), Some(IsarValue::Integer(1)));
+        assert_eq!(count(radius(50_000.0)), Some(IsarValue::Integer(2)));
+        assert_eq!(count(radius(300_000.0)), Some(IsarValue::Integer(3)));
+
+        let bounding_box = GeoShape::BoundingBox {
+            min_latitude: 53.0,
+            min_longitude: 9.0,
+            max_latitude: 54.0,
+            max_longitude: 11.0,
+        };
+        assert_eq!(count(bounding_box), Some(IsarValue::Integer(1)));
+
+        let polygon =
+            GeoShape::Polygon(vec![(52.0, 12.5), (53.0, 12.5), (53.0, 14.0), (52.0, 14.0)]);
+        assert_eq!(count(polygon), Some(IsarValue::Integer(2)));
+
+        let QueryPlan::SQLite(plan) = instance.explain(&txn, &build(radius(1_000.0))).unwrap()
+        else {
+            panic!("expected sqlite plan");
+        };
+        assert!(plan.rows.iter().any(|r| r.detail.contains("INDEX")));
+
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn uuid_json_round_trip_and_filters() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![PropertySchema::new("uuid", DataType::Uuid, None)],
+            vec![IndexSchema::new("uuid", vec!["uuid"], false, false)],
+            false,
+        );
+        let instance = SQLiteInstance::open_instance(
+            12,
+            "uuid_json_round_trip_and_filters",
+            SQLITE_MEMORY_DIR,
+            vec![schema],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let json = r#"[
+            {"id": 1, "uuid": "550E8400-E29B-41D4-A716-446655440000"},
+            {"id": 2, "uuid": "00000000000000000000000000000001"},
+            {"id": 3, "uuid": null}
+        ]"#;
+        let txn = instance.begin_txn(true).unwrap();
+        let (txn, count) = instance
+            .import_json(txn, 0, &mut serde_json::Deserializer::from_str(json), |_| 0)
+            .unwrap();
+        assert_eq!(count, 3);
+        instance.commit_txn(txn).unwrap();
+
+        let txn = instance.begin_txn(false).unwrap();
+        let ids = |filter: Filter| {
+            let mut qb = instance.query(0).unwrap();
+            qb.set_filter(filter);
+            let query = qb.build().unwrap();
+            let mut cursor = instance.query_cursor(&txn, &query, None, None).unwrap();
+            let mut ids = vec![];
+            while let Some(reader) = cursor.next() {
+                ids.push(reader.read_id());
+            }
+            ids
+        };
+        let uuid = |value: &str| Some(IsarValue::String(value.to_string()));
+
+        assert_eq!(
+            ids(Filter::Condition(FilterCondition::new(
+                1,
+                ConditionType::Equal,
+                vec![uuid("550e8400e29b41d4a716446655440000")],
+                true,
+            ))),
+            vec![1]
+        );
+        assert_eq!(
+            ids(Filter::Condition(FilterCondition::new(
+                1,
+                ConditionType::Between,
+                vec![
+                    uuid("00000000-0000-0000-0000-000000000000"),
+                    uuid("10000000-0000-0000-0000-000000000000"),
+                ],
+                true,
+            ))),
+            vec![2]
+        );
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_sort(1, Sort::Asc, true);
+        let query = qb.build().unwrap();
+        {
+            let mut cursor = instance.query_cursor(&txn, &query, None, None).unwrap();
+            assert_eq!(cursor.next().unwrap().read_uuid(1), None);
+            let reader = cursor.next().unwrap();
+            assert_eq!(
+                reader.read_uuid(1),
+                Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1])
+            );
+        }
+        let mut cursor = instance.query_cursor(&txn, &query, None, None).unwrap();
+        let mut json = vec![];
+        cursor
+            .serialize_values(1, &mut serde_json::Serializer::new(&mut json))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            r#"[null,"00000000-0000-0000-0000-000000000001","550e8400-e29b-41d4-a716-446655440000"]"#
+        );
+        drop(cursor);
+
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn updates_uuids_in_canonical_form() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![PropertySchema::new("uuid", DataType::Uuid, None)],
+            vec![],
+            false,
+        );
+        let instance = SQLiteInstance::open_instance(
+            31,
+            "updates_uuids_in_canonical_form",
+            SQLITE_MEMORY_DIR,
+            vec![schema],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 2)
+            .unwrap();
+        insert.write_uuid(1, &[1; 16]);
+        insert.save(1).unwrap();
+        insert.write_uuid(1, &NULL_UUID);
+        insert.save(2).unwrap();
+        let txn = insert.finish().unwrap();
+        let stored = |id: i64| {
+            let mut stmt = txn
+                .get_sqlite(false)
+                .unwrap()
+                .prepare(&format!("SELECT uuid FROM Doc WHERE _rowid_ = {}", id))
+                .unwrap();
+            stmt.step().unwrap();
+            (!stmt.is_null(0)).then(|| stmt.get_text(0).to_string())
+        };
+        assert_eq!(stored(2), None);
+
+        let set = |value: &str| UpdateOp::Set(Some(IsarValue::String(value.to_string())));
+        let update = (1, set("550E8400E29B41D4A716446655440000"));
+        assert_eq!(instance.update(&txn, 0, 2, &[update]), Ok(true));
+        assert_eq!(
+            stored(2).as_deref(),
+            Some("550e8400-e29b-41d4-a716-446655440000")
+        );
+        let update = (1, set("00000000-0000-0000-0000-000000000000"));
+        assert_eq!(instance.update(&txn, 0, 1, &[update]), Ok(true));
+        assert_eq!(stored(1), None);
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            assert!(cursor.next(1).unwrap().is_null(1));
+        }
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(1, set("invalid"))]),
+            Err(IsarError::IllegalArgument {})
+        );
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn saturates_increments_and_rejects_invalid_stored_lists() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![
+                PropertySchema::new("count", DataType::Byte, None),
+                PropertySchema::new("number", DataType::Int, None),
+                PropertySchema::new("total", DataType::Long, None),
+                PropertySchema::new("tags", DataType::LongList, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance = SQLiteInstance::open_instance(
+            32,
+            "saturates_increments_and_rejects_invalid_stored_lists",
+            SQLITE_MEMORY_DIR,
+            vec![schema],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 1)
+            .unwrap();
+        insert.write_byte(1, 250);
+        insert.write_int(2, i32::MAX - 1);
+        insert.write_long(3, i64::MAX - 1);
+        insert.write_null(4);
+        insert.save(1).unwrap();
+        let txn = insert.finish().unwrap();
+        let read = |txn: &SQLiteTxn| {
+            let mut cursor = instance.cursor(txn, 0).unwrap();
+            let reader = cursor.next(1).unwrap();
+            (reader.read_byte(1), reader.read_int(2), reader.read_long(3))
+        };
+
+        let int = |value| IsarValue::Integer(value);
+        let updates = [
+            (1, UpdateOp::Increment(int(10))),
+            (2, UpdateOp::Increment(int(5))),
+            (3, UpdateOp::Increment(int(i64::MAX))),
+        ];
+        assert_eq!(instance.update(&txn, 0, 1, &updates), Ok(true));
+        assert_eq!(read(&txn), (255, i32::MAX, i64::MAX));
+
+        let updates = [
+            (1, UpdateOp::Decrement(int(i64::MAX))),
+            (2, UpdateOp::Increment(int(i64::MIN))),
+            (3, UpdateOp::Decrement(int(i64::MIN))),
+        ];
+        assert_eq!(instance.update(&txn, 0, 1, &updates), Ok(true));
+        assert_eq!(read(&txn), (0, i32::MIN + 1, i64::MAX));
+
+        let updates = [(3, UpdateOp::Increment(int(i64::MIN)))];
+        assert_eq!(instance.update(&txn, 0, 1, &updates), Ok(true));
+        assert_eq!(read(&txn).2, -1);
+        let updates = [(3, UpdateOp::Decrement(int(i64::MIN)))];
+        assert_eq!(instance.update(&txn, 0, 1, &updates), Ok(true));
+        assert_eq!(read(&txn).2, i64::MAX);
+
+        txn.get_sqlite(false)
+            .unwrap()
+            .prepare("UPDATE Doc SET tags = 'invalid'")
+            .unwrap()
+            .step()
+            .unwrap();
+        let updates = [(4, UpdateOp::Append(int(1)))];
+        assert!(instance.update(&txn, 0, 1, &updates).is_err());
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn string_keys_resolve_ids() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![
+                PropertySchema::new("key", DataType::String, None),
+                PropertySchema::new("value", DataType::Int, None),
+            ],
+            vec![],
+            false,
+        )
+        .with_key("key");
+        let instance = SQLiteInstance::open_instance(
+            13,
+            "string_keys_resolve_ids",
+            SQLITE_MEMORY_DIR,
+            vec![schema],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let save = |insert: &mut SQLiteInsert, key: &str, value: i32| {
+            insert.write_string(1, key);
+            insert.write_int(2, value);
+            insert.save_by_key()
+        };
+
+        let txn = instance.begin_txn(true).unwrap();
+        let mut insert = instance.insert(txn, 0, 4).unwrap();
+        assert_eq!(save(&mut insert, "a", 1), Ok(1));
+        assert_eq!(save(&mut insert, "b", 2), Ok(2));
+        assert_eq!(save(&mut insert, "a", 3), Ok(1));
+        insert.write_string(1, "c");
+        insert.write_int(2, 4);
+        insert.save(10).unwrap();
+        let txn = insert.finish().unwrap();
+        assert_eq!(instance.get_id_by_key(&txn, 0, "a"), Ok(Some(1)));
+        assert_eq!(instance.get_id_by_key(&txn, 0, "c"), Ok(Some(10)));
+        assert_eq!(instance.get_id_by_key(&txn, 0, "z"), Ok(None));
+        assert_eq!(instance.count(&txn, 0), Ok(3));
+
+        let json = r#"[{"key": "b", "value": 20}, {"key": "d", "value": 5}]"#;
+        let (txn, _) = instance
+            .import_json(txn, 0, &mut serde_json::Deserializer::from_str(json), |_| 0)
+            .unwrap();
+        assert_eq!(instance.get_id_by_key(&txn, 0, "b"), Ok(Some(2)));
+        assert_eq!(instance.get_id_by_key(&txn, 0, "d"), Ok(Some(11)));
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(Filter::Condition(FilterCondition::new(
+            1,
+            ConditionType::Equal,
+            vec![Some(IsarValue::String("b".to_string()))],
+            true,
+        )));
+        let query = qb.build().unwrap();
+        assert_eq!(
+            instance.query_aggregate(&txn, &query, Aggregation::Max, Some(2)),
+            Ok(Some(IsarValue::Integer(20)))
+        );
+
+        let mut insert = instance.insert(txn, 0, 1).unwrap();
+        insert.write_int(2, 0);
+        assert!(matches!(
+            insert.save_by_key(),
+            Err(IsarError::KeyViolation { .. })
+        ));
+        insert.write_string(1, "a");
+        assert!(matches!(
+            insert.save(2),
+            Err(IsarError::KeyViolation { .. })
+        ));
+    }
+
+    #[test]
+    fn converts_between_instances() {
+        let schemas = || {
+            let person = IsarSchema::new(
+                "Person",
+                Some("id"),
+                vec![
+                    PropertySchema::new("name", DataType::String, None),
+                    PropertySchema::new("scores", DataType::IntList, None),
+                    PropertySchema::new("address", DataType::Object, Some("Address")),
+                ],
+                vec![],
+                false,
+            );
+            let address = IsarSchema::new(
+                "Address",
+                None,
+                vec![PropertySchema::new("street", DataType::String, None)],
+                vec![],
+                true,
+            );
+            vec![person, address]
+        };
+        let open = |instance_id: u32, name: &str| {
+            SQLiteInstance::open_instance(
+                instance_id,
+                name,
+                SQLITE_MEMORY_DIR,
+                schemas(),
+                0,
+                None,
+                None,
+            )
+            .unwrap()
+        };
+        let src = open(17, "converts_between_instances_src");
+        let dst = open(18, "converts_between_instances_dst");
+
+        let mut insert = src.insert(src.begin_txn(true).unwrap(), 0, 2).unwrap();
+        insert.write_string(1, "a");
+        let mut scores = insert.begin_list(2, 2).unwrap();
+        scores.write_int(0, 1);
+        scores.write_int(1, 2);
+        insert.end_list(scores);
+        let mut address = insert.begin_object(3).unwrap();
+        address.write_string(1, "street");
+        insert.end_object(address);
+        insert.save(5).unwrap();
+        insert.write_null(1);
+        insert.write_null(2);
+        insert.write_null(3);
+        insert.save(9).unwrap();
+        src.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let mut insert = dst.insert(dst.begin_txn(true).unwrap(), 0, 1).unwrap();
+        insert.write_string(1, "replaced");
+        insert.save(20).unwrap();
+        dst.commit_txn(insert.finish().unwrap()).unwrap();
+
+        assert_eq!(convert(&src, &dst, 1), Ok(2));
+        let txn = dst.begin_txn(false).unwrap();
+        assert_eq!(dst.count(&txn, 0), Ok(2));
+        let mut cursor = dst.cursor(&txn, 0).unwrap();
+        {
+            let reader = cursor.next(5).unwrap();
+            assert_eq!(reader.read_string(1), Some("a"));
+            let (scores, length) = reader.read_list(2).unwrap();
+            assert_eq!(length, 2);
+            assert_eq!((scores.read_int(0), scores.read_int(1)), (1, 2));
+            let address = reader.read_object(3).unwrap();
+            assert_eq!(address.read_string(1), Some("street"));
+        }
+        {
+            let reader = cursor.next(9).unwrap();
+            assert!(reader.is_null(1));
+            assert!(reader.is_null(2));
+            assert!(reader.is_null(3));
+        }
+        assert!(cursor.next(20).is_none());
+        drop(cursor);
+        dst.abort_txn(txn);
+
+        // a destination with different properties is rejected
+        let other = SQLiteInstance::open_instance(
+            19,
+            "converts_between_instances_other",
+            SQLITE_MEMORY_DIR,
+            vec![IsarSchema::new(
+                "Person",
+                Some("id"),
+                vec![PropertySchema::new("name", DataType::Int, None)],
+                vec![],
+                false,
+            )],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            convert(&src, &other, CONVERT_BATCH_SIZE),
+            Err(IsarError::SchemaError { .. })
+        ));
+    }
+
+    #[test]
+    fn reads_schemas_of_existing_database() {
+        let dir = std::env::temp_dir().join("isar_reads_schemas_of_existing_database");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap();
+
+        let person = IsarSchema::new(
+            "Person",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+            ],
+            vec![IndexSchema::new("name", vec!["name"], true, false)],
+            false,
+        )
+        .with_tombstones();
+        let address = IsarSchema::new(
+            "Address",
+            None,
+            vec![
+                PropertySchema::new("street", DataType::String, None),
+                PropertySchema::new("number", DataType::Long, None),
+                PropertySchema::new("unused", DataType::String, None),
+            ],
+            vec![],
+            true,
+        );
+        let instance =
+            SQLiteInstance::open_instance(20, "db", dir, vec![person, address], 0, None, None)
+                .unwrap();
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 1)
+            .unwrap();
+        insert.write_string(1, "a");
+        let mut address = insert.begin_object(2).unwrap();
+        address.write_string(1, "street");
+        address.write_long(2, 7);
+        address.write_null(3);
+        insert.end_object(address);
+        insert.save(3).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+        SQLiteInstance::close(instance, false);
+
+        let schemas = SQLiteInstance::read_schemas("db", dir, None).unwrap();
+        assert_eq!(schemas.len(), 2);
+        assert_eq!(schemas[0].name, "Person");
+        assert!(schemas[0].tombstones);
+        assert!(!schemas[0].field_timestamps);
+        assert_eq!(
+            schemas[0].properties,
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+            ]
+        );
+        assert_eq!(
+            schemas[0].indexes,
+            vec![IndexSchema::new("name", vec!["name"], true, false)]
+        );
+        assert!(schemas[1].embedded);
+        assert_eq!(
+            schemas[1].properties,
+            vec![
+                PropertySchema::new("street", DataType::String, None),
+                PropertySchema::new("number", DataType::Long, None),
+                PropertySchema::new("unused", DataType::String, None),
+            ]
+        );
+
+        // without stored embedded schemas, the properties are inferred from the
+        // stored values in key order
+        let path = format!("{}/db.sqlite", dir);
+        let sqlite = SQLite3::open(&path, None).unwrap();
+        sqlite
+            .prepare("DROP TABLE _embedded_schemas")
+            .unwrap()
+            .step()
+            .unwrap();
+        drop(sqlite);
+        let inferred = SQLiteInstance::read_schemas("db", dir, None).unwrap();
+        assert_eq!(inferred[0], schemas[0]);
+        assert_eq!(
+            inferred[1].properties,
+            vec![
+                PropertySchema::new("number", DataType::Long, None),
+                PropertySchema::new("street", DataType::String, None),
+            ]
+        );
+
+        let instance =
+            SQLiteInstance::open_instance(21, "db", dir, schemas, 0, None, None).unwrap();
+        let txn = instance.begin_txn(false).unwrap();
+        assert_eq!(instance.verify(&txn), Ok(()));
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(3).unwrap();
+            assert_eq!(reader.read_string(1), Some("a"));
+            let address = reader.read_object(2).unwrap();
+            assert_eq!(address.read_long(2), 7);
+        }
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+
+        assert_eq!(
+            SQLiteInstance::read_schemas("missing", dir, None),
+            Err(IsarError::PathError {})
+        );
+    }
+
+    #[test]
+    fn builds_query_from_description() {
+        let schema = IsarSchema::new(
+            "Person",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("age", DataType::Int, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance = SQLiteInstance::open_instance(
+            22,
+            "builds_query_from_description",
+            SQLITE_MEMORY_DIR,
+            vec![schema],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 4)
+            .unwrap();
+        for (id, name, age) in [(1, "a", 20), (2, "b", 30), (3, "a", 20), (4, "c", 10)] {
+            insert.write_string(1, name);
+            insert.write_int(2, age);
+            insert.save(id).unwrap();
+        }
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let description = QueryDescription::from_json(
+            r#"{
+                "filter": {"condition": {"property": "age", "type": "greater", "values": [15]}},
+                "sort": [{"property": "age", "order": "desc"}],
+                "distinct": [{"property": "name"}],
+                "offset": 1
+            }"#,
+        )
+        .unwrap();
+        let (query, offset, limit) = description.build(&instance, 0).unwrap();
+        assert_eq!((offset, limit), (Some(1), None));
+        let txn = instance.begin_txn(false).unwrap();
+        let mut cursor = instance.query_cursor(&txn, &query, offset, limit).unwrap();
+        let mut names = vec![];
+        while let Some(reader) = cursor.next() {
+            names.push(reader.read_string(1).unwrap().to_string());
+        }
+        assert_eq!(names, vec!["a"]);
+        drop(cursor);
+        instance.abort_txn(txn);
+
+        let unknown =
+            QueryDescription::from_json(r#"{"sort": [{"property": "unknown"}]}"#).unwrap();
+        assert!(matches!(
+            unknown.build(&instance, 0),
+            Err(IsarError::QueryError { .. })
+        ));
+    }
+
+    #[test]
+    fn queries_json_paths_of_parsed_query() {
+        let person = IsarSchema::new(
+            "Person",
+            Some("id"),
+            vec![
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("data", DataType::Json, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+            ],
+            vec![],
+            false,
+        );
+        let address = IsarSchema::new(
+            "Address",
+            None,
+            vec![PropertySchema::new("meta", DataType::Json, None)],
+            vec![],
+            true,
+        );
+        let schemas = vec![person, address];
+        let instance = SQLiteInstance::open_instance(
+            23,
+            "queries_json_paths_of_parsed_query",
+            SQLITE_MEMORY_DIR,
+            schemas.clone(),
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 3)
+            .unwrap();
+        let objects = [
+            (1, "a", r#"{"tags": ["x", "y"]}"#, r#"{"zip": "10115"}"#),
+            (2, "b", r#"{"tags": ["y"]}"#, r#"{"zip": "AB-20"}"#),
+            (3, "c", r#"{"tags": []}"#, r#"{"zip": "ab-30"}"#),
+        ];
+        for (id, name, data, meta) in objects {
+            insert.write_string(1, name);
+            insert.write_string(2, data);
+            let mut address = insert.begin_object(3).unwrap();
+            address.write_string(1, meta);
+            insert.end_object(address);
+            insert.save(id).unwrap();
+        }
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let description = parse_query(
+            r#"data.tags.0 = "x" OR address.meta.zip STARTSWITH "ab" IGNORE CASE
+               ORDER BY name DESC"#,
+            &schemas,
+            &schemas[0],
+        )
+        .unwrap();
+        let (query, offset, limit) = description.build(&instance, 0).unwrap();
+        let txn = instance.begin_txn(false).unwrap();
+        let mut cursor = instance.query_cursor(&txn, &query, offset, limit).unwrap();
+        let mut names = vec![];
+        while let Some(reader) = cursor.next() {
+            names.push(reader.read_string(1).unwrap().to_string());
+        }
+        assert_eq!(names, vec!["c", "b", "a"]);
+        drop(cursor);
+        instance.abort_txn(txn);
+
+        let description = parse_query("data.tags IS EMPTY", &schemas, &schemas[0]).unwrap();
+        let (query, _, _) = description.build(&instance, 0).unwrap();
+        let txn = instance.begin_txn(false).unwrap();
+        assert_eq!(
+            instance.query_aggregate(&txn, &query, Aggregation::Count, None),
+            Ok(Some(IsarValue::Integer(1)))
+        );
+        instance.abort_txn(txn);
+    }
+
+    #[test]
+    fn applies_update_operations_to_stored_values() {
+        let schema = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("count", DataType::Long, None),
+                PropertySchema::new("name", DataType::String, None),
+                PropertySchema::new("tags", DataType::StringList, None),
+                PropertySchema::new("flags", DataType::BoolList, None),
+                PropertySchema::new("bytes", DataType::ByteList, None),
+                PropertySchema::new("score", DataType::Double, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance = SQLiteInstance::open_instance(
+            25,
+            "applies_update_operations_to_stored_values",
+            SQLITE_MEMORY_DIR,
+            vec![schema],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 2)
+            .unwrap();
+        for id in [1, 2] {
+            insert.write_long(1, 5);
+            insert.write_string(2, "a");
+            let mut tags = insert.begin_list(3, 2).unwrap();
+            tags.write_string(0, "x");
+            tags.write_string(1, "y");
+            insert.end_list(tags);
+            insert.write_null(4);
+            insert.write_byte_list(5, &[1, 2, 1]);
+            insert.write_null(6);
+            insert.save(id).unwrap();
+        }
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let string = |value: &str| IsarValue::String(value.to_string());
+        let txn = instance.begin_txn(true).unwrap();
+        let updated = instance.update(
+            &txn,
+            0,
+            1,
+            &[
+                (1, UpdateOp::Increment(IsarValue::Integer(3))),
+                (2, UpdateOp::Concat(string("bc"))),
+                (3, UpdateOp::AddToSet(string("y"))),
+                (4, UpdateOp::Append(IsarValue::Bool(true))),
+                (5, UpdateOp::Remove(IsarValue::Integer(1))),
+                (6, UpdateOp::Decrement(IsarValue::Real(1.5))),
+            ],
+        );
+        assert_eq!(updated, Ok(true));
+        let query = instance.query(0).unwrap().build().unwrap();
+        let updated = instance.query_update(
+            &txn,
+            &query,
+            None,
+            None,
+            &[
+                (1, UpdateOp::Max(IsarValue::Integer(7))),
+                (3, UpdateOp::Append(string("z"))),
+            ],
+        );
+        assert_eq!(updated, Ok(2));
+        instance.commit_txn(txn).unwrap();
+
+        let txn = instance.begin_txn(true).unwrap();
+        assert_eq!(
+            instance.update(&txn, 0, 1, &[(3, UpdateOp::Concat(string("a")))]),
+            Err(IsarError::IllegalArgument {})
+        );
+        instance.abort_txn(txn);
+
+        let mut stmt = instance
+            .sqlite
+            .prepare("SELECT count, name, tags, flags, bytes, score FROM Item ORDER BY _rowid_")
+            .unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.get_long(0), 8);
+        assert_eq!(stmt.get_text(1), "abc");
+        assert_eq!(stmt.get_text(2), r#"["x","y","z"]"#);
+        assert_eq!(stmt.get_text(3), "[true]");
+        assert_eq!(stmt.get_blob(4), &[2]);
+        assert_eq!(stmt.get_double(5), -1.5);
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.get_long(0), 7);
+        assert_eq!(stmt.get_text(1), "a");
+        assert_eq!(stmt.get_text(2), r#"["x","y","z"]"#);
+        assert_eq!(stmt.get_blob(4), &[1, 2, 1]);
+    }
+
+    #[test]
+    fn updates_lists_embedded_objects_and_json() {
+        let item = IsarSchema::new(
+            "Item",
+            Some("id"),
+            vec![
+                PropertySchema::new("tags", DataType::LongList, None),
+                PropertySchema::new("address", DataType::Object, Some("Address")),
+                PropertySchema::new("meta", DataType::Json, None),
+                PropertySchema::new("bytes", DataType::ByteList, None),
+            ],
+            vec![],
+            false,
+        );
+        let address = IsarSchema::new(
+            "Address",
+            None,
+            vec![
+                PropertySchema::new("street", DataType::String, None),
+                PropertySchema::new("numbers", DataType::LongList, None),
+                PropertySchema::new("code", DataType::Uuid, None),
+            ],
+            vec![],
+            true,
+        );
+        let instance = SQLiteInstance::open_instance(
+            26,
+            "updates_lists_embedded_objects_and_json",
+            SQLITE_MEMORY_DIR,
+            vec![item, address],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut insert = instance
+            .insert(instance.begin_txn(true).unwrap(), 0, 1)
+            .unwrap();
+        for index in 1..=4 {
+            insert.write_null(index);
+        }
+        insert.save(1).unwrap();
+        instance.commit_txn(insert.finish().unwrap()).unwrap();
+
+        let int = |value| Some(IsarValue::Integer(value));
+        let txn = instance.begin_txn(true).unwrap();
+        let address = BTreeMap::from([
+            (1, Some(IsarValue::String("Main".to_string()))),
+            (2, Some(IsarValue::List(vec![int(1), int(2)]))),
+            (3, Some(IsarValue::String("550E8400E29B41D4A716446655440000".to_string()))),
+        ]);
+        let updated = instance.update(
+            &txn,
+            0,
+            1,
+            &[
+                (1, UpdateOp::Set(Some(IsarValue::List(vec![int(1), None, int(3)])))),
+                (2, UpdateOp::Set(Some(IsarValue::Object(address)))),
+                (3, UpdateOp::Set(Some(IsarValue::List(vec![int(1), None])))),
+                (4, UpdateOp::Set(Some(IsarValue::List(vec![int(4), int(5)])))),
+            ],
+        );
+        assert_eq!(updated, Ok(true));
+        instance.commit_txn(txn).unwrap();
+
+        let txn = instance.begin_txn(true).unwrap();
+        let invalid = BTreeMap::from([(3, int(1))]);
+        assert_eq!(
+            instance.update(
+                &txn,
+                0,
+                1,
+                &[(2, UpdateOp::Set(Some(IsarValue::Object(invalid))))]
+            ),
+            Err(IsarError::IllegalArgument {})
+        );
+        instance.abort_txn(txn);
+
+        let txn = instance.begin_txn(false).unwrap();
+        {
+            let mut cursor = instance.cursor(&txn, 0).unwrap();
+            let reader = cursor.next(1).unwrap();
+            let (tags, length) = reader.read_list(1).unwrap();
+            assert_eq!(length, 3);
+            assert_eq!(tags.read_long(0), 1);
+            assert!(tags.is_null(1));
+            assert_eq!(tags.read_long(2), 3);
+            let address = reader.read_object(2).unwrap();
+            assert_eq!(address.read_string(1), Some("Main"));
+            let (numbers, length) = address.read_list(2).unwrap();
+            assert_eq!(length, 2);
+            assert_eq!(numbers.read_long(1), 2);
+            assert_eq!(
+                address.read_string(3),
+                Some("550e8400-e29b-41d4-a716-446655440000")
+            );
+            assert_eq!(reader.read_string(3), Some("[1,null]"));
+            assert_eq!(reader.read_blob(4).unwrap().as_ref(), &[4, 5]);
+        }
+        instance.abort_txn(txn);
+    }
 }
@@ -1,9 +1,9 @@
-use super::schema_manager::perform_migration;
+use super::schema_manager::{perform_migration, read_schemas};
 use super::sqlite3::SQLite3;
 use super::sqlite_collection::{SQLiteCollection, SQLiteProperty};
 use super::sqlite_instance::SQLiteInstanceInfo;
 use super::sqlite_txn::SQLiteTxn;
-use crate::core::error::Result;
+use crate::core::error::{IsarError, Result};
 use crate::core::schema::IsarSchema;
 use crate::SQLITE_MEMORY_DIR;
 use intmap::IntMap;
@@ -34,6 +34,33 @@ impl Connections {
     }
 }
 
+fn get_sqlite_path(name: &str, dir: &str) -> String {
+    if dir == SQLITE_MEMORY_DIR {
+        format!("file:{}?mode=memory", name)
+    } else {
+        let mut path_buf = PathBuf::from(dir);
+        path_buf.push(format!("{}.sqlite", name));
+        path_buf.as_path().to_str().unwrap().to_string()
+    }
+}
+
+pub(crate) fn read_sqlite_schemas(
+    name: &str,
+    dir: &str,
+    encryption_key: Option<&str>,
+) -> Result<Vec<IsarSchema>> {
+    let path = get_sqlite_path(name, dir);
+    if dir != SQLITE_MEMORY_DIR && !PathBuf::from(&path).exists() {
+        return Err(IsarError::PathError {});
+    }
+
+    let sqlite = Rc::new(SQLite3::open(&path, encryption_key)?);
+    let txn = SQLiteTxn::new(sqlite.clone(), false)?;
+    let schemas = read_schemas(&txn);
+    txn.abort();
+    schemas
+}
+
 pub(crate) fn open_sqlite(
     instance_id: u32,
     name: &str,
@@ -42,14 +69,7 @@ pub(crate) fn open_sqlite(
     max_size_mib: u32,
     encryption_key: Option<&str>,
 ) -> Result<(SQLiteInstanceInfo, SQLite3)> {
-    let path = if dir == SQLITE_MEMORY_DIR {
-        format!("file:{}?mode=memory", name)
-    } else {
-        let mut path_buf = PathBuf::from(dir);
-        path_buf.push(format!("{}.sqlite", name));
-        path_buf.as_path().to_str().unwrap().to_string()
-    };
-
+    let path = get_sqlite_path(name, dir);
     let sqlite = SQLite3::open(&path, encryption_key)?;
 
     let max_size = (max_size_mib as usize).saturating_mul(MIB);
@@ -74,8 +94,15 @@ pub(crate) fn open_sqlite(
         txn.abort();
     }
 
-    let instance_info =
-        SQLiteInstanceInfo::new(instance_id, name, dir, &path, encryption_key, collections);
+    let instance_info = SQLiteInstanceInfo::new(
+        instance_id,
+        name,
+        dir,
+        &path,
+        encryption_key,
+        schemas,
+        collections,
+    );
     let sqlite = Rc::into_inner(sqlite).unwrap();
     Ok((instance_info, sqlite))
 }
@@ -99,11 +126,34 @@ fn get_collections(schemas: &[IsarSchema]) -> Vec<SQLiteCollection> {
                 }
             })
             .collect_vec();
+        let key_property = collection_schema.key_name.as_ref().and_then(|key_name| {
+            let position = properties.iter().position(|p| &p.name == key_name)?;
+            Some(position as u16 + 1)
+        });
+        let expiry_property = collection_schema
+            .expiry_name
+            .as_ref()
+            .and_then(|expiry_name| {
+                let position = properties.iter().position(|p| &p.name == expiry_name)?;
+                Some(position as u16 + 1)
+            });
+        let version_property = collection_schema
+            .version_name
+            .as_ref()
+            .and_then(|version_name| {
+                let position = properties.iter().position(|p| &p.name == version_name)?;
+                Some(position as u16 + 1)
+            });
         let collection = SQLiteCollection::new(
             collection_schema.name.clone(),
             collection_schema.id_name.clone(),
             properties,
             collection_schema.indexes.clone(),
+            key_property,
+            expiry_property,
+            version_property,
+            collection_schema.tombstones,
+            collection_schema.field_timestamps,
         );
         collections.push(collection);
     }
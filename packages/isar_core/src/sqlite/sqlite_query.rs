@@ -1,26 +1,43 @@
 use super::sql::{
-    offset_limit_sql, select_properties_sql, update_properties_sql, FN_FILTER_JSON_COND_PTR_TYPE,
+    offset_limit_sql, select_projected_properties_sql, select_properties_sql,
+    update_properties_sql, FN_FILTER_JSON_COND_PTR_TYPE, FN_GEO_SHAPE_PTR_TYPE, FN_REGEXP_PTR_TYPE,
+    FN_UPDATE_OP_PTR_TYPE,
 };
 use super::sqlite3::SQLiteStatement;
 use super::sqlite_collection::{SQLiteCollection, SQLiteProperty};
+use super::sqlite_query_builder::{collate_sql, where_sql};
 use super::sqlite_reader::SQLiteReader;
 use super::sqlite_txn::SQLiteTxn;
+use crate::core::continuation::QueryContinuation;
 use crate::core::cursor::IsarQueryCursor;
 use crate::core::data_type::DataType;
-use crate::core::error::Result;
-use crate::core::filter::JsonCondition;
+use crate::core::error::{IsarError, Result};
+use crate::core::filter::{Filter, JsonCondition};
+use crate::core::filter_regex::FilterRegex;
+use crate::core::geo::GeoShape;
 use crate::core::instance::Aggregation;
+use crate::core::query_builder::Sort;
+use crate::core::query_plan::{SQLitePlanRow, SQLiteQueryPlan};
 use crate::core::reader::IsarReader;
-use crate::core::value::IsarValue;
+use crate::core::update::UpdateOp;
+use crate::core::value::{IntegerSum, IsarValue};
+use crate::core::vector::{NearestNeighbors, VectorMetric};
 use crate::core::watcher::QueryMatches;
+use itertools::Itertools;
 use std::borrow::Cow;
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum QueryParam {
     Value(IsarValue),
     JsonCondition(JsonCondition),
+    Regex(FilterRegex),
+    Geo(GeoShape),
+    Update(UpdateOp),
+    Blob(Vec<u8>),
 }
 
+type BoundWhere<'a> = (Option<Cow<'a, str>>, Cow<'a, [QueryParam]>);
+
 #[cfg(test)]
 impl Eq for QueryParam {}
 
@@ -28,22 +45,106 @@ impl Eq for QueryParam {}
 pub struct SQLiteQuery {
     pub(crate) collection_index: u16,
     sql: String,
+    where_sql: Option<String>,
+    group_sql: String,
+    order_sql: String,
+    sort: Vec<(u16, Sort, bool)>,
+    properties: Vec<u16>,
     has_sort_distinct: bool,
     params: Vec<QueryParam>,
+    /// Filter with parameter conditions that is converted to SQL again for
+    /// every cursor. Equal SQL reuses the cached prepared statement.
+    param_filter: Option<Filter>,
 }
 
 impl SQLiteQuery {
     pub(crate) fn new(
         collection_index: u16,
-        sql: String,
-        has_sort_distinct: bool,
+        where_sql: Option<String>,
+        order_sql: String,
+        group_sql: String,
+        sort: Vec<(u16, Sort, bool)>,
+        mut properties: Vec<u16>,
         params: Vec<QueryParam>,
     ) -> Self {
+        // continuations are read from the sort columns so they are selected
+        // even if they are not projected
+        if !properties.is_empty() {
+            for (property_index, _, _) in &sort {
+                if !properties.contains(property_index) {
+                    properties.push(*property_index);
+                }
+            }
+        }
+        let sql = Self::query_sql(where_sql.as_deref(), &group_sql, &order_sql);
+        let has_sort_distinct = !sort.is_empty() || !group_sql.is_empty();
         Self {
             collection_index,
             sql,
+            where_sql,
+            group_sql,
+            order_sql,
+            sort,
+            properties,
             has_sort_distinct,
             params,
+            param_filter: None,
+        }
+    }
+
+    pub(crate) fn with_param_filter(mut self, filter: Filter) -> Self {
+        self.param_filter = Some(filter);
+        self
+    }
+
+    /// SQLite requires the GROUP BY clause of a distinct query to come before
+    /// the ORDER BY clause.
+    pub(crate) fn query_sql(where_sql: Option<&str>, group_sql: &str, order_sql: &str) -> String {
+        let mut sql = String::new();
+        if let Some(where_sql) = where_sql {
+            sql.push_str(" WHERE ");
+            sql.push_str(where_sql);
+        }
+        sql.push_str(group_sql);
+        sql.push_str(order_sql);
+        sql
+    }
+
+    /// The WHERE condition and its parameters with `values` bound to the
+    /// parameter conditions of the filter.
+    fn bind(
+        &self,
+        all_collections: &[SQLiteCollection],
+        values: &[Option<IsarValue>],
+    ) -> Result<BoundWhere<'_>> {
+        if let Some(filter) = &self.param_filter {
+            let filter = filter.bind(values)?;
+            let (where_sql, params) =
+                where_sql(all_collections, self.collection_index, Some(filter))?;
+            Ok((where_sql.map(Cow::Owned), Cow::Owned(params)))
+        } else {
+            Ok((
+                self.where_sql.as_deref().map(Cow::Borrowed),
+                Cow::Borrowed(&self.params),
+            ))
+        }
+    }
+
+    /// Fails if the query has parameter conditions. Only cursors bind
+    /// parameters, other operations would silently match nothing.
+    pub(crate) fn verify_bound(&self) -> Result<()> {
+        if self.param_filter.is_some() {
+            Err(IsarError::IllegalArgument {})
+        } else {
+            Ok(())
+        }
+    }
+
+    fn select_sql(&self, collection: &SQLiteCollection) -> String {
+        if self.properties.is_empty() {
+            select_properties_sql(collection)
+        } else {
+            select_projected_properties_sql(collection, &self.properties)
         }
     }
 
@@ -51,25 +152,189 @@ impl SQLiteQuery {
         &'a self,
         txn: &'a SQLiteTxn,
         all_collections: &'a [SQLiteCollection],
+        values: &[Option<IsarValue>],
         offset: Option<u32>,
         limit: Option<u32>,
     ) -> Result<SQLiteQueryCursor<'a>> {
         let collection = &all_collections[self.collection_index as usize];
+        let (where_sql, params) = self.bind(all_collections, values)?;
         let sql = format!(
             "SELECT {} FROM {} {} {}",
-            select_properties_sql(collection),
+            self.select_sql(collection),
             collection.name,
-            self.sql,
+            Self::query_sql(where_sql.as_deref(), &self.group_sql, &self.order_sql),
             offset_limit_sql(offset, limit)
         );
-        let mut stmt = txn.get_sqlite(false)?.prepare(&sql)?;
-        Self::bind_params(&mut stmt, &self.params, 0)?;
+        let mut stmt = txn.get_sqlite(false)?.prepare_cached(&sql)?;
+        Self::bind_params(&mut stmt, &params, 0)?;
 
-        Ok(SQLiteQueryCursor {
+        Ok(SQLiteQueryCursor::new(
+            txn,
             stmt,
+            self,
             collection,
             all_collections,
-        })
+        ))
+    }
+
+    pub(crate) fn cursor_after<'a>(
+        &'a self,
+        txn: &'a SQLiteTxn,
+        all_collections: &'a [SQLiteCollection],
+        after: Option<&QueryContinuation>,
+        limit: Option<u32>,
+    ) -> Result<SQLiteQueryCursor<'a>> {
+        let collection = &all_collections[self.collection_index as usize];
+
+        let mut keys = self
+            .sort
+            .iter()
+            .map(|(property_index, sort, case_sensitive)| {
+                let name = collection.get_property_name(*property_index);
+                (name, *sort, collate_sql(*case_sensitive))
+            })
+            .collect_vec();
+        keys.push((SQLiteProperty::ID_NAME, Sort::Asc, "BINARY"));
+
+        let (filter_sql, params) = self.bind(all_collections, &[])?;
+        let (where_sql, after_params) = if let Some(after) = after {
+            let mut values = after.values.clone();
+            values.resize(self.sort.len(), None);
+            values.push(Some(IsarValue::Integer(after.id)));
+            let (after_sql, after_params) = Self::after_sql(&keys, &values);
+            let where_sql = if let Some(where_sql) = &filter_sql {
+                format!("WHERE ({}) AND ({})", where_sql, after_sql)
+            } else {
+                format!("WHERE {}", after_sql)
+            };
+            (where_sql, after_params)
+        } else if let Some(where_sql) = &filter_sql {
+            (format!("WHERE {}", where_sql), vec![])
+        } else {
+            (String::new(), vec![])
+        };
+        let order_sql = keys
+            .iter()
+            .map(|(name, sort, collate)| {
+                format!(
+                    "{} COLLATE {}{}",
+                    name,
+                    collate,
+                    if *sort == Sort::Asc { "" } else { " DESC" }
+                )
+            })
+            .join(", ");
+        let sql = format!(
+            "SELECT {} FROM {} {}{} ORDER BY {} {}",
+            self.select_sql(collection),
+            collection.name,
+            where_sql,
+            self.group_sql,
+            order_sql,
+            offset_limit_sql(None, limit)
+        );
+        let mut stmt = txn.get_sqlite(false)?.prepare_cached(&sql)?;
+        Self::bind_params(&mut stmt, &params, 0)?;
+        Self::bind_params(&mut stmt, &after_params, params.len())?;
+
+        Ok(SQLiteQueryCursor::new(
+            txn,
+            stmt,
+            self,
+            collection,
+            all_collections,
+        ))
+    }
+
+    fn after_sql(
+        keys: &[(&str, Sort, &str)],
+        values: &[Option<IsarValue>],
+    ) -> (String, Vec<QueryParam>) {
+        let mut params = vec![];
+        let mut sql = vec![];
+        for (i, ((name, sort, collate), value)) in keys.iter().zip(values).enumerate() {
+            let mut conditions = vec![];
+            for ((name, _, collate), value) in keys[..i].iter().zip(values) {
+                if let Some(value) = value {
+                    conditions.push(format!("{} = ? COLLATE {}", name, collate));
+                    params.push(QueryParam::Value(value.clone()));
+                } else {
+                    conditions.push(format!("{} IS NULL", name));
+                }
+            }
+            let condition = match (sort, value) {
+                (Sort::Asc, Some(value)) => {
+                    params.push(QueryParam::Value(value.clone()));
+                    format!("{} > ? COLLATE {}", name, collate)
+                }
+                (Sort::Asc, None) => format!("{} IS NOT NULL", name),
+                (Sort::Desc, Some(value)) => {
+                    params.push(QueryParam::Value(value.clone()));
+                    format!("({} < ? COLLATE {} OR {} IS NULL)", name, collate, name)
+                }
+                (Sort::Desc, None) => "0".to_string(),
+            };
+            conditions.push(condition);
+            sql.push(format!("({})", conditions.join(" AND ")));
+        }
+        (sql.join(" OR "), params)
+    }
+
+    fn continuation(
+        &self,
+        stmt: &SQLiteStatement,
+        collection: &SQLiteCollection,
+    ) -> QueryContinuation {
+        let values = self
+            .sort
+            .iter()
+            .map(|(property_index, _, _)| {
+                let col = *property_index as u32;
+                if stmt.is_null(col) {
+                    return None;
+                }
+                let data_type = collection
+                    .get_property(*property_index)
+                    .map_or(DataType::Long, |p| p.data_type);
+                let value = match data_type {
+                    DataType::Bool => IsarValue::Bool(stmt.get_int(col) != 0),
+                    DataType::Byte | DataType::Int | DataType::Long => {
+                        IsarValue::Integer(stmt.get_long(col))
+                    }
+                    DataType::Float | DataType::Double => IsarValue::Real(stmt.get_double(col)),
+                    DataType::String => IsarValue::String(stmt.get_text(col).to_string()),
+                    _ => return None,
+                };
+                Some(value)
+            })
+            .collect();
+        QueryContinuation::new(values, stmt.get_long(0))
+    }
+
+    /// Reads the continuation of the object with `id` again once the cursor
+    /// has moved past it.
+    fn continuation_of(
+        &self,
+        txn: &SQLiteTxn,
+        collection: &SQLiteCollection,
+        id: i64,
+    ) -> Result<Option<QueryContinuation>> {
+        if self.sort.is_empty() {
+            return Ok(Some(QueryContinuation::new(vec![], id)));
+        }
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            self.select_sql(collection),
+            collection.name,
+            SQLiteProperty::ID_NAME
+        );
+        let mut stmt = txn.get_sqlite(false)?.prepare_cached(&sql)?;
+        stmt.bind_long(0, id)?;
+        if stmt.step()? {
+            Ok(Some(self.continuation(&stmt, collection)))
+        } else {
+            Ok(None)
+        }
     }
 
     pub(crate) fn aggregate(
@@ -86,6 +351,40 @@ impl SQLiteQuery {
             .map_or(DataType::Long, |p| p.data_type);
 
         let aggregation_sql = match aggregation {
+            Aggregation::CountDistinct => {
+                return self.aggregate_count_distinct(txn, collection, property_name, property_type)
+            }
+            Aggregation::Median => {
+                return self.aggregate_percentile(
+                    txn,
+                    collection,
+                    property_name,
+                    property_type,
+                    0.5,
+                )
+            }
+            Aggregation::Percentile(percentile) => {
+                let percentile = percentile.value();
+                if !(0.0..=1.0).contains(&percentile) {
+                    return Err(IsarError::IllegalArgument {});
+                }
+                return self.aggregate_percentile(
+                    txn,
+                    collection,
+                    property_name,
+                    property_type,
+                    percentile,
+                );
+            }
+            Aggregation::Variance | Aggregation::StdDev => {
+                return self.aggregate_variance(
+                    txn,
+                    collection,
+                    property_name,
+                    property_type,
+                    aggregation == Aggregation::StdDev,
+                )
+            }
             Aggregation::Count => "COUNT(*)".to_string(),
             Aggregation::IsEmpty => SQLiteProperty::ID_NAME.to_string(),
             Aggregation::Min => {
@@ -94,6 +393,9 @@ impl SQLiteQuery {
             Aggregation::Max => {
                 format!("MAX({})", property_name)
             }
+            Aggregation::Sum if Self::is_integer(property_type) => {
+                return self.aggregate_integer_sum(txn, collection, property_name)
+            }
             Aggregation::Sum => {
                 format!("SUM({})", property_name)
             }
@@ -133,17 +435,338 @@ impl SQLiteQuery {
                     return Ok(None);
                 }
             }
+            Aggregation::CountDistinct
+            | Aggregation::Median
+            | Aggregation::Percentile(_)
+            | Aggregation::Variance
+            | Aggregation::StdDev => unreachable!(),
         };
         Ok(Some(result))
     }
 
+    fn source_sql(&self, collection: &SQLiteCollection, property_name: &str) -> String {
+        let where_sql = self
+            .where_sql
+            .as_ref()
+            .map(|where_sql| format!(" WHERE {}", where_sql))
+            .unwrap_or_default();
+        format!(
+            "(SELECT {} AS value FROM {}{}{})",
+            property_name, collection.name, where_sql, self.group_sql
+        )
+    }
+
+    // SUM() fails once an integer sum overflows, so integers are summed with
+    // checked arithmetic and continue as REAL like on the native backend.
+    fn aggregate_integer_sum(
+        &self,
+        txn: &SQLiteTxn,
+        collection: &SQLiteCollection,
+        property_name: &str,
+    ) -> Result<Option<IsarValue>> {
+        let sql = format!(
+            "SELECT {} FROM {} {}",
+            property_name, collection.name, self.sql
+        );
+        let mut stmt = txn.get_sqlite(false)?.prepare(&sql)?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+        let mut sum = IntegerSum::default();
+        while stmt.step()? {
+            if !stmt.is_null(0) {
+                sum.add(stmt.get_long(0));
+            }
+        }
+        Ok(Some(sum.to_value()))
+    }
+
+    fn aggregate_count_distinct(
+        &self,
+        txn: &SQLiteTxn,
+        collection: &SQLiteCollection,
+        property_name: &str,
+        property_type: DataType,
+    ) -> Result<Option<IsarValue>> {
+        match property_type {
+            DataType::Bool
+            | DataType::Byte
+            | DataType::Int
+            | DataType::Float
+            | DataType::Long
+            | DataType::Double
+            | DataType::String => {}
+            _ => return Ok(None),
+        }
+        let sql = format!(
+            "SELECT COUNT(DISTINCT value) FROM {}",
+            self.source_sql(collection, property_name)
+        );
+        let mut stmt = txn.get_sqlite(false)?.prepare(&sql)?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+        stmt.step()?;
+        Ok(Some(IsarValue::Integer(stmt.get_long(0))))
+    }
+
+    fn aggregate_percentile(
+        &self,
+        txn: &SQLiteTxn,
+        collection: &SQLiteCollection,
+        property_name: &str,
+        property_type: DataType,
+        percentile: f64,
+    ) -> Result<Option<IsarValue>> {
+        if !Self::is_numeric(property_type) {
+            return Ok(None);
+        }
+        let source_sql = self.source_sql(collection, property_name);
+        let mut stmt = txn
+            .get_sqlite(false)?
+            .prepare(&format!("SELECT COUNT(value) FROM {}", source_sql))?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+        stmt.step()?;
+        let count = stmt.get_long(0);
+        if count == 0 {
+            return Ok(None);
+        }
+
+        let rank = percentile * (count - 1) as f64;
+        let lower = rank.floor();
+        let sql = format!(
+            "SELECT value FROM {} WHERE value IS NOT NULL ORDER BY value LIMIT 2 OFFSET {}",
+            source_sql, lower as i64
+        );
+        let mut stmt = txn.get_sqlite(false)?.prepare(&sql)?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+        stmt.step()?;
+        let lower_value = stmt.get_double(0);
+        let upper_value = if stmt.step()? {
+            stmt.get_double(0)
+        } else {
+            lower_value
+        };
+        Ok(Some(IsarValue::Real(
+            lower_value + (upper_value - lower_value) * (rank - lower),
+        )))
+    }
+
+    fn aggregate_variance(
+        &self,
+        txn: &SQLiteTxn,
+        collection: &SQLiteCollection,
+        property_name: &str,
+        property_type: DataType,
+        std_dev: bool,
+    ) -> Result<Option<IsarValue>> {
+        if !Self::is_numeric(property_type) {
+            return Ok(None);
+        }
+        let sql = format!(
+            "WITH src AS {} SELECT AVG((value - mean) * (value - mean)) \
+            FROM src, (SELECT AVG(value) AS mean FROM src) WHERE value IS NOT NULL",
+            self.source_sql(collection, property_name)
+        );
+        let mut stmt = txn.get_sqlite(false)?.prepare(&sql)?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+        stmt.step()?;
+        if stmt.is_null(0) {
+            return Ok(None);
+        }
+        let variance = stmt.get_double(0);
+        if std_dev {
+            Ok(Some(IsarValue::Real(variance.sqrt())))
+        } else {
+            Ok(Some(IsarValue::Real(variance)))
+        }
+    }
+
+    fn is_integer(data_type: DataType) -> bool {
+        matches!(data_type, DataType::Byte | DataType::Int | DataType::Long)
+    }
+
+    fn is_numeric(data_type: DataType) -> bool {
+        matches!(
+            data_type,
+            DataType::Byte | DataType::Int | DataType::Float | DataType::Long | DataType::Double
+        )
+    }
+
+    pub(crate) fn facets(
+        &self,
+        txn: &SQLiteTxn,
+        all_collections: &[SQLiteCollection],
+        property_index: u16,
+        sort_by_count: bool,
+        limit: Option<u32>,
+    ) -> Result<Vec<(Option<IsarValue>, u32)>> {
+        let collection = &all_collections[self.collection_index as usize];
+        let property = collection
+            .get_property(property_index)
+            .ok_or(IsarError::IllegalArgument {})?;
+        let data_type = property
+            .data_type
+            .element_type()
+            .unwrap_or(property.data_type);
+        if matches!(data_type, DataType::Object | DataType::Json) {
+            return Err(IsarError::IllegalArgument {});
+        }
+
+        let source_sql = self.source_sql(collection, &property.name);
+        let from_sql = if property.data_type.is_list() {
+            format!("{} AS q, json_each(q.value) AS j", source_sql)
+        } else {
+            source_sql
+        };
+        let value_sql = if property.data_type.is_list() {
+            "j.value"
+        } else {
+            "value"
+        };
+        let order_sql = if sort_by_count { "2 DESC, 1" } else { "1" };
+        let sql = format!(
+            "SELECT {}, COUNT(*) FROM {} GROUP BY 1 ORDER BY {} {}",
+            value_sql,
+            from_sql,
+            order_sql,
+            offset_limit_sql(None, limit)
+        );
+        let mut stmt = txn.get_sqlite(false)?.prepare(&sql)?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+
+        let mut facets = vec![];
+        while stmt.step()? {
+            let value = if stmt.is_null(0) {
+                None
+            } else {
+                let value = match data_type {
+                    DataType::Bool => IsarValue::Bool(stmt.get_long(0) != 0),
+                    DataType::Float | DataType::Double => IsarValue::Real(stmt.get_double(0)),
+                    DataType::String => IsarValue::String(stmt.get_text(0).to_string()),
+                    _ => IsarValue::Integer(stmt.get_long(0)),
+                };
+                Some(value)
+            };
+            facets.push((value, stmt.get_long(1) as u32));
+        }
+        Ok(facets)
+    }
+
+    pub(crate) fn nearest(
+        &self,
+        txn: &SQLiteTxn,
+        all_collections: &[SQLiteCollection],
+        property_index: u16,
+        vector: &[f64],
+        metric: VectorMetric,
+        k: u32,
+    ) -> Result<Vec<(i64, f64)>> {
+        let collection = &all_collections[self.collection_index as usize];
+        let property = collection
+            .get_property(property_index)
+            .ok_or(IsarError::IllegalArgument {})?;
+        if !matches!(
+            property.data_type,
+            DataType::FloatList | DataType::DoubleList
+        ) || vector.is_empty()
+        {
+            return Err(IsarError::IllegalArgument {});
+        }
+
+        let where_sql = self
+            .where_sql
+            .as_ref()
+            .map(|where_sql| format!(" WHERE {}", where_sql))
+            .unwrap_or_default();
+        let sql = format!(
+            "SELECT {}, {} FROM {}{}{}",
+            SQLiteProperty::ID_NAME,
+            property.name,
+            collection.name,
+            where_sql,
+            self.group_sql
+        );
+        let mut stmt = txn.get_sqlite(false)?.prepare(&sql)?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+
+        // lists are stored as JSON arrays, null elements make the vector invalid
+        let mut neighbors = NearestNeighbors::new(k);
+        while stmt.step()? {
+            if stmt.is_null(1) {
+                continue;
+            }
+            let Ok(values) = serde_json::from_str::<Vec<Option<f64>>>(stmt.get_text(1)) else {
+                continue;
+            };
+            let values = values.into_iter().map(|v| v.unwrap_or(f64::NAN));
+            if let Some(distance) = metric.distance(vector, values) {
+                neighbors.add(stmt.get_long(0), distance);
+            }
+        }
+        Ok(neighbors.into_sorted_vec())
+    }
+
+    pub(crate) fn explain(
+        &self,
+        txn: &SQLiteTxn,
+        all_collections: &[SQLiteCollection],
+    ) -> Result<SQLiteQueryPlan> {
+        let collection = &all_collections[self.collection_index as usize];
+        let sql = format!(
+            "SELECT {} FROM {} {}",
+            self.select_sql(collection),
+            collection.name,
+            self.sql
+        );
+        let mut stmt = txn
+            .get_sqlite(false)?
+            .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+
+        let mut rows = vec![];
+        while stmt.step()? {
+            rows.push(SQLitePlanRow {
+                id: stmt.get_int(0),
+                parent: stmt.get_int(1),
+                detail: stmt.get_text(3).to_string(),
+            });
+        }
+        Ok(SQLiteQueryPlan {
+            sql: sql.trim().to_string(),
+            rows,
+        })
+    }
+
+    /// Ids of the objects matched by the query.
+    pub(crate) fn ids(
+        &self,
+        txn: &SQLiteTxn,
+        all_collections: &[SQLiteCollection],
+        offset: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Vec<i64>> {
+        let collection = &all_collections[self.collection_index as usize];
+        let sql = format!(
+            "SELECT {} FROM {} {} {}",
+            SQLiteProperty::ID_NAME,
+            collection.name,
+            self.sql,
+            offset_limit_sql(offset, limit)
+        );
+        let sqlite = txn.get_sqlite(false)?;
+        let mut stmt = sqlite.prepare(&sql)?;
+        Self::bind_params(&mut stmt, &self.params, 0)?;
+        let mut ids = vec![];
+        while stmt.step()? {
+            ids.push(stmt.get_long(0));
+        }
+        Ok(ids)
+    }
+
     pub(crate) fn update(
         &self,
         txn: &SQLiteTxn,
         all_collections: &[SQLiteCollection],
         offset: Option<u32>,
         limit: Option<u32>,
-        updates: &[(u16, Option<IsarValue>)],
+        updates: &[(u16, UpdateOp)],
     ) -> Result<u32> {
         let collection: &SQLiteCollection = &all_collections[self.collection_index as usize];
 
@@ -180,27 +803,53 @@ impl SQLiteQuery {
             }
         }
 
-        let (update_sql, update_params) = update_properties_sql(collection, updates);
-        let sql = if offset.is_some() || limit.is_some() || self.has_sort_distinct {
-            format!(
-                "UPDATE {} SET {} WHERE {} IN (SELECT {} FROM {} {} {})",
+        // changes of other replicas already carry their version
+        let keep_version = txn.change_set.try_borrow().is_ok_and(|c| c.is_remote());
+        let (update_sql, update_params, version_sql) =
+            update_properties_sql(collection, all_collections, updates, keep_version)?;
+        let matching_sql = format!(
+            "SELECT {} FROM {} {} {}",
+            SQLiteProperty::ID_NAME,
+            collection.name,
+            self.sql,
+            offset_limit_sql(offset, limit)
+        );
+        let sqlite = txn.get_sqlite(true)?;
+        let (sql, matching) = if let Some(version_sql) = version_sql {
+            // only objects with the expected version are updated
+            let mut stmt = sqlite.prepare(&format!("SELECT COUNT(*) FROM ({})", matching_sql))?;
+            Self::bind_params(&mut stmt, &self.params, 0)?;
+            stmt.step()?;
+            let sql = format!(
+                "UPDATE {} SET {} WHERE {} IN ({}) AND {}",
                 collection.name,
                 update_sql,
                 SQLiteProperty::ID_NAME,
-                SQLiteProperty::ID_NAME,
+                matching_sql,
+                version_sql
+            );
+            (sql, Some(stmt.get_long(0)))
+        } else if offset.is_some() || limit.is_some() || self.has_sort_distinct {
+            let sql = format!(
+                "UPDATE {} SET {} WHERE {} IN ({})",
                 collection.name,
-                self.sql,
-                offset_limit_sql(offset, limit)
-            )
+                update_sql,
+                SQLiteProperty::ID_NAME,
+                matching_sql
+            );
+            (sql, None)
         } else {
-            format!("UPDATE {} SET {} {}", collection.name, update_sql, self.sql)
+            let sql = format!("UPDATE {} SET {} {}", collection.name, update_sql, self.sql);
+            (sql, None)
         };
-        let sqlite = txn.get_sqlite(true)?;
         let mut stmt = sqlite.prepare(&sql)?;
         Self::bind_params(&mut stmt, &update_params, 0)?;
         Self::bind_params(&mut stmt, &self.params, update_params.len())?;
         stmt.step()?;
         let count = sqlite.count_changes();
+        if matching.is_some_and(|matching| matching != count as i64) {
+            return Err(IsarError::VersionConflict {});
+        }
 
         // Generate detailed changes for each updated object
         if collection.watchers.has_detailed_watchers() && !before_data.is_empty() {
@@ -336,8 +985,22 @@ impl SQLiteQuery {
                 QueryParam::Value(IsarValue::Integer(value)) => stmt.bind_long(col, *value)?,
                 QueryParam::Value(IsarValue::Real(value)) => stmt.bind_double(col, *value)?,
                 QueryParam::Value(IsarValue::String(value)) => stmt.bind_text(col, value)?,
+                QueryParam::Value(IsarValue::List(_) | IsarValue::Object(_)) => {
+                    return Err(IsarError::IllegalArgument {})
+                }
+                QueryParam::Blob(value) => stmt.bind_blob(col, value)?,
+                // the statement may outlive the parameters, so it owns copies
                 QueryParam::JsonCondition(cond) => {
-                    stmt.bind_object(col, cond, FN_FILTER_JSON_COND_PTR_TYPE)?
+                    stmt.bind_object(col, Box::new(cond.clone()), FN_FILTER_JSON_COND_PTR_TYPE)?
+                }
+                QueryParam::Regex(regex) => {
+                    stmt.bind_object(col, Box::new(regex.clone()), FN_REGEXP_PTR_TYPE)?
+                }
+                QueryParam::Geo(shape) => {
+                    stmt.bind_object(col, Box::new(shape.clone()), FN_GEO_SHAPE_PTR_TYPE)?
+                }
+                QueryParam::Update(op) => {
+                    stmt.bind_object(col, Box::new(op.clone()), FN_UPDATE_OP_PTR_TYPE)?
                 }
             }
         }
@@ -354,9 +1017,33 @@ impl QueryMatches for SQLiteQuery {
 }
 
 pub struct SQLiteQueryCursor<'a> {
+    txn: &'a SQLiteTxn,
     stmt: SQLiteStatement<'a>,
+    query: &'a SQLiteQuery,
     collection: &'a SQLiteCollection,
     all_collections: &'a [SQLiteCollection],
+    has_row: bool,
+    last_id: Option<i64>,
+}
+
+impl<'a> SQLiteQueryCursor<'a> {
+    fn new(
+        txn: &'a SQLiteTxn,
+        stmt: SQLiteStatement<'a>,
+        query: &'a SQLiteQuery,
+        collection: &'a SQLiteCollection,
+        all_collections: &'a [SQLiteCollection],
+    ) -> Self {
+        Self {
+            txn,
+            stmt,
+            query,
+            collection,
+            all_collections,
+            has_row: false,
+            last_id: None,
+        }
+    }
 }
 
 impl<'a> IsarQueryCursor for SQLiteQueryCursor<'a> {
@@ -366,7 +1053,11 @@ impl<'a> IsarQueryCursor for SQLiteQueryCursor<'a> {
         Self: 'b;
 
     fn next(&mut self) -> Option<Self::Reader<'_>> {
+        if self.has_row {
+            self.last_id = Some(self.stmt.get_long(0));
+        }
         let has_next = self.stmt.step().ok()?;
+        self.has_row = has_next;
         if has_next {
             let reader = SQLiteReader::new(
                 Cow::Borrowed(&self.stmt),
@@ -378,4 +1069,263 @@ impl<'a> IsarQueryCursor for SQLiteQueryCursor<'a> {
             None
         }
     }
+
+    fn continuation(&self) -> Option<QueryContinuation> {
+        if self.has_row {
+            Some(self.query.continuation(&self.stmt, self.collection))
+        } else {
+            let id = self.last_id?;
+            self.query
+                .continuation_of(self.txn, self.collection, id)
+                .ok()
+                .flatten()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::{ConditionType, FilterCondition, FilterParam};
+    use crate::core::instance::IsarInstance;
+    use crate::core::normalize::StringNormalization;
+    use crate::core::query_builder::IsarQueryBuilder;
+    use crate::core::query_plan::QueryPlan;
+    use crate::core::schema::{IndexSchema, IsarSchema, PropertySchema};
+    use crate::core::test_instance::{insert_objects, open_instance};
+    use crate::core::writer::IsarWriter;
+    use crate::sqlite::sqlite_instance::SQLiteInstance;
+
+    fn schema() -> IsarSchema {
+        IsarSchema::new(
+            "TestCol",
+            None,
+            vec![PropertySchema::new("value", DataType::String, None)],
+            vec![IndexSchema::new("value", vec!["value"], false, false)],
+            false,
+        )
+    }
+
+    #[test]
+    fn explain_reports_index_usage() {
+        let instance =
+            open_instance::<SQLiteInstance>("explain_reports_index_usage", vec![schema()]);
+        let txn = instance.begin_txn(false).unwrap();
+
+        let query = instance.query(0).unwrap().build().unwrap();
+        let QueryPlan::SQLite(plan) = instance.explain(&txn, &query).unwrap() else {
+            panic!("expected sqlite plan");
+        };
+        assert!(plan.rows.iter().any(|r| r.detail.starts_with("SCAN")));
+
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(Filter::Condition(FilterCondition::new(
+            1,
+            ConditionType::Equal,
+            vec![Some(IsarValue::String("a".to_string()))],
+            true,
+        )));
+        let query = qb.build().unwrap();
+        let QueryPlan::SQLite(plan) = instance.explain(&txn, &query).unwrap() else {
+            panic!("expected sqlite plan");
+        };
+        assert!(plan.rows.iter().any(|r| r.detail.contains("INDEX")));
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn projected_query_reads_selected_properties() {
+        let instance = open_instance::<SQLiteInstance>(
+            "projected_query_reads_selected_properties",
+            vec![schema()],
+        );
+        insert_objects(&instance, 0, &[1, 2, 3], |insert, id| match id {
+            2 => insert.write_null(1),
+            _ => insert.write_string(1, if id == 1 { "a" } else { "b" }),
+        });
+        let txn = instance.begin_txn(false).unwrap();
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_property(0);
+        let query = qb.build().unwrap();
+        {
+            let mut cursor = instance.query_cursor(&txn, &query, None, None).unwrap();
+            let reader = cursor.next().unwrap();
+            assert_eq!(reader.read_id(), 1);
+            assert!(reader.is_null(1));
+        }
+
+        let read_values = |query: &SQLiteQuery, property_index: u16| {
+            let mut cursor = instance.query_cursor(&txn, query, None, None).unwrap();
+            let mut json = vec![];
+            cursor
+                .serialize_values(property_index, &mut serde_json::Serializer::new(&mut json))
+                .unwrap();
+            String::from_utf8(json).unwrap()
+        };
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_property(1);
+        let query = qb.build().unwrap();
+        assert_eq!(read_values(&query, 1), r#"["a",null,"b"]"#);
+        assert_eq!(read_values(&query, 0), "[1,2,3]");
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_property(0);
+        qb.add_sort(1, Sort::Desc, false);
+        let query = qb.build().unwrap();
+        let (id, after) = {
+            let mut cursor = instance
+                .query_cursor_after(&txn, &query, None, Some(1))
+                .unwrap();
+            let id = cursor.next().unwrap().read_id();
+            (id, cursor.continuation().unwrap())
+        };
+        assert_eq!(id, 3);
+        assert_eq!(after.values, vec![Some(IsarValue::String("b".to_string()))]);
+        let mut cursor = instance
+            .query_cursor_after(&txn, &query, Some(&after), None)
+            .unwrap();
+        assert_eq!(cursor.next().unwrap().read_id(), 1);
+        drop(cursor);
+
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn query_nearest_orders_by_distance() {
+        let schema = IsarSchema::new(
+            "Doc",
+            Some("id"),
+            vec![
+                PropertySchema::new("tag", DataType::String, None),
+                PropertySchema::new("embedding", DataType::FloatList, None),
+            ],
+            vec![],
+            false,
+        );
+        let instance =
+            open_instance::<SQLiteInstance>("query_nearest_orders_by_distance", vec![schema]);
+        let rows: [(&str, Option<&[Option<f32>]>); 7] = [
+            ("a", Some(&[Some(1.0), Some(0.0)])),
+            ("a", Some(&[Some(0.0), Some(1.0)])),
+            ("b", Some(&[Some(0.9), Some(0.1)])),
+            ("a", Some(&[Some(0.7), Some(0.7)])),
+            ("a", Some(&[Some(1.0), Some(0.0), Some(0.0)])),
+            ("a", Some(&[None, Some(1.0)])),
+            ("a", None),
+        ];
+        insert_objects(&instance, 0, &[1, 2, 3, 4, 5, 6, 7], |insert, id| {
+            let (tag, embedding) = rows[id as usize - 1];
+            insert.write_string(1, tag);
+            if let Some(embedding) = embedding {
+                let mut floats = insert.begin_list(2, embedding.len() as u32).unwrap();
+                for (i, value) in embedding.iter().enumerate() {
+                    match value {
+                        Some(value) => floats.write_float(i as u32, *value),
+                        None => floats.write_null(i as u32),
+                    }
+                }
+                insert.end_list(floats);
+            } else {
+                insert.write_null(2);
+            }
+        });
+        let txn = instance.begin_txn(false).unwrap();
+        let nearest = |query: &SQLiteQuery, metric: VectorMetric, k: u32| {
+            instance
+                .query_nearest(&txn, query, 2, &[1.0, 0.0], metric, k)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect_vec()
+        };
+
+        let all = instance.query(0).unwrap().build().unwrap();
+        assert_eq!(nearest(&all, VectorMetric::Cosine, 10), vec![1, 3, 4, 2]);
+        assert_eq!(nearest(&all, VectorMetric::L2, 2), vec![1, 3]);
+        assert_eq!(nearest(&all, VectorMetric::DotProduct, 2), vec![1, 3]);
+
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(Filter::Condition(FilterCondition::new(
+            1,
+            ConditionType::Equal,
+            vec![Some(IsarValue::String("a".to_string()))],
+            true,
+        )));
+        let filtered = qb.build().unwrap();
+        assert_eq!(nearest(&filtered, VectorMetric::Cosine, 2), vec![1, 4]);
+
+        let distances = instance
+            .query_nearest(&txn, &all, 2, &[1.0, 0.0], VectorMetric::L2, 1)
+            .unwrap();
+        assert_eq!(distances, vec![(1, 0.0)]);
+        assert!(instance
+            .query_nearest(&txn, &all, 1, &[1.0, 0.0], VectorMetric::L2, 1)
+            .is_err());
+
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn binds_query_params_for_each_cursor() {
+        let instance =
+            open_instance::<SQLiteInstance>("binds_query_params_for_each_cursor", vec![schema()]);
+        let values = ["a", "ab", "b"];
+        insert_objects(&instance, 0, &[1, 2, 3], |insert, id| {
+            insert.write_string(1, values[id as usize - 1]);
+        });
+        let mut qb = instance.query(0).unwrap();
+        qb.set_filter(Filter::Param(FilterParam::new(
+            1,
+            ConditionType::StringStartsWith,
+            vec![0],
+            StringNormalization::NONE,
+        )));
+        qb.add_sort(1, Sort::Asc, true);
+        let query = qb.build().unwrap();
+
+        let txn = instance.begin_txn(false).unwrap();
+        let read = |prefix: &str| {
+            let params = [Some(IsarValue::String(prefix.to_string()))];
+            let mut cursor = instance
+                .query_cursor_with_params(&txn, &query, &params, None, None)
+                .unwrap();
+            let mut values = vec![];
+            while let Some(reader) = cursor.next() {
+                values.push(reader.read_string(1).unwrap().to_string());
+            }
+            values
+        };
+        assert_eq!(read("a"), vec!["a", "ab"]);
+        assert_eq!(read("b"), vec!["b"]);
+        assert_eq!(read("c"), Vec::<String>::new());
+
+        assert!(matches!(
+            instance.query_cursor(&txn, &query, None, None),
+            Err(IsarError::IllegalArgument {})
+        ));
+        assert!(matches!(
+            instance.query_aggregate(&txn, &query, Aggregation::Count, None),
+            Err(IsarError::IllegalArgument {})
+        ));
+        instance.abort_txn(txn);
+
+        let txn = instance.begin_txn(true).unwrap();
+        let updates = [(1, UpdateOp::Set(Some(IsarValue::String("c".to_string()))))];
+        assert!(matches!(
+            instance.query_update(&txn, &query, None, None, &updates),
+            Err(IsarError::IllegalArgument {})
+        ));
+        assert!(matches!(
+            instance.query_delete(&txn, &query, None, None),
+            Err(IsarError::IllegalArgument {})
+        ));
+        assert_eq!(instance.count(&txn, 0), Ok(3));
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
 }
@@ -1,9 +1,11 @@
 use std::vec;
 
-use super::sql::filter_sql;
+use super::sql::{collation_name, expiry_sql, filter_sql};
 use super::sqlite_collection::SQLiteCollection;
 use super::sqlite_query::{QueryParam, SQLiteQuery};
+use crate::core::error::Result;
 use crate::core::filter::Filter;
+use crate::core::normalize::StringNormalization;
 use crate::core::query_builder::{IsarQueryBuilder, Sort};
 use itertools::Itertools;
 
@@ -11,8 +13,9 @@ pub struct SQLiteQueryBuilder<'a> {
     all_collections: &'a [SQLiteCollection],
     collection_index: u16,
     filter: Option<Filter>,
-    sort: Vec<(&'a str, Sort, bool)>,
+    sort: Vec<(u16, Sort, bool)>,
     distinct: Vec<(&'a str, bool)>,
+    properties: Vec<u16>,
 }
 
 impl SQLiteQueryBuilder<'_> {
@@ -26,57 +29,62 @@ impl SQLiteQueryBuilder<'_> {
             filter: None,
             sort: Vec::new(),
             distinct: Vec::new(),
+            properties: Vec::new(),
         }
     }
 }
 
+pub(crate) fn collate_sql(case_sensitive: bool) -> &'static str {
+    collation_name(StringNormalization::from_case_sensitive(case_sensitive))
+}
+
 impl<'a> SQLiteQueryBuilder<'a> {
-    fn build_query(self) -> (String, Vec<QueryParam>) {
-        let mut filter_params = vec![];
-
-        let mut sql = String::new();
-        if let Some(filter) = self.filter {
-            sql.push_str(" WHERE ");
-            let (filter_sql, params) =
-                filter_sql(self.collection_index, self.all_collections, filter);
-            sql.push_str(&filter_sql);
-            filter_params = params;
-        }
+    fn build_parts(self) -> Result<(Option<String>, String, String, Vec<QueryParam>)> {
+        let (where_sql, filter_params) =
+            where_sql(self.all_collections, self.collection_index, self.filter)?;
+
+        let mut order_sql = String::new();
         if !self.sort.is_empty() {
-            sql.push_str(" ORDER BY ");
-            sql.push_str(
+            let collection = &self.all_collections[self.collection_index as usize];
+            order_sql.push_str(" ORDER BY ");
+            order_sql.push_str(
                 &self
                     .sort
                     .iter()
-                    .map(|(prop, sort, case_sensitive)| {
+                    .map(|(property_index, sort, case_sensitive)| {
                         format!(
                             "{} COLLATE {}{}",
-                            prop,
-                            if *case_sensitive { "NOCASE" } else { "BINARY" },
+                            collection.get_property_name(*property_index),
+                            collate_sql(*case_sensitive),
                             if *sort == Sort::Asc { "" } else { " DESC" }
                         )
                     })
                     .join(", "),
             );
         }
+
+        let mut group_sql = String::new();
         if !self.distinct.is_empty() {
-            sql.push_str(" GROUP BY ");
-            sql.push_str(
+            group_sql.push_str(" GROUP BY ");
+            group_sql.push_str(
                 &self
                     .distinct
                     .iter()
                     .map(|(prop, case_sensitive)| {
-                        format!(
-                            "{} COLLATE {}",
-                            prop,
-                            if *case_sensitive { "NOCASE" } else { "BINARY" }
-                        )
+                        format!("{} COLLATE {}", prop, collate_sql(*case_sensitive))
                     })
                     .join(", "),
             );
         }
 
-        (sql, filter_params)
+        Ok((where_sql, order_sql, group_sql, filter_params))
+    }
+
+    #[cfg(test)]
+    fn build_query(self) -> Result<(String, Vec<QueryParam>)> {
+        let (where_sql, order_sql, group_sql, filter_params) = self.build_parts()?;
+        let sql = SQLiteQuery::query_sql(where_sql.as_deref(), &group_sql, &order_sql);
+        Ok((sql, filter_params))
     }
 }
 
@@ -88,11 +96,7 @@ impl<'a> IsarQueryBuilder for SQLiteQueryBuilder<'a> {
     }
 
     fn add_sort(&mut self, property_index: u16, sort: Sort, case_sensitive: bool) {
-        self.sort.push((
-            self.all_collections[self.collection_index as usize].get_property_name(property_index),
-            sort,
-            case_sensitive,
-        ));
+        self.sort.push((property_index, sort, case_sensitive));
     }
 
     fn add_distinct(&mut self, property_index: u16, case_sensitive: bool) {
@@ -102,21 +106,91 @@ impl<'a> IsarQueryBuilder for SQLiteQueryBuilder<'a> {
         ));
     }
 
-    fn build(self) -> Self::Query {
+    fn add_property(&mut self, property_index: u16) {
+        if !self.properties.contains(&property_index) {
+            self.properties.push(property_index);
+        }
+    }
+
+    fn build(self) -> Result<Self::Query> {
         let collection_index = self.collection_index;
-        let has_sort_distinct = !self.sort.is_empty() || !self.distinct.is_empty();
-        let (sql, filter_params) = self.build_query();
-        SQLiteQuery::new(collection_index, sql, has_sort_distinct, filter_params)
+        let sort = self.sort.clone();
+        let properties = self.properties.clone();
+        let param_filter = self.filter.clone().filter(|f| f.has_params());
+        let (where_sql, order_sql, group_sql, filter_params) = self.build_parts()?;
+        let query = SQLiteQuery::new(
+            collection_index,
+            where_sql,
+            order_sql,
+            group_sql,
+            sort,
+            properties,
+            filter_params,
+        );
+        if let Some(filter) = param_filter {
+            Ok(query.with_param_filter(filter))
+        } else {
+            Ok(query)
+        }
     }
 }
 
+pub(crate) fn where_sql(
+    all_collections: &[SQLiteCollection],
+    collection_index: u16,
+    filter: Option<Filter>,
+) -> Result<(Option<String>, Vec<QueryParam>)> {
+    let (mut where_sql, filter_params) = if let Some(filter) = filter {
+        let (filter_sql, params) = filter_sql(collection_index, all_collections, filter)?;
+        (Some(filter_sql), params)
+    } else {
+        (None, vec![])
+    };
+    let not_expired_sql = all_collections
+        .get(collection_index as usize)
+        .and_then(|c| expiry_sql(c, false));
+    if let Some(not_expired_sql) = not_expired_sql {
+        where_sql = Some(match where_sql {
+            Some(where_sql) => format!("({}) AND {}", where_sql, not_expired_sql),
+            None => not_expired_sql,
+        });
+    }
+    Ok((where_sql, filter_params))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::core::cursor::IsarQueryCursor;
     use crate::core::data_type::DataType;
-    use crate::core::filter::{ConditionType::*, Filter::*, FilterCondition};
+    use crate::core::error::IsarError;
+    use crate::core::filter::{
+        ConditionType, ConditionType::*, Filter::*, FilterCondition, FilterElements, FilterGeo,
+        FilterJson, JsonCondition, ListQuantifier,
+    };
+    use crate::core::filter_regex::FilterRegex;
+    use crate::core::geo::GeoShape;
+    use crate::core::instance::{Aggregation, IsarInstance};
+    use crate::core::normalize::StringNormalization;
+    use crate::core::query_plan::QueryPlan;
+    use crate::core::schema::IndexSchema;
+    use crate::core::schema::{IsarSchema, PropertySchema};
+    use crate::core::test_instance::{insert_objects, open_instance};
     use crate::core::value::IsarValue;
+    use crate::core::writer::IsarWriter;
     use crate::sqlite::sqlite_collection::SQLiteProperty;
+    use crate::sqlite::sqlite_instance::SQLiteInstance;
+    use crate::sqlite::sqlite_query::SQLiteQuery;
+
+    fn schema() -> IsarSchema {
+        IsarSchema::new(
+            "TestCol",
+            None,
+            vec![PropertySchema::new("value", DataType::String, None)],
+            vec![IndexSchema::new("value", vec!["value"], false, false)],
+            false,
+        )
+    }
 
     fn debug_col() -> SQLiteCollection {
         SQLiteCollection::new(
@@ -125,8 +199,14 @@ mod test {
             vec![
                 SQLiteProperty::new("prop1", DataType::Long, None),
                 SQLiteProperty::new("prop2", DataType::String, None),
+                SQLiteProperty::new("prop3", DataType::LongList, None),
             ],
             vec![],
+            None,
+            None,
+            None,
+            false,
+            false,
         )
     }
 
@@ -134,31 +214,56 @@ mod test {
         let cols = vec![debug_col()];
         let mut qb = SQLiteQueryBuilder::new(&cols, 0);
         qb.set_filter(filter);
-        qb.build_query()
+        qb.build_query().unwrap()
     }
 
     #[test]
     fn test_build_empty() {
         let qb = SQLiteQueryBuilder::new(&[], 0);
-        let (sql, params) = qb.build_query();
+        let (sql, params) = qb.build_query().unwrap();
         assert_eq!(sql, "");
         assert_eq!(params.is_empty(), true);
     }
 
+    #[test]
+    fn test_build_hides_expired() {
+        let mut col = debug_col();
+        col.expiry_property = Some(1);
+        let cols = vec![col];
+
+        let qb = SQLiteQueryBuilder::new(&cols, 0);
+        let (sql, _) = qb.build_query().unwrap();
+        assert_eq!(sql, " WHERE (prop1 IS NULL OR prop1 > isar_now_micros())");
+
+        let mut qb = SQLiteQueryBuilder::new(&cols, 0);
+        qb.set_filter(Condition(FilterCondition::new(
+            2,
+            Equal,
+            vec![Some(IsarValue::String("a".to_string()))],
+            true,
+        )));
+        let (sql, params) = qb.build_query().unwrap();
+        assert_eq!(
+            sql,
+            " WHERE (prop2 = ?) AND (prop1 IS NULL OR prop1 > isar_now_micros())"
+        );
+        assert_eq!(params.len(), 1);
+    }
+
     #[test]
     fn test_build_single_sort() {
         let cols = vec![debug_col()];
 
         let mut qb = SQLiteQueryBuilder::new(&cols, 0);
         qb.add_sort(0, Sort::Asc, false);
-        let (sql, params) = qb.build_query();
-        assert_eq!(sql.trim(), "ORDER BY _rowid_ COLLATE BINARY");
+        let (sql, params) = qb.build_query().unwrap();
+        assert_eq!(sql.trim(), "ORDER BY _rowid_ COLLATE ISAR_FOLD");
         assert_eq!(params.is_empty(), true);
 
         let mut qb = SQLiteQueryBuilder::new(&cols, 0);
         qb.add_sort(2, Sort::Desc, true);
-        let (sql, params) = qb.build_query();
-        assert_eq!(sql.trim(), "ORDER BY prop2 COLLATE NOCASE DESC");
+        let (sql, params) = qb.build_query().unwrap();
+        assert_eq!(sql.trim(), "ORDER BY prop2 COLLATE BINARY DESC");
         assert_eq!(params.is_empty(), true);
     }
 
@@ -170,10 +275,10 @@ mod test {
         qb.add_sort(0, Sort::Asc, false);
         qb.add_sort(1, Sort::Desc, false);
         qb.add_sort(2, Sort::Asc, true);
-        let (sql, params) = qb.build_query();
+        let (sql, params) = qb.build_query().unwrap();
         assert_eq!(
             sql.trim(),
-            "ORDER BY _rowid_ COLLATE BINARY, prop1 COLLATE BINARY DESC, prop2 COLLATE NOCASE"
+            "ORDER BY _rowid_ COLLATE ISAR_FOLD, prop1 COLLATE ISAR_FOLD DESC, prop2 COLLATE BINARY"
         );
         assert_eq!(params.is_empty(), true);
     }
@@ -184,14 +289,29 @@ mod test {
 
         let mut qb = SQLiteQueryBuilder::new(&cols, 0);
         qb.add_distinct(0, false);
-        let (sql, params) = qb.build_query();
-        assert_eq!(sql.trim(), "GROUP BY _rowid_ COLLATE BINARY");
+        let (sql, params) = qb.build_query().unwrap();
+        assert_eq!(sql.trim(), "GROUP BY _rowid_ COLLATE ISAR_FOLD");
         assert_eq!(params.is_empty(), true);
 
         let mut qb = SQLiteQueryBuilder::new(&cols, 0);
         qb.add_distinct(2, true);
-        let (sql, params) = qb.build_query();
-        assert_eq!(sql.trim(), "GROUP BY prop2 COLLATE NOCASE");
+        let (sql, params) = qb.build_query().unwrap();
+        assert_eq!(sql.trim(), "GROUP BY prop2 COLLATE BINARY");
+        assert_eq!(params.is_empty(), true);
+    }
+
+    #[test]
+    fn test_build_sort_and_distinct() {
+        let cols = vec![debug_col()];
+
+        let mut qb = SQLiteQueryBuilder::new(&cols, 0);
+        qb.add_sort(1, Sort::Desc, false);
+        qb.add_distinct(2, true);
+        let (sql, params) = qb.build_query().unwrap();
+        assert_eq!(
+            sql.trim(),
+            "GROUP BY prop2 COLLATE BINARY ORDER BY prop1 COLLATE ISAR_FOLD DESC"
+        );
         assert_eq!(params.is_empty(), true);
     }
 
@@ -203,10 +323,10 @@ mod test {
         qb.add_distinct(0, false);
         qb.add_distinct(1, false);
         qb.add_distinct(2, true);
-        let (sql, params) = qb.build_query();
+        let (sql, params) = qb.build_query().unwrap();
         assert_eq!(
             sql.trim(),
-            "GROUP BY _rowid_ COLLATE BINARY, prop1 COLLATE BINARY, prop2 COLLATE NOCASE"
+            "GROUP BY _rowid_ COLLATE ISAR_FOLD, prop1 COLLATE ISAR_FOLD, prop2 COLLATE BINARY"
         );
         assert_eq!(params.is_empty(), true);
     }
@@ -236,10 +356,25 @@ mod test {
         let cond = FilterCondition::new(1, Equal, vec![Some(value.clone())], false);
 
         let (sql, params) = qb_filter(Condition(cond));
-        assert_eq!(sql.trim(), "WHERE prop1 = ? COLLATE NOCASE");
+        assert_eq!(sql.trim(), "WHERE prop1 = ? COLLATE ISAR_FOLD");
         assert_eq!(params, vec![QueryParam::Value(value)]);
     }
 
+    #[test]
+    fn test_filter_case_sensitive_field() {
+        let value = IsarValue::Integer(123);
+        let mut cond = FilterCondition::new(1, Equal, vec![Some(value.clone())], true);
+        cond.case_sensitive = false;
+
+        let (sql, _) = qb_filter(Condition(cond.clone()));
+        assert_eq!(sql.trim(), "WHERE prop1 = ? COLLATE ISAR_FOLD");
+
+        cond.set_normalization(StringNormalization::NONE);
+        assert!(cond.case_sensitive);
+        let (sql, _) = qb_filter(Condition(cond));
+        assert_eq!(sql.trim(), "WHERE prop1 = ?");
+    }
+
     #[test]
     fn test_filter_equal_null() {
         let cond = FilterCondition::new(1, Equal, vec![None], true);
@@ -265,7 +400,7 @@ mod test {
         let cond = FilterCondition::new(1, Greater, vec![Some(value.clone())], false);
 
         let (sql, params) = qb_filter(Condition(cond));
-        assert_eq!(sql.trim(), "WHERE prop1 > ? COLLATE NOCASE");
+        assert_eq!(sql.trim(), "WHERE prop1 > ? COLLATE ISAR_FOLD");
         assert_eq!(params, vec![QueryParam::Value(value)]);
     }
 
@@ -294,7 +429,7 @@ mod test {
         let cond = FilterCondition::new(1, GreaterOrEqual, vec![Some(value.clone())], false);
 
         let (sql, params) = qb_filter(Condition(cond));
-        assert_eq!(sql.trim(), "WHERE prop1 >= ? COLLATE NOCASE");
+        assert_eq!(sql.trim(), "WHERE prop1 >= ? COLLATE ISAR_FOLD");
         assert_eq!(params, vec![QueryParam::Value(value)]);
     }
 
@@ -325,7 +460,7 @@ mod test {
         let (sql, params) = qb_filter(Condition(cond));
         assert_eq!(
             sql.trim(),
-            "WHERE prop1 < ? COLLATE NOCASE OR prop1 IS NULL"
+            "WHERE prop1 < ? COLLATE ISAR_FOLD OR prop1 IS NULL"
         );
         assert_eq!(params, vec![QueryParam::Value(value)]);
     }
@@ -357,7 +492,7 @@ mod test {
         let (sql, params) = qb_filter(Condition(cond));
         assert_eq!(
             sql.trim(),
-            "WHERE prop1 <= ? COLLATE NOCASE OR prop1 IS NULL"
+            "WHERE prop1 <= ? COLLATE ISAR_FOLD OR prop1 IS NULL"
         );
         assert_eq!(params, vec![QueryParam::Value(value)]);
     }
@@ -488,6 +623,22 @@ mod test {
         assert_eq!(params.is_empty(), true);
     }
 
+    #[test]
+    fn test_filter_string_contains_case_insensitive() {
+        let value = IsarValue::String("ÄB%c".to_string());
+        let cond = FilterCondition::new(1, StringContains, vec![Some(value.clone())], false);
+
+        let (sql, params) = qb_filter(Condition(cond));
+        assert_eq!(
+            sql.trim(),
+            "WHERE isar_normalize(prop1, 1) LIKE ? ESCAPE '\\'"
+        );
+        assert_eq!(
+            params,
+            vec![QueryParam::Value(IsarValue::String("%äb\\%c%".to_string()))]
+        );
+    }
+
     #[test]
     fn test_filter_string_matches() {
         let value = IsarValue::String("a?b%c*".to_string());
@@ -511,6 +662,152 @@ mod test {
         assert_eq!(params.is_empty(), true);
     }
 
+    #[test]
+    fn test_filter_string_regex() {
+        let value = IsarValue::String("^a+b$".to_string());
+        let cond = FilterCondition::new(1, StringRegex, vec![Some(value.clone())], true);
+
+        let (sql, params) = qb_filter(Condition(cond));
+        assert_eq!(sql.trim(), "WHERE prop1 REGEXP ?");
+        let regex = FilterRegex::new("^a+b$", StringNormalization::NONE).unwrap();
+        assert_eq!(params, vec![QueryParam::Regex(regex)]);
+
+        let value = IsarValue::String("(a".to_string());
+        let cond = FilterCondition::new(1, StringRegex, vec![Some(value.clone())], true);
+        let json = JsonCondition::new(vec![], StringRegex, false, vec![Some(value)], true);
+        for filter in [Condition(cond), Json(FilterJson::new(1, json))] {
+            let cols = vec![debug_col()];
+            let mut qb = SQLiteQueryBuilder::new(&cols, 0);
+            qb.set_filter(filter);
+            assert!(matches!(
+                qb.build_query(),
+                Err(IsarError::IllegalArgument {})
+            ));
+        }
+    }
+
+    #[test]
+    fn test_filter_in() {
+        let values = vec![Some(IsarValue::Integer(1)), Some(IsarValue::Integer(2))];
+        let cond = FilterCondition::new(1, In, values, true);
+        let (sql, params) = qb_filter(Condition(cond));
+        assert_eq!(sql.trim(), "WHERE prop1 IN (?, ?)");
+        assert_eq!(
+            params,
+            vec![
+                QueryParam::Value(IsarValue::Integer(1)),
+                QueryParam::Value(IsarValue::Integer(2))
+            ]
+        );
+
+        let values = vec![Some(IsarValue::String("a".to_string())), None];
+        let cond = FilterCondition::new(2, In, values, false);
+        let (sql, params) = qb_filter(Condition(cond));
+        assert_eq!(
+            sql.trim(),
+            "WHERE (prop2 COLLATE ISAR_FOLD IN (?) OR prop2 IS NULL)"
+        );
+        assert_eq!(
+            params,
+            vec![QueryParam::Value(IsarValue::String("a".to_string()))]
+        );
+
+        let cond = FilterCondition::new(1, In, vec![None], true);
+        let (sql, _) = qb_filter(Condition(cond));
+        assert_eq!(sql.trim(), "WHERE prop1 IS NULL");
+
+        let cond = FilterCondition::new(1, In, vec![], true);
+        let (sql, _) = qb_filter(Condition(cond));
+        assert_eq!(sql.trim(), "WHERE FALSE");
+    }
+
+    #[test]
+    fn test_filter_geo() {
+        let shape = GeoShape::Radius {
+            latitude: 52.52,
+            longitude: 13.4,
+            meters: 1000.0,
+        };
+        let (sql, params) = qb_filter(Geo(FilterGeo::new(1, 2, shape.clone())));
+        assert_eq!(sql.trim(), "WHERE FALSE");
+        assert!(params.is_empty());
+
+        let mut cols = vec![SQLiteCollection::new(
+            "col".to_string(),
+            Some("id".to_string()),
+            vec![
+                SQLiteProperty::new("lat", DataType::Double, None),
+                SQLiteProperty::new("lng", DataType::Double, None),
+            ],
+            vec![],
+            None,
+            None,
+            None,
+            false,
+            false,
+        )];
+        let mut qb = SQLiteQueryBuilder::new(&cols, 0);
+        qb.set_filter(Geo(FilterGeo::new(1, 2, shape.clone())));
+        let (sql, params) = qb.build_query().unwrap();
+        assert_eq!(sql.trim(), "WHERE isar_geo_within(lat, lng, ?)");
+        assert_eq!(params, vec![QueryParam::Geo(shape.clone())]);
+
+        cols[0].indexes = vec![IndexSchema::new_geo("pos", "lat", "lng")];
+        let mut qb = SQLiteQueryBuilder::new(&cols, 0);
+        qb.set_filter(Geo(FilterGeo::new(1, 2, shape.clone())));
+        let (sql, params) = qb.build_query().unwrap();
+        let ranges = shape.geohash_ranges();
+        assert!(!ranges.is_empty());
+        assert!(sql.contains("isar_geohash(lat, lng) BETWEEN ? AND ?"));
+        assert!(sql.trim().ends_with(") AND isar_geo_within(lat, lng, ?)"));
+        assert_eq!(params.len(), ranges.len() * 2 + 1);
+        assert_eq!(
+            params[0],
+            QueryParam::Value(IsarValue::Integer(ranges[0].0 as i64))
+        );
+    }
+
+    #[test]
+    fn test_filter_list_length() {
+        let values = vec![Some(IsarValue::Integer(1)), None];
+        let cond = FilterCondition::new(3, ListLength, values, true);
+        let (sql, params) = qb_filter(Condition(cond));
+        assert_eq!(sql.trim(), "WHERE json_array_length(prop3) BETWEEN ? AND ?");
+        assert_eq!(
+            params,
+            vec![
+                QueryParam::Value(IsarValue::Integer(1)),
+                QueryParam::Value(IsarValue::Integer(i64::MAX))
+            ]
+        );
+
+        let cond = FilterCondition::new(3, ListIsEmpty, vec![], true);
+        let (sql, params) = qb_filter(Condition(cond));
+        assert_eq!(sql.trim(), "WHERE json_array_length(prop3) = 0");
+        assert_eq!(params.is_empty(), true);
+
+        let cond = FilterCondition::new(1, ListIsEmpty, vec![], true);
+        let (sql, _) = qb_filter(Condition(cond));
+        assert_eq!(sql.trim(), "WHERE FALSE");
+    }
+
+    #[test]
+    fn test_filter_list_elements() {
+        let value = Some(IsarValue::Integer(5));
+        let cond = FilterCondition::new(3, Greater, vec![value.clone()], true);
+        let elements = FilterElements::new(3, ListQuantifier::All, Condition(cond.clone()));
+        let (sql, params) = qb_filter(Elements(elements));
+        assert_eq!(sql.trim(), "WHERE isar_filter_json(prop3, ?)");
+        let json_condition = JsonCondition::new(vec![], Greater, true, vec![value], true)
+            .with_quantifier(ListQuantifier::All);
+        assert_eq!(params, vec![QueryParam::JsonCondition(json_condition)]);
+
+        let other = FilterCondition::new(1, IsNull, vec![], true);
+        let elements = FilterElements::new(3, ListQuantifier::All, Condition(other));
+        let (sql, _) = qb_filter(Elements(elements));
+        assert_eq!(sql.trim(), "WHERE FALSE");
+    }
+
     #[test]
     fn test_filter_and() {
         let cond1 = FilterCondition::new(1, IsNull, vec![], true);
@@ -566,4 +863,183 @@ mod test {
         );
         assert_eq!(params.is_empty(), true);
     }
+
+    #[test]
+    fn normalized_indexes_serve_conditions_with_the_same_normalization() {
+        let schema = IsarSchema::new(
+            "TestCol",
+            Some("id"),
+            vec![PropertySchema::new("value", DataType::String, None)],
+            vec![IndexSchema::new("value", vec!["value"], false, false)
+                .with_normalization(StringNormalization::CASE_INSENSITIVE)],
+            false,
+        );
+        let instance = open_instance::<SQLiteInstance>(
+            "normalized_indexes_serve_conditions_with_the_same_normalization",
+            vec![schema],
+        );
+        let values = ["Ärger", "ärger", "arger"];
+        insert_objects(&instance, 0, &[1, 2, 3], |insert, id| {
+            insert.write_string(1, values[id as usize - 1]);
+        });
+
+        let txn = instance.begin_txn(false).unwrap();
+        // the normalization of the index is read back from its SQL
+        assert_eq!(instance.verify(&txn), Ok(()));
+        let query = |normalization| {
+            let mut qb = instance.query(0).unwrap();
+            qb.set_filter(Filter::Condition(FilterCondition::new_normalized(
+                1,
+                ConditionType::Equal,
+                vec![Some(IsarValue::String("ÄRGER".to_string()))],
+                normalization,
+            )));
+            qb.build().unwrap()
+        };
+        let uses_index = |query: &SQLiteQuery| {
+            let QueryPlan::SQLite(plan) = instance.explain(&txn, query).unwrap() else {
+                panic!("expected sqlite plan");
+            };
+            plan.rows.iter().any(|r| r.detail.contains("INDEX"))
+        };
+
+        let folded = query(StringNormalization::CASE_INSENSITIVE);
+        assert!(uses_index(&folded));
+        assert_eq!(
+            instance.query_aggregate(&txn, &folded, Aggregation::Count, None),
+            Ok(Some(IsarValue::Integer(2)))
+        );
+        let exact = query(StringNormalization::NONE);
+        assert!(!uses_index(&exact));
+        assert_eq!(
+            instance.query_aggregate(&txn, &exact, Aggregation::Count, None),
+            Ok(Some(IsarValue::Integer(0)))
+        );
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn query_filters_with_unicode_normalization() {
+        let instance = open_instance::<SQLiteInstance>(
+            "query_filters_with_unicode_normalization",
+            vec![schema()],
+        );
+        let values = ["Ärger", "ärger", "arger", "Straße"];
+        insert_objects(&instance, 0, &[1, 2, 3, 4], |insert, id| {
+            insert.write_string(1, values[id as usize - 1]);
+        });
+        let txn = instance.begin_txn(false).unwrap();
+        let count = |condition_type: ConditionType, value: &str, normalization| {
+            let mut qb = instance.query(0).unwrap();
+            qb.set_filter(Filter::Condition(FilterCondition::new_normalized(
+                1,
+                condition_type,
+                vec![Some(IsarValue::String(value.to_string()))],
+                normalization,
+            )));
+            let query = qb.build().unwrap();
+            instance
+                .query_aggregate(&txn, &query, Aggregation::Count, None)
+                .unwrap()
+        };
+        let fold_no_mark = StringNormalization::from_flags(
+            StringNormalization::CASE_FOLD | StringNormalization::STRIP_DIACRITICS,
+        );
+
+        let result = count(ConditionType::Equal, "ÄRGER", StringNormalization::NONE);
+        assert_eq!(result, Some(IsarValue::Integer(0)));
+        let result = count(
+            ConditionType::Equal,
+            "ÄRGER",
+            StringNormalization::CASE_INSENSITIVE,
+        );
+        assert_eq!(result, Some(IsarValue::Integer(2)));
+        let result = count(ConditionType::Equal, "ARGER", fold_no_mark);
+        assert_eq!(result, Some(IsarValue::Integer(3)));
+        let result = count(
+            ConditionType::StringContains,
+            "ÄR",
+            StringNormalization::CASE_INSENSITIVE,
+        );
+        assert_eq!(result, Some(IsarValue::Integer(2)));
+        let result = count(
+            ConditionType::StringStartsWith,
+            "STRASS",
+            StringNormalization::CASE_INSENSITIVE,
+        );
+        assert_eq!(result, Some(IsarValue::Integer(1)));
+
+        let mut qb = instance.query(0).unwrap();
+        qb.add_distinct(1, false);
+        let query = qb.build().unwrap();
+        let mut distinct = 0;
+        {
+            let mut cursor = instance.query_cursor(&txn, &query, None, None).unwrap();
+            while cursor.next().is_some() {
+                distinct += 1;
+            }
+        }
+        assert_eq!(distinct, 3);
+
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
+
+    #[test]
+    fn query_filters_with_in() {
+        let instance = open_instance::<SQLiteInstance>("query_filters_with_in", vec![schema()]);
+        let values = [Some("a"), Some("B"), Some("c"), None];
+        insert_objects(&instance, 0, &[1, 2, 3, 4], |insert, id| {
+            match values[id as usize - 1] {
+                Some(value) => insert.write_string(1, value),
+                None => insert.write_null(1),
+            }
+        });
+        let txn = instance.begin_txn(false).unwrap();
+        let build = |values: Vec<Option<&str>>, case_sensitive: bool| {
+            let values = values
+                .into_iter()
+                .map(|v| v.map(|v| IsarValue::String(v.to_string())))
+                .collect();
+            let mut qb = instance.query(0).unwrap();
+            qb.set_filter(Filter::Condition(FilterCondition::new(
+                1,
+                ConditionType::In,
+                values,
+                case_sensitive,
+            )));
+            qb.build().unwrap()
+        };
+        let count = |values: Vec<Option<&str>>, case_sensitive: bool| {
+            let query = build(values, case_sensitive);
+            instance
+                .query_aggregate(&txn, &query, Aggregation::Count, None)
+                .unwrap()
+        };
+
+        assert_eq!(
+            count(vec![Some("a"), Some("b"), Some("x")], true),
+            Some(IsarValue::Integer(1))
+        );
+        assert_eq!(
+            count(vec![Some("a"), Some("b"), Some("x")], false),
+            Some(IsarValue::Integer(2))
+        );
+        assert_eq!(
+            count(vec![Some("c"), None], true),
+            Some(IsarValue::Integer(2))
+        );
+        assert_eq!(count(vec![None], true), Some(IsarValue::Integer(1)));
+        assert_eq!(count(vec![], true), Some(IsarValue::Integer(0)));
+
+        let query = build(vec![Some("a"), Some("c")], true);
+        let QueryPlan::SQLite(plan) = instance.explain(&txn, &query).unwrap() else {
+            panic!("expected sqlite plan");
+        };
+        assert!(plan.rows.iter().any(|r| r.detail.contains("INDEX")));
+
+        instance.abort_txn(txn);
+        SQLiteInstance::close(instance, true);
+    }
 }
@@ -1,5 +1,6 @@
 use super::sqlite3::SQLiteStatement;
 use super::sqlite_collection::SQLiteCollection;
+use crate::core::uuid::{parse_uuid, NULL_UUID};
 use crate::core::{data_type::DataType, reader::IsarReader};
 use base64::{engine::general_purpose, Engine as _};
 use serde_json::{Map, Value};
@@ -107,6 +108,10 @@ impl<'a> IsarReader for SQLiteReader<'a> {
         }
     }
 
+    fn read_uuid(&self, index: u32) -> Option<[u8; 16]> {
+        parse_uuid(self.read_string(index)?).filter(|uuid| uuid != &NULL_UUID)
+    }
+
     fn read_blob(&self, index: u32) -> Option<Cow<'_, [u8]>> {
         if self.is_null(index) {
             None
@@ -157,6 +162,20 @@ pub struct SQLiteObjectReader<'a> {
     all_collections: &'a [SQLiteCollection],
 }
 
+impl<'a> SQLiteObjectReader<'a> {
+    pub(crate) fn new(
+        object: &'a Map<String, Value>,
+        collection: &'a SQLiteCollection,
+        all_collections: &'a [SQLiteCollection],
+    ) -> Self {
+        Self {
+            object: Cow::Borrowed(object),
+            collection,
+            all_collections,
+        }
+    }
+}
+
 impl<'a> IsarReader for SQLiteObjectReader<'a> {
     type ObjectReader<'b>
         = SQLiteObjectReader<'b>
@@ -251,6 +270,10 @@ impl<'a> IsarReader for SQLiteObjectReader<'a> {
         None
     }
 
+    fn read_uuid(&self, index: u32) -> Option<[u8; 16]> {
+        parse_uuid(self.read_string(index)?).filter(|uuid| uuid != &NULL_UUID)
+    }
+
     fn read_blob(&self, index: u32) -> Option<Cow<'a, [u8]>> {
         if let Some(property) = self.collection.get_property(index as u16) {
             if let Some(Value::String(val)) = self.object.get(&property.name) {
@@ -379,6 +402,10 @@ impl<'a> IsarReader for SQLiteListReader<'a> {
         }
     }
 
+    fn read_uuid(&self, index: u32) -> Option<[u8; 16]> {
+        parse_uuid(self.read_string(index)?).filter(|uuid| uuid != &NULL_UUID)
+    }
+
     fn read_blob(&self, _index: u32) -> Option<Cow<'a, [u8]>> {
         None
     }
@@ -442,7 +469,7 @@ impl<'a> SQLiteReader<'a> {
                         serde_json::Value::Null
                     }
                 }
-                DataType::String => {
+                DataType::String | DataType::Uuid => {
                     if let Some(s) = self.read_string(index) {
                         serde_json::Value::String(s.to_string())
                     } else {
@@ -1,5 +1,8 @@
+use super::sql::{
+    sql_data_type, timestamps_table_name, tombstones_table_name, EMBEDDED_SCHEMAS_TABLE,
+};
+use super::sqlite3::SQLite3;
 use super::sqlite_collection::{SQLiteCollection, SQLiteProperty};
-use super::{sql::sql_data_type, sqlite3::SQLite3};
 use crate::core::error::{IsarError, Result};
 
 pub(crate) fn verify_sqlite(sqlite: &SQLite3, cols: &[SQLiteCollection]) -> Result<()> {
@@ -11,6 +14,17 @@ pub(crate) fn verify_sqlite(sqlite: &SQLite3, cols: &[SQLiteCollection]) -> Resu
         }
     }
     let mut actual_table_names = sqlite.get_table_names()?;
+    actual_table_names.retain(|t| t != EMBEDDED_SCHEMAS_TABLE);
+    for col in cols {
+        if col.tombstones {
+            let tombstones = tombstones_table_name(&col.name);
+            actual_table_names.retain(|t| t != &tombstones);
+        }
+        if col.field_timestamps {
+            let timestamps = timestamps_table_name(&col.name);
+            actual_table_names.retain(|t| t != &timestamps);
+        }
+    }
 
     table_names.sort();
     actual_table_names.sort();
@@ -61,10 +75,14 @@ pub(crate) fn verify_sqlite(sqlite: &SQLite3, cols: &[SQLiteCollection]) -> Resu
             return Err(IsarError::DbCorrupted {});
         }
 
-        for (index, unique, cols) in indexes {
+        for (index, unique, cols, geo, normalization) in indexes {
             let name = index.strip_prefix(&format!("{}_", table));
             let index = collection.indexes.iter().find(|i| {
-                Some(i.name.as_str()) == name && i.unique == unique && i.properties == cols
+                Some(i.name.as_str()) == name
+                    && i.unique == unique
+                    && i.properties == cols
+                    && i.geo == geo
+                    && (i.geo || i.normalization == normalization)
             });
 
             if index.is_none() {
@@ -1,12 +1,19 @@
 use super::sqlite_collection::SQLiteCollection;
 use super::sqlite_insert::SQLiteInsert;
-use crate::core::{data_type::DataType, writer::IsarWriter};
+use crate::core::data_type::DataType;
+use crate::core::error::{IsarError, Result};
+use crate::core::uuid::{format_uuid, NULL_UUID};
+use crate::core::value::IsarValue;
+use crate::core::writer::{write_value, IsarWriter};
 use base64::{engine::general_purpose, Engine};
 use serde_json::{Map, Number, Value};
 use std::iter::empty;
 
 impl<'a> SQLiteInsert<'a> {
-    fn property_index(&self, index: u32) -> u32 {
+    pub(crate) fn property_index(&mut self, index: u32) -> u32 {
+        if let Some(written) = self.written.get_mut(index as usize) {
+            *written = true;
+        }
         (self.batch_size - self.batch_remaining) * (self.collection.properties.len() as u32 + 1)
             + index
     }
@@ -29,6 +36,11 @@ impl<'a> IsarWriter<'a> for SQLiteInsert<'a> {
     }
 
     fn write_null(&mut self, index: u32) {
+        if self.collection.key_property == Some(index as u16) {
+            self.key = None;
+        } else if self.collection.version_property == Some(index as u16) {
+            self.version = None;
+        }
         let col = self.property_index(index);
         let _ = self.with_stmt(|stmt| stmt.bind_null(col));
     }
@@ -66,6 +78,9 @@ impl<'a> IsarWriter<'a> for SQLiteInsert<'a> {
     }
 
     fn write_long(&mut self, index: u32, value: i64) {
+        if self.collection.version_property == Some(index as u16) {
+            self.version = Some(value).filter(|v| *v != i64::MIN);
+        }
         let col = self.property_index(index);
         let _ = self.with_stmt(|stmt| {
             if value != i64::MIN {
@@ -88,10 +103,21 @@ impl<'a> IsarWriter<'a> for SQLiteInsert<'a> {
     }
 
     fn write_string(&mut self, index: u32, value: &str) {
+        if self.collection.key_property == Some(index as u16) {
+            self.key = Some(value.to_string());
+        }
         let col = self.property_index(index);
         let _ = self.with_stmt(|stmt| stmt.bind_text(col, value));
     }
 
+    fn write_uuid(&mut self, index: u32, value: &[u8; 16]) {
+        if value == &NULL_UUID {
+            self.write_null(index);
+        } else {
+            self.write_string(index, &format_uuid(value));
+        }
+    }
+
     fn write_byte_list(&mut self, index: u32, value: &[u8]) {
         let col = self.property_index(index);
         let _ = self.with_stmt(|stmt| stmt.bind_blob(col, value));
@@ -139,15 +165,15 @@ impl<'a> IsarWriter<'a> for SQLiteInsert<'a> {
 
 pub struct SQLiteObjectWriter<'a> {
     collection: &'a SQLiteCollection,
-    all_collections: &'a Vec<SQLiteCollection>,
+    all_collections: &'a [SQLiteCollection],
     property_index: u16,
-    map: Map<String, Value>,
+    pub(crate) map: Map<String, Value>,
 }
 
 impl<'a> SQLiteObjectWriter<'a> {
-    fn new(
+    pub(crate) fn new(
         collection: &'a SQLiteCollection,
-        all_collections: &'a Vec<SQLiteCollection>,
+        all_collections: &'a [SQLiteCollection],
         property_index: u16,
     ) -> Self {
         Self {
@@ -157,6 +183,26 @@ impl<'a> SQLiteObjectWriter<'a> {
             map: Map::new(),
         }
     }
+
+    /// Encodes `value` the way it is stored in the column of the property.
+    pub(crate) fn encode_property(
+        collection: &'a SQLiteCollection,
+        all_collections: &'a [SQLiteCollection],
+        property_index: u16,
+        value: &IsarValue,
+    ) -> Result<Option<Value>> {
+        let property = collection
+            .get_property(property_index)
+            .ok_or(IsarError::IllegalArgument {})?;
+        let mut writer = Self::new(collection, all_collections, 0);
+        write_value(
+            &mut writer,
+            property_index as u32,
+            property.data_type,
+            Some(value),
+        )?;
+        Ok(writer.map.remove(&property.name))
+    }
 }
 
 impl<'a> IsarWriter<'a> for SQLiteObjectWriter<'a> {
@@ -239,6 +285,14 @@ impl<'a> IsarWriter<'a> for SQLiteObjectWriter<'a> {
         }
     }
 
+    fn write_uuid(&mut self, index: u32, value: &[u8; 16]) {
+        if value == &NULL_UUID {
+            self.write_null(index);
+        } else {
+            self.write_string(index, &format_uuid(value));
+        }
+    }
+
     fn write_byte_list(&mut self, index: u32, value: &[u8]) {
         let mut string = String::new();
         let _ = general_purpose::STANDARD_NO_PAD.encode_string(value, &mut string);
@@ -288,7 +342,7 @@ impl<'a> IsarWriter<'a> for SQLiteObjectWriter<'a> {
 
 pub struct SQLiteListWriter<'a> {
     collection_index: Option<u16>,
-    all_collections: &'a Vec<SQLiteCollection>,
+    all_collections: &'a [SQLiteCollection],
     property_index: u16,
     list: Vec<Value>,
 }
@@ -296,7 +350,7 @@ pub struct SQLiteListWriter<'a> {
 impl<'a> SQLiteListWriter<'a> {
     fn new(
         collection_index: Option<u16>,
-        all_collections: &'a Vec<SQLiteCollection>,
+        all_collections: &'a [SQLiteCollection],
         property_index: u16,
         length: u32,
     ) -> Self {
@@ -398,6 +452,14 @@ impl<'a> IsarWriter<'a> for SQLiteListWriter<'a> {
         }
     }
 
+    fn write_uuid(&mut self, index: u32, value: &[u8; 16]) {
+        if value == &NULL_UUID {
+            self.write_null(index);
+        } else {
+            self.write_string(index, &format_uuid(value));
+        }
+    }
+
     fn write_byte_list(&mut self, _index: u32, _value: &[u8]) {}
 
     fn begin_object(&mut self, index: u32) -> Option<Self::ObjectWriter> {
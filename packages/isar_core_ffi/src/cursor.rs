@@ -59,6 +59,34 @@ pub unsafe extern "C" fn isar_plus_query_cursor_next(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_cursor_continuation(
+    cursor: &'static CIsarQueryCursor,
+    buffer: *mut *mut u8,
+    buffer_size: *mut u32,
+) -> u32 {
+    let continuation = match cursor {
+        #[cfg(feature = "native")]
+        CIsarQueryCursor::Native(cursor) => cursor.continuation(),
+        #[cfg(feature = "sqlite")]
+        CIsarQueryCursor::SQLite(cursor) => cursor.continuation(),
+    };
+
+    let mut new_buffer = if (*buffer).is_null() {
+        vec![]
+    } else {
+        Vec::from_raw_parts(*buffer, 0, *buffer_size as usize)
+    };
+    if let Some(continuation) = continuation {
+        new_buffer.extend_from_slice(continuation.to_token().as_bytes());
+    }
+
+    let len = new_buffer.len();
+    *buffer_size = new_buffer.capacity() as u32;
+    *buffer = new_buffer.leak().as_mut_ptr();
+    len as u32
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_query_cursor_free(
     cursor: *mut CIsarQueryCursor,
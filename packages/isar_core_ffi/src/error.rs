@@ -38,6 +38,7 @@ pub const fn error_code(err: &IsarError) -> Option<u8> {
         IsarError::InstanceMismatch {} => ERROR_INSTANCE_MISMATCH,
         IsarError::EncryptionError {} => ERROR_ENCRYPTION,
         IsarError::DbFull {} => ERROR_DB_FULL,
+        IsarError::VersionConflict {} => ERROR_VERSION_CONFLICT,
         _ => return None,
     };
     Some(code)
@@ -50,6 +51,7 @@ pub const ERROR_OBJECT_LIMIT_REACHED: u8 = 4;
 pub const ERROR_INSTANCE_MISMATCH: u8 = 5;
 pub const ERROR_ENCRYPTION: u8 = 6;
 pub const ERROR_DB_FULL: u8 = 7;
+pub const ERROR_VERSION_CONFLICT: u8 = 8;
 
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_get_error(value: *mut *const u8) -> u32 {
@@ -1,6 +1,13 @@
+use crate::{isar_to_i64, IsarI64};
 use core::slice;
 use isar_core::core::{
-    filter::{ConditionType, Filter, FilterCondition, FilterNested},
+    error::IsarError,
+    filter::{
+        ConditionType, Filter, FilterCondition, FilterElements, FilterGeo, FilterNested,
+        FilterParam, ListQuantifier,
+    },
+    geo::GeoShape,
+    normalize::StringNormalization,
     value::IsarValue,
 };
 use std::vec;
@@ -128,6 +135,41 @@ pub unsafe extern "C" fn isar_plus_filter_between(
     Box::into_raw(Box::new(Filter::Condition(filter)))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_in(
+    property_index: u16,
+    values: *mut *mut IsarValue,
+    length: u32,
+    case_sensitive: bool,
+) -> *const Filter {
+    let values = slice::from_raw_parts(values, length as usize)
+        .iter()
+        .map(|value| {
+            if value.is_null() {
+                None
+            } else {
+                Some(*Box::from_raw(*value))
+            }
+        })
+        .collect();
+    let filter = FilterCondition::new(property_index, ConditionType::In, values, case_sensitive);
+    Box::into_raw(Box::new(Filter::Condition(filter)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_in_integers(
+    property_index: u16,
+    values: *const IsarI64,
+    length: u32,
+) -> *const Filter {
+    let values = slice::from_raw_parts(values, length as usize)
+        .iter()
+        .map(|value| Some(IsarValue::Integer(isar_to_i64(*value))))
+        .collect();
+    let filter = FilterCondition::new(property_index, ConditionType::In, values, true);
+    Box::into_raw(Box::new(Filter::Condition(filter)))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_filter_string_starts_with(
     property_index: u16,
@@ -192,6 +234,34 @@ pub unsafe extern "C" fn isar_plus_filter_string_matches(
     Box::into_raw(Box::new(filter))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_string_regex(
+    property_index: u16,
+    value: *mut IsarValue,
+    case_sensitive: bool,
+) -> *const Filter {
+    let value = *Box::from_raw(value);
+    let filter = Filter::Condition(FilterCondition::new(
+        property_index,
+        ConditionType::StringRegex,
+        vec![Some(value)],
+        case_sensitive,
+    ));
+    Box::into_raw(Box::new(filter))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_set_normalization(
+    filter: *mut Filter,
+    normalization: u8,
+) -> *const Filter {
+    let mut filter = *Box::from_raw(filter);
+    if let Filter::Condition(condition) = &mut filter {
+        condition.set_normalization(StringNormalization::from_flags(normalization));
+    }
+    Box::into_raw(Box::new(filter))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_filter_nested(
     property_index: u16,
@@ -201,6 +271,120 @@ pub unsafe extern "C" fn isar_plus_filter_nested(
     Box::into_raw(Box::new(filter))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_list_length(
+    property_index: u16,
+    lower: u32,
+    upper: u32,
+) -> *const Filter {
+    let filter = Filter::Condition(FilterCondition::new(
+        property_index,
+        ConditionType::ListLength,
+        vec![
+            Some(IsarValue::Integer(lower as i64)),
+            Some(IsarValue::Integer(upper as i64)),
+        ],
+        false,
+    ));
+    Box::into_raw(Box::new(filter))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_list_is_empty(property_index: u16) -> *const Filter {
+    let filter = FilterCondition::new(property_index, ConditionType::ListIsEmpty, vec![], false);
+    Box::into_raw(Box::new(Filter::Condition(filter)))
+}
+
+unsafe fn filter_elements(
+    property_index: u16,
+    quantifier: ListQuantifier,
+    filter: *mut Filter,
+) -> *const Filter {
+    let filter = Filter::Elements(FilterElements::new(
+        property_index,
+        quantifier,
+        *Box::from_raw(filter),
+    ));
+    Box::into_raw(Box::new(filter))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_any_elements(
+    property_index: u16,
+    filter: *mut Filter,
+) -> *const Filter {
+    filter_elements(property_index, ListQuantifier::Any, filter)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_all_elements(
+    property_index: u16,
+    filter: *mut Filter,
+) -> *const Filter {
+    filter_elements(property_index, ListQuantifier::All, filter)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_no_elements(
+    property_index: u16,
+    filter: *mut Filter,
+) -> *const Filter {
+    filter_elements(property_index, ListQuantifier::None, filter)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_geo_bounding_box(
+    latitude_index: u16,
+    longitude_index: u16,
+    min_latitude: f64,
+    min_longitude: f64,
+    max_latitude: f64,
+    max_longitude: f64,
+) -> *const Filter {
+    let shape = GeoShape::BoundingBox {
+        min_latitude,
+        min_longitude,
+        max_latitude,
+        max_longitude,
+    };
+    let filter = Filter::Geo(FilterGeo::new(latitude_index, longitude_index, shape));
+    Box::into_raw(Box::new(filter))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_geo_radius(
+    latitude_index: u16,
+    longitude_index: u16,
+    latitude: f64,
+    longitude: f64,
+    meters: f64,
+) -> *const Filter {
+    let shape = GeoShape::Radius {
+        latitude,
+        longitude,
+        meters,
+    };
+    let filter = Filter::Geo(FilterGeo::new(latitude_index, longitude_index, shape));
+    Box::into_raw(Box::new(filter))
+}
+
+/// `points` contains `length` latitude / longitude pairs.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_geo_polygon(
+    latitude_index: u16,
+    longitude_index: u16,
+    points: *const f64,
+    length: u32,
+) -> *const Filter {
+    let vertices = slice::from_raw_parts(points, length as usize * 2)
+        .chunks_exact(2)
+        .map(|point| (point[0], point[1]))
+        .collect();
+    let shape = GeoShape::Polygon(vertices);
+    let filter = Filter::Geo(FilterGeo::new(latitude_index, longitude_index, shape));
+    Box::into_raw(Box::new(filter))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_filter_and(filters: *mut *mut Filter, lenght: u32) -> *const Filter {
     let filters = slice::from_raw_parts(filters, lenght as usize)
@@ -226,3 +410,32 @@ pub unsafe extern "C" fn isar_plus_filter_not(filter: *mut Filter) -> *const Fil
     let filter = Filter::Not(Box::from_raw(filter));
     Box::into_raw(Box::new(filter))
 }
+
+/// Turns a condition into a parameter condition whose values are bound when
+/// the query is executed. The condition is consumed. Fails if the filter is
+/// not a condition or if the number of parameters does not match its values.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_filter_with_params(
+    filter: *mut Filter,
+    params: *const u16,
+    length: u32,
+    param_filter: *mut *const Filter,
+) -> u8 {
+    let filter = *Box::from_raw(filter);
+    let params = slice::from_raw_parts(params, length as usize).to_vec();
+    isar_try! {
+        let Filter::Condition(condition) = filter else {
+            return Err(IsarError::IllegalArgument {});
+        };
+        if condition.values.len() != params.len() {
+            return Err(IsarError::IllegalArgument {});
+        }
+        let filter = Filter::Param(FilterParam::new(
+            condition.property_index,
+            condition.condition_type,
+            params,
+            condition.string_normalization(),
+        ));
+        *param_filter = Box::into_raw(Box::new(filter));
+    }
+}
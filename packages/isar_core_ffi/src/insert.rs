@@ -1,6 +1,6 @@
 #![allow(unreachable_patterns)]
 
-use crate::{isar_to_i64, CIsarInstance, CIsarTxn, CIsarWriter, IsarI64};
+use crate::{i64_to_isar, isar_to_i64, CIsarInstance, CIsarTxn, CIsarWriter, IsarI64};
 use isar_core::core::error::IsarError;
 use isar_core::core::insert::IsarInsert;
 use isar_core::core::instance::IsarInstance;
@@ -46,6 +46,53 @@ pub unsafe extern "C" fn isar_plus_insert_save(insert: &mut CIsarWriter<'static>
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_insert_save_merge(
+    insert: &mut CIsarWriter<'static>,
+    id: IsarI64,
+) -> u8 {
+    let id = isar_to_i64(id);
+    isar_try! {
+        match insert {
+            #[cfg(feature = "native")]
+            CIsarWriter::Native(insert) => insert.save_merge(id)?,
+            #[cfg(feature = "sqlite")]
+            CIsarWriter::SQLite(insert) => insert.save_merge(id)?,
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_insert_keep_versions(insert: &mut CIsarWriter<'static>) -> u8 {
+    isar_try! {
+        match insert {
+            #[cfg(feature = "native")]
+            CIsarWriter::Native(insert) => insert.keep_versions(),
+            #[cfg(feature = "sqlite")]
+            CIsarWriter::SQLite(insert) => insert.keep_versions(),
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_insert_save_by_key(
+    insert: &mut CIsarWriter<'static>,
+    id: *mut IsarI64,
+) -> u8 {
+    isar_try! {
+        let new_id = match insert {
+            #[cfg(feature = "native")]
+            CIsarWriter::Native(insert) => insert.save_by_key()?,
+            #[cfg(feature = "sqlite")]
+            CIsarWriter::SQLite(insert) => insert.save_by_key()?,
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        *id = i64_to_isar(new_id);
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_insert_finish(
     insert: *mut CIsarWriter,
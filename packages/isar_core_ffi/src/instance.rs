@@ -3,6 +3,7 @@
 use crate::{
     dart_fast_hash, i64_to_isar, isar_to_i64, CIsarCursor, CIsarInstance, CIsarTxn, IsarI64,
 };
+use isar_core::core::convert::{convert, CONVERT_BATCH_SIZE};
 use isar_core::core::error::IsarError;
 use isar_core::core::instance::{CompactCondition, IsarInstance};
 use isar_core::core::schema::IsarSchema;
@@ -269,6 +270,35 @@ pub unsafe extern "C" fn isar_plus_delete(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_get_id_by_key(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    collection_index: u16,
+    key: *mut String,
+    id: *mut IsarI64,
+    found: *mut bool,
+) -> u8 {
+    let key = *Box::from_raw(key);
+    isar_try! {
+        let key_id = match (isar, txn) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn)) => {
+                isar.get_id_by_key(txn, collection_index, &key)?
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn)) => {
+                isar.get_id_by_key(txn, collection_index, &key)?
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        *found = key_id.is_some();
+        if let Some(key_id) = key_id {
+            *id = i64_to_isar(key_id);
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_count(
     isar: &'static CIsarInstance,
@@ -292,6 +322,91 @@ pub unsafe extern "C" fn isar_plus_count(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_purge_expired(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    collection_index: u16,
+    limit: u32,
+    count: *mut u32,
+) -> u8 {
+    let limit = if limit == 0 { None } else { Some(limit) };
+    isar_try! {
+        let purged = match (isar, txn) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn)) => {
+                isar.purge_expired(txn, collection_index, limit)?
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn)) => {
+                isar.purge_expired(txn, collection_index, limit)?
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        *count = purged;
+    }
+}
+
+/// Writes the tombstones as JSON array of `{id, deletedAt, sequence}` objects.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_get_tombstones(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    collection_index: u16,
+    after_sequence: IsarI64,
+    limit: u32,
+    buffer: *mut *mut u8,
+    buffer_size: *mut u32,
+    length: *mut u32,
+) -> u8 {
+    let after_sequence = isar_to_i64(after_sequence);
+    let limit = if limit == 0 { None } else { Some(limit) };
+    isar_try! {
+        let tombstones = match (isar, txn) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn)) => {
+                isar.get_tombstones(txn, collection_index, after_sequence, limit)?
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn)) => {
+                isar.get_tombstones(txn, collection_index, after_sequence, limit)?
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        let json = serde_json::to_vec(&tombstones).map_err(|e| IsarError::JsonError {
+            message: e.to_string(),
+        })?;
+        *length = json.len() as u32;
+        *buffer_size = json.capacity() as u32;
+        *buffer = json.leak().as_mut_ptr();
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_purge_tombstones(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    collection_index: u16,
+    deleted_before: IsarI64,
+    count: *mut u32,
+) -> u8 {
+    let deleted_before = isar_to_i64(deleted_before);
+    isar_try! {
+        let purged = match (isar, txn) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn)) => {
+                isar.purge_tombstones(txn, collection_index, deleted_before)?
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn)) => {
+                isar.purge_tombstones(txn, collection_index, deleted_before)?
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        *count = purged;
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_clear(
     isar: &'static CIsarInstance,
@@ -377,6 +492,43 @@ pub unsafe extern "C" fn isar_plus_copy(isar: &'static CIsarInstance, path: *mut
     }
 }
 
+/// Copies all objects of `src` to `dst`. Both instances may use either backend.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_convert(
+    src: &'static CIsarInstance,
+    dst: &'static CIsarInstance,
+    batch_size: u32,
+    count: *mut u32,
+) -> u8 {
+    let batch_size = if batch_size == 0 {
+        CONVERT_BATCH_SIZE
+    } else {
+        batch_size
+    };
+    isar_pause_isolate! {
+        isar_try! {
+            *count = match (src, dst) {
+                #[cfg(feature = "native")]
+                (CIsarInstance::Native(src), CIsarInstance::Native(dst)) => {
+                    convert(src.as_ref(), dst.as_ref(), batch_size)?
+                }
+                #[cfg(all(feature = "native", feature = "sqlite"))]
+                (CIsarInstance::Native(src), CIsarInstance::SQLite(dst)) => {
+                    convert(src.as_ref(), dst, batch_size)?
+                }
+                #[cfg(all(feature = "native", feature = "sqlite"))]
+                (CIsarInstance::SQLite(src), CIsarInstance::Native(dst)) => {
+                    convert(src, dst.as_ref(), batch_size)?
+                }
+                #[cfg(feature = "sqlite")]
+                (CIsarInstance::SQLite(src), CIsarInstance::SQLite(dst)) => {
+                    convert(src, dst, batch_size)?
+                }
+            };
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_verify(isar: &'static CIsarInstance, txn: &'static CIsarTxn) -> u8 {
     isar_try! {
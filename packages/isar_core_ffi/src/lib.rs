@@ -3,8 +3,9 @@
 use core::slice;
 use isar_core::core::cursor::IsarCursor;
 use isar_core::core::instance::IsarInstance;
+use isar_core::core::merge::ObjectChange;
 use isar_core::core::reader::IsarReader;
-use isar_core::core::value::IsarValue;
+use isar_core::core::update::UpdateOp;
 use isar_core::core::writer::IsarWriter;
 
 #[cfg(feature = "native")]
@@ -140,7 +141,9 @@ pub enum CIsarReader<'a> {
     SQLiteList(SListReader<'a>),
 }
 
-pub struct CIsarUpdate(pub(crate) Vec<(u16, Option<IsarValue>)>);
+pub struct CIsarUpdate(pub(crate) Vec<(u16, UpdateOp)>);
+
+pub struct CIsarMerge(pub(crate) Vec<ObjectChange>);
 
 pub enum CIsarQueryBuilder<'a> {
     #[cfg(feature = "native")]
@@ -1,12 +1,16 @@
 #![allow(unreachable_patterns)]
 
 use crate::{CIsarInstance, CIsarQuery, CIsarQueryBuilder, CIsarQueryCursor, CIsarTxn};
+use isar_core::core::continuation::QueryContinuation;
+use isar_core::core::cursor::IsarQueryCursor;
 use isar_core::core::error::IsarError;
 use isar_core::core::filter::Filter;
 use isar_core::core::instance::{Aggregation, IsarInstance};
 use isar_core::core::query_builder::{IsarQueryBuilder, Sort};
+use isar_core::core::query_description::QueryDescription;
 use isar_core::core::value::IsarValue;
-use std::ptr;
+use isar_core::core::vector::VectorMetric;
+use std::{ptr, slice};
 
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_query_new(
@@ -74,19 +78,64 @@ pub unsafe extern "C" fn isar_plus_query_add_distinct(
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn isar_plus_query_build(builder: *mut CIsarQueryBuilder) -> *mut CIsarQuery {
-    let builder = *Box::from_raw(builder);
+pub unsafe extern "C" fn isar_plus_query_add_property(
+    builder: &'static mut CIsarQueryBuilder,
+    property_index: u16,
+) {
     match builder {
         #[cfg(feature = "native")]
-        CIsarQueryBuilder::Native(builder) => {
-            let query = builder.build();
-            Box::into_raw(Box::new(CIsarQuery::Native(query)))
-        }
+        CIsarQueryBuilder::Native(builder) => builder.add_property(property_index),
         #[cfg(feature = "sqlite")]
-        CIsarQueryBuilder::SQLite(builder) => {
-            let query = builder.build();
-            Box::into_raw(Box::new(CIsarQuery::SQLite(query)))
-        }
+        CIsarQueryBuilder::SQLite(builder) => builder.add_property(property_index),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_build(
+    builder: *mut CIsarQueryBuilder,
+    query: *mut *const CIsarQuery,
+) -> u8 {
+    let builder = *Box::from_raw(builder);
+    isar_try! {
+        let new_query = match builder {
+            #[cfg(feature = "native")]
+            CIsarQueryBuilder::Native(builder) => CIsarQuery::Native(builder.build()?),
+            #[cfg(feature = "sqlite")]
+            CIsarQueryBuilder::SQLite(builder) => CIsarQuery::SQLite(builder.build()?),
+        };
+        *query = Box::into_raw(Box::new(new_query));
+    }
+}
+
+/// Builds a query from its JSON description. An offset or limit of 0 means
+/// that the description has none.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_from_json(
+    isar: &'static CIsarInstance,
+    collection_index: u16,
+    json: *mut String,
+    query: *mut *const CIsarQuery,
+    offset: *mut u32,
+    limit: *mut u32,
+) -> u8 {
+    let json = *Box::from_raw(json);
+    isar_try! {
+        let description = QueryDescription::from_json(&json)?;
+        let (new_query, query_offset, query_limit) = match isar {
+            #[cfg(feature = "native")]
+            CIsarInstance::Native(isar) => {
+                let (query, offset, limit) = description.build(isar.as_ref(), collection_index)?;
+                (CIsarQuery::Native(query), offset, limit)
+            }
+            #[cfg(feature = "sqlite")]
+            CIsarInstance::SQLite(isar) => {
+                let (query, offset, limit) = description.build(isar, collection_index)?;
+                (CIsarQuery::SQLite(query), offset, limit)
+            }
+        };
+        *query = Box::into_raw(Box::new(new_query));
+        *offset = query_offset.unwrap_or(0);
+        *limit = query_limit.unwrap_or(0);
     }
 }
 
@@ -120,12 +169,98 @@ pub unsafe extern "C" fn isar_plus_query_cursor(
     }
 }
 
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn isar_plus_query_cursor_with_params(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    query: &'static CIsarQuery,
+    cursor: *mut *const CIsarQueryCursor,
+    params: *mut *mut IsarValue,
+    length: u32,
+    offset: u32,
+    limit: u32,
+) -> u8 {
+    let offset = if offset == 0 { None } else { Some(offset) };
+    let limit = if limit == 0 { None } else { Some(limit) };
+    let params = slice::from_raw_parts(params, length as usize)
+        .iter()
+        .map(|value| {
+            if value.is_null() {
+                None
+            } else {
+                Some(*Box::from_raw(*value))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    isar_try! {
+        let new_cursor = match (isar, txn, query) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn), CIsarQuery::Native(query)) => {
+                let cursor = isar.query_cursor_with_params(txn, query, &params, offset, limit)?;
+                CIsarQueryCursor::Native(cursor)
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn), CIsarQuery::SQLite(query)) => {
+                let cursor = isar.query_cursor_with_params(txn, query, &params, offset, limit)?;
+                CIsarQueryCursor::SQLite(cursor)
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        *cursor = Box::into_raw(Box::new(new_cursor));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_cursor_after(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    query: &'static CIsarQuery,
+    cursor: *mut *const CIsarQueryCursor,
+    after: *mut String,
+    limit: u32,
+) -> u8 {
+    let limit = if limit == 0 { None } else { Some(limit) };
+    let after = if after.is_null() {
+        None
+    } else {
+        Some(*Box::from_raw(after))
+    };
+
+    isar_try! {
+        let after = if let Some(after) = after {
+            Some(QueryContinuation::from_token(&after)?)
+        } else {
+            None
+        };
+        let new_cursor = match (isar, txn, query) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn), CIsarQuery::Native(query)) => {
+                let cursor = isar.query_cursor_after(txn, query, after.as_ref(), limit)?;
+                CIsarQueryCursor::Native(cursor)
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn), CIsarQuery::SQLite(query)) => {
+                let cursor = isar.query_cursor_after(txn, query, after.as_ref(), limit)?;
+                CIsarQueryCursor::SQLite(cursor)
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        *cursor = Box::into_raw(Box::new(new_cursor));
+    }
+}
+
 pub const AGGREGATION_COUNT: u8 = 0;
 pub const AGGREGATION_IS_EMPTY: u8 = 1;
 pub const AGGREGATION_MIN: u8 = 2;
 pub const AGGREGATION_MAX: u8 = 3;
 pub const AGGREGATION_SUM: u8 = 4;
 pub const AGGREGATION_AVERAGE: u8 = 5;
+pub const AGGREGATION_COUNT_DISTINCT: u8 = 6;
+pub const AGGREGATION_MEDIAN: u8 = 7;
+pub const AGGREGATION_VARIANCE: u8 = 8;
+pub const AGGREGATION_STD_DEV: u8 = 9;
 
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_query_aggregate(
@@ -143,11 +278,39 @@ pub unsafe extern "C" fn isar_plus_query_aggregate(
         AGGREGATION_MAX => Aggregation::Max,
         AGGREGATION_SUM => Aggregation::Sum,
         AGGREGATION_AVERAGE => Aggregation::Average,
+        AGGREGATION_COUNT_DISTINCT => Aggregation::CountDistinct,
+        AGGREGATION_MEDIAN => Aggregation::Median,
+        AGGREGATION_VARIANCE => Aggregation::Variance,
+        AGGREGATION_STD_DEV => Aggregation::StdDev,
         _ => {
             *value = ptr::null();
             return 0;
         }
     };
+    query_aggregate(isar, txn, query, aggregation, property_index, value)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_aggregate_percentile(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    query: &'static CIsarQuery,
+    property_index: u16,
+    percentile: f64,
+    value: *mut *const IsarValue,
+) -> u8 {
+    let aggregation = Aggregation::Percentile(percentile.into());
+    query_aggregate(isar, txn, query, aggregation, property_index, value)
+}
+
+unsafe fn query_aggregate(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    query: &'static CIsarQuery,
+    aggregation: Aggregation,
+    property_index: u16,
+    value: *mut *const IsarValue,
+) -> u8 {
     isar_try! {
         let new_value = match (isar, txn, query) {
             #[cfg(feature = "native")]
@@ -168,6 +331,158 @@ pub unsafe extern "C" fn isar_plus_query_aggregate(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_facets(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    query: &'static CIsarQuery,
+    property_index: u16,
+    sort_by_count: bool,
+    limit: u32,
+    buffer: *mut *mut u8,
+    buffer_size: *mut u32,
+    length: *mut u32,
+) -> u8 {
+    let limit = if limit == 0 { None } else { Some(limit) };
+    isar_try! {
+        let facets = match (isar, txn, query) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn), CIsarQuery::Native(query)) => {
+                isar.query_facets(txn, query, property_index, sort_by_count, limit)?
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn), CIsarQuery::SQLite(query)) => {
+                isar.query_facets(txn, query, property_index, sort_by_count, limit)?
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        let json = serde_json::to_vec(&facets).map_err(|e| IsarError::JsonError {
+            message: e.to_string(),
+        })?;
+        *length = json.len() as u32;
+        *buffer_size = json.capacity() as u32;
+        *buffer = json.leak().as_mut_ptr();
+    }
+}
+
+pub const VECTOR_METRIC_COSINE: u8 = 0;
+pub const VECTOR_METRIC_DOT_PRODUCT: u8 = 1;
+pub const VECTOR_METRIC_L2: u8 = 2;
+
+/// Writes the ids and distances of the nearest objects as JSON array of
+/// `[id, distance]` pairs.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_nearest(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    query: &'static CIsarQuery,
+    property_index: u16,
+    vector: *const f64,
+    dimensions: u32,
+    metric: u8,
+    k: u32,
+    buffer: *mut *mut u8,
+    buffer_size: *mut u32,
+    length: *mut u32,
+) -> u8 {
+    let vector = if vector.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(vector, dimensions as usize)
+    };
+    isar_try! {
+        let metric = match metric {
+            VECTOR_METRIC_COSINE => VectorMetric::Cosine,
+            VECTOR_METRIC_DOT_PRODUCT => VectorMetric::DotProduct,
+            VECTOR_METRIC_L2 => VectorMetric::L2,
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        let neighbors = match (isar, txn, query) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn), CIsarQuery::Native(query)) => {
+                isar.query_nearest(txn, query, property_index, vector, metric, k)?
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn), CIsarQuery::SQLite(query)) => {
+                isar.query_nearest(txn, query, property_index, vector, metric, k)?
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        let json = serde_json::to_vec(&neighbors).map_err(|e| IsarError::JsonError {
+            message: e.to_string(),
+        })?;
+        *length = json.len() as u32;
+        *buffer_size = json.capacity() as u32;
+        *buffer = json.leak().as_mut_ptr();
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_explain(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    query: &'static CIsarQuery,
+    buffer: *mut *mut u8,
+    buffer_size: *mut u32,
+    length: *mut u32,
+) -> u8 {
+    isar_try! {
+        let plan = match (isar, txn, query) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn), CIsarQuery::Native(query)) => {
+                isar.explain(txn, query)?
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn), CIsarQuery::SQLite(query)) => {
+                isar.explain(txn, query)?
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        let json = serde_json::to_vec(&plan).map_err(|e| IsarError::JsonError {
+            message: e.to_string(),
+        })?;
+        *length = json.len() as u32;
+        *buffer_size = json.capacity() as u32;
+        *buffer = json.leak().as_mut_ptr();
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_query_values(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    query: &'static CIsarQuery,
+    property_index: u16,
+    offset: u32,
+    limit: u32,
+    buffer: *mut *mut u8,
+    buffer_size: *mut u32,
+    length: *mut u32,
+) -> u8 {
+    let offset = if offset == 0 { None } else { Some(offset) };
+    let limit = if limit == 0 { None } else { Some(limit) };
+    isar_try! {
+        let mut json = Vec::new();
+        let serializer = &mut serde_json::Serializer::new(&mut json);
+        match (isar, txn, query) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn), CIsarQuery::Native(query)) => {
+                let mut cursor = isar.query_cursor(txn, query, offset, limit)?;
+                cursor.serialize_values(property_index, serializer)?;
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn), CIsarQuery::SQLite(query)) => {
+                let mut cursor = isar.query_cursor(txn, query, offset, limit)?;
+                cursor.serialize_values(property_index, serializer)?;
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        *length = json.len() as u32;
+        *buffer_size = json.capacity() as u32;
+        *buffer = json.leak().as_mut_ptr();
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_query_delete(
     isar: &'static CIsarInstance,
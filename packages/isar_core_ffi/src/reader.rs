@@ -173,6 +173,32 @@ pub unsafe extern "C" fn isar_plus_read_string(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_read_uuid(
+    reader: &'static CIsarReader,
+    index: u32,
+    value: *mut u8,
+) -> bool {
+    let uuid = match reader {
+        #[cfg(feature = "native")]
+        CIsarReader::Native(reader) => reader.read_uuid(index),
+        #[cfg(feature = "native")]
+        CIsarReader::NativeList(reader) => reader.read_uuid(index),
+        #[cfg(feature = "sqlite")]
+        CIsarReader::SQLite(reader) => reader.read_uuid(index),
+        #[cfg(feature = "sqlite")]
+        CIsarReader::SQLiteObject(reader) => reader.read_uuid(index),
+        #[cfg(feature = "sqlite")]
+        CIsarReader::SQLiteList(reader) => reader.read_uuid(index),
+    };
+    if let Some(uuid) = uuid {
+        ptr::copy_nonoverlapping(uuid.as_ptr(), value, uuid.len());
+        true
+    } else {
+        false
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_read_object(
     reader: &'static CIsarReader,
@@ -1,8 +1,10 @@
 #![allow(unreachable_patterns)]
 
-use crate::{isar_to_i64, CIsarInstance, CIsarQuery, CIsarTxn, CIsarUpdate, IsarI64};
+use crate::{isar_to_i64, CIsarInstance, CIsarMerge, CIsarQuery, CIsarTxn, CIsarUpdate, IsarI64};
 use isar_core::core::error::IsarError;
 use isar_core::core::instance::IsarInstance;
+use isar_core::core::merge::{FieldUpdate, HlcTimestamp, ObjectChange};
+use isar_core::core::update::UpdateOp;
 use isar_core::core::value::IsarValue;
 
 #[no_mangle]
@@ -77,5 +79,117 @@ pub unsafe extern "C" fn isar_plus_update_add_value(
     } else {
         None
     };
-    update.0.push((property_index, value));
+    update.0.push((property_index, UpdateOp::Set(value)));
+}
+
+pub const UPDATE_INCREMENT: u8 = 0;
+pub const UPDATE_DECREMENT: u8 = 1;
+pub const UPDATE_MIN: u8 = 2;
+pub const UPDATE_MAX: u8 = 3;
+pub const UPDATE_CONCAT: u8 = 4;
+pub const UPDATE_APPEND: u8 = 5;
+pub const UPDATE_REMOVE: u8 = 6;
+pub const UPDATE_ADD_TO_SET: u8 = 7;
+
+/// Adds an operation that is applied to the stored value of the property.
+/// Fails with an illegal argument error for unknown operations or a missing
+/// value.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_update_add_op(
+    update: &'static mut CIsarUpdate,
+    property_index: u16,
+    op: u8,
+    value: *mut IsarValue,
+) -> u8 {
+    isar_try! {
+        if value.is_null() {
+            return Err(IsarError::IllegalArgument {});
+        }
+        let value = *Box::from_raw(value);
+        let op = match op {
+            UPDATE_INCREMENT => UpdateOp::Increment(value),
+            UPDATE_DECREMENT => UpdateOp::Decrement(value),
+            UPDATE_MIN => UpdateOp::Min(value),
+            UPDATE_MAX => UpdateOp::Max(value),
+            UPDATE_CONCAT => UpdateOp::Concat(value),
+            UPDATE_APPEND => UpdateOp::Append(value),
+            UPDATE_REMOVE => UpdateOp::Remove(value),
+            UPDATE_ADD_TO_SET => UpdateOp::AddToSet(value),
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        update.0.push((property_index, op));
+    }
+}
+
+/// Writes the number of merged objects to `merged` and the ids of missing
+/// objects as JSON array to `buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_merge_changes(
+    isar: &'static CIsarInstance,
+    txn: &'static CIsarTxn,
+    collection_index: u16,
+    merge: *mut CIsarMerge,
+    remote: bool,
+    merged: *mut u32,
+    buffer: *mut *mut u8,
+    buffer_size: *mut u32,
+    length: *mut u32,
+) -> u8 {
+    let merge = Box::from_raw(merge);
+    isar_try! {
+        let result = match (isar, txn) {
+            #[cfg(feature = "native")]
+            (CIsarInstance::Native(isar), CIsarTxn::Native(txn)) => {
+                isar.merge_changes(txn, collection_index, &merge.0, remote)?
+            }
+            #[cfg(feature = "sqlite")]
+            (CIsarInstance::SQLite(isar), CIsarTxn::SQLite(txn)) => {
+                isar.merge_changes(txn, collection_index, &merge.0, remote)?
+            }
+            _ => return Err(IsarError::IllegalArgument {}),
+        };
+        let json = serde_json::to_vec(&result.missing).map_err(|e| IsarError::JsonError {
+            message: e.to_string(),
+        })?;
+        *merged = result.merged;
+        *length = json.len() as u32;
+        *buffer_size = json.capacity() as u32;
+        *buffer = json.leak().as_mut_ptr();
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_merge_new() -> *mut CIsarMerge {
+    Box::into_raw(Box::new(CIsarMerge(Vec::new())))
+}
+
+/// Adds a field update. Consecutive updates of the same object are grouped.
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_merge_add_value(
+    merge: &'static mut CIsarMerge,
+    id: IsarI64,
+    property_index: u16,
+    value: *mut IsarValue,
+    millis: IsarI64,
+    counter: u32,
+    node: u32,
+) {
+    let id = isar_to_i64(id);
+    let value = if !value.is_null() {
+        Some(*Box::from_raw(value))
+    } else {
+        None
+    };
+    let update = FieldUpdate {
+        property_index,
+        value,
+        timestamp: HlcTimestamp::new(isar_to_i64(millis) as u64, counter, node),
+    };
+    match merge.0.last_mut() {
+        Some(change) if change.id == id => change.updates.push(update),
+        _ => merge.0.push(ObjectChange {
+            id,
+            updates: vec![update],
+        }),
+    }
 }
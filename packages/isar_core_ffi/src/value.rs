@@ -1,6 +1,6 @@
 use crate::{i64_to_isar, isar_to_i64, IsarI64};
 use isar_core::core::value::IsarValue;
-use std::ptr;
+use std::{ptr, slice};
 
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_value_bool(value: bool) -> *const IsarValue {
@@ -23,6 +23,46 @@ pub unsafe extern "C" fn isar_plus_value_string(value: *mut String) -> *const Is
     Box::into_raw(Box::new(IsarValue::String(*Box::from_raw(value))))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_value_list(
+    values: *mut *mut IsarValue,
+    length: u32,
+) -> *const IsarValue {
+    let values = slice::from_raw_parts(values, length as usize)
+        .iter()
+        .map(|value| {
+            if value.is_null() {
+                None
+            } else {
+                Some(*Box::from_raw(*value))
+            }
+        })
+        .collect();
+    Box::into_raw(Box::new(IsarValue::List(values)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_value_object(
+    property_indexes: *const u16,
+    values: *mut *mut IsarValue,
+    length: u32,
+) -> *const IsarValue {
+    let property_indexes = slice::from_raw_parts(property_indexes, length as usize);
+    let values = slice::from_raw_parts(values, length as usize);
+    let values = property_indexes
+        .iter()
+        .zip(values)
+        .map(|(index, value)| {
+            if value.is_null() {
+                (*index, None)
+            } else {
+                (*index, Some(*Box::from_raw(*value)))
+            }
+        })
+        .collect();
+    Box::into_raw(Box::new(IsarValue::Object(values)))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_value_get_bool(value: *const IsarValue) -> u8 {
     let value = value.as_ref().map(|v| v.bool()).flatten().unwrap_or(false);
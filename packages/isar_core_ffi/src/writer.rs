@@ -168,6 +168,29 @@ pub unsafe extern "C" fn isar_plus_write_string(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_plus_write_uuid(
+    writer: &'static mut CIsarWriter,
+    index: u32,
+    value: *const u8,
+) {
+    let value = &*(value as *const [u8; 16]);
+    match writer {
+        #[cfg(feature = "native")]
+        CIsarWriter::Native(writer) => writer.write_uuid(index, value),
+        #[cfg(feature = "native")]
+        CIsarWriter::NativeObject(writer) => writer.write_uuid(index, value),
+        #[cfg(feature = "native")]
+        CIsarWriter::NativeList(writer) => writer.write_uuid(index, value),
+        #[cfg(feature = "sqlite")]
+        CIsarWriter::SQLite(writer) => writer.write_uuid(index, value),
+        #[cfg(feature = "sqlite")]
+        CIsarWriter::SQLiteObject(writer) => writer.write_uuid(index, value),
+        #[cfg(feature = "sqlite")]
+        CIsarWriter::SQLiteList(writer) => writer.write_uuid(index, value),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_plus_write_json(
     writer: &'static mut CIsarWriter,
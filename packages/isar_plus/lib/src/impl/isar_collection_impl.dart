@@ -426,10 +426,11 @@ class _IsarCollectionImpl<ID, OBJ> extends IsarCollection<ID, OBJ> {
                 as R;
     }
 
-    final queryPtr = IsarCore.b.isar_plus_query_build(builderPtr);
+    final queryPtrPtr = IsarCore.ptrPtr.cast<Pointer<CIsarQuery>>();
+    IsarCore.b.isar_plus_query_build(builderPtr, queryPtrPtr).checkNoError();
     return _IsarQueryImpl(
       instanceId: isar.instanceId,
-      ptrAddress: queryPtr.address,
+      ptrAddress: queryPtrPtr.ptrValue.address,
       properties: properties,
       deserialize: deserialize,
     );
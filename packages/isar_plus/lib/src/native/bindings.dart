@@ -1180,21 +1180,28 @@ class IsarCoreBindings {
   late final _isar_plus_query_add_distinct = _isar_plus_query_add_distinctPtr
       .asFunction<void Function(ffi.Pointer<CIsarQueryBuilder>, int, bool)>();
 
-  ffi.Pointer<CIsarQuery> isar_plus_query_build(
+  int isar_plus_query_build(
     ffi.Pointer<CIsarQueryBuilder> builder,
+    ffi.Pointer<ffi.Pointer<CIsarQuery>> query,
   ) {
-    return _isar_plus_query_build(builder);
+    return _isar_plus_query_build(builder, query);
   }
 
   late final _isar_plus_query_buildPtr =
       _lookup<
         ffi.NativeFunction<
-          ffi.Pointer<CIsarQuery> Function(ffi.Pointer<CIsarQueryBuilder>)
+          ffi.Uint8 Function(
+            ffi.Pointer<CIsarQueryBuilder>,
+            ffi.Pointer<ffi.Pointer<CIsarQuery>>,
+          )
         >
       >('isar_plus_query_build');
   late final _isar_plus_query_build = _isar_plus_query_buildPtr
       .asFunction<
-        ffi.Pointer<CIsarQuery> Function(ffi.Pointer<CIsarQueryBuilder>)
+        int Function(
+          ffi.Pointer<CIsarQueryBuilder>,
+          ffi.Pointer<ffi.Pointer<CIsarQuery>>,
+        )
       >();
 
   int isar_plus_query_cursor(
@@ -492,10 +492,14 @@ extension IsarBindingsX on JSIsar {
   );
 
   @ffi.Native<
-    ffi.Pointer<CIsarQuery> Function(ffi.Pointer<CIsarQueryBuilder>)
+    ffi.Uint8 Function(
+      ffi.Pointer<CIsarQueryBuilder>,
+      ffi.Pointer<ffi.Pointer<CIsarQuery>>,
+    )
   >()
-  external ffi.Pointer<CIsarQuery> isar_plus_query_build(
+  external int isar_plus_query_build(
     ffi.Pointer<CIsarQueryBuilder> builder,
+    ffi.Pointer<ffi.Pointer<CIsarQuery>> query,
   );
 
   @ffi.Native<